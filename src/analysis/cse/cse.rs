@@ -5,6 +5,9 @@
 //! opcodes. On the other hand, considering too much will cause
 //! a huge memory consume. Thus, a balanced solution should be 
 //! improved.
+//!
+//! Only expressions in the same block are eliminated, see `analysis::gvn`
+//! for the dominator-based version.
 
 use std::fmt::Display;
 use std::collections::HashMap;
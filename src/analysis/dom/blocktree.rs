@@ -0,0 +1,116 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Dominator tree over the basic blocks of a `CFG`, e.g. `SSAStorage`.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use petgraph::graph::{Graph, NodeIndex};
+
+use middle::ssa::cfg_traits::CFG;
+use super::domtree::DomTree;
+
+#[derive(Clone, Debug)]
+/// Dominator tree of the basic blocks reachable from the entry block.
+pub struct BlockDomTree<B: Copy + Eq + Hash> {
+    tree: DomTree,
+    index: HashMap<B, NodeIndex>,
+    blocks: Vec<B>,
+    children: Vec<Vec<usize>>,
+}
+
+impl<B: Copy + Eq + Hash> BlockDomTree<B> {
+    /// Computes the dominator tree of the blocks in `cfg`.
+    pub fn new<T>(cfg: &T) -> BlockDomTree<B>
+        where T: CFG<ActionRef = B>
+    {
+        let mut g = Graph::<NodeIndex, u8>::new();
+        let mut index = HashMap::new();
+        let mut blocks = Vec::new();
+        let entry = entry_node_err!(cfg);
+
+        // Only reachable blocks, unreachable ones have no dominators.
+        let mut queue = VecDeque::new();
+        index.insert(entry, g.add_node(NodeIndex::new(0)));
+        blocks.push(entry);
+        queue.push_back(entry);
+        while let Some(block) = queue.pop_front() {
+            for succ in cfg.succs_of(block) {
+                if !index.contains_key(&succ) {
+                    let n = g.add_node(NodeIndex::new(blocks.len()));
+                    index.insert(succ, n);
+                    blocks.push(succ);
+                    queue.push_back(succ);
+                }
+                g.add_edge(index[&block], index[&succ], 0);
+            }
+        }
+
+        let tree = DomTree::build_dom_tree(&g, index[&entry]);
+        let mut children = vec![Vec::new(); blocks.len()];
+        for i in 0..blocks.len() {
+            let idom = tree.idom(NodeIndex::new(i)).index();
+            if idom != i {
+                children[idom].push(i);
+            }
+        }
+
+        BlockDomTree {
+            tree: tree,
+            index: index,
+            blocks: blocks,
+            children: children,
+        }
+    }
+
+    /// Returns true if block `a` dominates block `b`. Every block dominates
+    /// itself.
+    pub fn dominates(&self, a: &B, b: &B) -> bool {
+        match (self.index.get(a), self.index.get(b)) {
+            (Some(a), Some(b)) => self.tree.doms(*b).contains(a),
+            _ => false,
+        }
+    }
+
+    /// Returns the immediate dominator of `block`, None for the entry block
+    /// and unreachable blocks.
+    pub fn idom(&self, block: &B) -> Option<B> {
+        let n = *self.index.get(block)?;
+        let idom = self.tree.idom(n);
+        if idom == n {
+            None
+        } else {
+            Some(self.blocks[idom.index()])
+        }
+    }
+
+    /// Returns the blocks immediately dominated by `block`.
+    pub fn children(&self, block: &B) -> Vec<B> {
+        match self.index.get(block) {
+            Some(n) => self.children[n.index()].iter().map(|&i| self.blocks[i]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the reachable blocks in preorder of the dominator tree.
+    pub fn preorder(&self) -> Vec<B> {
+        let mut order = Vec::new();
+        let mut stack = self.blocks.first().cloned().into_iter().collect::<Vec<_>>();
+        while let Some(block) = stack.pop() {
+            order.push(block);
+            let mut children = self.children(&block);
+            children.reverse();
+            stack.extend(children);
+        }
+        order
+    }
+
+    /// Returns true if `block` is reachable from the entry block.
+    pub fn is_reachable(&self, block: &B) -> bool {
+        self.index.contains_key(block)
+    }
+}
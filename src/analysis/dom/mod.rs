@@ -8,5 +8,7 @@
 /// Module for computing dominance and post-dominance information
 mod index;
 pub mod domtree;
+pub mod blocktree;
 
 pub use self::domtree::DomTree;
+pub use self::blocktree::BlockDomTree;
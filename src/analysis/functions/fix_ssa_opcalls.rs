@@ -2,6 +2,7 @@
 
 use frontend::radeco_containers::*;
 use middle::ir;
+use middle::prototypes::Prototype;
 use middle::ssa::ssa_traits::*;
 use middle::ssa::ssastorage::SSAStorage;
 
@@ -12,15 +13,32 @@ use std::collections::HashMap;
 /// operand of the SSA node with a constant value for the address of the actual
 /// call target.
 ///
+/// Calls to functions with a known prototype of fixed arguments are also
+/// fixed to pass only the argument registers of the prototype, see
+/// [`middle::prototypes`].
+///
 /// [`OpCall`]: ir::MOpcode::OpCall
 /// [the callgraph]: RadecoModule::callgraph
 pub fn go(rmod: &mut RadecoModule) -> () {
+    let mut prototypes = rmod.functions
+        .values()
+        .filter_map(|rfn| Some((rfn.offset, rfn.prototype()?.clone())))
+        .collect::<HashMap<_, _>>();
+    for (&plt, ii) in &rmod.imports {
+        if let Some(proto) = ii.rfn.borrow().prototype() {
+            prototypes.insert(plt, proto.clone());
+        }
+    }
     for rfun in rmod.functions.values_mut() {
-        go_fn(rfun, &rmod.callgraph);
+        go_fn(rfun, &rmod.callgraph, &prototypes);
     }
 }
 
-fn go_fn(rfun: &mut RadecoFunction, callgraph: &CallGraph) -> () {
+fn go_fn(
+    rfun: &mut RadecoFunction,
+    callgraph: &CallGraph,
+    prototypes: &HashMap<u64, Prototype>,
+) -> () {
     let fn_addr = rfun.offset;
     let call_site_addr_to_target_addr: HashMap<u64, u64> = callgraph
         .callees(rfun.cgid())
@@ -29,13 +47,14 @@ fn go_fn(rfun: &mut RadecoFunction, callgraph: &CallGraph) -> () {
     let ssa = rfun.ssa_mut();
     for node in ssa.inorder_walk() {
         if let Ok(NodeType::Op(ir::MOpcode::OpCall)) = ssa.node_data(node).map(|x| x.nt) {
-            fix_call_site(ssa, node, &call_site_addr_to_target_addr).unwrap_or_else(|| {
-                radeco_err!(
-                    "failed to fix call site {:?} in function at {:#X}",
-                    node,
-                    fn_addr
-                )
-            });
+            fix_call_site(ssa, node, &call_site_addr_to_target_addr, prototypes)
+                .unwrap_or_else(|| {
+                    radeco_err!(
+                        "failed to fix call site {:?} in function at {:#X}",
+                        node,
+                        fn_addr
+                    )
+                });
         }
     }
 }
@@ -44,6 +63,7 @@ fn fix_call_site(
     ssa: &mut SSAStorage,
     call_node: <SSAStorage as SSA>::ValueRef,
     fn_call_map: &HashMap<u64, u64>,
+    prototypes: &HashMap<u64, Prototype>,
 ) -> Option<()> {
     let call_site_addr = ssa.address(call_node)?.address;
     if let Some(&call_target_addr) = fn_call_map.get(&call_site_addr) {
@@ -54,6 +74,9 @@ fn fix_call_site(
         let new_opcall_tgt_node = ssa.insert_const(call_target_addr)?;
         ssa.op_unuse(call_node, old_opcall_tgt_node);
         ssa.op_use(call_node, 0, new_opcall_tgt_node);
+        if let Some(proto) = prototypes.get(&call_target_addr) {
+            trim_args(ssa, call_node, proto);
+        }
     } else {
         radeco_trace!(
             "call site at {:#X} isn't in call graph; perhaps the call is indirect?",
@@ -62,3 +85,33 @@ fn fix_call_site(
     }
     Some(())
 }
+
+// Removes the argument registers following the ones of `proto` from the
+// operands of `call_node`.
+fn trim_args(
+    ssa: &mut SSAStorage,
+    call_node: <SSAStorage as SSA>::ValueRef,
+    proto: &Prototype,
+) -> () {
+    if !proto.fixed_args() {
+        return;
+    }
+    let regfile = ssa.regfile.clone();
+    let extra = (proto.args.len()..regfile.iter_args().count())
+        .filter_map(|i| regfile.register_id_by_alias(&format!("A{}", i)))
+        .map(|id| id.to_u8() + 1)
+        .collect::<Vec<_>>();
+    let operands = ssa.sparse_operands_of(call_node);
+    for &(idx, node) in &operands {
+        if !extra.contains(&idx) {
+            continue;
+        }
+        // Operands are removed by value, keep the other uses of it.
+        ssa.op_unuse(call_node, node);
+        for &(other_idx, other) in &operands {
+            if other == node && !extra.contains(&other_idx) {
+                ssa.op_use(call_node, other_idx, node);
+            }
+        }
+    }
+}
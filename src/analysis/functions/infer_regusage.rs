@@ -42,8 +42,8 @@ impl Inferer {
         }
     }
 
-    /// Calls `patch_fn`, `dce::collect`, and `analyze_fn` on every function,
-    /// callees first
+    /// Calls `patch_fn`, `dce::collect`, `dce::collect_stores`, and
+    /// `analyze_fn` on every function, callees first
     fn run(&mut self, rmod: &mut RadecoModule, reginfo: &SubRegisterFile) -> () {
         // for imports, *ASSUME* that the callconv that r2 says is correct
         {
@@ -75,6 +75,7 @@ impl Inferer {
 
                     let rfn = &mut rmod.functions.get_mut(&fn_addr).unwrap();
                     dce::collect(rfn.ssa_mut());
+                    dce::collect_stores(rfn);
                     inst_combine::run(rfn.ssa_mut());
 
                     let ru = self.analyze_fn(rfn, reginfo).unwrap_or_else(|| {
@@ -0,0 +1,419 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Global Value Numbering (GVN)
+//!
+//! Dominator-based value numbering over `SSAStorage`, which supersedes the
+//! block-local `analysis::cse`. Blocks are visited in preorder of the
+//! dominator tree, with a scoped table of the expressions available in the
+//! dominating blocks. Besides, it
+//!     * sorts operands of commutative opcodes with `ssasort::Sorter` first;
+//!     * removes trivial phis and merges phis with the same incoming values;
+//!     * simplifies phi-of-ops, i.e. replaces `op(phi(a, b), c)` with an
+//!       existing `phi(op(a, c), op(b, c))`;
+//!     * merges loads of a stack slot reached by the same memory definitions
+//!       in MemorySSA.
+//!
+//! Phi operands are not ordered by predecessors in `SSAStorage`, thus the
+//! incoming value of every predecessor is recovered through dominance, and
+//! phis whose incoming values could not be told are left alone.
+
+use std::collections::HashMap;
+
+use petgraph::graph::NodeIndex;
+
+use analysis::cse::ssasort::Sorter;
+use analysis::dom::BlockDomTree;
+use analysis::vsa::value_set::MemRegion;
+use frontend::radeco_containers::RadecoFunction;
+use middle::ir::{MOpcode, WidthSpec};
+use middle::ssa::cfg_traits::CFG;
+use middle::ssa::memoryssa::{MemoryDef, MemorySSAQuery};
+use middle::ssa::ssa_traits::{NodeType, SSA, SSAMod};
+use middle::ssa::ssastorage::SSAStorage;
+
+/// Runs GVN on `rfn`, returns the number of replaced values.
+pub fn run(rfn: &mut RadecoFunction) -> usize {
+    // Sort the operands for commutative opcode first;
+    {
+        let mut sorter = Sorter::new(rfn.ssa_mut());
+        sorter.run();
+    }
+
+    if rfn.memory_ssa().is_none() {
+        rfn.build_memory_ssa();
+    }
+    let load_keys = memory_keys(rfn);
+    let mut gvn = GVN::new(rfn.ssa_mut());
+    gvn.load_keys = load_keys;
+    gvn.run()
+}
+
+// Keys of loads from stack slots, which are the definitions reaching them in
+// MemorySSA, calls among them. Loads of the same address with the same key
+// read the same value.
+fn memory_keys(rfn: &RadecoFunction) -> HashMap<NodeIndex, Vec<MemoryDef<NodeIndex>>> {
+    let mssa = rfn.memory_ssa().expect("MemorySSA not built");
+
+    let mut keys = HashMap::new();
+    for node in rfn.ssa().values() {
+        if rfn.ssa().opcode(node) != Some(MOpcode::OpLoad) {
+            continue;
+        }
+        let a_loc = match mssa.accessed_a_loc(&node) {
+            Some(a_loc) => a_loc,
+            None => continue,
+        };
+        if a_loc.region != MemRegion::Stack {
+            continue;
+        }
+        let mut defs = mssa.reaching_defs(&node);
+        defs.sort_by_key(|def| match *def {
+            MemoryDef::Store(n) => (0, n.index()),
+            MemoryDef::Call(n) => (1, n.index()),
+            MemoryDef::Phi(n) => (2, n.index()),
+            MemoryDef::Entry => (3, 0),
+            MemoryDef::Undefined => (4, 0),
+        });
+        defs.dedup();
+        keys.insert(node, defs);
+    }
+    keys
+}
+
+// Key of a numbered value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    // Opcode, width and operands.
+    Expr(MOpcode, Option<WidthSpec>, Vec<NodeIndex>),
+    // Block, width and incoming values in the order of predecessors.
+    Phi(NodeIndex, Option<WidthSpec>, Vec<NodeIndex>),
+    // Width, definitions reaching the load in MemorySSA and address.
+    Load(Option<WidthSpec>, Vec<MemoryDef<NodeIndex>>, Option<NodeIndex>),
+}
+
+// Items of the explicit stack used to walk the dominator tree
+enum Visit {
+    Enter(NodeIndex),
+    // Keys made available by the block
+    Exit(Vec<Key>),
+}
+
+pub struct GVN<'a> {
+    ssa: &'a mut SSAStorage,
+    doms: BlockDomTree<NodeIndex>,
+    /// Expressions available in the current block, i.e. defined in the
+    /// dominating blocks.
+    available: HashMap<Key, NodeIndex>,
+    /// All numbered expressions, used to look up expressions translated
+    /// into predecessors.
+    numbered: HashMap<Key, Vec<NodeIndex>>,
+    /// Keys of loads computed from MemorySSA, loads without keys are numbered
+    /// by their memory operands.
+    pub load_keys: HashMap<NodeIndex, Vec<MemoryDef<NodeIndex>>>,
+    replaced: usize,
+}
+
+impl<'a> GVN<'a> {
+    pub fn new(ssa: &'a mut SSAStorage) -> GVN<'a> {
+        let doms = BlockDomTree::new(&*ssa);
+        GVN {
+            ssa: ssa,
+            doms: doms,
+            available: HashMap::new(),
+            numbered: HashMap::new(),
+            load_keys: HashMap::new(),
+            replaced: 0,
+        }
+    }
+
+    /// Numbers values and replaces the redundant ones, returns the number of
+    /// replaced values.
+    pub fn run(&mut self) -> usize {
+        let mut stack = vec![Visit::Enter(entry_node_err!(self.ssa))];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(block) => {
+                    let added = self.visit_block(block);
+                    stack.push(Visit::Exit(added));
+                    let mut children = self.doms.children(&block);
+                    children.reverse();
+                    stack.extend(children.into_iter().map(Visit::Enter));
+                }
+                Visit::Exit(added) => {
+                    for key in &added {
+                        self.available.remove(key);
+                    }
+                }
+            }
+        }
+        self.replaced
+    }
+
+    fn visit_block(&mut self, block: NodeIndex) -> Vec<Key> {
+        let mut added = Vec::new();
+
+        for phi in self.ssa.phis_in(block) {
+            let mut operands = self.ssa.operands_of(phi);
+            operands.retain(|op| *op != phi);
+            operands.sort();
+            operands.dedup();
+            if operands.len() == 1 {
+                self.replace(phi, operands[0]);
+                continue;
+            }
+            let key = match self.phi_incoming(phi, block) {
+                Some(incoming) => Key::Phi(block, self.width_of(phi), incoming),
+                None => continue,
+            };
+            self.number(phi, key, &mut added);
+        }
+
+        for expr in self.ssa.exprs_in(block) {
+            let key = match self.key_of(expr) {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Some(rep) = self.available.get(&key).cloned() {
+                self.replace(expr, rep);
+                continue;
+            }
+            if let Some(rep) = self.phi_of_ops(expr, block) {
+                self.replace(expr, rep);
+                continue;
+            }
+            self.number(expr, key, &mut added);
+        }
+
+        added
+    }
+
+    // Replaces `node` with the available value of the same key, or makes
+    // `node` available.
+    fn number(&mut self, node: NodeIndex, key: Key, added: &mut Vec<Key>) {
+        if let Some(rep) = self.available.get(&key).cloned() {
+            self.replace(node, rep);
+            return;
+        }
+        self.available.insert(key.clone(), node);
+        self.numbered.entry(key.clone()).or_insert_with(Vec::new).push(node);
+        added.push(key);
+    }
+
+    fn replace(&mut self, node: NodeIndex, rep: NodeIndex) {
+        radeco_trace!("gvn|Replace {:?} with {:?}", node, rep);
+        self.ssa.replace_value(node, rep);
+        self.replaced += 1;
+    }
+
+    fn width_of(&self, node: NodeIndex) -> Option<WidthSpec> {
+        self.ssa.node_data(node).ok().map(|nd| nd.vt.width().clone())
+    }
+
+    // Returns the key of an expression, None if it could not be numbered.
+    fn key_of(&self, expr: NodeIndex) -> Option<Key> {
+        let opc = match self.ssa.node_data(expr).ok()?.nt {
+            NodeType::Op(opc) => opc,
+            _ => return None,
+        };
+        let width = self.width_of(expr);
+        let args = self.ssa.operands_of(expr);
+        match opc {
+            // Constants are unique already.
+            MOpcode::OpConst(_) |
+            MOpcode::OpIntrinsic(_) |
+            MOpcode::OpInvalid |
+            MOpcode::OpNop => None,
+            MOpcode::OpLoad => {
+                match self.load_keys.get(&expr) {
+                    Some(defs) => Some(Key::Load(width, defs.clone(), args.get(1).cloned())),
+                    None => Some(Key::Expr(opc, width, args)),
+                }
+            }
+            _ if opc.has_sideeffects() => None,
+            _ => Some(Key::Expr(opc, width, args)),
+        }
+    }
+
+    // Returns true if the value is available at the end of `block`.
+    fn dominates(&self, value: NodeIndex, block: &NodeIndex) -> bool {
+        match self.ssa.block_for(value) {
+            Some(b) => self.doms.dominates(&b, block),
+            // Constants are available everywhere.
+            None => true,
+        }
+    }
+
+    // Returns incoming values of the phi in the order of predecessors of the
+    // block, None if they could not be told. Every incoming value dominates
+    // its predecessor, thus
+    //      a value dominating only one predecessor comes from it;
+    //      a predecessor dominated by only one value gets it.
+    fn phi_incoming(&self, phi: NodeIndex, block: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let preds = self.ssa.preds_of(block);
+        let mut operands = self.ssa.operands_of(phi);
+        operands.sort();
+        operands.dedup();
+        let mut incoming: Vec<Option<NodeIndex>> = vec![None; preds.len()];
+
+        for op in &operands {
+            let dominated = (0..preds.len())
+                .filter(|i| self.dominates(*op, &preds[*i]))
+                .collect::<Vec<_>>();
+            match dominated.len() {
+                0 => return None,
+                1 => {
+                    if incoming[dominated[0]].map_or(false, |v| v != *op) {
+                        return None;
+                    }
+                    incoming[dominated[0]] = Some(*op);
+                }
+                _ => {}
+            }
+        }
+        for i in 0..preds.len() {
+            if incoming[i].is_some() {
+                continue;
+            }
+            let candidates = operands.iter()
+                .filter(|op| self.dominates(**op, &preds[i]))
+                .collect::<Vec<_>>();
+            if candidates.len() != 1 {
+                return None;
+            }
+            incoming[i] = Some(*candidates[0]);
+        }
+
+        let incoming = incoming.into_iter().collect::<Option<Vec<_>>>()?;
+        // Every operand must come from some predecessor.
+        if operands.iter().any(|op| !incoming.contains(op)) {
+            return None;
+        }
+        Some(incoming)
+    }
+
+    // Phi-of-ops: if `expr` uses phis of the block, translates it into every
+    // predecessor. If all translated expressions are available as the same
+    // value, or as the incoming values of an existing phi, `expr` could be
+    // replaced with it.
+    fn phi_of_ops(&self, expr: NodeIndex, block: NodeIndex) -> Option<NodeIndex> {
+        let opc = match self.ssa.node_data(expr).ok()?.nt {
+            NodeType::Op(opc) => opc,
+            _ => return None,
+        };
+        // Memory could not be translated.
+        if opc == MOpcode::OpLoad {
+            return None;
+        }
+        let width = self.width_of(expr);
+        let args = self.ssa.operands_of(expr);
+        let mut incoming = HashMap::new();
+        for arg in &args {
+            if self.ssa.is_phi(*arg) && self.ssa.block_for(*arg) == Some(block) {
+                incoming.insert(*arg, self.phi_incoming(*arg, block)?);
+            }
+        }
+        if incoming.is_empty() {
+            return None;
+        }
+
+        let preds = self.ssa.preds_of(block);
+        let mut values = Vec::new();
+        for (i, pred) in preds.iter().enumerate() {
+            let translated = args.iter()
+                .map(|arg| incoming.get(arg).map_or(*arg, |inc| inc[i]))
+                .collect::<Vec<_>>();
+            let key = Key::Expr(opc.clone(), width, translated);
+            let value = *self.numbered
+                .get(&key)?
+                .iter()
+                .find(|n| self.dominates(**n, pred))?;
+            values.push(value);
+        }
+
+        let first = *values.first()?;
+        if values.iter().all(|v| *v == first) {
+            // Available in all predecessors, thus in the block.
+            return match self.ssa.block_for(first) {
+                Some(b) if b != block => Some(first),
+                _ => None,
+            };
+        }
+        self.ssa.phis_in(block).into_iter().find(|phi| {
+            self.width_of(*phi) == width && self.phi_incoming(*phi, block).as_ref() == Some(&values)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::{il_function, load_il_function};
+
+    fn subs(rfn: &RadecoFunction) -> Vec<Vec<NodeIndex>> {
+        let ssa = rfn.ssa();
+        ssa.values()
+            .into_iter()
+            .filter(|n| ssa.opcode(*n) == Some(MOpcode::OpSub))
+            .map(|n| ssa.operands_of(n))
+            .collect()
+    }
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const REDUNDANT_TXT: &str = "\
+define-fun sym.redundant(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rsp;
+        %2: $Unknown0 = $mem;
+    bb_0x001000.0000(sz 0x8):
+        [@0x001000.0001] %3: $Unknown64 = %1 - #x8;
+        [@0x001000.0002] %4: $Unknown64 = %1 - #x8;
+        [@0x001000.0003] %5: $Unknown64 = %1 + #x10;
+        JMP 0x001008.0000
+    bb_0x001008.0000(sz 0x8):
+        [@0x001008.0001] %6: $Unknown64 = %1 - #x8;
+        [@0x001008.0002] %7: $Unknown64 = #x10 + %1;
+        [@0x001008.0003] %8: $Unknown32 = Narrow32(%1);
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %4;
+        $rcx = %6;
+        $rdx = %7;
+        $rsi = %5;
+        $rdi = %8;
+        $mem = %2;
+}
+";
+
+    #[test]
+    fn gvn_redundant_test() {
+        // `%4` and `%6` are `%3`, `%7` is `%5` once the operands are sorted.
+        let mut rfn = il_function(REDUNDANT_TXT);
+        assert_eq!(run(&mut rfn), 3);
+        assert_eq!(subs(&rfn).len(), 1);
+    }
+
+    #[test]
+    fn gvn_across_blocks_test() {
+        // Stack addresses `rbp - c` are computed again in blocks dominated
+        // by the entry block.
+        let mut rfn = load_il_function("./test_files/bin1_main_ssa");
+        let before = subs(&rfn);
+        let mut unique = before.clone();
+        unique.sort();
+        unique.dedup();
+        assert!(unique.len() < before.len());
+
+        assert!(run(&mut rfn) >= before.len() - unique.len());
+        let mut after = subs(&rfn);
+        let len = after.len();
+        after.sort();
+        after.dedup();
+        assert_eq!(after.len(), len);
+    }
+}
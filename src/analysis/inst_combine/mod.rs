@@ -251,6 +251,10 @@ fn extract_opinfo(
 ) -> Option<Either<(SSAValue, CombinableOpInfo, ValueInfo), u64>> {
     // bail if non-`NodeType::Op`
     let (cur_opcode, cur_vt) = extract_opcode(cur_node, ssa)?;
+    // bail if wider than the constants, which are folded in 64 bits
+    if cur_vt.width().get_width().map_or(false, |w| w > 64) {
+        return None;
+    }
     let cur_operands = ssa.operands_of(cur_node);
     match cur_operands.as_slice() {
         &[sub_node] => {
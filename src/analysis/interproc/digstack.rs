@@ -200,16 +200,25 @@ fn generic_frontward_analysis(ssa: &SSAStorage,
                 continue;
             }
 
-            let const_arg: i64;
-            let opcode_arg: i64;
+            let const_arg: usize;
+            let opcode_arg: usize;
+            let sign: i64;
             match opc {
                 MOpcode::OpSub => {
                     const_arg = 1;
                     opcode_arg = 0;
+                    sign = -1;
                 }
+                // The constant may be either operand, as in `8,rsp,+=`.
                 MOpcode::OpAdd => {
-                    const_arg = 0;
-                    opcode_arg = 1;
+                    if let Some(MOpcode::OpConst(_)) = ssa.opcode(args[1]) {
+                        const_arg = 1;
+                        opcode_arg = 0;
+                    } else {
+                        const_arg = 0;
+                        opcode_arg = 1;
+                    }
+                    sign = 1;
                 }
                 // Some compiler will initial SP with and 0xfffffff0
                 MOpcode::OpAnd => {
@@ -221,22 +230,22 @@ fn generic_frontward_analysis(ssa: &SSAStorage,
                     continue;
                 }
             }
-            if ssa.opcode(args[opcode_arg as usize]).is_some() || 
-                ssa.comment(args[opcode_arg as usize]).is_some() ||
-                (ssa.is_phi(args[opcode_arg as usize]) && is_global) {
+            if ssa.opcode(args[opcode_arg]).is_some() || 
+                ssa.comment(args[opcode_arg]).is_some() ||
+                (ssa.is_phi(args[opcode_arg]) && is_global) {
                 if let Some(MOpcode::OpConst(num)) = 
-                            ssa.opcode(args[const_arg as usize]) {
+                            ssa.opcode(args[const_arg]) {
                     // TODO: Some special cases may by not consided
-                    if !stack_offset.contains_key(&args[opcode_arg as usize]) {
+                    if !stack_offset.contains_key(&args[opcode_arg]) {
                         continue;
                     }
-                    let base = stack_offset.get(&args[opcode_arg as usize])
+                    let base = stack_offset.get(&args[opcode_arg])
                                                             .unwrap_or_else(|| {
                                                                 radeco_err!("Stack offset not found");
                                                                 &0
                                                             }).clone() as i64;
                     stack_offset.insert(*node, 
-                                base + (opcode_arg - const_arg) * (num as i64));
+                                base + sign * (num as i64));
                     continue;
                 }
             }
@@ -37,7 +37,19 @@ pub struct CallFixer<'a> {
 }
 
 impl<'a> CallFixer<'a> {
+    /// Names not given are taken from the "BP" and "SP" aliases of the register
+    /// profile the functions of `rmod` were lifted with.
     pub fn new(rmod: &'a mut RadecoModule, bp_name: Option<String>, sp_name: Option<String>) -> CallFixer<'a> {
+            let (bp_name, sp_name) = {
+                let alias = |role: &str| {
+                    rmod.functions
+                        .values()
+                        .next()
+                        .and_then(|rfn| rfn.ssa().regfile.get_name_by_alias(&role.to_owned()))
+                        .map(|name| name.to_owned())
+                };
+                (bp_name.or_else(|| alias("BP")), sp_name.or_else(|| alias("SP")))
+            };
             CallFixer {
                 bp_name: bp_name,
                 sp_name: sp_name,
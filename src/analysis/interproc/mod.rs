@@ -5,7 +5,7 @@
 // This file may not be copied, modified, or distributed
 // except according to those terms.
 
-mod digstack;
+pub mod digstack;
 pub mod summary;
 pub mod interproc;
 pub mod transfer;
@@ -96,7 +96,11 @@ impl InterProcAnalysis for CallSummary {
 
             rfn.set_returns(&returns.into_iter().map(From::from).collect::<Vec<_>>());
             rfn.set_modifides(&modifides.into_iter().map(From::from).collect::<Vec<_>>());
-            rfn.set_args(&args.into_iter().map(From::from).collect::<Vec<_>>());
+            let bound = rfn.args()
+                .into_iter()
+                .filter(|vb| vb.ridx.map_or(true, |ridx| args.contains(&(ridx as usize))))
+                .collect();
+            rfn.set_args(bound);
         }
     }
 
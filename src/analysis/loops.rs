@@ -0,0 +1,655 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Loop analysis on the CFG of `SSAStorage`.
+//!
+//! Natural loops are found from back edges, i.e. edges whose targets dominate
+//! their sources, and loops sharing the same header are merged. Loops are
+//! organized as a nesting forest, where the parent of a loop is the smallest
+//! loop containing it. Retreating edges of irreducible regions are not back
+//! edges, thus such regions are not regarded as loops.
+//!
+//! For every loop, the induction variables and the trip count are computed:
+//!     * basic induction variable: phi in the header, which is a value from
+//!       outside the loop at the entry and `phi + step` at the back edges;
+//!     * derived induction variable: `scale * base + offset`, where `base` is
+//!       a basic induction variable;
+//!     * counter: the basic induction variable compared by the only exit test
+//!       of the loop, with a constant or a value computed before the loop;
+//!     * trip count: the number of times the exit test keeps executing the
+//!       loop, when the counter is compared with a constant and starts from a
+//!       constant.
+//!
+//! Resizing operations are regarded as identity. The exit test compares the
+//! values unsigned in the width of its operands, and the trip count is unknown
+//! if the induction variable wraps around before the test fails.
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::graph::NodeIndex;
+
+use analysis::dom::BlockDomTree;
+use middle::ir::MOpcode;
+use middle::ssa::cfg_traits::CFG;
+use middle::ssa::ssa_traits::SSA;
+use middle::ssa::ssastorage::SSAStorage;
+
+/// Index of a loop in `LoopForest`
+pub type LoopId = usize;
+
+/// Induction variables of a loop.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InductionVar {
+    /// Phi in the loop header, which is `init` at the entry of the loop and
+    /// increased by `step` in every iteration.
+    Basic { init: NodeIndex, step: i64 },
+    /// `scale * base + offset`, where `base` is a basic induction variable of
+    /// the same loop.
+    Derived { base: NodeIndex, scale: i64, offset: i64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct Loop {
+    pub header: NodeIndex,
+    /// Blocks of the loop, including the ones of nested loops.
+    pub blocks: HashSet<NodeIndex>,
+    /// Sources of the back edges.
+    pub latches: Vec<NodeIndex>,
+    /// Blocks outside the loop which are targets of edges leaving it.
+    pub exits: Vec<NodeIndex>,
+    pub parent: Option<LoopId>,
+    pub children: Vec<LoopId>,
+    /// Nesting depth, 1 for outermost loops.
+    pub depth: usize,
+    pub induction_vars: HashMap<NodeIndex, InductionVar>,
+    /// Basic induction variable compared by the exit test.
+    pub counter: Option<NodeIndex>,
+    pub trip_count: Option<u64>,
+}
+
+impl Loop {
+    pub fn contains(&self, block: &NodeIndex) -> bool {
+        self.blocks.contains(block)
+    }
+}
+
+/// Loop nesting forest of a function.
+#[derive(Clone, Debug)]
+pub struct LoopForest {
+    /// Loops in preorder of their headers in the dominator tree, thus outer
+    /// loops come before the inner ones.
+    loops: Vec<Loop>,
+    /// Innermost loop of every block in loops.
+    innermost: HashMap<NodeIndex, LoopId>,
+}
+
+impl LoopForest {
+    pub fn new(ssa: &SSAStorage) -> LoopForest {
+        let doms = BlockDomTree::new(ssa);
+        let mut forest = LoopForest {
+            loops: Vec::new(),
+            innermost: HashMap::new(),
+        };
+        forest.find_loops(ssa, &doms);
+        forest.build_nesting();
+        for id in 0..forest.loops.len() {
+            let ivs = forest.find_induction_vars(ssa, id);
+            forest.loops[id].induction_vars = ivs;
+            if let Some((counter, trip_count)) = forest.find_exit_test(ssa, &doms, id) {
+                forest.loops[id].counter = Some(counter);
+                forest.loops[id].trip_count = trip_count;
+            }
+        }
+        radeco_trace!("loops|{:?}", forest.loops);
+        forest
+    }
+
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    pub fn get(&self, id: LoopId) -> Option<&Loop> {
+        self.loops.get(id)
+    }
+
+    /// Returns the outermost loops.
+    pub fn roots(&self) -> Vec<LoopId> {
+        (0..self.loops.len()).filter(|id| self.loops[*id].parent.is_none()).collect()
+    }
+
+    /// Returns the innermost loop containing `block`.
+    pub fn loop_of(&self, block: &NodeIndex) -> Option<LoopId> {
+        self.innermost.get(block).cloned()
+    }
+
+    /// Returns the loop whose header is `block`.
+    pub fn loop_with_header(&self, block: &NodeIndex) -> Option<LoopId> {
+        self.loop_of(block).and_then(|id| {
+            if self.loops[id].header == *block {
+                Some(id)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the number of loops containing `block`.
+    pub fn depth_of(&self, block: &NodeIndex) -> usize {
+        self.loop_of(block).map_or(0, |id| self.loops[id].depth)
+    }
+
+    fn find_loops(&mut self, ssa: &SSAStorage, doms: &BlockDomTree<NodeIndex>) {
+        for header in doms.preorder() {
+            let latches = ssa.preds_of(header)
+                .into_iter()
+                .filter(|pred| doms.dominates(&header, pred))
+                .collect::<Vec<_>>();
+            if latches.is_empty() {
+                continue;
+            }
+
+            // Blocks reaching latches without passing through the header.
+            let mut blocks = HashSet::new();
+            blocks.insert(header);
+            let mut worklist = latches.clone();
+            while let Some(block) = worklist.pop() {
+                if !blocks.insert(block) {
+                    continue;
+                }
+                worklist.extend(ssa.preds_of(block).into_iter().filter(|p| doms.is_reachable(p)));
+            }
+
+            let mut exits = Vec::new();
+            for block in &blocks {
+                for succ in ssa.succs_of(*block) {
+                    if !blocks.contains(&succ) && !exits.contains(&succ) {
+                        exits.push(succ);
+                    }
+                }
+            }
+
+            self.loops.push(Loop {
+                header: header,
+                blocks: blocks,
+                latches: latches,
+                exits: exits,
+                parent: None,
+                children: Vec::new(),
+                depth: 1,
+                induction_vars: HashMap::new(),
+                counter: None,
+                trip_count: None,
+            });
+        }
+    }
+
+    // Natural loops with different headers are either disjoint or nested, so
+    // the parent of a loop is the smallest one containing its header.
+    fn build_nesting(&mut self) {
+        for id in 0..self.loops.len() {
+            let parent = (0..self.loops.len())
+                .filter(|p| *p != id && self.loops[*p].contains(&self.loops[id].header))
+                .min_by_key(|p| self.loops[*p].blocks.len());
+            self.loops[id].parent = parent;
+            if let Some(p) = parent {
+                self.loops[p].children.push(id);
+            }
+        }
+        // Parents come before their children.
+        for id in 0..self.loops.len() {
+            self.loops[id].depth = self.loops[id].parent.map_or(1, |p| self.loops[p].depth + 1);
+        }
+        for id in 0..self.loops.len() {
+            for block in &self.loops[id].blocks {
+                let inner = match self.innermost.get(block) {
+                    Some(other) => self.loops[*other].blocks.len() > self.loops[id].blocks.len(),
+                    None => true,
+                };
+                if inner {
+                    self.innermost.insert(*block, id);
+                }
+            }
+        }
+    }
+
+    fn find_induction_vars(&self, ssa: &SSAStorage, id: LoopId) -> HashMap<NodeIndex, InductionVar> {
+        let lp = &self.loops[id];
+        let mut ivs = HashMap::new();
+
+        for phi in ssa.phis_in(lp.header) {
+            let (inside, outside): (Vec<NodeIndex>, Vec<NodeIndex>) = ssa.operands_of(phi)
+                .into_iter()
+                .partition(|op| ssa.block_for(*op).map_or(false, |b| lp.contains(&b)));
+            if outside.len() != 1 || inside.is_empty() {
+                continue;
+            }
+            let is_phi = |n: NodeIndex| n == phi;
+            let mut steps = inside.iter().map(|v| linear_form(ssa, *v, &is_phi));
+            let step = match steps.next() {
+                Some(Some((_, 1, step))) => step,
+                _ => continue,
+            };
+            if steps.all(|s| s == Some((phi, 1, step))) {
+                ivs.insert(phi, InductionVar::Basic { init: outside[0], step: step });
+            }
+        }
+
+        let basics = ivs.keys().cloned().collect::<HashSet<_>>();
+        let is_basic = |n: NodeIndex| basics.contains(&n);
+        for block in &lp.blocks {
+            for expr in ssa.exprs_in(*block) {
+                if let Some((base, scale, offset)) = linear_form(ssa, expr, &is_basic) {
+                    if base != expr {
+                        ivs.insert(expr, InductionVar::Derived {
+                            base: base,
+                            scale: scale,
+                            offset: offset,
+                        });
+                    }
+                }
+            }
+        }
+        ivs
+    }
+
+    // Returns the counter of the loop, with the trip count if it is known.
+    fn find_exit_test(&self,
+                      ssa: &SSAStorage,
+                      doms: &BlockDomTree<NodeIndex>,
+                      id: LoopId)
+                      -> Option<(NodeIndex, Option<u64>)> {
+        let lp = &self.loops[id];
+        let exiting = lp.blocks
+            .iter()
+            .filter(|b| ssa.succs_of(**b).iter().any(|s| !lp.contains(s)))
+            .cloned()
+            .collect::<Vec<_>>();
+        // The only exit test must be executed in every iteration.
+        if exiting.len() != 1 || !lp.latches.iter().all(|l| doms.dominates(&exiting[0], l)) {
+            return None;
+        }
+        let sides = ssa.conditional_blocks(exiting[0])?;
+        let stay_on_true = match (lp.contains(&sides.true_side), lp.contains(&sides.false_side)) {
+            (true, false) => true,
+            (false, true) => false,
+            _ => return None,
+        };
+
+        // Strip negations and narrowing to get the comparison.
+        let mut cond = ssa.selector_in(exiting[0])?;
+        let mut stay_if_holds = stay_on_true;
+        loop {
+            let operands = ssa.operands_of(cond);
+            match ssa.opcode(cond)? {
+                MOpcode::OpNarrow(_) => cond = operands[0],
+                MOpcode::OpNot => {
+                    stay_if_holds = !stay_if_holds;
+                    cond = operands[0];
+                }
+                MOpcode::OpXor if ssa.constant(operands[1]) == Some(1) => {
+                    stay_if_holds = !stay_if_holds;
+                    cond = operands[0];
+                }
+                _ => break,
+            }
+        }
+        let rel = match ssa.opcode(cond)? {
+            MOpcode::OpLt => Relation::Lt,
+            MOpcode::OpGt => Relation::Gt,
+            MOpcode::OpEq => Relation::Eq,
+            _ => return None,
+        };
+        let rel = if stay_if_holds { rel } else { rel.negate() };
+
+        let operands = ssa.operands_of(cond);
+        if operands.len() != 2 {
+            return None;
+        }
+        let is_basic = |n: NodeIndex| match lp.induction_vars.get(&n) {
+            Some(&InductionVar::Basic { .. }) => true,
+            _ => false,
+        };
+        let invariant = |n: NodeIndex| ssa.block_for(n).map_or(false, |b| !lp.contains(&b));
+        let (form, bound, rel) = match (ssa.constant(operands[0]), ssa.constant(operands[1])) {
+            (None, Some(c)) => (linear_form(ssa, operands[0], &is_basic)?, c, rel),
+            (Some(c), None) => (linear_form(ssa, operands[1], &is_basic)?, c, rel.flip()),
+            // Compared with a value computed before the loop, as in `i < n`.
+            (None, None) if invariant(operands[1]) => {
+                return linear_form(ssa, operands[0], &is_basic).map(|(base, _, _)| (base, None));
+            }
+            (None, None) if invariant(operands[0]) => {
+                return linear_form(ssa, operands[1], &is_basic).map(|(base, _, _)| (base, None));
+            }
+            _ => return None,
+        };
+
+        let (base, scale, offset) = form;
+        let trip_count = match lp.induction_vars.get(&base) {
+            Some(&InductionVar::Basic { init, step }) => {
+                ssa.constant(init).and_then(|init| {
+                    trip_count(ssa, operands[0], rel, (init as i64, step), (scale, offset), bound)
+                })
+            }
+            _ => return None,
+        };
+        Some((base, trip_count))
+    }
+}
+
+// Returns the number of iterations until `scale * iv + offset` does not
+// satisfy `rel` with `bound`, where the basic induction variable `iv` is
+// `init` at the entry and increased by `step`. `operand` is one side of the
+// comparison, giving its width.
+fn trip_count(ssa: &SSAStorage,
+              operand: NodeIndex,
+              rel: Relation,
+              (init, step): (i64, i64),
+              (scale, offset): (i64, i64),
+              bound: u64)
+              -> Option<u64> {
+    // `OpLt` and `OpGt` compare unsigned, as `eval_binop_width` does, so the
+    // values are taken modulo the width of the operands. The count is only
+    // known if the test fails before the value wraps around.
+    let width = ssa.node_data(operand).ok()?.vt.width().get_width()?;
+    if width == 0 || width > 64 {
+        return None;
+    }
+    let modulus = 1i128 << width;
+    let unsigned = |v: i128| ((v % modulus) + modulus) % modulus;
+    let start = unsigned((scale as i128) * (init as i128) + (offset as i128));
+    let step = (scale as i128) * (step as i128);
+    let count = rel.first_failure(start, step, unsigned(bound as i128))?;
+    let last = start + (count as i128) * step;
+    if last < 0 || last >= modulus {
+        return None;
+    }
+    Some(count)
+}
+
+// Returns (base, scale, offset) if `node` is `scale * base + offset`, where
+// `base` satisfies `is_base`.
+fn linear_form(ssa: &SSAStorage,
+               node: NodeIndex,
+               is_base: &Fn(NodeIndex) -> bool)
+               -> Option<(NodeIndex, i64, i64)> {
+    if is_base(node) {
+        return Some((node, 1, 0));
+    }
+    let operands = ssa.operands_of(node);
+    let constant = |i: usize| operands.get(i).and_then(|n| ssa.constant(*n)).map(|c| c as i64);
+    let form = |i: usize| operands.get(i).and_then(|n| linear_form(ssa, *n, is_base));
+    match ssa.opcode(node)? {
+        MOpcode::OpNarrow(_) | MOpcode::OpZeroExt(_) | MOpcode::OpSignExt(_) => form(0),
+        MOpcode::OpAdd => {
+            match (constant(0), constant(1)) {
+                (Some(c), None) => form(1).map(|(b, s, o)| (b, s, o.wrapping_add(c))),
+                (None, Some(c)) => form(0).map(|(b, s, o)| (b, s, o.wrapping_add(c))),
+                (None, None) => {
+                    let (b0, s0, o0) = form(0)?;
+                    let (b1, s1, o1) = form(1)?;
+                    if b0 == b1 {
+                        Some((b0, s0.wrapping_add(s1), o0.wrapping_add(o1)))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        }
+        MOpcode::OpSub => {
+            match (constant(0), constant(1)) {
+                (Some(c), None) => {
+                    form(1).map(|(b, s, o)| (b, s.wrapping_neg(), c.wrapping_sub(o)))
+                }
+                (None, Some(c)) => form(0).map(|(b, s, o)| (b, s, o.wrapping_sub(c))),
+                (None, None) => {
+                    let (b0, s0, o0) = form(0)?;
+                    let (b1, s1, o1) = form(1)?;
+                    if b0 == b1 {
+                        Some((b0, s0.wrapping_sub(s1), o0.wrapping_sub(o1)))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        }
+        MOpcode::OpMul => {
+            let (i, c) = match (constant(0), constant(1)) {
+                (Some(c), None) => (1, c),
+                (None, Some(c)) => (0, c),
+                _ => return None,
+            };
+            form(i).map(|(b, s, o)| (b, s.wrapping_mul(c), o.wrapping_mul(c)))
+        }
+        MOpcode::OpLsl => {
+            let c = constant(1)?;
+            if c < 0 || c >= 64 {
+                return None;
+            }
+            form(0).map(|(b, s, o)| (b, s.wrapping_shl(c as u32), o.wrapping_shl(c as u32)))
+        }
+        _ => None,
+    }
+}
+
+// Relation between the induction variable (on the left) and the bound, which
+// holds while the loop is executing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Relation {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Relation {
+    fn negate(self) -> Relation {
+        match self {
+            Relation::Lt => Relation::Ge,
+            Relation::Le => Relation::Gt,
+            Relation::Gt => Relation::Le,
+            Relation::Ge => Relation::Lt,
+            Relation::Eq => Relation::Ne,
+            Relation::Ne => Relation::Eq,
+        }
+    }
+
+    // Swaps the two sides of the relation
+    fn flip(self) -> Relation {
+        match self {
+            Relation::Lt => Relation::Gt,
+            Relation::Le => Relation::Ge,
+            Relation::Gt => Relation::Lt,
+            Relation::Ge => Relation::Le,
+            Relation::Eq => Relation::Eq,
+            Relation::Ne => Relation::Ne,
+        }
+    }
+
+    // Returns the smallest k >= 0 where `start + k * step` does not satisfy
+    // the relation with `bound`, None if there is no such k.
+    fn first_failure(self, start: i128, step: i128, bound: i128) -> Option<u64> {
+        let k = match self {
+            Relation::Le => return Relation::Lt.first_failure(start, step, bound + 1),
+            Relation::Ge => return Relation::Gt.first_failure(start, step, bound - 1),
+            Relation::Lt => {
+                if start >= bound {
+                    0
+                } else if step <= 0 {
+                    return None;
+                } else {
+                    (bound - start + step - 1) / step
+                }
+            }
+            Relation::Gt => {
+                if start <= bound {
+                    0
+                } else if step >= 0 {
+                    return None;
+                } else {
+                    (start - bound - step - 1) / (-step)
+                }
+            }
+            Relation::Eq => {
+                if start != bound {
+                    0
+                } else if step == 0 {
+                    return None;
+                } else {
+                    1
+                }
+            }
+            Relation::Ne => {
+                let distance = bound - start;
+                if distance == 0 {
+                    0
+                } else if step == 0 || distance % step != 0 || distance / step < 0 {
+                    return None;
+                } else {
+                    distance / step
+                }
+            }
+        };
+        if k > (u64::max_value() as i128) {
+            None
+        } else {
+            Some(k as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::{load_il, parse_il};
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const COUNT_TXT: &str = "\
+define-fun sym.count(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rax;
+        %2: $Unknown0 = $mem;
+    bb_0x001000.0000(sz 0x4):
+        JMP 0x001004.0000
+    bb_0x001004.0000(sz 0x4):
+        %3: $Unknown64 = Phi(#x0, %4);
+        [@0x001004.0001] %5: $Unknown1 = %3 < #xa;
+        JMP IF %5 0x001008.0000 ELSE 0x00100C.0000
+    bb_0x001008.0000(sz 0x4):
+        [@0x001008.0001] %4: $Unknown64 = %3 + #x1;
+        [@0x001008.0002] %6: $Unknown64 = %4 * #x4;
+        JMP 0x001004.0000
+    bb_0x00100C.0000(sz 0x1):
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %3;
+        $mem = %2;
+}
+";
+
+    #[test]
+    fn loops_forest_test() {
+        let ssa = load_il("./test_files/loopy_main_ssa");
+        let forest = LoopForest::new(&ssa);
+        assert_eq!(forest.loops().len(), 1);
+        assert_eq!(forest.roots(), vec![0]);
+        let lp = &forest.loops()[0];
+        assert_eq!(lp.blocks.len(), 3);
+        assert_eq!(lp.latches.len(), 1);
+        assert_eq!(lp.exits.len(), 1);
+        assert_eq!(forest.depth_of(&lp.header), 1);
+        assert_eq!(forest.loop_with_header(&lp.header), Some(0));
+        // The loop exits depending on the result of a call.
+        assert_eq!(lp.trip_count, None);
+    }
+
+    #[test]
+    fn loops_induction_vars_test() {
+        let ssa = parse_il(COUNT_TXT);
+        let forest = LoopForest::new(&ssa);
+        assert_eq!(forest.loops().len(), 1);
+        let lp = &forest.loops()[0];
+        assert_eq!(lp.trip_count, Some(10));
+
+        let phi = ssa.phis_in(lp.header)[0];
+        assert_eq!(lp.counter, Some(phi));
+        match lp.induction_vars.get(&phi) {
+            Some(&InductionVar::Basic { init, step }) => {
+                assert_eq!(ssa.constant(init), Some(0));
+                assert_eq!(step, 1);
+            }
+            iv => panic!("Unexpected induction variable: {:?}", iv),
+        }
+        let mut derived = lp.induction_vars
+            .values()
+            .filter_map(|iv| match *iv {
+                InductionVar::Derived { base, scale, offset } => {
+                    assert_eq!(base, phi);
+                    Some((scale, offset))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        derived.sort();
+        assert_eq!(derived, vec![(1, 1), (4, 4)]);
+    }
+
+    #[test]
+    fn loops_counter_test() {
+        // `i < n`, where `n` is the argument in rax.
+        let ir = COUNT_TXT.replace("%3 < #xa", "%3 < %1");
+        let ssa = parse_il(&ir);
+        let forest = LoopForest::new(&ssa);
+        let lp = &forest.loops()[0];
+        assert_eq!(lp.counter, Some(ssa.phis_in(lp.header)[0]));
+        assert_eq!(lp.trip_count, None);
+
+        // The exit test does not compare an induction variable.
+        let ir = COUNT_TXT.replace("%3 < #xa", "%1 < #xa");
+        let ssa = parse_il(&ir);
+        let forest = LoopForest::new(&ssa);
+        assert_eq!(forest.loops()[0].counter, None);
+    }
+
+    #[test]
+    fn loops_trip_count_test() {
+        assert_eq!(Relation::Lt.first_failure(0, 1, 10), Some(10));
+        assert_eq!(Relation::Le.first_failure(0, 2, 10), Some(6));
+        assert_eq!(Relation::Gt.first_failure(10, -3, 0), Some(4));
+        assert_eq!(Relation::Ne.first_failure(0, 4, 16), Some(4));
+        assert_eq!(Relation::Ne.first_failure(0, 4, 15), None);
+        assert_eq!(Relation::Lt.first_failure(0, -1, 10), None);
+        assert_eq!(Relation::Lt.first_failure(10, 1, 10), Some(0));
+    }
+
+    fn trip_count(ir: &str) -> Option<u64> {
+        let ssa = parse_il(ir);
+        let forest = LoopForest::new(&ssa);
+        assert_eq!(forest.loops().len(), 1);
+        forest.loops()[0].trip_count
+    }
+
+    #[test]
+    fn loops_unsigned_trip_count_test() {
+        // -1 is the largest unsigned bound.
+        let ir = COUNT_TXT.replace("%3 < #xa", "%3 < #xffffffffffffffff");
+        assert_eq!(trip_count(&ir), Some(u64::max_value()));
+
+        let ir = COUNT_TXT.replace("Phi(#x0, %4)", "Phi(#xa, %4)")
+            .replace("%3 < #xa", "%3 > #x0")
+            .replace("%3 + #x1", "%3 - #x1");
+        assert_eq!(trip_count(&ir), Some(10));
+        // Counting down by 3 from 10 wraps around below 0, which is still
+        // greater than 0.
+        let ir = ir.replace("%3 - #x1", "%3 - #x3");
+        assert_eq!(trip_count(&ir), None);
+    }
+}
@@ -12,6 +12,8 @@
 // pub mod propagate;
 pub mod dom;
 pub mod sccp;
+pub mod gvn;
+pub mod loops;
 pub mod cse {
     pub mod cse;
     pub mod ssasort;
@@ -30,3 +32,6 @@ pub mod reference_marking;
 pub mod constraint_set;
 pub mod functions;
 pub mod inst_combine;
+pub mod store_forwarding;
+pub mod noreturn;
+pub mod stack_args;
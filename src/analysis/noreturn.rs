@@ -0,0 +1,211 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Detects functions which never return.
+//!
+//! Functions like `exit` or `abort` are known not to return by their
+//! `_Noreturn` prototype, see `middle::prototypes`.
+//! Any other function does not return if its exit cannot be reached without
+//! calling a noreturn function. Starting from the known functions, this is
+//! checked for their callers in the call graph, and so on, until no new
+//! noreturn function is found.
+//!
+//! The calls to noreturn functions are recorded in the calling function, the
+//! SSA construction then ends their blocks at these calls, see
+//! `PhiPlacer::add_noreturn`.
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::Direction;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use frontend::radeco_containers::{RadecoFunction, RadecoModule};
+use middle::ir::MOpcode;
+use middle::prototypes;
+use middle::ssa::cfg_traits::CFG;
+use middle::ssa::ssa_traits::SSA;
+
+/// Returns true if the bundled prototype of the function named `name` is
+/// `_Noreturn`. Names are taken as radare2 gives them, e.g. `sym.imp.exit`.
+pub fn is_noreturn_name(name: &str) -> bool {
+    prototypes::lookup(name).map_or(false, |p| p.noreturn)
+}
+
+/// Finds the noreturn functions of `rmod`, marks them and records the calls
+/// made to them. Needs the call graph and the SSA of the functions.
+/// Returns the start addresses of the noreturn functions.
+pub fn analyze(rmod: &mut RadecoModule) -> HashSet<u64> {
+    let mut noreturn = rmod.imports
+        .iter()
+        .filter(|&(_, ii)| {
+            is_noreturn_name(&ii.name) ||
+                ii.rfn.borrow().prototype().map_or(false, |p| p.noreturn)
+        })
+        .map(|(&addr, _)| addr)
+        .collect::<HashSet<_>>();
+    noreturn.extend(rmod.functions
+                        .values()
+                        .filter(|rfn| {
+                            is_noreturn_name(&rfn.name) ||
+                                rfn.prototype().map_or(false, |p| p.noreturn)
+                        })
+                        .map(|rfn| rfn.offset));
+
+    let nodes = rmod.callgraph
+        .node_indices()
+        .map(|n| (rmod.callgraph[n], n))
+        .collect::<HashMap<u64, NodeIndex>>();
+    let mut worklist = noreturn.iter().cloned().collect::<Vec<_>>();
+    while let Some(callee) = worklist.pop() {
+        let node = match nodes.get(&callee) {
+            Some(&node) => node,
+            None => continue,
+        };
+        let callers = rmod.callgraph
+            .edges_directed(node, Direction::Incoming)
+            .map(|e| rmod.callgraph[e.source()])
+            .collect::<Vec<_>>();
+        for caller in callers {
+            if noreturn.contains(&caller) {
+                continue;
+            }
+            let returns = match rmod.functions.get(&caller) {
+                Some(rfn) => returns(rfn, &noreturn),
+                None => continue,
+            };
+            if !returns {
+                radeco_trace!("noreturn|{:#x}", caller);
+                noreturn.insert(caller);
+                worklist.push(caller);
+            }
+        }
+    }
+
+    for rfn in rmod.functions.values_mut() {
+        let calls = noreturn_calls(rfn, &noreturn);
+        if noreturn.contains(&rfn.offset) {
+            rfn.mark_noreturn();
+        }
+        rfn.noreturn_calls_mut().extend(calls);
+    }
+    for ii in rmod.imports.values() {
+        if noreturn.contains(&ii.plt) {
+            ii.rfn.borrow_mut().mark_noreturn();
+        }
+    }
+    noreturn
+}
+
+// Returns the addresses of the calls made by `rfn` to `noreturn` functions.
+fn noreturn_calls(rfn: &RadecoFunction, noreturn: &HashSet<u64>) -> HashSet<u64> {
+    rfn.instructions()
+        .iter()
+        .filter(|op| op.optype.as_ref().map_or(false, |ty| ty == "call"))
+        .filter(|op| op.jump.map_or(false, |target| noreturn.contains(&target)))
+        .filter_map(|op| op.offset)
+        .collect()
+}
+
+// Returns true if the exit of `rfn` can be reached without calling one of the
+// `noreturn` functions.
+fn returns(rfn: &RadecoFunction, noreturn: &HashSet<u64>) -> bool {
+    let ssa = rfn.ssa();
+    let (entry, exit) = match (ssa.entry_node(), ssa.exit_node()) {
+        (Some(entry), Some(exit)) => (entry, exit),
+        _ => return true,
+    };
+    let calls = noreturn_calls(rfn, noreturn);
+    let ends = ssa.values()
+        .into_iter()
+        .filter(|&node| ssa.opcode(node) == Some(MOpcode::OpCall))
+        .filter(|&node| ssa.address(node).map_or(false, |addr| calls.contains(&addr.address)))
+        .filter_map(|node| ssa.block_for(node))
+        .collect::<HashSet<_>>();
+
+    let mut seen = HashSet::new();
+    let mut worklist = vec![entry];
+    while let Some(block) = worklist.pop() {
+        if block == exit {
+            return true;
+        }
+        if ends.contains(&block) || !seen.insert(block) {
+            continue;
+        }
+        worklist.extend(ssa.succs_of(block));
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use frontend::imports::ImportInfo;
+    use frontend::radeco_containers::CallContextInfo;
+    use std::borrow::Cow;
+    use utils::test_utils::{self, REGISTER_PROFILE};
+
+    fn function(name: &'static str, offset: u64, ops: &str) -> RadecoFunction {
+        let mut rfn = test_utils::function(REGISTER_PROFILE, ops, false);
+        rfn.name = Cow::from(name);
+        rfn.offset = offset;
+        rfn
+    }
+
+    #[test]
+    fn noreturn_names() {
+        assert!(is_noreturn_name("exit"));
+        assert!(is_noreturn_name("sym.imp.__stack_chk_fail"));
+        assert!(is_noreturn_name("sym.abort"));
+        assert!(is_noreturn_name("sym.imp.__cxa_throw"));
+        assert!(is_noreturn_name("__fortify_fail"));
+        assert!(!is_noreturn_name("sym.imp.printf"));
+        assert!(!is_noreturn_name("sym.exit_handler"));
+    }
+
+    #[test]
+    fn noreturn_wrapper() {
+        // `die` calls `exit`, `fail` calls `die`, `check` calls `die` only
+        // if `zf` is set.
+        let die = r#"[{"offset":4096,"size":5,"opcode":"mov edi, 1","esil":"1,rdi,=","type":"mov"},
+                      {"offset":4101,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4106,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let fail = r#"[{"offset":4352,"size":5,"opcode":"call 0x1000","esil":"rip,8,rsp,-=,rsp,=[],4096,rip,=","type":"call","jump":4096},
+                       {"offset":4357,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let check = r#"[{"offset":4608,"size":2,"opcode":"je 0x1207","esil":"zf,?{,4615,rip,=,}","type":"cjmp","jump":4615,"fail":4610},
+                        {"offset":4610,"size":5,"opcode":"call 0x1000","esil":"rip,8,rsp,-=,rsp,=[],4096,rip,=","type":"call","jump":4096},
+                        {"offset":4615,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+
+        let mut rmod = RadecoModule::default();
+        rmod.imports.insert(0x2000, ImportInfo::new_stub(0x2000, Cow::from("sym.imp.exit")));
+        for rfn in vec![function("sym.die", 0x1000, die),
+                        function("sym.fail", 0x1100, fail),
+                        function("sym.check", 0x1200, check)] {
+            rmod.functions.insert(rfn.offset, rfn);
+        }
+        let nodes = [0x1000, 0x1100, 0x1200, 0x2000]
+            .iter()
+            .map(|&addr| (addr, rmod.callgraph.add_node(addr)))
+            .collect::<HashMap<_, _>>();
+        for &(caller, csite, callee) in &[(0x1000, 4101, 0x2000),
+                                          (0x1100, 4352, 0x1000),
+                                          (0x1200, 4610, 0x1000)] {
+            let mut cctx = CallContextInfo::default();
+            cctx.csite = csite;
+            rmod.callgraph.add_edge(nodes[&caller], nodes[&callee], cctx);
+        }
+
+        let noreturn = analyze(&mut rmod);
+        assert_eq!(noreturn, [0x1000, 0x1100, 0x2000].iter().cloned().collect::<HashSet<_>>());
+        assert!(rmod.functions[&0x1000].is_noreturn());
+        assert!(rmod.functions[&0x1100].is_noreturn());
+        assert!(!rmod.functions[&0x1200].is_noreturn());
+        assert!(rmod.imports[&0x2000].rfn.borrow().is_noreturn());
+        assert!(rmod.functions[&0x1100].noreturn_calls().contains(&4352));
+        assert!(rmod.functions[&0x1200].noreturn_calls().contains(&4610));
+    }
+}
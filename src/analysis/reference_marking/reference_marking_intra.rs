@@ -22,10 +22,12 @@
 
 use analysis::constraint_set::ConstraintSet;
 use frontend::radeco_containers::RadecoFunction;
+use middle::intrinsics;
 use middle::ir::MOpcode;
 use middle::regfile::SubRegisterFile;
 use middle::ssa::ssa_traits::{ValueType, SSA, SSAWalk, NodeType};
 use middle::ssa::ssastorage::SSAStorage;
+use middle::ssa::utils;
 use petgraph::graph::NodeIndex;
 use r2api::structs::LSectionInfo;
 use std::collections::HashSet;
@@ -138,7 +140,17 @@ impl ReferenceMarker {
                             // later on.
                             // In case of load, nothing can be said about the returned value.
                         }
-                        &MOpcode::OpCustom(_) |
+                        &MOpcode::OpIntrinsic(ref name)
+                            if intrinsics::lookup(name).map_or(false, |i| !i.explicit && !i.memory) => {
+                            // Intrinsics not accessing memory, like `cpuid`, only
+                            // read and write numbers
+                            if let Some(info) = utils::intrinsic_info(idx, ssa) {
+                                for &node in info.args.iter().chain(info.outputs.iter()) {
+                                    self.cs.add_eq(node, ValueType::Scalar);
+                                }
+                            }
+                        }
+                        &MOpcode::OpIntrinsic(_) |
                         &MOpcode::OpInvalid |
                         &MOpcode::OpNop => {
                             // Can't say anything about these operands
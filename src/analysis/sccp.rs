@@ -12,12 +12,13 @@
 //!    * https://www.cs.utexas.edu/~lin/cs380c/wegman.pdf.
 //!
 
+use std::cmp;
 use std::collections::{HashMap, VecDeque};
-use std::u64;
 use middle::ssa::ssa_traits::{SSA, SSAMod};
 use middle::ssa::ssa_traits::{NodeData, NodeType, ValueInfo, ValueType};
 use middle::ssa::graph_traits::{Graph, ConditionInfo};
-use middle::ir::{MArity, MOpcode, WidthSpec, MAddress};
+use middle::ir::{self, MArity, MOpcode, WidthSpec, MAddress};
+use middle::wide::U256;
 
 #[macro_export]
 macro_rules! node_data_from_g {
@@ -36,7 +37,8 @@ macro_rules! node_data_from_g {
 enum LatticeValue {
     Top,
     Bottom,
-    Const(u64),
+    // Values up to 256 bits wide are tracked, wider ones are bottom.
+    Const(U256),
 }
 
 impl LatticeValue {
@@ -181,7 +183,7 @@ impl<T> Analyzer<T>
                 // TODO: Not really sure what to do here.
             }
             LatticeValue::Const(cval) => {
-                if cval == 1 {
+                if cval == U256::from(1u64) {
                     self.cfgwl_push(&true_branch);
                 } else {
                     self.cfgwl_push(&false_branch);
@@ -191,6 +193,11 @@ impl<T> Analyzer<T>
     }
 
     fn evaluate_unary_op(&mut self, i: &T::ValueRef, opcode: MOpcode) -> LatticeValue {
+        // Do not evaluate floating point operations.
+        if opcode.is_float() {
+            return LatticeValue::Bottom;
+        }
+
         let operand = self.g.operands_of(*i);
         let operand = if operand.is_empty() {
             return LatticeValue::Top;
@@ -205,20 +212,26 @@ impl<T> Analyzer<T>
             return val;
         };
 
+        let w = self.width_of(i);
+        let w0 = self.width_of(&operand);
+        if w > 256 || w0 > 256 {
+            return LatticeValue::Bottom;
+        }
 
-        let mut val: u64 = match opcode {
-            MOpcode::OpZeroExt(_) | MOpcode::OpSignExt(_) => {
-                // Nothing to do in case of widen as the value cannot change.
+        let val = match opcode {
+            MOpcode::OpZeroExt(_) | MOpcode::OpNarrow(_) => {
+                // The value does not change, narrowing is done by the mask below.
                 const_val
             }
-            MOpcode::OpNarrow(size) => {
-                // Max size is 64. Therefore, we can _never_ narrow to 64.
-                assert!(size < 64);
-                let mask = (1 << (size)) - 1;
-                const_val & mask
+            MOpcode::OpSignExt(_) => {
+                if w0 > 0 && w0 < 256 && const_val.bit(w0 as u32 - 1) {
+                    const_val | !ir::width_mask(w0)
+                } else {
+                    const_val
+                }
             }
             MOpcode::OpNot => {
-                !const_val as u64
+                !const_val
             }
             MOpcode::OpCall => {
                 return LatticeValue::Bottom;
@@ -227,23 +240,19 @@ impl<T> Analyzer<T>
         };
 
         // We should consider width.
-        let ndata = node_data_from_g!(self, i);
-        let w = ndata.vt.width().get_width().unwrap_or(64);
-        if w < 64 {
-            val = val & ((1 << (w)) - 1);
-        }
-
-        LatticeValue::Const(val)
+        LatticeValue::Const(val & ir::width_mask(w))
     }
 
     fn evaluate_binary_op(&mut self, i: &T::ValueRef, opcode: MOpcode) -> LatticeValue {
-        // Do not reason about load/stores.
+        // Do not reason about load/stores and floating point operations.
         match opcode {
             MOpcode::OpLoad | MOpcode::OpStore => return LatticeValue::Bottom,
+            ref op if op.is_float() => return LatticeValue::Bottom,
             _ => { },
         }
 
-        let operands = self.g.operands_of(*i).iter().map(|x| self.get_value(x)).collect::<Vec<_>>();
+        let operand_nodes = self.g.operands_of(*i);
+        let operands = operand_nodes.iter().map(|x| self.get_value(x)).collect::<Vec<_>>();
 
         let lhs = operands[0];
         let rhs = operands[1];
@@ -259,71 +268,18 @@ impl<T> Analyzer<T>
             return rhs;
         };
 
-        let mut val: u64 = match opcode {
-            MOpcode::OpAdd => {
-                // lhs_val + rhs_val
-                // we should avoid integer overflow panic
-                let remained: u64 = u64::MAX - lhs_val;
-                if remained >= rhs_val {
-                    lhs_val + rhs_val
-                } else {
-                    (rhs_val - remained - 1) as u64
-                }
-            }
-            MOpcode::OpSub => {
-                if lhs_val >= rhs_val {
-                    lhs_val - rhs_val
-                } else {
-                    // rhs_val - lhs_val
-                    // This will never integer overflow 
-                    (u64::MAX - rhs_val + lhs_val + 1) as u64
-                }
-            }
-            MOpcode::OpMul => {
-                // TODO: how to handle integer overflow.
-                lhs_val * rhs_val
-            }
-            MOpcode::OpDiv => {
-                lhs_val / rhs_val
-            }
-            MOpcode::OpMod => {
-                lhs_val % rhs_val
-            }
-            MOpcode::OpAnd => {
-                lhs_val & rhs_val
-            }
-            MOpcode::OpOr => {
-                lhs_val | rhs_val
-            }
-            MOpcode::OpXor => {
-                lhs_val ^ rhs_val
-            }
-            MOpcode::OpEq => {
-                (lhs_val == rhs_val) as u64
-            }
-            MOpcode::OpGt => {
-                (lhs_val > rhs_val) as u64
-            }
-            MOpcode::OpLt => {
-                (lhs_val < rhs_val) as u64
-            }
-            MOpcode::OpLsl => {
-                lhs_val << rhs_val
-            }
-            MOpcode::OpLsr => {
-                lhs_val >> rhs_val
-            }
-            _ => unreachable!(),
-        };
-
-        // We should consider width.
-        let ndata = node_data_from_g!(self, i);
-        let w = ndata.vt.width().get_width().unwrap_or(64);
-        if w < 64 {
-            val = val & ((1 << (w)) - 1);
+        // Operations are evaluated in the width of their operands, which is
+        // not the width of the result for comparisons.
+        let w = self.width_of(i);
+        let w0 = cmp::max(self.width_of(&operand_nodes[0]), self.width_of(&operand_nodes[1]));
+        if w > 256 {
+            return LatticeValue::Bottom;
+        }
+        match opcode.eval_binop_width(lhs_val, rhs_val, w0) {
+            Some(val) => LatticeValue::Const(val & ir::width_mask(w)),
+            // Division by zero or too wide operands
+            None => LatticeValue::Bottom,
         }
-
-        LatticeValue::Const(val)
     }
 
     fn evaluate_ternary_op(&mut self, i: &T::ValueRef, opcode: MOpcode) -> LatticeValue {
@@ -350,7 +306,12 @@ impl<T> Analyzer<T>
         };
 
         if let MOpcode::OpConst(v) = opcode {
-            return LatticeValue::Const(v as u64);
+            return LatticeValue::Const(U256::from(v));
+        }
+
+        // Do not reason about intrinsics.
+        if let MOpcode::OpIntrinsic(_) = opcode {
+            return LatticeValue::Bottom;
         }
 
         let val = match opcode.arity() {
@@ -449,46 +410,39 @@ impl<T> Analyzer<T>
     }
 
     pub fn emit_ssa(&mut self) -> T {
-        for (k, v) in &self.expr_val {
+        let expr_val = self.expr_val.clone();
+        for (k, v) in &expr_val {
             if self.g.constant(*k).is_some() {
                 continue;
             }
             if let LatticeValue::Const(val) = *v {
-                radeco_trace!("{:?} with {:?} --> Const {:#}", 
+                radeco_trace!("{:?} with {:?} --> Const {:#x}", 
                               k, self.g.node_data(*k), val);
-                // BUG: Width may be changed just using a simple replace.
-                let const_node = self.g.insert_const(val)
-                                    .unwrap_or_else(|| {
-                                        radeco_err!("Cannot insert new constants");
-                                        self.g.invalid_value().unwrap()
-                                    });
                 let ndata = node_data_from_g!(self, k);
                 let w = ndata.vt.width().get_width().unwrap_or(64);
-                let new_node = if w == 64 {
-                    const_node
-                } else {
-                    // val should not be larger than the k node could be.  
-                    assert!(w < 64 && val < (1 << (w)));
-                    let block = self.g.block_for(*k)
-                                        .unwrap_or_else(|| {
-                                            radeco_err!("No block information found");
-                                            self.g.invalid_action().unwrap()
-                                        });
-                    let address = self.g.address(*k)
-                                        .unwrap_or_else(|| {
-                                            radeco_err!("No address information found");
-                                            MAddress::invalid_address()
-                                        });
-                    let opcode = MOpcode::OpNarrow(w as u16);
-                    let new_node = self.g.insert_op(opcode, ndata.vt, None)
-                                        .unwrap_or_else(|| {
-                                            radeco_err!("Cannot insert new values");
-                                            self.g.invalid_value().unwrap()
-                                        });
-                    self.g.insert_into_block(new_node, block, address);
-                    self.g.op_use(new_node, 0, const_node);
-                    new_node
-                };
+                let block = self.g.block_for(*k)
+                                    .unwrap_or_else(|| {
+                                        radeco_err!("No block information found");
+                                        self.g.invalid_action().unwrap()
+                                    });
+                let address = self.g.address(*k)
+                                    .unwrap_or_else(|| {
+                                        radeco_err!("No address information found");
+                                        MAddress::invalid_address()
+                                    });
+                // val should not be larger than the k node could be.
+                assert!(val.fits(w));
+                // Constants are 64 bits wide, thus wider values are built from
+                // their 64-bit limbs, most significant first.
+                let limbs = val.limbs();
+                let top = limbs.iter().rposition(|&l| l != 0).unwrap_or(0);
+                let mut new_node = self.resized_const(limbs[top], ndata.vt, block, address);
+                for &limb in limbs[..top].iter().rev() {
+                    let amount = self.resized_const(64, ndata.vt, block, address);
+                    let low = self.resized_const(limb, ndata.vt, block, address);
+                    let high = self.insert_op_at(MOpcode::OpLsl, ndata.vt, &[new_node, amount], block, address);
+                    new_node = self.insert_op_at(MOpcode::OpOr, ndata.vt, &[high, low], block, address);
+                }
                 self.g.replace_value(*k, new_node);
             }
         }
@@ -520,6 +474,53 @@ impl<T> Analyzer<T>
     /// / Helper functions.
     /// ////////////////////////////////////////////////////////////////////////
 
+    fn width_of(&self, i: &T::ValueRef) -> u16 {
+        let ndata = node_data_from_g!(self, i);
+        ndata.vt.width().get_width().unwrap_or(64)
+    }
+
+    // Returns the constant `val` in the width of `vt`.
+    fn resized_const(&mut self,
+                     val: u64,
+                     vt: ValueInfo,
+                     block: T::ActionRef,
+                     address: MAddress)
+                     -> T::ValueRef {
+        let const_node = self.g.insert_const(val)
+                            .unwrap_or_else(|| {
+                                radeco_err!("Cannot insert new constants");
+                                self.g.invalid_value().unwrap()
+                            });
+        let w = vt.width().get_width().unwrap_or(64);
+        let opcode = if w < 64 {
+            MOpcode::OpNarrow(w)
+        } else if w > 64 {
+            MOpcode::OpZeroExt(w)
+        } else {
+            return const_node;
+        };
+        self.insert_op_at(opcode, vt, &[const_node], block, address)
+    }
+
+    fn insert_op_at(&mut self,
+                    opcode: MOpcode,
+                    vt: ValueInfo,
+                    operands: &[T::ValueRef],
+                    block: T::ActionRef,
+                    address: MAddress)
+                    -> T::ValueRef {
+        let new_node = self.g.insert_op(opcode, vt, None)
+                            .unwrap_or_else(|| {
+                                radeco_err!("Cannot insert new values");
+                                self.g.invalid_value().unwrap()
+                            });
+        self.g.insert_into_block(new_node, block, address);
+        for (idx, operand) in operands.iter().enumerate() {
+            self.g.op_use(new_node, idx as u8, *operand);
+        }
+        new_node
+    }
+
     fn is_executable(&self, i: &T::CFEdgeRef) -> bool {
         self.executable.get(i).cloned().unwrap_or(false)
     }
@@ -534,7 +535,7 @@ impl<T> Analyzer<T>
         //TODO replace unwrap
         let node_data = self.g.node_data(*i).unwrap();
         match node_data.nt {
-            NodeType::Op(MOpcode::OpConst(v)) => LatticeValue::Const(v),
+            NodeType::Op(MOpcode::OpConst(v)) => LatticeValue::Const(U256::from(v)),
             NodeType::Undefined => LatticeValue::Bottom,
             _ => LatticeValue::Top,
         }
@@ -590,14 +591,54 @@ impl<T> Analyzer<T>
 
 #[cfg(test)]
 mod test {
-    use super::{LatticeValue, meet};
+    use super::{Analyzer, LatticeValue, meet};
+    use middle::ir::MOpcode;
+    use middle::ssa::ssa_traits::SSA;
+    use middle::ssa::ssastorage::SSAStorage;
+    use middle::wide::U256;
+    use utils::test_utils::parse_il;
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const WIDE_TXT: &str = "\
+define-fun sym.wide(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rax;
+    bb_0x001000.0000(sz 0x4):
+        [@0x001000.0001] %2: $Unknown128 = ZeroExt128(#xffffffffffffffff);
+        [@0x001000.0002] %3: $Unknown128 = %2 * %2;
+        [@0x001000.0003] %4: $Unknown128 = %3 >> #x40;
+        [@0x001000.0004] %5: $Unknown64 = Narrow64(%4);
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %5;
+}
+";
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const WIDER_TXT: &str = "\
+define-fun sym.wider(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rax;
+    bb_0x001000.0000(sz 0x4):
+        [@0x001000.0001] %2: $Unknown256 = ZeroExt256(#xffffffffffffffff);
+        [@0x001000.0002] %3: $Unknown256 = %2 * %2;
+        [@0x001000.0003] %4: $Unknown256 = %3 * %3;
+        [@0x001000.0004] %5: $Unknown256 = %4 >> #xc0;
+        [@0x001000.0005] %6: $Unknown64 = Narrow64(%5);
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %6;
+}
+";
 
     #[test]
     fn test_meet() {
         let t = LatticeValue::Top;
         let b = LatticeValue::Bottom;
-        let c1 = LatticeValue::Const(1);
-        let c2 = LatticeValue::Const(2);
+        let c1 = LatticeValue::Const(U256::from(1u64));
+        let c2 = LatticeValue::Const(U256::from(2u64));
 
         assert_eq!(meet(&t, &t), t);
         assert_eq!(meet(&t, &b), b);
@@ -607,4 +648,63 @@ mod test {
         assert_eq!(meet(&c2, &c1), b);
         assert_eq!(meet(&c1, &c1), c1);
     }
+
+    #[test]
+    fn test_wide_values() {
+        let mut ssa = parse_il(WIDE_TXT);
+        let node = |ssa: &SSAStorage, opcode: MOpcode| {
+            ssa.values().into_iter().find(|&n| ssa.opcode(n) == Some(opcode.clone())).unwrap()
+        };
+        let mul = node(&ssa, MOpcode::OpMul);
+        let narrow = node(&ssa, MOpcode::OpNarrow(64));
+
+        let mut analyzer = Analyzer::new(&mut ssa);
+        analyzer.analyze();
+        // (2^64 - 1)^2 does not fit in 64 bits.
+        assert_eq!(analyzer.get_value(&mul),
+                   LatticeValue::Const(U256::from(0xfffffffffffffffe0000000000000001u128)));
+        assert_eq!(analyzer.get_value(&narrow), LatticeValue::Const(U256::from(0xfffffffffffffffeu64)));
+
+        // The product is built from its 64-bit halves.
+        let ssa = analyzer.emit_ssa();
+        let consts = ssa.values()
+            .into_iter()
+            .filter_map(|n| ssa.constant(n))
+            .collect::<Vec<_>>();
+        assert!(consts.contains(&0xfffffffffffffffe) && consts.contains(&1));
+        assert!(ssa.values().into_iter().any(|n| ssa.opcode(n) == Some(MOpcode::OpLsl)));
+    }
+
+    #[test]
+    fn test_256_bit_values() {
+        let mut ssa = parse_il(WIDER_TXT);
+        let muls = ssa.values()
+            .into_iter()
+            .filter(|&n| ssa.opcode(n) == Some(MOpcode::OpMul))
+            .collect::<Vec<_>>();
+        let narrow = ssa.values()
+            .into_iter()
+            .find(|&n| ssa.opcode(n) == Some(MOpcode::OpNarrow(64)))
+            .unwrap();
+
+        let mut analyzer = Analyzer::new(&mut ssa);
+        analyzer.analyze();
+        // ((2^64 - 1)^2)^2 wraps around in 256 bits.
+        let values = muls.iter().map(|m| analyzer.get_value(m)).collect::<Vec<_>>();
+        let square = U256::from(0xfffffffffffffffe0000000000000001u128);
+        let fourth = square.wrapping_mul(square);
+        assert!(values.contains(&LatticeValue::Const(square)));
+        assert!(values.contains(&LatticeValue::Const(fourth)));
+        assert_eq!(fourth.limbs(),
+                   [1, 0xfffffffffffffffc, 5, 0xfffffffffffffffc]);
+        assert_eq!(analyzer.get_value(&narrow), LatticeValue::Const(U256::from(0xfffffffffffffffcu64)));
+
+        // The fourth power is built from its four 64-bit limbs.
+        let ssa = analyzer.emit_ssa();
+        let consts = ssa.values()
+            .into_iter()
+            .filter_map(|n| ssa.constant(n))
+            .collect::<Vec<_>>();
+        assert!(consts.contains(&0xfffffffffffffffc) && consts.contains(&5));
+    }
 }
@@ -0,0 +1,368 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Detects the arguments passed on the stack.
+//!
+//! Arguments pushed by the caller are read above the return address, i.e. at
+//! a positive offset from the stack pointer the function was entered with.
+//! The offsets of the stack and base pointers are computed by
+//! `interproc::digstack`. A slot is an argument if it may be loaded before the
+//! function writes to it, so that slots only used to spill registers, like
+//! the home space of the Windows x64 calling convention, are not.
+//!
+//! A function which pops its arguments on return, as in the stdcall calling
+//! convention, takes every slot below the bytes popped by its `ret N`, even
+//! the ones it does not read.
+//!
+//! The arguments are bound as `StackArgument`s with their offset. At a call,
+//! the values of the arguments of the callee are the ones last stored to the
+//! same offsets from the stack pointer passed to the call, see `call_args`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use analysis::interproc::digstack;
+use frontend::radeco_containers::{BindingType, RadecoFunction, RadecoModule, VarBinding};
+use middle::ir::MOpcode;
+use middle::ssa::cfg_traits::CFG;
+use middle::ssa::ssa_traits::SSA;
+use middle::ssa::ssastorage::SSAStorage;
+use middle::ssa::utils;
+
+use petgraph::Direction;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use r2api::structs::LOpInfo;
+
+/// Binds the stack arguments of `rfn`, replacing the ones bound before.
+/// Returns the number of stack arguments.
+pub fn analyze(rfn: &mut RadecoFunction) -> usize {
+    let mut loads = stack_args(rfn.ssa());
+    if let (Some(word), Some(popped)) = (word_size(rfn.ssa()), popped_bytes(&rfn.instructions)) {
+        let word = word as u64;
+        let mut offset = word;
+        while word > 0 && offset <= popped {
+            loads.entry(offset).or_insert_with(Vec::new);
+            offset += word;
+        }
+    }
+    let mut args = rfn.args()
+        .into_iter()
+        .filter(|vb| !is_stack_arg(vb))
+        .collect::<Vec<_>>();
+    for (&offset, loads) in &loads {
+        let name = format!("arg_{:x}", offset);
+        args.push(VarBinding::new(BindingType::StackArgument(offset as usize),
+                                  String::new(),
+                                  Some(name),
+                                  loads.first().cloned().unwrap_or_else(NodeIndex::end),
+                                  None));
+    }
+    if !loads.is_empty() {
+        radeco_trace!("stack_args|{:#x}|{:?}", rfn.offset, loads.keys().collect::<Vec<_>>());
+    }
+    rfn.set_args(args);
+    loads.len()
+}
+
+/// Records the offsets of the stack arguments of the callees at the calls of
+/// every function of `rmod`, see `RadecoFunction::callee_stack_args`.
+pub fn record_calls(rmod: &mut RadecoModule) {
+    let calls = rmod.functions
+        .iter()
+        .map(|(&offset, rfn)| {
+            let callees = rmod.callgraph
+                .edges_directed(rfn.cgid(), Direction::Outgoing)
+                .filter_map(|e| {
+                    let callee = rmod.functions.get(&rmod.callgraph[e.target()])?;
+                    let offsets = arg_offsets(callee);
+                    if offsets.is_empty() {
+                        None
+                    } else {
+                        Some((e.weight().csite, offsets))
+                    }
+                })
+                .collect::<HashMap<_, _>>();
+            (offset, callees)
+        })
+        .collect::<Vec<_>>();
+
+    for (offset, callees) in calls {
+        if let Some(rfn) = rmod.functions.get_mut(&offset) {
+            *rfn.callee_stack_args_mut() = callees;
+        }
+    }
+}
+
+/// Returns the offsets of the stack arguments bound to `rfn`, in order.
+pub fn arg_offsets(rfn: &RadecoFunction) -> Vec<u64> {
+    rfn.args()
+        .iter()
+        .filter_map(|vb| match vb.btype {
+            BindingType::StackArgument(offset) => Some(offset as u64),
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_stack_arg(vb: &VarBinding) -> bool {
+    match vb.btype {
+        BindingType::StackArgument(_) => true,
+        _ => false,
+    }
+}
+
+/// Computes the offsets of the values of the stack and base pointers from the
+/// stack pointer at the entry.
+pub fn stack_offsets(ssa: &SSAStorage) -> HashMap<NodeIndex, i64> {
+    let sp = match ssa.regfile.get_name_by_alias(&"SP".to_owned()) {
+        Some(sp) => sp.to_owned(),
+        None => return HashMap::new(),
+    };
+    let bp = ssa.regfile
+        .get_name_by_alias(&"BP".to_owned())
+        .map(|bp| bp.to_owned())
+        .unwrap_or_else(|| sp.clone());
+    digstack::rounded_analysis(ssa, sp, bp)
+}
+
+/// Returns the loads of the stack arguments, by their offset from the stack
+/// pointer at the entry.
+pub fn stack_args(ssa: &SSAStorage) -> BTreeMap<u64, Vec<NodeIndex>> {
+    let mut args = BTreeMap::new();
+    let (word, entry_mem) = match (word_size(ssa), entry_memory(ssa)) {
+        (Some(word), Some(entry_mem)) => (word, entry_mem),
+        _ => return args,
+    };
+    let offsets = stack_offsets(ssa);
+
+    for node in ssa.values() {
+        if ssa.opcode(node) != Some(MOpcode::OpLoad) {
+            continue;
+        }
+        let ops = ssa.operands_of(node);
+        if ops.len() != 2 {
+            continue;
+        }
+        // The return address is at offset 0.
+        let offset = match address_offset(ssa, &offsets, ops[1]) {
+            Some(offset) if offset >= word => offset,
+            _ => continue,
+        };
+        if reads_entry(ssa, &offsets, ops[0], offset, entry_mem) {
+            args.entry(offset as u64).or_insert_with(Vec::new).push(node);
+        }
+    }
+    args
+}
+
+/// Returns the values stored to the stack arguments of the callee of `call`,
+/// given by their offsets `arg_offsets`. `offsets` are the stack offsets of
+/// the caller, see `stack_offsets`. The value of an argument is `None` if it
+/// is not stored right before the call.
+pub fn call_args(ssa: &SSAStorage,
+                 offsets: &HashMap<NodeIndex, i64>,
+                 call: NodeIndex,
+                 arg_offsets: &[u64])
+                 -> Vec<Option<NodeIndex>> {
+    let mut values = HashMap::new();
+    let info = utils::call_info(call, ssa);
+    let sp_offset = ssa.regfile
+        .register_id_by_alias("SP")
+        .and_then(|sp| info.as_ref()?.register_args.get(sp))
+        .and_then(|sp_value| offsets.get(sp_value));
+    if let (Some(word), Some(&sp_offset), Some(info)) = (word_size(ssa), sp_offset, info.as_ref()) {
+        // The arguments are stored to the stack right before the call. The
+        // return address then takes the word below them.
+        let mut mem = info.register_args.get(ssa.regfile.mem_id()).cloned();
+        while let Some(node) = mem {
+            let ops = ssa.operands_of(node);
+            if ssa.opcode(node) != Some(MOpcode::OpStore) || ops.len() != 3 {
+                break;
+            }
+            if let Some(offset) = address_offset(ssa, offsets, ops[1]) {
+                // The last store to a slot is the one passed.
+                values.entry(offset - sp_offset + word).or_insert(ops[2]);
+            }
+            mem = Some(ops[0]);
+        }
+    }
+    arg_offsets.iter().map(|&offset| values.get(&(offset as i64)).cloned()).collect()
+}
+
+// Returns the number of bytes of arguments popped by the returns of a
+// function, as by `ret 8`.
+fn popped_bytes(instructions: &[LOpInfo]) -> Option<u64> {
+    instructions.iter()
+        .filter(|op| op.optype.as_ref().map_or(false, |ty| ty == "ret"))
+        .filter_map(|op| {
+            let imm = op.opcode.as_ref()?.split_whitespace().nth(1)?;
+            if imm.starts_with("0x") {
+                u64::from_str_radix(&imm[2..], 16).ok()
+            } else {
+                imm.parse().ok()
+            }
+        })
+        .max()
+}
+
+// Size of a stack slot in bytes, the width of the stack pointer.
+fn word_size(ssa: &SSAStorage) -> Option<i64> {
+    let sp = ssa.regfile.register_id_by_alias("SP")?;
+    ssa.regfile.get_width(sp).map(|width| (width / 8) as i64)
+}
+
+// Memory at the entry of the function.
+fn entry_memory(ssa: &SSAStorage) -> Option<NodeIndex> {
+    let regstate = ssa.registers_in(ssa.entry_node()?)?;
+    utils::register_state_info(regstate, ssa)
+        .get(ssa.regfile.mem_id())
+        .map(|&(node, _)| node)
+}
+
+// Returns the offset of the address `addr` from the stack pointer at the
+// entry, if it is a stack or base pointer plus a constant.
+fn address_offset(ssa: &SSAStorage,
+                  offsets: &HashMap<NodeIndex, i64>,
+                  addr: NodeIndex)
+                  -> Option<i64> {
+    if let Some(&offset) = offsets.get(&addr) {
+        return Some(offset);
+    }
+    let ops = ssa.operands_of(addr);
+    if ops.len() != 2 {
+        return None;
+    }
+    let base = |i: usize| offsets.get(&ops[i]).cloned();
+    let constant = |i: usize| ssa.constant_value(ops[i]).map(|c| c as i64);
+    match ssa.opcode(addr)? {
+        MOpcode::OpAdd => {
+            base(0)
+                .and_then(|b| constant(1).map(|c| b.wrapping_add(c)))
+                .or_else(|| base(1).and_then(|b| constant(0).map(|c| b.wrapping_add(c))))
+        }
+        MOpcode::OpSub => base(0).and_then(|b| constant(1).map(|c| b.wrapping_sub(c))),
+        _ => None,
+    }
+}
+
+// Checks if the memory `mem`, which a load from the slot at `offset` reads,
+// may be the memory at the entry `entry_mem`, with the slot not written
+// since.
+fn reads_entry(ssa: &SSAStorage,
+               offsets: &HashMap<NodeIndex, i64>,
+               mem: NodeIndex,
+               offset: i64,
+               entry_mem: NodeIndex)
+               -> bool {
+    let mut worklist = vec![mem];
+    let mut visited = HashSet::new();
+    while let Some(mem) = worklist.pop() {
+        if mem == entry_mem {
+            return true;
+        }
+        if !visited.insert(mem) {
+            continue;
+        }
+        if ssa.is_phi(mem) {
+            worklist.extend(ssa.operands_of(mem));
+            continue;
+        }
+        let ops = ssa.operands_of(mem);
+        match ssa.opcode(mem) {
+            Some(MOpcode::OpStore) if ops.len() == 3 => {
+                if address_offset(ssa, offsets, ops[1]) != Some(offset) {
+                    worklist.push(ops[0]);
+                }
+            }
+            // Memory written by a call or an intrinsic, which read the memory
+            // among their operands.
+            None if ssa.comment(mem).is_some() => {
+                for definer in ops {
+                    worklist.extend(ssa.operands_of(definer)
+                        .into_iter()
+                        .filter(|&op| is_memory(ssa, op)));
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+fn is_memory(ssa: &SSAStorage, node: NodeIndex) -> bool {
+    ssa.node_data(node).ok().map_or(false, |data| data.vt.width().get_width() == Some(0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::{construct, function, REGISTER_PROFILE_32};
+
+    fn function_32(ops: &str) -> RadecoFunction {
+        function(REGISTER_PROFILE_32, ops, false)
+    }
+
+    #[test]
+    fn stack_args_test() {
+        // `[rsp + 0x18]` is spilled to before it is read, `[rsp]` is the return
+        // address.
+        let ops = r#"[{"offset":4096,"size":5,"opcode":"mov rax, qword [rsp + 8]","esil":"rsp,8,+,[8],rax,=","type":"mov"},
+                      {"offset":4101,"size":5,"opcode":"mov qword [rsp + 0x18], rdi","esil":"rdi,rsp,24,+,=[8]","type":"mov"},
+                      {"offset":4106,"size":5,"opcode":"mov rcx, qword [rsp + 0x18]","esil":"rsp,24,+,[8],rcx,=","type":"mov"},
+                      {"offset":4111,"size":5,"opcode":"mov rdx, qword [rsp + 0x10]","esil":"rsp,16,+,[8],rdx,=","type":"mov"},
+                      {"offset":4116,"size":4,"opcode":"mov rsi, qword [rsp]","esil":"rsp,[8],rsi,=","type":"mov"},
+                      {"offset":4120,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let ssa = construct(ops);
+        let args = stack_args(&ssa);
+        assert_eq!(args.keys().cloned().collect::<Vec<_>>(), [8, 16]);
+    }
+
+    #[test]
+    fn cdecl_test() {
+        // The arguments of an i386 cdecl function are all on the stack, in
+        // 4 byte slots.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"mov eax, dword [esp + 4]","esil":"esp,4,+,[4],eax,=","type":"mov"},
+                      {"offset":4100,"size":4,"opcode":"mov ecx, dword [esp + 8]","esil":"esp,8,+,[4],ecx,=","type":"mov"},
+                      {"offset":4104,"size":2,"opcode":"add eax, ecx","esil":"ecx,eax,+=","type":"add"},
+                      {"offset":4106,"size":1,"opcode":"ret","esil":"esp,[4],eip,=,4,esp,+=","type":"ret"}]"#;
+        let mut rfn = function_32(ops);
+        assert_eq!(analyze(&mut rfn), 2);
+        assert_eq!(arg_offsets(&rfn), [4, 8]);
+    }
+
+    #[test]
+    fn stdcall_test() {
+        // `ret 0xc` pops three arguments, though only the first is read.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"mov eax, dword [esp + 4]","esil":"esp,4,+,[4],eax,=","type":"mov"},
+                      {"offset":4100,"size":3,"opcode":"ret 0xc","esil":"esp,[4],eip,=,4,esp,+=,12,esp,+=","type":"ret"}]"#;
+        let mut rfn = function_32(ops);
+        assert_eq!(stack_args(rfn.ssa()).keys().cloned().collect::<Vec<_>>(), [4]);
+        assert_eq!(analyze(&mut rfn), 3);
+        assert_eq!(arg_offsets(&rfn), [4, 8, 12]);
+    }
+
+    #[test]
+    fn call_args_test() {
+        let ops = r#"[{"offset":4096,"size":2,"opcode":"push 2","esil":"2,8,rsp,-=,rsp,=[8]","type":"upush"},
+                      {"offset":4098,"size":2,"opcode":"push 1","esil":"1,8,rsp,-=,rsp,=[8]","type":"upush"},
+                      {"offset":4100,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4105,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4109,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let ssa = construct(ops);
+        let offsets = stack_offsets(&ssa);
+        let call = ssa.values()
+            .into_iter()
+            .find(|&node| ssa.opcode(node) == Some(MOpcode::OpCall))
+            .unwrap();
+        let values = call_args(&ssa, &offsets, call, &[8, 16, 24])
+            .into_iter()
+            .map(|value| value.and_then(|value| ssa.constant_value(value)))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [Some(1), Some(2), None]);
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Store-to-load forwarding.
+//!
+//! Replaces an OpLoad with the value stored by a dominating OpStore to the
+//! same location, if no other store between them may alias it. Spill code
+//! turns locals into memory traffic, this pass collapses such locals back
+//! into SSA values.
+//!
+//! Only stack slots are forwarded. Calls which may access a slot define it
+//! in MemorySSA, thus loads after them are left alone.
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::graph::NodeIndex;
+
+use analysis::dom::BlockDomTree;
+use analysis::vsa::value_set::MemRegion;
+use frontend::radeco_containers::RadecoFunction;
+use middle::ir::MOpcode;
+use middle::ssa::memoryssa::{MemoryDef, MemorySSAInfo, MemorySSAQuery};
+use middle::ssa::ssa_traits::{SSA, SSAMod};
+use middle::ssa::ssastorage::SSAStorage;
+
+/// Forwards stored values to loads in `rfn`, returns the number of
+/// replaced loads.
+pub fn run(rfn: &mut RadecoFunction) -> usize {
+    if rfn.memory_ssa().is_none() {
+        rfn.build_memory_ssa();
+    }
+    let forwards = {
+        let mssa = rfn.memory_ssa().expect("MemorySSA not built");
+        let doms = BlockDomTree::new(rfn.ssa());
+        forwardable_loads(rfn.ssa(), mssa, &doms)
+    };
+    if forwards.is_empty() {
+        return 0;
+    }
+
+    {
+        let ssa = rfn.ssa_mut();
+        // Stored values may be loads replaced before.
+        let mut replaced: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        for &(load, value) in &forwards {
+            let mut value = value;
+            while let Some(v) = replaced.get(&value) {
+                value = *v;
+            }
+            radeco_trace!("store_forwarding|{:?} -> {:?}", load, value);
+            ssa.replace_value(load, value);
+            replaced.insert(load, value);
+        }
+    }
+    forwards.len()
+}
+
+// Returns pairs of OpLoad and the value it could be replaced with, in the
+// order of the walk.
+fn forwardable_loads(ssa: &SSAStorage,
+                     mssa: &MemorySSAInfo<NodeIndex, NodeIndex>,
+                     doms: &BlockDomTree<NodeIndex>)
+                     -> Vec<(NodeIndex, NodeIndex)> {
+    let mut forwards = Vec::new();
+    for node in ssa.inorder_walk() {
+        if ssa.opcode(node) != Some(MOpcode::OpLoad) {
+            continue;
+        }
+        let a_loc = match mssa.accessed_a_loc(&node) {
+            Some(a_loc) => a_loc,
+            None => continue,
+        };
+        if a_loc.region != MemRegion::Stack {
+            continue;
+        }
+
+        // All variables of the load must be defined by the same store.
+        let defs: HashSet<MemoryDef<NodeIndex>> = mssa.reaching_defs(&node).into_iter().collect();
+        if defs.len() != 1 {
+            continue;
+        }
+        let store = match defs.into_iter().next() {
+            Some(MemoryDef::Store(store)) => store,
+            _ => continue,
+        };
+        if mssa.accessed_a_loc(&store) != Some(a_loc) {
+            continue;
+        }
+
+        let value = match ssa.operands_of(store).get(2) {
+            Some(value) => *value,
+            None => continue,
+        };
+        if width_of(ssa, &value).is_none() || width_of(ssa, &value) != width_of(ssa, &node) {
+            continue;
+        }
+
+        let dominated = match (ssa.block_for(store), ssa.block_for(node)) {
+            (Some(sb), Some(lb)) => doms.dominates(&sb, &lb),
+            _ => false,
+        };
+        if dominated {
+            forwards.push((node, value));
+        }
+    }
+    forwards
+}
+
+fn width_of(ssa: &SSAStorage, node: &NodeIndex) -> Option<u16> {
+    ssa.node_data(*node).ok().and_then(|nd| nd.vt.width().get_width())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::{il_function, load_il_function};
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const SPILL_TXT: &str = "\
+define-fun sym.spill(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rsp;
+        %2: $Unknown0 = $mem;
+        %3: $Unknown64 = $rdi;
+    bb_0x001000.0000(sz 0x4):
+        [@0x001000.0001] %4: $Unknown64 = %1 - #x8;
+        [@0x001000.0002] %5: $Unknown0 = Store(%2, %4, %3);
+        JMP 0x001004.0000
+    bb_0x001004.0000(sz 0x8):
+        [@0x001004.0001] %6: $Unknown64 = Load(%5, %4);
+        [@0x001008.0001] %7: $Unknown64 = %6 + #x1;
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %7;
+        $rsp = %1;
+        $mem = %5;
+}
+";
+
+    fn count_loads(rfn: &RadecoFunction) -> usize {
+        rfn.ssa()
+            .values()
+            .into_iter()
+            .filter(|n| rfn.ssa().opcode(*n) == Some(MOpcode::OpLoad))
+            .count()
+    }
+
+    #[test]
+    fn store_forwarding_escaped_test() {
+        // Locals of this function are passed to scanf, thus none of the
+        // loads could be forwarded.
+        let mut rfn = load_il_function("./test_files/bin1_main_ssa");
+        let loads = count_loads(&rfn);
+        assert_eq!(run(&mut rfn), 0);
+        assert_eq!(count_loads(&rfn), loads);
+    }
+
+    #[test]
+    fn store_forwarding_spill_test() {
+        let mut rfn = il_function(SPILL_TXT);
+        assert_eq!(count_loads(&rfn), 1);
+        assert_eq!(run(&mut rfn), 1);
+        assert_eq!(count_loads(&rfn), 0);
+
+        // The increment now uses the stored register directly.
+        let ssa = rfn.ssa();
+        let store = ssa.values()
+            .into_iter()
+            .find(|n| ssa.opcode(*n) == Some(MOpcode::OpStore))
+            .expect("Store not found");
+        let value = ssa.operands_of(store)[2];
+        let add = ssa.values()
+            .into_iter()
+            .find(|n| ssa.opcode(*n) == Some(MOpcode::OpAdd))
+            .expect("Add not found");
+        assert!(ssa.operands_of(add).contains(&value));
+    }
+}
@@ -7,10 +7,1669 @@
 
 //! Module that implements math operation on the BDD.
 //!
+//! A k-bits BDD (Reduced Ordered Binary Decision Diagram) represents a set of
+//! integers by its characteristic function over the k bits of the members:
+//!     bdd --> {i | f(bit_(k-1)(i), .., bit_1(i), bit_0(i)) == true}
+//! Variable 0 is the most significant bit (the sign bit), variable k-1 is the
+//! least significant bit.
+//!
+//! All nodes live in a global unique table, thus a BDD is only an index into
+//! this table and two BDDs are equal iff they are the same set.
+//!
+//! Bitwise operations with a constant, shifting, extension, narrowing and
+//! addition of a constant are computed exactly on the diagram. Other
+//! operations are computed exactly by enumeration when the operands are small
+//! enough, otherwise they fall back to strided intervals (or known bits for
+//! bitwise operations), which is sound but loses precision.
+//!
 //! More information are available from "BDD-based Value Analysis for X86 Executables".
 //! Please refer here:
 //!     * https://tubdok.tub.tuhh.de/bitstream/11420/1510/1/dis.pdf
 //!
 
+use std::fmt;
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::ops::{Neg, Add, Sub, Div, Rem, Mul};
+use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+
+use super::abstract_set::{inum, unum, _bits};
+use super::abstract_set::{AbstractSet, Container};
+use super::strided_interval::StridedInterval;
+
+// Node ids of the two terminal nodes
+const FALSE: u32 = 0;
+const TRUE: u32 = 1;
+// Variable of terminal nodes, which is bigger than any variable of a bit
+const TERMINAL: u8 = 0xff;
+
+// Sets (or pairs of operands) not bigger than this are enumerated to get
+// exact results.
+const ENUM_THRESHOLD: unum = 0x400;
+// Binary operations whose smaller operand is not bigger than this are split
+// into a union of operations with a constant.
+const SPLIT_THRESHOLD: unum = 0x40;
+
+/// Returns the mask of k bits
+fn mask(k: u8) -> unum {
+    if k >= _bits {
+        unum::max_value()
+    } else {
+        ((1 as unum) << k) - 1
+    }
+}
+
+/// Returns the min value in k bits
+fn min_in_k(k: u8) -> inum {
+    to_signed((1 as unum) << (k - 1), k)
+}
+
+/// Returns the max value in k bits
+fn max_in_k(k: u8) -> inum {
+    (mask(k) >> 1) as inum
+}
+
+/// Returns the k-bits pattern of n
+fn to_pattern(n: inum, k: u8) -> unum {
+    (n as unum) & mask(k)
+}
+
+/// Returns the signed number of the k-bits pattern x
+fn to_signed(x: unum, k: u8) -> inum {
+    if k >= _bits {
+        x as inum
+    } else {
+        let shift = _bits - k;
+        ((x << shift) as inum) >> shift
+    }
+}
+
+/// Returns 2^e, or unum::max_value() if it overflows
+fn pow2_saturating(e: u8) -> unum {
+    if e >= _bits {
+        unum::max_value()
+    } else {
+        (1 as unum) << e
+    }
+}
+
+/// Returns the mask of BDD variables for the bits in k-bits pattern `bits`
+fn vars_of_bits(bits: unum, k: u8) -> unum {
+    let mut vars: unum = 0;
+    for b in 0..k {
+        if (bits >> b) & 1 == 1 {
+            vars |= (1 as unum) << (k - 1 - b);
+        }
+    }
+    vars
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum BoolOp {
+    And,
+    Or,
+    Xor,
+}
+
+/// Global storage for the nodes of all BDDs.
+// XXX: Nodes are never collected.
+struct BddManager {
+    // (var, low, high) of every node, indexed by node id
+    nodes: Vec<(u8, u32, u32)>,
+    // Used to keep all BDDs reduced and canonical
+    unique: HashMap<(u8, u32, u32), u32>,
+}
+
+lazy_static! {
+    static ref MANAGER: Mutex<BddManager> = Mutex::new(BddManager::new());
+}
+
+impl BddManager {
+    fn new() -> BddManager {
+        BddManager {
+            nodes: vec![(TERMINAL, FALSE, FALSE), (TERMINAL, TRUE, TRUE)],
+            unique: HashMap::new(),
+        }
+    }
+
+    fn var(&self, n: u32) -> u8 {
+        self.nodes[n as usize].0
+    }
+
+    fn low(&self, n: u32) -> u32 {
+        self.nodes[n as usize].1
+    }
+
+    fn high(&self, n: u32) -> u32 {
+        self.nodes[n as usize].2
+    }
+
+    /// Returns the unique node testing `var`.
+    fn mk(&mut self, var: u8, low: u32, high: u32) -> u32 {
+        if low == high {
+            return low;
+        }
+        if let Some(&n) = self.unique.get(&(var, low, high)) {
+            return n;
+        }
+        let n = self.nodes.len() as u32;
+        self.nodes.push((var, low, high));
+        self.unique.insert((var, low, high), n);
+        n
+    }
+
+    /// Returns the cofactors of `n` with respect to `var`, `var` must not be
+    /// bigger than the variable of `n`.
+    fn cofactors(&self, n: u32, var: u8) -> (u32, u32) {
+        if self.var(n) == var {
+            (self.low(n), self.high(n))
+        } else {
+            (n, n)
+        }
+    }
+
+    fn apply(&mut self, op: BoolOp, a: u32, b: u32, memo: &mut HashMap<(u32, u32), u32>) -> u32 {
+        match op {
+            BoolOp::And => {
+                if a == FALSE || b == FALSE {
+                    return FALSE;
+                } else if a == TRUE || a == b {
+                    return b;
+                } else if b == TRUE {
+                    return a;
+                }
+            }
+            BoolOp::Or => {
+                if a == TRUE || b == TRUE {
+                    return TRUE;
+                } else if a == FALSE || a == b {
+                    return b;
+                } else if b == FALSE {
+                    return a;
+                }
+            }
+            BoolOp::Xor => {
+                if a == b {
+                    return FALSE;
+                } else if a == FALSE {
+                    return b;
+                } else if b == FALSE {
+                    return a;
+                }
+            }
+        }
+        if let Some(&n) = memo.get(&(a, b)) {
+            return n;
+        }
+        let var = cmp::min(self.var(a), self.var(b));
+        let (a0, a1) = self.cofactors(a, var);
+        let (b0, b1) = self.cofactors(b, var);
+        let low = self.apply(op, a0, b0, memo);
+        let high = self.apply(op, a1, b1, memo);
+        let n = self.mk(var, low, high);
+        memo.insert((a, b), n);
+        n
+    }
+
+    fn and(&mut self, a: u32, b: u32) -> u32 {
+        self.apply(BoolOp::And, a, b, &mut HashMap::new())
+    }
+
+    fn or(&mut self, a: u32, b: u32) -> u32 {
+        self.apply(BoolOp::Or, a, b, &mut HashMap::new())
+    }
+
+    fn xor(&mut self, a: u32, b: u32) -> u32 {
+        self.apply(BoolOp::Xor, a, b, &mut HashMap::new())
+    }
+
+    fn not(&mut self, a: u32) -> u32 {
+        self.xor(a, TRUE)
+    }
+
+    /// Existentially quantifies all variables in mask `vars` out of `n`.
+    fn exists(&mut self, n: u32, vars: unum, memo: &mut HashMap<u32, u32>) -> u32 {
+        let var = self.var(n);
+        if var == TERMINAL {
+            return n;
+        }
+        if let Some(&r) = memo.get(&n) {
+            return r;
+        }
+        let (low, high) = (self.low(n), self.high(n));
+        let low = self.exists(low, vars, memo);
+        let high = self.exists(high, vars, memo);
+        let r = if (vars >> var) & 1 == 1 {
+            self.or(low, high)
+        } else {
+            self.mk(var, low, high)
+        };
+        memo.insert(n, r);
+        r
+    }
+
+    /// Swaps the children of all nodes testing a variable in mask `vars`,
+    /// which flips the according bits of all members.
+    fn flip(&mut self, n: u32, vars: unum, memo: &mut HashMap<u32, u32>) -> u32 {
+        let var = self.var(n);
+        if var == TERMINAL {
+            return n;
+        }
+        if let Some(&r) = memo.get(&n) {
+            return r;
+        }
+        let (low, high) = (self.low(n), self.high(n));
+        let low = self.flip(low, vars, memo);
+        let high = self.flip(high, vars, memo);
+        let r = if (vars >> var) & 1 == 1 {
+            self.mk(var, high, low)
+        } else {
+            self.mk(var, low, high)
+        };
+        memo.insert(n, r);
+        r
+    }
+
+    /// Adds `delta` to every variable of `n`, the order of variables is kept.
+    fn rename(&mut self, n: u32, delta: i16, memo: &mut HashMap<u32, u32>) -> u32 {
+        let var = self.var(n);
+        if var == TERMINAL {
+            return n;
+        }
+        if let Some(&r) = memo.get(&n) {
+            return r;
+        }
+        let (low, high) = (self.low(n), self.high(n));
+        let low = self.rename(low, delta, memo);
+        let high = self.rename(high, delta, memo);
+        let r = self.mk((var as i16 + delta) as u8, low, high);
+        memo.insert(n, r);
+        r
+    }
+
+    /// Returns the k-bits set whose members have `value` on all bits of `bits`.
+    fn bits_eq(&mut self, k: u8, bits: unum, value: unum) -> u32 {
+        let mut r = TRUE;
+        for b in 0..k {
+            if (bits >> b) & 1 == 0 {
+                continue;
+            }
+            let var = k - 1 - b;
+            r = if (value >> b) & 1 == 1 {
+                self.mk(var, FALSE, r)
+            } else {
+                self.mk(var, r, FALSE)
+            };
+        }
+        r
+    }
+
+    /// Returns the k-bits set {x | x >= c} (unsigned).
+    fn ge(&mut self, k: u8, c: unum) -> u32 {
+        let mut r = TRUE;
+        for b in 0..k {
+            let var = k - 1 - b;
+            r = if (c >> b) & 1 == 1 {
+                self.mk(var, FALSE, r)
+            } else {
+                self.mk(var, r, TRUE)
+            };
+        }
+        r
+    }
+
+    /// Returns the k-bits set {x | x <= c} (unsigned).
+    fn le(&mut self, k: u8, c: unum) -> u32 {
+        let mut r = TRUE;
+        for b in 0..k {
+            let var = k - 1 - b;
+            r = if (c >> b) & 1 == 1 {
+                self.mk(var, TRUE, r)
+            } else {
+                self.mk(var, r, FALSE)
+            };
+        }
+        r
+    }
+
+    /// Returns the k-bits set {x | lb <= x <= ub} (signed).
+    fn interval(&mut self, k: u8, lb: inum, ub: inum) -> u32 {
+        if lb > ub {
+            return FALSE;
+        }
+        let ge = self.ge(k, to_pattern(lb, k));
+        let le = self.le(k, to_pattern(ub, k));
+        if (lb < 0) == (ub < 0) {
+            self.and(ge, le)
+        } else {
+            // Negative numbers are the upper half of the unsigned space
+            self.or(ge, le)
+        }
+    }
+
+    /// Returns whether the k-bits pattern x is a member of `n`.
+    fn eval(&self, n: u32, k: u8, x: unum) -> bool {
+        let mut n = n;
+        while self.var(n) != TERMINAL {
+            let var = self.var(n);
+            n = if (x >> (k - 1 - var)) & 1 == 0 {
+                self.low(n)
+            } else {
+                self.high(n)
+            };
+        }
+        n == TRUE
+    }
+
+    /// Returns the number of members of the k-bits set `n`, saturated at
+    /// unum::max_value().
+    fn count(&self, n: u32, k: u8) -> unum {
+        let level = |v: u8| if v == TERMINAL { k } else { v };
+        let mut memo: HashMap<u32, unum> = HashMap::new();
+        let r = self.count_rec(n, k, &mut memo);
+        r.saturating_mul(pow2_saturating(level(self.var(n))))
+    }
+
+    // Counts the assignments of the variables from `var(n)` to k - 1
+    fn count_rec(&self, n: u32, k: u8, memo: &mut HashMap<u32, unum>) -> unum {
+        if n == FALSE {
+            return 0;
+        } else if n == TRUE {
+            return 1;
+        }
+        if let Some(&c) = memo.get(&n) {
+            return c;
+        }
+        let var = self.var(n);
+        let level = |v: u8| if v == TERMINAL { k } else { v };
+        let (low, high) = (self.low(n), self.high(n));
+        let c_low = self.count_rec(low, k, memo)
+            .saturating_mul(pow2_saturating(level(self.var(low)) - var - 1));
+        let c_high = self.count_rec(high, k, memo)
+            .saturating_mul(pow2_saturating(level(self.var(high)) - var - 1));
+        let c = c_low.saturating_add(c_high);
+        memo.insert(n, c);
+        c
+    }
+
+    /// Pushes all k-bits patterns of `n` into `out`, deciding bits from
+    /// variable `var` on.
+    fn enumerate(&self, n: u32, k: u8, var: u8, prefix: unum, out: &mut Vec<unum>) {
+        if n == FALSE {
+            return;
+        }
+        if var == k {
+            out.push(prefix);
+            return;
+        }
+        let bit = (1 as unum) << (k - 1 - var);
+        let (low, high) = self.cofactors(n, var);
+        self.enumerate(low, k, var + 1, prefix, out);
+        self.enumerate(high, k, var + 1, prefix | bit, out);
+    }
+
+    /// Returns the k-bits pattern of the smallest (or biggest) signed member
+    /// of the non-empty set `n`.
+    fn signed_bound(&self, n: u32, k: u8, max: bool) -> unum {
+        let mut n = n;
+        let mut x: unum = 0;
+        for var in 0..k {
+            // The smallest number prefers a set sign bit and clear other bits
+            let prefer_one = if var == 0 { !max } else { max };
+            let (low, high) = self.cofactors(n, var);
+            let one = if prefer_one {
+                high != FALSE
+            } else {
+                low == FALSE
+            };
+            if one {
+                n = high;
+                x |= (1 as unum) << (k - 1 - var);
+            } else {
+                n = low;
+            }
+        }
+        x
+    }
+
+    /// Returns (zeros, ones), the k-bits masks of the bits which are
+    /// respectively clear and set in all members of `n`.
+    fn known_bits(&mut self, n: u32, k: u8) -> (unum, unum) {
+        let mut zeros: unum = 0;
+        let mut ones: unum = 0;
+        for var in 0..k {
+            let bit = (1 as unum) << (k - 1 - var);
+            let set = self.mk(var, FALSE, TRUE);
+            let clear = self.mk(var, TRUE, FALSE);
+            if self.and(n, set) == FALSE {
+                zeros |= bit;
+            } else if self.and(n, clear) == FALSE {
+                ones |= bit;
+            }
+        }
+        (zeros, ones)
+    }
+
+    /// Returns the k-bits set {x + c | x in n}.
+    fn add_constant(&mut self, n: u32, k: u8, c: unum) -> u32 {
+        let mut memo = HashMap::new();
+        let no_carry = self.add_rec(n, 0, false, k, c, &mut memo);
+        let carry = self.add_rec(n, 0, true, k, c, &mut memo);
+        // The carry out of the most significant bit is dropped
+        self.or(no_carry, carry)
+    }
+
+    // Returns the low bits (variables from `var` to k - 1) of {x + c | x in n},
+    // for whose addition the carry out of variable `var` is `cout`.
+    fn add_rec(&mut self,
+               n: u32,
+               var: u8,
+               cout: bool,
+               k: u8,
+               c: unum,
+               memo: &mut HashMap<(u32, u8, bool), u32>)
+               -> u32 {
+        if n == FALSE {
+            return FALSE;
+        }
+        if var == k {
+            // There is no carry into the least significant bit
+            return if cout { FALSE } else { TRUE };
+        }
+        if let Some(&r) = memo.get(&(n, var, cout)) {
+            return r;
+        }
+        let c_bit = (c >> (k - 1 - var)) & 1 == 1;
+        let (n0, n1) = self.cofactors(n, var);
+        let mut r = FALSE;
+        for &(x_bit, sub) in [(false, n0), (true, n1)].iter() {
+            for &cin in [false, true].iter() {
+                let carry = (x_bit && c_bit) || (x_bit && cin) || (c_bit && cin);
+                if carry != cout {
+                    continue;
+                }
+                let lower = self.add_rec(sub, var + 1, cin, k, c, memo);
+                if lower == FALSE {
+                    continue;
+                }
+                let node = if x_bit ^ c_bit ^ cin {
+                    self.mk(var, FALSE, lower)
+                } else {
+                    self.mk(var, lower, FALSE)
+                };
+                r = self.or(r, node);
+            }
+        }
+        memo.insert((n, var, cout), r);
+        r
+    }
+}
+
+/// A k-bits BDD.
+///
+/// A BDD is a handle to the root of a reduced ordered binary decision diagram
+/// in the global unique table, thus it is cheap to copy and two BDDs are equal
+/// iff they represent the same set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BDD {
+    // Used for indicate radix of BDD (k-bits)
+    // k == 0 means this BDD is an empty set
+    pub k: u8,
+    // Id of the root node
+    root: u32,
+}
+
+
+//
+// Utility functions go here
+//
+
+impl fmt::Display for BDD {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "{{}}");
+        }
+        let values = if self.capacity() <= 0x10 {
+            self.values()
+        } else {
+            None
+        };
+        if let Some(values) = values {
+            write!(f, "{}-bits: {:?}", self.k, values)
+        } else {
+            write!(f, "{}-bits: {} members in [{}, {}]",
+                   self.k,
+                   self.capacity(),
+                   self.min().unwrap_or(0),
+                   self.max().unwrap_or(0))
+        }
+    }
+}
+
+
+//
+// Basic functions for BDD go here
+//
+
+impl BDD {
+    fn with_manager<T, F>(f: F) -> T
+        where F: FnOnce(&mut BddManager) -> T
+    {
+        let mut manager = MANAGER.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut *manager)
+    }
+
+    fn from_root(k: u8, root: u32) -> Self {
+        if root == FALSE {
+            BDD::null()
+        } else {
+            BDD {
+                k: k,
+                root: root,
+            }
+        }
+    }
+
+    /// Returns the empty set of BDD.
+    pub fn null() -> Self {
+        BDD { k: 0, root: FALSE }
+    }
+
+    /// Returns a BDD containing all numbers in k bits.
+    pub fn default_k(k: u8) -> Self {
+        if k == 0 {
+            BDD::null()
+        } else {
+            BDD {
+                k: cmp::min(k, _bits),
+                root: TRUE,
+            }
+        }
+    }
+
+    /// Returns the BDD of {i | lb <= i <= ub} in k bits.
+    pub fn interval(k: u8, lb: inum, ub: inum) -> Self {
+        if k == 0 {
+            return BDD::null();
+        }
+        let k = cmp::min(k, _bits);
+        let (lb, ub) = (to_signed(to_pattern(lb, k), k), to_signed(to_pattern(ub, k), k));
+        let root = BDD::with_manager(|m| m.interval(k, lb, ub));
+        BDD::from_root(k, root)
+    }
+
+    /// Returns the BDD of all numbers in `values` in k bits.
+    pub fn from_values(k: u8, values: &[inum]) -> Self {
+        if k == 0 {
+            return BDD::null();
+        }
+        let k = cmp::min(k, _bits);
+        let root = BDD::with_manager(|m| {
+            let mut root = FALSE;
+            for n in values {
+                let cube = m.bits_eq(k, mask(k), to_pattern(*n, k));
+                root = m.or(root, cube);
+            }
+            root
+        });
+        BDD::from_root(k, root)
+    }
+
+    /// Returns the smallest BDD containing all numbers in `si`.
+    pub fn from_strided_interval(si: &StridedInterval) -> Self {
+        if si.is_empty() {
+            BDD::null()
+        } else if let Some(n) = si.constant() {
+            BDD::from((si.k, n))
+        } else if (si.s & (si.s - 1)) == 0 {
+            // Stride is power of two, the low bits of members are the same
+            let low_bits = mask(si.s.trailing_zeros() as u8);
+            let (k, lb, ub) = (si.k, si.lb, si.ub);
+            let root = BDD::with_manager(|m| {
+                let interval = m.interval(k, lb, ub);
+                let stride = m.bits_eq(k, low_bits, to_pattern(lb, k));
+                m.and(interval, stride)
+            });
+            BDD::from_root(k, root)
+        } else if (si.capacity() as unum) <= ENUM_THRESHOLD {
+            let values: Vec<inum> = (0..si.capacity()).map(|i| si.lb + i * si.s).collect();
+            BDD::from_values(si.k, &values)
+        } else {
+            // Over-approximate the stride by its biggest power-of-two factor
+            let s = (1 as inum) << si.s.trailing_zeros();
+            BDD::from_strided_interval(&StridedInterval::new(si.k, s, si.lb, si.ub))
+        }
+    }
+
+    /// Returns the smallest StridedInterval containing all members of `self`.
+    pub fn to_strided_interval(&self) -> StridedInterval {
+        if self.is_empty() {
+            return StridedInterval::null();
+        }
+        if let Some(n) = self.constant() {
+            return StridedInterval::from((self.k, n));
+        }
+        let (zeros, ones) = self.known_bits();
+        let known = zeros | ones;
+        // Members share the trailing `t` bits
+        let t = (!known).trailing_zeros() as u8;
+        let s = if t + 1 >= self.k {
+            1
+        } else {
+            (1 as inum) << t
+        };
+        StridedInterval::new(self.k,
+                             s,
+                             self.min().unwrap_or(0),
+                             self.max().unwrap_or(0))
+    }
+
+    /// Returns all members of `self`, or None if there are too many of them.
+    pub fn values(&self) -> Option<Vec<inum>> {
+        if self.is_empty() {
+            return Some(Vec::new());
+        }
+        if self.count() > ENUM_THRESHOLD {
+            return None;
+        }
+        let (k, root) = (self.k, self.root);
+        let mut patterns = Vec::new();
+        BDD::with_manager(|m| m.enumerate(root, k, 0, 0, &mut patterns));
+        Some(patterns.into_iter().map(|x| to_signed(x, k)).collect())
+    }
+
+    /// Returns the smallest member of `self`.
+    pub fn min(&self) -> Option<inum> {
+        if self.is_empty() {
+            None
+        } else {
+            let (k, root) = (self.k, self.root);
+            Some(to_signed(BDD::with_manager(|m| m.signed_bound(root, k, false)), k))
+        }
+    }
+
+    /// Returns the biggest member of `self`.
+    pub fn max(&self) -> Option<inum> {
+        if self.is_empty() {
+            None
+        } else {
+            let (k, root) = (self.k, self.root);
+            Some(to_signed(BDD::with_manager(|m| m.signed_bound(root, k, true)), k))
+        }
+    }
+
+    /// Returns (zeros, ones), the k-bits masks of the bits which are
+    /// respectively clear and set in all members of `self`.
+    pub fn known_bits(&self) -> (unum, unum) {
+        if self.is_empty() {
+            return (0, 0);
+        }
+        let (k, root) = (self.k, self.root);
+        BDD::with_manager(|m| m.known_bits(root, k))
+    }
+
+    // Saturated number of members
+    fn count(&self) -> unum {
+        if self.is_empty() {
+            0
+        } else {
+            let (k, root) = (self.k, self.root);
+            BDD::with_manager(|m| m.count(root, k))
+        }
+    }
+
+    /// Returns {x + n | x in self}.
+    pub fn add_constant(&self, n: inum) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let (k, root) = (self.k, self.root);
+        let root = BDD::with_manager(|m| m.add_constant(root, k, to_pattern(n, k)));
+        BDD::from_root(k, root)
+    }
+
+    /// Returns {x & n | x in self}.
+    pub fn and_constant(&self, n: inum) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let (k, root) = (self.k, self.root);
+        let zeros = !to_pattern(n, k) & mask(k);
+        let root = BDD::with_manager(|m| {
+            let r = m.exists(root, vars_of_bits(zeros, k), &mut HashMap::new());
+            let cleared = m.bits_eq(k, zeros, 0);
+            m.and(r, cleared)
+        });
+        BDD::from_root(k, root)
+    }
+
+    /// Returns {x | n | x in self}.
+    pub fn or_constant(&self, n: inum) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let (k, root) = (self.k, self.root);
+        let ones = to_pattern(n, k);
+        let root = BDD::with_manager(|m| {
+            let r = m.exists(root, vars_of_bits(ones, k), &mut HashMap::new());
+            let set = m.bits_eq(k, ones, ones);
+            m.and(r, set)
+        });
+        BDD::from_root(k, root)
+    }
+
+    /// Returns {x ^ n | x in self}.
+    pub fn xor_constant(&self, n: inum) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let (k, root) = (self.k, self.root);
+        let vars = vars_of_bits(to_pattern(n, k), k);
+        let root = BDD::with_manager(|m| m.flip(root, vars, &mut HashMap::new()));
+        BDD::from_root(k, root)
+    }
+
+    /// Returns {x << i | x in self}.
+    pub fn shl_constant(&self, i: u8) -> Self {
+        if self.is_empty() || i == 0 {
+            self.clone()
+        } else if i >= self.k {
+            BDD::from((self.k, 0))
+        } else {
+            let (k, root) = (self.k, self.root);
+            let root = BDD::with_manager(|m| {
+                // Drop the highest i bits (variables 0 .. i)
+                let r = m.exists(root, mask(i), &mut HashMap::new());
+                let r = m.rename(r, -(i as i16), &mut HashMap::new());
+                let cleared = m.bits_eq(k, mask(i), 0);
+                m.and(r, cleared)
+            });
+            BDD::from_root(k, root)
+        }
+    }
+
+    /// Returns {x >> i | x in self}, which is a logical shift.
+    pub fn shr_constant(&self, i: u8) -> Self {
+        if self.is_empty() || i == 0 {
+            self.clone()
+        } else if i >= self.k {
+            BDD::from((self.k, 0))
+        } else {
+            let (k, root) = (self.k, self.root);
+            let root = BDD::with_manager(|m| {
+                // Drop the lowest i bits (variables k - i .. k)
+                let r = m.exists(root, vars_of_bits(mask(i), k), &mut HashMap::new());
+                let r = m.rename(r, i as i16, &mut HashMap::new());
+                let cleared = m.bits_eq(k, mask(k) & !mask(k - i), 0);
+                m.and(r, cleared)
+            });
+            BDD::from_root(k, root)
+        }
+    }
+
+    // Applies the concrete operation `op` on every pair of members, returns
+    // None if there are too many pairs.
+    fn enumerate_binop<F>(&self, other: &Self, op: F) -> Option<Self>
+        where F: Fn(inum, inum) -> inum
+    {
+        if self.count().saturating_mul(other.count()) > ENUM_THRESHOLD {
+            return None;
+        }
+        let a = self.values()?;
+        let b = other.values()?;
+        let mut res = Vec::with_capacity(a.len() * b.len());
+        for x in &a {
+            for y in &b {
+                res.push(op(*x, *y));
+            }
+        }
+        Some(BDD::from_values(self.k, &res))
+    }
+
+    // Splits the operation into an union of operations with a constant from
+    // the smaller operand, returns None if both operands are big.
+    fn split_binop<F>(&self, other: &Self, op: F) -> Option<Self>
+        where F: Fn(&Self, inum) -> Self
+    {
+        let (small, large) = if self.count() <= other.count() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        if small.count() > SPLIT_THRESHOLD {
+            return None;
+        }
+        let values = small.values()?;
+        Some(values.iter().fold(BDD::null(), |acc, n| acc.join(&op(large, *n))))
+    }
+
+    // Computes the operation on the StridedIntervals of both operands
+    fn via_strided_interval<F>(&self, other: &Self, op: F) -> Self
+        where F: Fn(StridedInterval, StridedInterval) -> StridedInterval
+    {
+        BDD::from_strided_interval(&op(self.to_strided_interval(),
+                                       other.to_strided_interval()))
+    }
+
+    // Returns the set of all numbers whose known bits are (zeros, ones)
+    fn from_known_bits(k: u8, zeros: unum, ones: unum) -> Self {
+        let root = BDD::with_manager(|m| m.bits_eq(k, zeros | ones, ones));
+        BDD::from_root(k, root)
+    }
+}
+
+
+//
+// Operations for BDD go here
+//
+
+impl Default for BDD {
+    fn default() -> Self {
+        BDD::default_k(_bits)
+    }
+}
+
+// Generate BDD by a constant
+impl From<inum> for BDD {
+    fn from(number: inum) -> Self {
+        BDD::from((_bits, number))
+    }
+}
+
+impl From<(u8, inum)> for BDD {
+    fn from(number_k: (u8, inum)) -> Self {
+        BDD::from_values(number_k.0, &[number_k.1])
+    }
+}
+
+impl Container<inum> for BDD {
+    fn contains(&self, object: &inum) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let x = to_pattern(*object, self.k);
+        if to_signed(x, self.k) != *object {
+            // object cannot be hold by k bits
+            return false;
+        }
+        let (k, root) = (self.k, self.root);
+        BDD::with_manager(|m| m.eval(root, k, x))
+    }
+}
+
+impl Container<BDD> for BDD {
+    fn contains(&self, object: &BDD) -> bool {
+        if object.is_empty() {
+            true
+        } else if self.is_empty() || self.k != object.k {
+            false
+        } else {
+            let (a, b) = (self.root, object.root);
+            BDD::with_manager(|m| {
+                let outside = m.not(a);
+                m.and(b, outside) == FALSE
+            })
+        }
+    }
+}
+
+impl Container<Vec<inum>> for BDD {
+    fn contains(&self, object: &Vec<inum>) -> bool {
+        object.iter().fold(true, |acc, x| acc & self.contains(x))
+    }
+}
+
+impl Neg for BDD {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        if self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else {
+            // -x == !x + 1
+            (!self).add_constant(1)
+        }
+    }
+}
+
+impl Add for BDD {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if self.k != other.k {
+            radeco_err!("Addition between two BDDs with different radices");
+            BDD::default()
+        } else if let Some(res) = self.split_binop(&other, |x, n| x.add_constant(n)) {
+            res
+        } else {
+            self.via_strided_interval(&other, |x, y| x + y)
+        }
+    }
+}
+
+impl Sub for BDD {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + (-other)
+    }
+}
+
+impl Mul for BDD {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            return BDD::default();
+        } else if self.k != other.k {
+            radeco_err!("Multiplication between two BDDs with different radices");
+            return BDD::default();
+        }
+        // Multiplication by (the negation of) a power of two is a shift
+        if self.constant().is_some() || other.constant().is_some() {
+            let (cons, set) = if self.constant().is_some() {
+                (&self, &other)
+            } else {
+                (&other, &self)
+            };
+            let n = cons.constant().expect("BDD is not a constant");
+            let k = self.k;
+            if n == 0 {
+                return BDD::from((k, 0));
+            } else if to_pattern(n, k).is_power_of_two() {
+                return set.shl_constant(to_pattern(n, k).trailing_zeros() as u8);
+            } else if to_pattern(n.wrapping_neg(), k).is_power_of_two() {
+                return -set.shl_constant(to_pattern(n.wrapping_neg(), k).trailing_zeros() as u8);
+            }
+        }
+        if let Some(res) = self.enumerate_binop(&other, |x, y| x.wrapping_mul(y)) {
+            res
+        } else {
+            self.via_strided_interval(&other, |x, y| x * y)
+        }
+    }
+}
+
+impl Div for BDD {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if self.k != other.k {
+            radeco_err!("Division between two BDDs with different radices");
+            BDD::default()
+        } else if other.contains(&0) {
+            // Divided by zero
+            radeco_err!("Divied by zero");
+            BDD::default_k(self.k)
+        } else if let Some(res) = self.enumerate_binop(&other, |x, y| x.wrapping_div(y)) {
+            res
+        } else {
+            self.via_strided_interval(&other, |x, y| x / y)
+        }
+    }
+}
+
+impl Rem for BDD {
+    type Output = Self;
+
+    fn rem(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if self.k != other.k {
+            radeco_err!("Remainder between two BDDs with different radices");
+            BDD::default()
+        } else if other.contains(&0) {
+            // Divided by zero
+            radeco_err!("Divied by zero");
+            BDD::default_k(self.k)
+        } else if let Some(res) = self.enumerate_binop(&other, |x, y| x.wrapping_rem(y)) {
+            res
+        } else {
+            match other.constant() {
+                // Non-negative numbers modulo a power of two is a mask
+                Some(n) if n > 0 && (n & (n - 1)) == 0 && self.min().unwrap_or(-1) >= 0 => {
+                    self.and_constant(n - 1)
+                }
+                _ => self.via_strided_interval(&other, |x, y| x % y),
+            }
+        }
+    }
+}
+
+impl BitAnd for BDD {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if self.k != other.k {
+            radeco_err!("BitAnd between two BDDs with different radices");
+            BDD::default()
+        } else if let Some(res) = self.split_binop(&other, |x, n| x.and_constant(n)) {
+            res
+        } else {
+            let (zeros_a, ones_a) = self.known_bits();
+            let (zeros_b, ones_b) = other.known_bits();
+            BDD::from_known_bits(self.k, zeros_a | zeros_b, ones_a & ones_b)
+        }
+    }
+}
+
+impl BitOr for BDD {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if self.k != other.k {
+            radeco_err!("BitOr between two BDDs with different radices");
+            BDD::default()
+        } else if let Some(res) = self.split_binop(&other, |x, n| x.or_constant(n)) {
+            res
+        } else {
+            let (zeros_a, ones_a) = self.known_bits();
+            let (zeros_b, ones_b) = other.known_bits();
+            BDD::from_known_bits(self.k, zeros_a & zeros_b, ones_a | ones_b)
+        }
+    }
+}
+
+impl BitXor for BDD {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if self.k != other.k {
+            radeco_err!("BitXor between two BDDs with different radices");
+            BDD::default()
+        } else if let Some(res) = self.split_binop(&other, |x, n| x.xor_constant(n)) {
+            res
+        } else {
+            let (zeros_a, ones_a) = self.known_bits();
+            let (zeros_b, ones_b) = other.known_bits();
+            let known = (zeros_a | ones_a) & (zeros_b | ones_b);
+            let ones = (ones_a ^ ones_b) & known;
+            BDD::from_known_bits(self.k, known & !ones, ones)
+        }
+    }
+}
+
+impl Not for BDD {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        if self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else {
+            self.xor_constant(-1)
+        }
+    }
+}
+
+impl Shr for BDD {
+    type Output = Self;
+
+    // All shift right in ESIl is logical shift
+    fn shr(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if other.min().unwrap_or(0) < 0 {
+            radeco_err!("Bitwise shift's operation cannot be negative");
+            BDD::default_k(self.k)
+        } else {
+            let mut res = BDD::null();
+            for i in 0..self.k {
+                if other.contains(&(i as inum)) {
+                    res = res.join(&self.shr_constant(i));
+                }
+            }
+            if other.max().unwrap_or(0) >= self.k as inum {
+                res = res.join(&BDD::from((self.k, 0)));
+            }
+            res
+        }
+    }
+}
+
+impl Shl for BDD {
+    type Output = Self;
+
+    fn shl(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            BDD::default()
+        } else if other.min().unwrap_or(0) < 0 {
+            radeco_err!("Bitwise shift's operation cannot be negative");
+            BDD::default_k(self.k)
+        } else {
+            let mut res = BDD::null();
+            for i in 0..self.k {
+                if other.contains(&(i as inum)) {
+                    res = res.join(&self.shl_constant(i));
+                }
+            }
+            if other.max().unwrap_or(0) >= self.k as inum {
+                res = res.join(&BDD::from((self.k, 0)));
+            }
+            res
+        }
+    }
+}
+
+// Implement trait AbstractSet for BDD
+impl AbstractSet for BDD {
+    fn meet(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            // meet an empty set --> empty set
+            BDD::null()
+        } else if self.k != other.k {
+            radeco_err!("Meet two BDDs with different radices");
+            BDD::default()
+        } else {
+            let (a, b) = (self.root, other.root);
+            BDD::from_root(self.k, BDD::with_manager(|m| m.and(a, b)))
+        }
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            other.clone()
+        } else if other.is_empty() {
+            self.clone()
+        } else if self.k != other.k {
+            radeco_err!("Join two BDDs with different radices");
+            BDD::default()
+        } else {
+            let (a, b) = (self.root, other.root);
+            BDD::from_root(self.k, BDD::with_manager(|m| m.or(a, b)))
+        }
+    }
+
+    // The lattice of BDDs is too high, thus the bounds are widened as what
+    // StridedInterval does once the set grows.
+    fn widen(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            other.clone()
+        } else if other.is_empty() {
+            self.clone()
+        } else if self.k != other.k {
+            radeco_err!("Widen two BDDs with different radices");
+            BDD::default()
+        } else if self.contains(other) {
+            self.clone()
+        } else {
+            let joined = self.join(other);
+            let si = self.to_strided_interval().widen(&joined.to_strided_interval());
+            joined.join(&BDD::from_strided_interval(&si))
+        }
+    }
+
+    fn remove_lower_bound(&self) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else {
+            let si = self.to_strided_interval();
+            self.join(&BDD::from_strided_interval(&si.remove_lower_bound()))
+        }
+    }
+
+    fn set_lower_bound(&self, x: inum) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else if x > self.max().unwrap_or(0) {
+            radeco_warn!("Set a lower bound which is bigger than upper bound");
+            BDD::null()
+        } else {
+            let lb = cmp::max(x, min_in_k(self.k));
+            self.meet(&BDD::interval(self.k, lb, max_in_k(self.k)))
+        }
+    }
+
+    fn remove_upper_bound(&self) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else {
+            let si = self.to_strided_interval();
+            self.join(&BDD::from_strided_interval(&si.remove_upper_bound()))
+        }
+    }
+
+    fn set_upper_bound(&self, x: inum) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else if x < self.min().unwrap_or(0) {
+            radeco_warn!("Set a upper bound which is smaller than lower bound");
+            BDD::null()
+        } else {
+            let ub = cmp::min(x, max_in_k(self.k));
+            self.meet(&BDD::interval(self.k, min_in_k(self.k), ub))
+        }
+    }
+
+    fn narrow(&self, k: u8) -> Self {
+        if self.is_empty() {
+            radeco_warn!("Empty BDD cannot be narrow");
+            self.clone()
+        } else if k > self.k {
+            radeco_warn!("BDD cannot be narrowed to a bigger bits");
+            self.clone()
+        } else if k == self.k {
+            self.clone()
+        } else if k == 0 {
+            BDD::null()
+        } else {
+            // Drop the highest (self.k - k) bits
+            let d = self.k - k;
+            let root = self.root;
+            let root = BDD::with_manager(|m| {
+                let r = m.exists(root, mask(d), &mut HashMap::new());
+                m.rename(r, -(d as i16), &mut HashMap::new())
+            });
+            BDD::from_root(k, root)
+        }
+    }
+
+    fn sign_extend(&self, k: u8) -> Self {
+        if self.is_empty() {
+            radeco_warn!("Empty BDD cannot be extended");
+            self.clone()
+        } else if k < self.k {
+            radeco_warn!("BDD cannot be extended to a smaller bits");
+            self.clone()
+        } else if k == self.k {
+            self.clone()
+        } else {
+            let k = cmp::min(k, _bits);
+            // The new highest d bits (variables 0 .. d) equal the old sign bit
+            // (variable d)
+            let d = k - self.k;
+            let root = self.root;
+            let root = BDD::with_manager(|m| {
+                let r = m.rename(root, d as i16, &mut HashMap::new());
+                let mut neg = m.mk(d, FALSE, TRUE);
+                let mut pos = m.mk(d, TRUE, FALSE);
+                for var in (1..d).rev() {
+                    neg = m.mk(var, FALSE, neg);
+                    pos = m.mk(var, pos, FALSE);
+                }
+                let ext = m.mk(0, pos, neg);
+                m.and(r, ext)
+            });
+            BDD::from_root(k, root)
+        }
+    }
+
+    fn zero_extend(&self, k: u8) -> Self {
+        if self.is_empty() {
+            radeco_warn!("Empty BDD cannot be extended");
+            self.clone()
+        } else if k < self.k {
+            radeco_warn!("BDD cannot be extended to a smaller bits");
+            self.clone()
+        } else if k == self.k {
+            self.clone()
+        } else {
+            let k = cmp::min(k, _bits);
+            let d = k - self.k;
+            let (old_k, root) = (self.k, self.root);
+            let root = BDD::with_manager(|m| {
+                let r = m.rename(root, d as i16, &mut HashMap::new());
+                let ext = m.bits_eq(k, mask(k) & !mask(old_k), 0);
+                m.and(r, ext)
+            });
+            BDD::from_root(k, root)
+        }
+    }
+
+    fn constant(&self) -> Option<inum> {
+        if self.count() == 1 {
+            self.min()
+        } else {
+            None
+        }
+    }
+
+    // XXX: saturated when capacity > inum::max_value()
+    fn capacity(&self) -> inum {
+        cmp::min(self.count(), inum::max_value() as unum) as inum
+    }
+
+    fn is_empty(&self) -> bool {
+        self.k == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+    use quickcheck::TestResult;
+
+    // Returns the corresponding number of n in k bits
+    fn wrap(n: inum, k: u8) -> inum {
+        to_signed(to_pattern(n, k), k)
+    }
+
+    // Checks that `res` contains `op(x, y)` for every x in `a` and y in `b`
+    fn sound<F>(res: BDD, a: &[inum], b: &[inum], k: u8, op: F) -> bool
+        where F: Fn(inum, inum) -> inum
+    {
+        a.iter().all(|x| b.iter().all(|y| res.contains(&wrap(op(*x, *y), k))))
+    }
+
+    // Checks that `res` is exactly {op(x) | lb <= x <= ub}
+    fn exact<F>(res: BDD, lb: inum, ub: inum, k: u8, op: F) -> bool
+        where F: Fn(inum) -> inum
+    {
+        let expected: HashSet<inum> = (lb..ub + 1).map(|x| wrap(op(x), k)).collect();
+        res.capacity() == expected.len() as inum && expected.iter().all(|x| res.contains(x))
+    }
+
+    #[test]
+    fn bdd_test_basicfn() {
+        assert_eq!(0x7f, max_in_k(8));
+        assert_eq!(-0x80, min_in_k(8));
+        assert_eq!(inum::min_value(), min_in_k(_bits));
+        assert_eq!(0xff, to_pattern(-1, 8));
+        assert_eq!(-1, to_signed(0xff, 8));
+        assert_eq!(0x80, vars_of_bits(1, 8));
+        assert_eq!(BDD::null(), BDD::from_values(8, &[]));
+        assert!(BDD::null().is_empty());
+        assert_eq!(BDD::default_k(8), BDD::interval(8, -128, 127));
+        assert_eq!(256, BDD::default_k(8).capacity());
+        assert_eq!(inum::max_value(), BDD::default().capacity());
+        assert_eq!(Some(0xdeadbeef), BDD::from(0xdeadbeef).constant());
+        assert_eq!(Some(-1), BDD::from((8, 0xff)).constant());
+        assert_eq!(None, BDD::interval(8, 0, 1).constant());
+
+        let op = BDD::interval(64, 0, 4096);
+        assert_eq!(4097, op.capacity());
+        assert!(op.contains(&1024));
+        assert!(!op.contains(&-4));
+        assert!(!op.contains(&4097));
+        assert!(op.contains(&vec![0, 4, 16, 20]));
+        assert!(!op.contains(&vec![0, 4, -16, 20]));
+        assert!(op.contains(&BDD::interval(64, 16, 32)));
+        assert!(!op.contains(&BDD::interval(64, -16, 32)));
+        assert!(op.contains(&BDD::null()));
+        assert!(!BDD::null().contains(&op));
+        assert!(!BDD::from((8, 1)).contains(&257));
+
+        let op = BDD::from_values(8, &[5, -3, 100]);
+        assert_eq!(Some(-3), op.min());
+        assert_eq!(Some(100), op.max());
+        assert_eq!(Some(vec![5, 100, -3]), op.values());
+        assert_eq!(None, BDD::default().values());
+
+        let si = StridedInterval::new(16, 4, 0, 64);
+        assert_eq!(si, BDD::from_strided_interval(&si).to_strided_interval());
+        let op = BDD::from_strided_interval(&StridedInterval::new(16, 3, 0, 30));
+        assert_eq!(11, op.capacity());
+        assert!(op.contains(&27));
+        assert!(!op.contains(&28));
+    }
+
+    #[test]
+    fn bdd_test_add() {
+        let op1 = BDD::from_values(8, &[1, 2]);
+        let op2 = BDD::from_values(8, &[10, 20]);
+        assert_eq!(BDD::from_values(8, &[11, 12, 21, 22]), op1 + op2);
+
+        let op1 = BDD::from((8, 127));
+        let op2 = BDD::from((8, 1));
+        assert_eq!(BDD::from((8, -128)), op1 + op2);
+
+        let op1 = BDD::interval(64, 0, 1000);
+        let op2 = BDD::from((64, 5));
+        assert_eq!(BDD::interval(64, 5, 1005), op1 + op2);
+
+        let op1 = BDD::interval(16, 0x7ff0, 0x7fff);
+        let op2 = BDD::from((16, 0x20));
+        assert_eq!(BDD::interval(16, -0x7ff0, -0x7fe1), op1 + op2);
+
+        let op1 = BDD::interval(8, 120, 127);
+        let op2 = BDD::from((8, 4));
+        assert_eq!(BDD::interval(8, 124, 127).join(&BDD::interval(8, -128, -125)),
+                   op1 + op2);
+    }
+
+    #[test]
+    fn bdd_test_neg() {
+        let op = BDD::interval(8, -128, 2);
+        assert_eq!(BDD::interval(8, -2, 127).join(&BDD::from((8, -128))), -op);
+
+        let op = BDD::from((8, -128));
+        assert_eq!(op, -op);
+
+        let op = BDD::interval(64, 0xbadcaffe, 0xdeadbeef);
+        assert_eq!(BDD::interval(64, -0xdeadbeef, -0xbadcaffe), -op);
+    }
+
+    #[test]
+    fn bdd_test_sub() {
+        let op1 = BDD::interval(8, 1, 7);
+        let op2 = BDD::interval(8, 2, 6);
+        assert_eq!(BDD::interval(8, -5, 5), op1 - op2);
+
+        let op1 = BDD::from((8, -128));
+        let op2 = BDD::from((8, 1));
+        assert_eq!(BDD::from((8, 127)), op1 - op2);
+    }
+
+    #[test]
+    fn bdd_test_mul() {
+        let op1 = BDD::from_values(8, &[3, 5]);
+        let op2 = BDD::from((8, -3));
+        assert_eq!(BDD::from_values(8, &[-9, -15]), op1 * op2);
+
+        let op1 = BDD::interval(64, 0, 1000);
+        let op2 = BDD::from((64, 8));
+        let res = op1 * op2;
+        assert_eq!(1001, res.capacity());
+        assert_eq!(StridedInterval::new(64, 8, 0, 8000), res.to_strided_interval());
+
+        let op1 = BDD::interval(8, 0x10, 0x13);
+        let op2 = BDD::from((8, -2));
+        assert_eq!(BDD::from_values(8, &[-32, -34, -36, -38]), op1 * op2);
+
+        let op1 = BDD::interval(8, 0x10, 0x13);
+        let op2 = BDD::from((8, 0));
+        assert_eq!(BDD::from((8, 0)), op1 * op2);
+    }
+
+    #[test]
+    fn bdd_test_div() {
+        let op1 = BDD::from_values(8, &[-7, 7]);
+        let op2 = BDD::from((8, 2));
+        assert_eq!(BDD::from_values(8, &[-3, 3]), op1 / op2);
+
+        let op1 = BDD::from((8, -7));
+        let op2 = BDD::from_values(8, &[0, 1]);
+        assert_eq!(BDD::default_k(8), op1 / op2);
+
+        let op1 = BDD::from((8, -128));
+        let op2 = BDD::from((8, -1));
+        assert_eq!(BDD::from((8, -128)), op1 / op2);
+    }
+
+    #[test]
+    fn bdd_test_rem() {
+        let op1 = BDD::from_values(8, &[-7, 7]);
+        let op2 = BDD::from((8, 4));
+        assert_eq!(BDD::from_values(8, &[-3, 3]), op1 % op2);
+
+        let op1 = BDD::interval(64, 0, 1000);
+        let op2 = BDD::from((64, 16));
+        assert_eq!(BDD::interval(64, 0, 15), op1 % op2);
+
+        let op1 = BDD::interval(64, 0, 100000);
+        let op2 = BDD::from((64, 16));
+        assert_eq!(BDD::interval(64, 0, 15), op1 % op2);
+
+        let op1 = BDD::from((8, -7));
+        let op2 = BDD::from((8, 0));
+        assert_eq!(BDD::default_k(8), op1 % op2);
+    }
+
+    #[test]
+    fn bdd_test_bitop() {
+        let op1 = BDD::interval(8, 0, 0x10);
+        assert_eq!(BDD::interval(8, -17, -1), !op1);
+
+        let op1 = BDD::from((8, 0x0f));
+        let op2 = BDD::interval(8, 0x31, 0x3a);
+        assert_eq!(BDD::interval(8, 1, 0xa), op1 & op2);
+        assert_eq!(BDD::interval(8, 1, 0xa), op2 & op1);
+
+        let op1 = BDD::from((8, 0xff));
+        let op2 = BDD::from_values(8, &[4, 12, 68]);
+        assert_eq!(op2, op1 & op2);
+        assert_eq!(!op2, op1 ^ op2);
+
+        let op1 = BDD::from_values(8, &[1, 3, 5]);
+        let op2 = BDD::from_values(8, &[3, 9]);
+        assert_eq!(BDD::from_values(8, &[3, 7, 9, 11, 13]), op1 | op2);
+
+        let op1 = BDD::interval(16, -300, 1000);
+        let op2 = BDD::from((16, 0x1234));
+        assert_eq!(op1, (op1 ^ op2) ^ op2);
+
+        // Both are too big to split, only known bits are kept
+        let op1 = BDD::interval(16, 0, 0xfff);
+        let op2 = BDD::interval(16, 0x100, 0x1ff);
+        assert_eq!(BDD::interval(16, 0, 0x1ff), op1 & op2);
+
+        let op1 = BDD::from((4, 0x1));
+        let op2 = BDD::from((8, 0x6));
+        assert_eq!(BDD::default(), op1 | op2);
+
+        let op1 = BDD::from_values(8, &[1, 3]);
+        let op2 = BDD::from_values(8, &[0, 2]);
+        assert_eq!(BDD::from_values(8, &[1, 3, 4, 12]), op1 << op2);
+
+        let op1 = BDD::from((8, 1));
+        let op2 = BDD::from((8, 8));
+        assert_eq!(BDD::from((8, 0)), op1 << op2);
+
+        let op1 = BDD::from((8, -1));
+        let op2 = BDD::from((8, 4));
+        assert_eq!(BDD::from((8, 0x0f)), op1 >> op2);
+
+        let op1 = BDD::from((8, -128));
+        let op2 = BDD::from((8, 7));
+        assert_eq!(BDD::from((8, 1)), op1 >> op2);
+    }
+
+    #[test]
+    fn bdd_test_setop() {
+        let op1 = BDD::interval(16, 1, 100);
+        let op2 = BDD::interval(16, 50, 200);
+        let op3 = BDD::interval(16, 300, 400);
+        assert_eq!(BDD::interval(16, 50, 100), op1.meet(&op2));
+        assert_eq!(BDD::null(), op1.meet(&op3));
+        assert_eq!(BDD::null(), op1.meet(&BDD::null()));
+        assert_eq!(BDD::interval(16, 1, 200), op1.join(&op2));
+        assert_eq!(op1, op1.join(&BDD::null()));
+        assert_eq!(op1, BDD::null().join(&op1));
+
+        let op1 = BDD::interval(16, 1, 901);
+        assert_eq!(BDD::null(), op1.set_lower_bound(902));
+        assert_eq!(BDD::interval(16, 2, 901), op1.set_lower_bound(2));
+        assert_eq!(BDD::null(), op1.set_upper_bound(-1));
+        assert_eq!(BDD::interval(16, 1, 33), op1.set_upper_bound(33));
+
+        let op1 = BDD::interval(16, 0, 10);
+        let op2 = BDD::interval(16, 0, 20);
+        assert_eq!(BDD::interval(16, 0, 0x7fff), op1.widen(&op2));
+        assert_eq!(op2, op2.widen(&op1));
+        assert_eq!(op1, op1.widen(&BDD::null()));
+        assert_eq!(op1, BDD::null().widen(&op1));
+
+        let op1 = BDD::interval(8, 0, 10);
+        assert_eq!(BDD::interval(8, 0, 127), op1.remove_upper_bound());
+        assert_eq!(BDD::interval(8, -128, 10), op1.remove_lower_bound());
+
+        let op1 = BDD::interval(16, 0x100, 0x103);
+        assert_eq!(BDD::interval(8, 0, 3), op1.narrow(8));
+
+        let op1 = BDD::interval(16, 0xfe, 0x101);
+        assert_eq!(BDD::interval(8, -2, 1), op1.narrow(8));
+
+        let op1 = BDD::interval(8, -2, 1);
+        assert_eq!(BDD::interval(16, -2, 1), op1.sign_extend(16));
+        assert_eq!(BDD::interval(16, 0, 1).join(&BDD::interval(16, 0xfe, 0xff)),
+                   op1.zero_extend(16));
+    }
+
+    #[quickcheck]
+    fn qc_bdd_arith(a: Vec<i8>, b: Vec<i8>) -> TestResult {
+        // Keep operands small enough to be enumerated
+        let a: Vec<inum> = a.into_iter().take(32).map(|x| x as inum).collect();
+        let b: Vec<inum> = b.into_iter().take(32).map(|x| x as inum).collect();
+        if a.is_empty() || b.is_empty() {
+            return TestResult::discard();
+        }
+        let (x, y) = (BDD::from_values(8, &a), BDD::from_values(8, &b));
+        let sums: Vec<inum> = a.iter().flat_map(|i| b.iter().map(move |j| i + j)).collect();
+        let mut res = x + y == BDD::from_values(8, &sums) &&
+                      sound(x + y, &a, &b, 8, |i, j| i + j) &&
+                      sound(x - y, &a, &b, 8, |i, j| i - j) &&
+                      sound(x * y, &a, &b, 8, |i, j| i * j) &&
+                      sound(x & y, &a, &b, 8, |i, j| i & j) &&
+                      sound(x | y, &a, &b, 8, |i, j| i | j) &&
+                      sound(x ^ y, &a, &b, 8, |i, j| i ^ j);
+        if !b.contains(&0) {
+            res = res && sound(x / y, &a, &b, 8, |i, j| i / j) &&
+                  sound(x % y, &a, &b, 8, |i, j| i % j);
+        }
+        let c: Vec<inum> = b.iter().map(|j| j & 7).collect();
+        let z = BDD::from_values(8, &c);
+        res = res && sound(x << z, &a, &c, 8, |i, j| i << j) &&
+              sound(x >> z, &a, &c, 8, |i, j| (to_pattern(i, 8) >> j) as inum);
+        TestResult::from_bool(res)
+    }
+
+    #[quickcheck]
+    fn qc_bdd_constant(lb: i16, span: u16, c: i16) -> bool {
+        let lb = lb as inum;
+        let ub = cmp::min(lb + (span % 0x800) as inum, 0x7fff);
+        let c = c as inum;
+        let op = BDD::interval(16, lb, ub);
+        let i = (c & 0xf) as u8;
+        exact(op.add_constant(c), lb, ub, 16, |x| x + c) &&
+        exact(op.and_constant(c), lb, ub, 16, |x| x & c) &&
+        exact(op.or_constant(c), lb, ub, 16, |x| x | c) &&
+        exact(op.xor_constant(c), lb, ub, 16, |x| x ^ c) &&
+        exact(op.shl_constant(i), lb, ub, 16, |x| x << i) &&
+        exact(op.shr_constant(i), lb, ub, 16, |x| (to_pattern(x, 16) >> i) as inum) &&
+        exact(-op, lb, ub, 16, |x| -x) &&
+        exact(!op, lb, ub, 16, |x| !x)
+    }
 
-// TODO
+    #[quickcheck]
+    fn qc_bdd_extend(lb: i16, span: u16) -> bool {
+        let lb = lb as inum;
+        let ub = cmp::min(lb + (span % 0x800) as inum, 0x7fff);
+        let op = BDD::interval(16, lb, ub);
+        exact(op.sign_extend(32), lb, ub, 32, |x| x) &&
+        exact(op.zero_extend(32), lb, ub, 32, |x| to_pattern(x, 16) as inum) &&
+        exact(op.narrow(8), lb, ub, 8, |x| x) &&
+        op.to_strided_interval().contains(&(lb..ub + 1).collect::<Vec<inum>>())
+    }
+}
@@ -26,16 +26,18 @@ use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
 use std::fmt::Debug;
 use std::hash::Hash;
 
-// XXX: Try to support 128-bit registers in the future
-// type inum = i128; <-- this one do influence performance
-// const full_bits: u8 = 128;
+// 128 bits are needed for SSE registers and wide multiplications. Values
+// wider than 128 bits, like AVX registers, are not supported by abstract sets,
+// they are top. Only SCCP folds them, see `middle::wide`. There is no wider
+// type to compute in, thus domains which need one, like polynomials, hold
+// fewer bits, see `AbstractSet::max_k`.
 
 /// All numbers (except k) in AbstractSet will be stored as `inum` type
-pub type inum = i64;
+pub type inum = i128;
 /// Unsigned type according to inum
-pub type unum = u64;
+pub type unum = u128;
 /// All members (except k) in AbstractSet will be hold in `_bits` bits
-pub const _bits: u8 = 64;
+pub const _bits: u8 = 128;
 
 /// Trait used to indicate this is a container which contains something
 pub trait Container<T: Clone> {
@@ -55,8 +57,10 @@ pub enum AbstractSetType {
 }
 
 pub trait AbstractSet: Copy + Clone + Debug + PartialEq + Eq + Hash +
-                        Neg + Add + Sub + Div + Rem + Mul +
-                        BitAnd + BitOr + BitXor + Not + Shl + Shr +
+                        Neg<Output = Self> + Add<Output = Self> + Sub<Output = Self> +
+                        Div<Output = Self> + Rem<Output = Self> + Mul<Output = Self> +
+                        BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> +
+                        Not<Output = Self> + Shl<Output = Self> + Shr<Output = Self> +
                         Container<inum> + Container<Self> + Container<Vec<inum>> +
                         From<inum> + From<(u8, inum)> + Default
 {
@@ -124,4 +128,9 @@ pub trait AbstractSet: Copy + Clone + Debug + PartialEq + Eq + Hash +
     fn is_empty(&self) -> bool {
         unimplemented!()
     }
+
+    /// Returns the widest k-bits-filed the AbstractSet could hold.
+    fn max_k() -> u8 {
+        _bits
+    }
 }
@@ -10,7 +10,1095 @@
 //! A polynomial abstract set goes like:
 //!     base + [a1]x1 + [a2]x2 + ... + [an]xn
 //! which means:
-//!     {base + k1 * x1 + k2 * x2 + ... + kn * xn | 0 <= ki <= ai}     
+//!     {base + k1 * x1 + k2 * x2 + ... + kn * xn | 0 <= ki <= ai}
+//!
+//! Without loss of generality, we will assume that all polynomials are
+//! normalized, i.e. every xi and ai is positive (thus base is the smallest
+//! member), terms are sorted by xi and no two terms could be merged exactly.
+//! Besides, all members of a polynomial are inside the k-bits-filed, a
+//! polynomial wrapping around the k-bits-filed is over-approximated by the
+//! k-bits top.
+//!
+//! Addition, subtraction, negation and multiplication (or division without
+//! remainder) by a constant are exact. Other operations are computed on
+//! strided intervals.
+
+use std::fmt;
+use std::cmp;
+use std::ops::{Neg, Add, Sub, Div, Rem, Mul};
+use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+
+use super::abstract_set::{inum, unum};
+use super::abstract_set::{AbstractSet, Container};
+use super::strided_interval::StridedInterval;
+
+/// Max number of terms in a polynomial, more terms will be merged
+/// approximately.
+pub const MAX_TERMS: usize = 4;
+
+// Max number of steps to decide whether a number is a member, after which
+// the number is assumed to be a member.
+const SEARCH_LIMIT: usize = 0x10000;
+// Polynomials whose capacities are not bigger than this could be enumerated.
+const ENUM_THRESHOLD: inum = 0x400;
+
+// Bounds of polynomials are computed in 128 bits, twice POLY_BITS, to avoid
+// overflow. This is the same type as inum.
+#[allow(non_camel_case_types)]
+type wnum = i128;
+
+/// Polynomials are at most POLY_BITS bits, so that their bounds could be
+/// computed in wnum.
+pub const POLY_BITS: u8 = 64;
+
+/// Euclid's two-thousand-year-old algorithm for finding the greatest common
+/// divisor.
+fn gcd(x: wnum, y: wnum) -> wnum {
+    let mut x = x;
+    let mut y = y;
+    while y != 0 {
+        let t = y;
+        y = x % y;
+        x = t;
+    }
+    x.abs()
+}
+
+fn min_in_k(k: u8) -> wnum {
+    -((1 as wnum) << (k - 1))
+}
+
+fn max_in_k(k: u8) -> wnum {
+    ((1 as wnum) << (k - 1)) - 1
+}
+
+// Returns the corresponding number of n in k bits
+fn in_k(n: wnum, k: u8) -> wnum {
+    let period = (1 as wnum) << k;
+    let r = ((n % period) + period) % period;
+    if r > max_in_k(k) {
+        r - period
+    } else {
+        r
+    }
+}
+
+/// A k-bits polynomial base + [a1]x1 + ... + [an]xn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Polynomial {
+    // Used for indicate radix of polynomial (k-bits)
+    // k == 0 means this Polynomial is an empty set
+    pub k: u8,
+    // The smallest member
+    pub base: inum,
+    // Number of used terms
+    pub len: u8,
+    // (xi, ai) of terms, sorted by xi. Unused terms are (0, 0)
+    pub terms: [(unum, unum); MAX_TERMS],
+}
+
+
+//
+// Utility functions go here
+//
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-bits: {}", self.k, self.base)?;
+        for &(x, a) in self.used_terms() {
+            write!(f, " + [{}]{}", a, x)?;
+        }
+        Ok(())
+    }
+}
+
+
+//
+// Basic functions for Polynomial go here
+//
+
+impl Polynomial {
+    /// Returns the empty set of Polynomial.
+    pub fn null() -> Self {
+        Polynomial {
+            k: 0,
+            base: 0,
+            len: 0,
+            terms: [(0, 0); MAX_TERMS],
+        }
+    }
+
+    /// Returns a polynomial containing all numbers in k bits.
+    pub fn default_k(k: u8) -> Self {
+        if k == 0 {
+            return Polynomial::null();
+        }
+        let k = cmp::min(k, POLY_BITS);
+        let mut terms = [(0, 0); MAX_TERMS];
+        terms[0] = (1, (((1 as wnum) << k) - 1) as unum);
+        Polynomial {
+            k: k,
+            base: min_in_k(k) as inum,
+            len: 1,
+            terms: terms,
+        }
+    }
+
+    /// Returns the polynomial base + [a1]x1 + ... + [an]xn, in which terms
+    /// are (xi, ai).
+    pub fn new(k: u8, base: inum, terms: &[(inum, inum)]) -> Self {
+        if k == 0 {
+            return Polynomial::null();
+        }
+        let k = cmp::min(k, POLY_BITS);
+        let terms = terms.iter().map(|&(x, a)| (in_k(x, k), a as wnum)).collect();
+        Polynomial::from_wide(k, in_k(base, k), terms)
+    }
+
+    // Normalizes the polynomial. Algorithm's correctness is heavily depended
+    // on this function.
+    fn from_wide(k: u8, base: wnum, terms: Vec<(wnum, wnum)>) -> Self {
+        if k == 0 {
+            return Polynomial::null();
+        }
+        let k = cmp::min(k, POLY_BITS);
+        let period = (1 as wnum) << k;
+
+        // Make all xi positive, thus base is the smallest member
+        let mut base = base;
+        let mut span: wnum = 0;
+        let mut ts: Vec<(wnum, wnum)> = Vec::new();
+        for (x, a) in terms {
+            if a < 0 {
+                radeco_warn!("Term [{:?}]{:?} has a negative number", a, x);
+                continue;
+            }
+            if (x == 0) || (a == 0) {
+                continue;
+            }
+            let d = match x.checked_mul(a) {
+                Some(d) => d,
+                None => return Polynomial::default_k(k),
+            };
+            if x < 0 {
+                base = match base.checked_add(d) {
+                    Some(b) => b,
+                    None => return Polynomial::default_k(k),
+                };
+            }
+            span = match span.checked_add(d.abs()) {
+                Some(s) => s,
+                None => return Polynomial::default_k(k),
+            };
+            ts.push((x.abs(), a));
+        }
+        if span >= period {
+            // More members than k bits could hold
+            return Polynomial::default_k(k);
+        }
+
+        loop {
+            ts.sort();
+            if let Some((i, j, t)) = Polynomial::exact_merge(&ts) {
+                ts[i] = t;
+                ts.remove(j);
+            } else if ts.len() > MAX_TERMS {
+                // [a1]x1 + [a2]x2 is a subset of [(a1x1 + a2x2) / g]g,
+                // in which g = gcd(x1, x2)
+                let ((x1, a1), (x2, a2)) = (ts[0], ts[1]);
+                let g = gcd(x1, x2);
+                ts[0] = (g, (x1 * a1 + x2 * a2) / g);
+                ts.remove(1);
+            } else {
+                break;
+            }
+        }
+
+        // Move base into k-bits-filed
+        base = in_k(base, k);
+        if base + span > max_in_k(k) {
+            // Members wrap around the k-bits-filed
+            return Polynomial::default_k(k);
+        }
+
+        let mut terms = [(0, 0); MAX_TERMS];
+        for (i, &(x, a)) in ts.iter().enumerate() {
+            terms[i] = (x as unum, a as unum);
+        }
+        Polynomial {
+            k: k,
+            base: base as inum,
+            len: ts.len() as u8,
+            terms: terms,
+        }
+    }
+
+    // Returns (i, j, t) if the term i and term j could be merged into t
+    // exactly.
+    //      e.g.
+    //          [a1]x1 + [a2]x1 == [a1 + a2]x1
+    //          [a1]x1 + [a2](a1 + 1)x1 == [(a1 + 1)(a2 + 1) - 1]x1
+    fn exact_merge(ts: &[(wnum, wnum)]) -> Option<(usize, usize, (wnum, wnum))> {
+        for i in 0..ts.len() {
+            for j in (i + 1)..ts.len() {
+                let ((x1, a1), (x2, a2)) = (ts[i], ts[j]);
+                if x1 == x2 {
+                    return Some((i, j, (x1, a1 + a2)));
+                } else if x2 == x1 * (a1 + 1) {
+                    return Some((i, j, (x1, (a1 + 1) * (a2 + 1) - 1)));
+                }
+            }
+        }
+        None
+    }
+
+    fn used_terms(&self) -> &[(unum, unum)] {
+        &self.terms[..self.len as usize]
+    }
+
+    fn wide_terms(&self) -> Vec<(wnum, wnum)> {
+        self.used_terms().iter().map(|&(x, a)| (x as wnum, a as wnum)).collect()
+    }
+
+    // a1x1 + ... + anxn
+    fn span(&self) -> wnum {
+        self.wide_terms().iter().fold(0, |acc, &(x, a)| acc + x * a)
+    }
+
+    /// Returns the smallest member.
+    pub fn min(&self) -> Option<inum> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.base)
+        }
+    }
+
+    /// Returns the biggest member.
+    pub fn max(&self) -> Option<inum> {
+        if self.is_empty() {
+            None
+        } else {
+            Some((self.base as wnum + self.span()) as inum)
+        }
+    }
+
+    /// Returns all members of `self`, or None if there are too many of them.
+    pub fn values(&self) -> Option<Vec<inum>> {
+        if self.is_empty() {
+            return Some(Vec::new());
+        }
+        if self.capacity() > ENUM_THRESHOLD {
+            return None;
+        }
+        let mut values = vec![self.base];
+        for &(x, a) in self.used_terms() {
+            let mut next = Vec::with_capacity(values.len() * (a as usize + 1));
+            for v in &values {
+                for i in 0..(a + 1) {
+                    next.push(*v + (i * x) as inum);
+                }
+            }
+            values = next;
+        }
+        values.sort();
+        values.dedup();
+        Some(values)
+    }
+
+    /// Returns the polynomial of StridedInterval `si`.
+    pub fn from_strided_interval(si: &StridedInterval) -> Self {
+        if si.is_empty() {
+            Polynomial::null()
+        } else if si.s == 0 {
+            Polynomial::from((si.k, si.lb))
+        } else {
+            let a = (si.ub as wnum - si.lb as wnum) / si.s as wnum;
+            Polynomial::from_wide(si.k, si.lb as wnum, vec![(si.s as wnum, a)])
+        }
+    }
+
+    /// Returns the smallest StridedInterval containing all members of `self`.
+    pub fn to_strided_interval(&self) -> StridedInterval {
+        if self.is_empty() {
+            StridedInterval::null()
+        } else if self.len == 0 {
+            StridedInterval::from((self.k, self.base))
+        } else {
+            let g = self.wide_terms().iter().fold(0, |acc, &(x, _)| gcd(acc, x));
+            let s = if g > inum::max_value() as wnum {
+                1
+            } else {
+                g as inum
+            };
+            StridedInterval::new(self.k,
+                                 s,
+                                 self.base,
+                                 self.max().unwrap_or(self.base))
+        }
+    }
+
+    // Returns whether d == k1 * x1 + ... + kn * xn, in which 0 <= ki <= ai.
+    // Returns true if budget runs out.
+    fn search(d: wnum, terms: &[(wnum, wnum)], budget: &mut usize) -> bool {
+        match terms.split_last() {
+            None => d == 0,
+            Some((&(x, a), rest)) => {
+                if rest.is_empty() {
+                    return (d % x == 0) && (d / x <= a);
+                }
+                let rest_span = rest.iter().fold(0, |acc, &(x, a)| acc + x * a);
+                // ki should make 0 <= d - ki * x <= rest_span
+                let mut ki = if d > rest_span {
+                    (d - rest_span + x - 1) / x
+                } else {
+                    0
+                };
+                let max_ki = cmp::min(a, d / x);
+                while ki <= max_ki {
+                    if *budget == 0 {
+                        radeco_warn!("Cannot decide whether {:?} is a member", d);
+                        return true;
+                    }
+                    *budget -= 1;
+                    if Polynomial::search(d - ki * x, rest, budget) {
+                        return true;
+                    }
+                    ki += 1;
+                }
+                false
+            }
+        }
+    }
+
+    fn mul_wide(&self, c: wnum) -> Self {
+        let terms = self.wide_terms().into_iter().map(|(x, a)| (x * c, a)).collect();
+        Polynomial::from_wide(self.k, self.base as wnum * c, terms)
+    }
+
+    /// Returns {x * c | x in self}.
+    pub fn mul_constant(&self, c: inum) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else {
+            self.mul_wide(in_k(c, self.k))
+        }
+    }
+
+    /// Returns {x + c | x in self}.
+    pub fn add_constant(&self, c: inum) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else {
+            Polynomial::from_wide(self.k, self.base as wnum + in_k(c, self.k), self.wide_terms())
+        }
+    }
+
+    // Returns a copy of self in k bits, all members must be inside the
+    // k-bits-filed.
+    fn with_k(&self, k: u8) -> Self {
+        Polynomial::from_wide(k, self.base as wnum, self.wide_terms())
+    }
+
+    // Computes the operation on the StridedIntervals of both operands
+    fn via_strided_interval<F>(&self, other: &Self, op: F) -> Self
+        where F: Fn(StridedInterval, StridedInterval) -> StridedInterval
+    {
+        Polynomial::from_strided_interval(&op(self.to_strided_interval(),
+                                              other.to_strided_interval()))
+    }
+
+    // Returns the one with less capacity
+    fn smaller(self, other: Self) -> Self {
+        if other.capacity() < self.capacity() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+
+//
+// Operations for Polynomial go here
+//
+
+impl Default for Polynomial {
+    fn default() -> Self {
+        Polynomial::default_k(POLY_BITS)
+    }
+}
+
+// Generate Polynomial by a constant
+impl From<inum> for Polynomial {
+    fn from(number: inum) -> Self {
+        Polynomial::new(POLY_BITS, number, &[])
+    }
+}
+
+impl From<(u8, inum)> for Polynomial {
+    fn from(number_k: (u8, inum)) -> Self {
+        Polynomial::new(number_k.0, number_k.1, &[])
+    }
+}
+
+impl Container<inum> for Polynomial {
+    fn contains(&self, object: &inum) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let n = *object as wnum;
+        let base = self.base as wnum;
+        // Numbers outside k-bits-filed are not members
+        if (n < base) || (n > max_in_k(self.k)) || (n > base + self.span()) {
+            return false;
+        }
+        let mut budget = SEARCH_LIMIT;
+        Polynomial::search(n - base, &self.wide_terms(), &mut budget)
+    }
+}
+
+// XXX: It might return false when object is a big subset of self
+impl Container<Polynomial> for Polynomial {
+    fn contains(&self, object: &Polynomial) -> bool {
+        if object.is_empty() {
+            true
+        } else if self.is_empty() || (self.k != object.k) {
+            false
+        } else if self == object {
+            true
+        } else if let Some(values) = object.values() {
+            values.iter().all(|v| self.contains(v))
+        } else if self.len <= 1 {
+            // self is a strided interval exactly
+            self.to_strided_interval().contains(&object.to_strided_interval())
+        } else {
+            false
+        }
+    }
+}
+
+impl Container<Vec<inum>> for Polynomial {
+    fn contains(&self, object: &Vec<inum>) -> bool {
+        object.iter().fold(true, |acc, x| acc & self.contains(x))
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        if self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            // -(base + k1x1 + ...) == -max + (a1 - k1)x1 + ...
+            let max = self.base as wnum + self.span();
+            Polynomial::from_wide(self.k, -max, self.wide_terms())
+        }
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else if self.k != other.k {
+            radeco_err!("Addition between two polynomials with different radices");
+            Polynomial::default()
+        } else {
+            let mut terms = self.wide_terms();
+            terms.extend(other.wide_terms());
+            Polynomial::from_wide(self.k, self.base as wnum + other.base as wnum, terms)
+        }
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + (-other)
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else if self.k != other.k {
+            radeco_err!("Multiplication between two polynomials with different radices");
+            Polynomial::default()
+        } else if let Some(n) = other.constant() {
+            self.mul_constant(n)
+        } else if let Some(n) = self.constant() {
+            other.mul_constant(n)
+        } else {
+            self.via_strided_interval(&other, |x, y| x * y)
+        }
+    }
+}
+
+impl Div for Polynomial {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else if self.k != other.k {
+            radeco_err!("Division between two polynomials with different radices");
+            Polynomial::default()
+        } else if other.contains(&0) {
+            // Divided by zero
+            radeco_err!("Divied by zero");
+            Polynomial::default_k(self.k)
+        } else {
+            match other.constant() {
+                // Division without remainder keeps the shape of polynomial
+                Some(n) if (self.base % n == 0) &&
+                           self.used_terms().iter().all(|&(x, _)| x as wnum % n as wnum == 0) => {
+                    let n = n as wnum;
+                    let terms = self.wide_terms().into_iter().map(|(x, a)| (x / n, a)).collect();
+                    Polynomial::from_wide(self.k, self.base as wnum / n, terms)
+                }
+                _ => self.via_strided_interval(&other, |x, y| x / y),
+            }
+        }
+    }
+}
+
+impl Rem for Polynomial {
+    type Output = Self;
+
+    fn rem(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else if self.k != other.k {
+            radeco_err!("Remainder between two polynomials with different radices");
+            Polynomial::default()
+        } else if other.contains(&0) {
+            // Divided by zero
+            radeco_err!("Divied by zero");
+            Polynomial::default_k(self.k)
+        } else {
+            match other.constant() {
+                // All members are in the same residue class of n
+                Some(n) if self.used_terms().iter().all(|&(x, _)| x as wnum % n as wnum == 0) => {
+                    let n = (n as wnum).abs();
+                    let base = self.base as wnum;
+                    let max = base + self.span();
+                    // 0 <= r < n
+                    let r = ((base % n) + n) % n;
+                    if base >= 0 {
+                        Polynomial::from_wide(self.k, r, Vec::new())
+                    } else if (max < 0) || (r == 0) {
+                        Polynomial::from_wide(self.k, base % n, Vec::new())
+                    } else {
+                        // {r - n, r}
+                        Polynomial::from_wide(self.k, r - n, vec![(n, 1)])
+                    }
+                }
+                _ => self.via_strided_interval(&other, |x, y| x % y),
+            }
+        }
+    }
+}
+
+impl BitAnd for Polynomial {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            self.via_strided_interval(&other, |x, y| x & y)
+        }
+    }
+}
+
+impl BitOr for Polynomial {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            self.via_strided_interval(&other, |x, y| x | y)
+        }
+    }
+}
+
+impl BitXor for Polynomial {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            self.via_strided_interval(&other, |x, y| x ^ y)
+        }
+    }
+}
+
+impl Not for Polynomial {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        if self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            // !x == -x - 1
+            (-self).add_constant(-1)
+        }
+    }
+}
+
+impl Shr for Polynomial {
+    type Output = Self;
+
+    // All shift right in ESIl is logical shift
+    fn shr(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            self.via_strided_interval(&other, |x, y| x >> y)
+        }
+    }
+}
+
+impl Shl for Polynomial {
+    type Output = Self;
+
+    fn shl(self, other: Self) -> Self {
+        if other.is_empty() || self.is_empty() {
+            radeco_err!("Empty set cannot be used in arithmetical operation");
+            Polynomial::default()
+        } else {
+            match other.constant() {
+                Some(n) if (n >= 0) && (n < self.k as inum) => self.mul_wide((1 as wnum) << n),
+                _ => self.via_strided_interval(&other, |x, y| x << y),
+            }
+        }
+    }
+}
+
+// Implement trait AbstractSet for Polynomial
+impl AbstractSet for Polynomial {
+    fn meet(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            // meet an empty set --> empty set
+            Polynomial::null()
+        } else if self.k != other.k {
+            radeco_err!("Meet two polynomials with different radices");
+            Polynomial::default()
+        } else if self.contains(other) {
+            other.clone()
+        } else if other.contains(self) {
+            self.clone()
+        } else {
+            // All of them contain the intersection
+            let si = self.to_strided_interval().meet(&other.to_strided_interval());
+            self.smaller(*other).smaller(Polynomial::from_strided_interval(&si))
+        }
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            other.clone()
+        } else if other.is_empty() {
+            self.clone()
+        } else if self.k != other.k {
+            radeco_err!("Join two polynomials with different radices");
+            Polynomial::default()
+        } else if self.contains(other) {
+            self.clone()
+        } else if other.contains(self) {
+            other.clone()
+        } else {
+            // p1 U p2 is a subset of base1 + (base2 - base1) * [0, 1] + terms1 + terms2,
+            // in which base1 <= base2. Shared terms are only kept once.
+            let (lo, hi) = if self.base <= other.base {
+                (self, other)
+            } else {
+                (other, self)
+            };
+            let mut terms = lo.wide_terms();
+            for t in hi.wide_terms() {
+                if !terms.contains(&t) {
+                    terms.push(t);
+                }
+            }
+            terms.push((hi.base as wnum - lo.base as wnum, 1));
+            Polynomial::from_wide(self.k, lo.base as wnum, terms)
+        }
+    }
+
+    // Terms whose numbers grow are widened to fill the k-bits-filed, the
+    // shape of self is kept if possible.
+    fn widen(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        } else if other.is_empty() {
+            return self.clone();
+        } else if self.k != other.k {
+            radeco_err!("Widen two polynomials with different radices");
+            return Polynomial::default();
+        } else if self.contains(other) {
+            return self.clone();
+        }
+        let joined = self.join(other);
+        let fallback = || {
+            let si = self.to_strided_interval().widen(&joined.to_strided_interval());
+            Polynomial::from_strided_interval(&si)
+        };
+        if joined.base < self.base {
+            return fallback();
+        }
+        let old = self.wide_terms();
+        let (stable, unstable): (Vec<(wnum, wnum)>, Vec<(wnum, wnum)>) = joined.wide_terms()
+            .into_iter()
+            .partition(|&(x, a)| old.iter().any(|&(y, b)| (x == y) && (a <= b)));
+        let stable_span = stable.iter().fold(0, |acc, &(x, a)| acc + x * a);
+        let room = (max_in_k(self.k) - joined.base as wnum - stable_span) / unstable.len() as wnum;
+        let mut terms = stable;
+        for (x, a) in unstable {
+            if room / x < a {
+                return fallback();
+            }
+            terms.push((x, room / x));
+        }
+        Polynomial::from_wide(self.k, joined.base as wnum, terms)
+    }
+
+    fn remove_lower_bound(&self) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else {
+            Polynomial::from_strided_interval(&self.to_strided_interval().remove_lower_bound())
+        }
+    }
+
+    fn set_lower_bound(&self, x: inum) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else if x > self.max().unwrap_or(self.base) {
+            radeco_warn!("Set a lower bound which is bigger than upper bound");
+            Polynomial::null()
+        } else if x <= self.base {
+            self.clone()
+        } else {
+            let si = self.to_strided_interval().set_lower_bound(x);
+            self.smaller(Polynomial::from_strided_interval(&si))
+        }
+    }
+
+    fn remove_upper_bound(&self) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else {
+            Polynomial::from_strided_interval(&self.to_strided_interval().remove_upper_bound())
+        }
+    }
+
+    fn set_upper_bound(&self, x: inum) -> Self {
+        if self.is_empty() {
+            self.clone()
+        } else if x < self.base {
+            radeco_warn!("Set a upper bound which is smaller than lower bound");
+            Polynomial::null()
+        } else if x >= self.max().unwrap_or(self.base) {
+            self.clone()
+        } else {
+            let si = self.to_strided_interval().set_upper_bound(x);
+            self.smaller(Polynomial::from_strided_interval(&si))
+        }
+    }
+
+    fn narrow(&self, k: u8) -> Self {
+        if self.is_empty() {
+            radeco_warn!("Empty Polynomial cannot be narrow");
+            self.clone()
+        } else if k > self.k {
+            radeco_warn!("Polynomial cannot be narrowed to a bigger bits");
+            self.clone()
+        } else if k == self.k {
+            self.clone()
+        } else if k == 0 {
+            Polynomial::null()
+        } else if (self.base as wnum >= min_in_k(k)) &&
+                  (self.base as wnum + self.span() <= max_in_k(k)) {
+            // All members are inside the k-bits-filed
+            self.with_k(k)
+        } else {
+            Polynomial::from_strided_interval(&self.to_strided_interval().narrow(k))
+        }
+    }
+
+    fn sign_extend(&self, k: u8) -> Self {
+        if self.is_empty() {
+            radeco_warn!("Empty Polynomial cannot be extended");
+            self.clone()
+        } else if k < self.k {
+            radeco_warn!("Polynomial cannot be extended to a smaller bits");
+            self.clone()
+        } else {
+            self.with_k(k)
+        }
+    }
+
+    fn zero_extend(&self, k: u8) -> Self {
+        if self.is_empty() {
+            radeco_warn!("Empty Polynomial cannot be extended");
+            self.clone()
+        } else if k < self.k {
+            radeco_warn!("Polynomial cannot be extended to a smaller bits");
+            self.clone()
+        } else if k == self.k {
+            self.clone()
+        } else if self.base >= 0 {
+            // all number in self is non-negative
+            self.with_k(k)
+        } else if self.base as wnum + self.span() < 0 {
+            // all number in self is negative
+            let base = self.base as wnum + ((1 as wnum) << self.k);
+            Polynomial::from_wide(k, base, self.wide_terms())
+        } else {
+            Polynomial::from_strided_interval(&self.to_strided_interval().zero_extend(k))
+        }
+    }
+
+    fn constant(&self) -> Option<inum> {
+        if self.is_empty() || (self.len != 0) {
+            None
+        } else {
+            Some(self.base)
+        }
+    }
+
+    // XXX: It is an upper bound when different ki give the same member.
+    // XXX: saturated when capacity > inum::max_value()
+    fn capacity(&self) -> inum {
+        if self.is_empty() {
+            return 0;
+        }
+        let c = self.used_terms().iter().fold(1 as wnum, |acc, &(_, a)| {
+            acc.saturating_mul(a as wnum + 1)
+        });
+        cmp::min(c, inum::max_value() as wnum) as inum
+    }
+
+    fn is_empty(&self) -> bool {
+        self.k == 0
+    }
+
+    fn max_k() -> u8 {
+        POLY_BITS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::TestResult;
+
+    // Returns a polynomial in 16 bits and all of its members
+    fn arbitrary_polynomial(base: i8, terms: Vec<(u8, u8)>) -> (Polynomial, Vec<inum>) {
+        let terms: Vec<(inum, inum)> = terms.into_iter()
+            .take(3)
+            .map(|(x, a)| ((x % 0x10) as inum + 1, (a % 4) as inum))
+            .collect();
+        let mut values = vec![base as inum];
+        for &(x, a) in &terms {
+            values = values.iter().flat_map(|v| (0..a + 1).map(move |i| v + i * x)).collect();
+        }
+        values.sort();
+        values.dedup();
+        (Polynomial::new(16, base as inum, &terms), values)
+    }
+
+    // Returns the corresponding number of n in 16 bits
+    fn wrap(n: inum) -> inum {
+        n as i16 as inum
+    }
+
+    fn sound<F>(res: Polynomial, a: &[inum], b: &[inum], op: F) -> bool
+        where F: Fn(inum, inum) -> inum
+    {
+        a.iter().all(|x| b.iter().all(|y| res.contains(&wrap(op(*x, *y)))))
+    }
+
+    #[test]
+    fn polynomial_test_basicfn() {
+        assert_eq!(4, gcd(12, -8));
+        assert_eq!(Polynomial::null(), Polynomial::new(0, 3, &[(2, 3)]));
+        assert!(Polynomial::null().is_empty());
+        assert_eq!(Some(0xdeadbeef), Polynomial::from(0xdeadbeef).constant());
+        assert_eq!(Some(-1), Polynomial::from((8, 0xff)).constant());
+
+        // Negative strides are normalized
+        assert_eq!(Polynomial::new(16, -6, &[(2, 3)]), Polynomial::new(16, 0, &[(-2, 3)]));
+        // Terms with the same stride are merged
+        assert_eq!(Polynomial::new(16, 0, &[(2, 5)]), Polynomial::new(16, 0, &[(2, 3), (2, 2)]));
+        // [3]2 + [1]8 == [7]2
+        assert_eq!(Polynomial::new(16, 0, &[(2, 7)]), Polynomial::new(16, 0, &[(2, 3), (8, 1)]));
+        // Too many terms
+        let p = Polynomial::new(16, 0, &[(3, 1), (5, 1), (7, 1), (11, 1), (13, 1)]);
+        assert_eq!(4, p.len);
+        assert!(p.contains(&vec![0, 3, 5, 7, 11, 13, 39]));
+        // Wrap around
+        assert_eq!(Polynomial::default_k(8), Polynomial::new(8, 120, &[(4, 3)]));
+        assert_eq!(Polynomial::new(8, -126, &[(4, 1)]), Polynomial::new(8, 130, &[(4, 1)]));
+
+        let p = Polynomial::new(16, 10, &[(4, 3), (100, 2)]);
+        assert_eq!(Some(10), p.min());
+        assert_eq!(Some(222), p.max());
+        assert_eq!(12, p.capacity());
+        assert!(p.contains(&vec![10, 14, 110, 122, 210, 222]));
+        assert!(!p.contains(&12));
+        assert!(!p.contains(&26));
+        assert!(!p.contains(&226));
+        assert!(p.contains(&Polynomial::new(16, 14, &[(100, 1)])));
+        assert!(!p.contains(&Polynomial::new(16, 14, &[(50, 1)])));
+        assert_eq!(StridedInterval::new(16, 4, 10, 222), p.to_strided_interval());
+
+        let si = StridedInterval::new(16, 3, -9, 30);
+        assert_eq!(si, Polynomial::from_strided_interval(&si).to_strided_interval());
+        assert_eq!(Polynomial::default(),
+                   Polynomial::from_strided_interval(&StridedInterval::default()));
+        assert_eq!((1 as inum) << POLY_BITS, Polynomial::default().capacity());
+    }
+
+    #[test]
+    fn polynomial_test_arith() {
+        let op1 = Polynomial::new(16, 0x100, &[(8, 9)]);
+        let op2 = Polynomial::new(16, 4, &[(0x20, 3)]);
+        assert_eq!(Polynomial::new(16, 0x104, &[(8, 9), (0x20, 3)]), op1 + op2);
+        assert_eq!(Polynomial::new(16, 0x104, &[(8, 9), (0x20, 3)]), op2 + op1);
+        assert_eq!(Polynomial::new(16, 0x100 - 0x64, &[(8, 9), (0x20, 3)]), op1 - op2);
+        assert_eq!(Polynomial::new(16, -0x148, &[(8, 9)]), -op1);
+        assert_eq!(Polynomial::new(16, -0x149, &[(8, 9)]), !op1);
+        assert_eq!(Polynomial::new(16, 0x300, &[(24, 9)]), op1 * Polynomial::from((16, 3)));
+        assert_eq!(Polynomial::new(16, -0x300, &[(-24, 9)]), op1 * Polynomial::from((16, -3)));
+        assert_eq!(Polynomial::new(16, 0x400, &[(32, 9)]), op1 << Polynomial::from((16, 2)));
+        assert_eq!(Polynomial::new(16, 0x80, &[(4, 9)]), op1 / Polynomial::from((16, 2)));
+        assert_eq!(Polynomial::from((16, 0)), op1 % Polynomial::from((16, 8)));
+        assert_eq!(Polynomial::from((16, 4)), op2 % Polynomial::from((16, 8)));
+
+        let op1 = Polynomial::new(16, -7, &[(8, 2)]);
+        // {-7, 1, 9} % 4 = {-3, 1, 1}
+        assert_eq!(Polynomial::new(16, -3, &[(4, 1)]), op1 % Polynomial::from((16, 4)));
+        assert_eq!(Polynomial::default_k(16), op1 / Polynomial::new(16, -1, &[(1, 2)]));
+
+        let op1 = Polynomial::from((8, 127));
+        assert_eq!(Polynomial::from((8, -128)), op1 + Polynomial::from((8, 1)));
+
+        // Constants are taken in k bits, 0xff is -1 and 0x101 is 1 in 8 bits
+        let op1 = Polynomial::new(8, 0, &[(1, 1)]);
+        assert_eq!(Polynomial::new(8, -1, &[(1, 1)]), Polynomial::new(8, 0, &[(0xff, 1)]));
+        assert_eq!(op1, op1.mul_constant(0x101));
+        assert_eq!(op1, op1.add_constant(0x100));
+    }
+
+    #[test]
+    fn polynomial_test_setop() {
+        let op1 = Polynomial::new(16, 0, &[(4, 10)]);
+        let op2 = Polynomial::new(16, 8, &[(4, 2)]);
+        assert_eq!(op2, op1.meet(&op2));
+        assert_eq!(op1, op1.join(&op2));
+        assert_eq!(Polynomial::null(), op1.meet(&Polynomial::from((16, 1))));
+        assert_eq!(op1, op1.join(&Polynomial::null()));
+        assert_eq!(Polynomial::new(16, 0, &[(4, 1)]),
+                   Polynomial::from((16, 0)).join(&Polynomial::from((16, 4))));
+
+        // Two induction variables
+        let op1 = Polynomial::new(16, 0x1000, &[(4, 3)]);
+        let op2 = Polynomial::new(16, 0x1000 + 0x40, &[(4, 3)]);
+        assert_eq!(Polynomial::new(16, 0x1000, &[(4, 3), (0x40, 1)]), op1.join(&op2));
+        let op3 = op1.join(&op2).widen(&op2.add_constant(0x40).join(&op1));
+        assert!(op3.contains(&vec![0x1000, 0x1004, 0x1044, 0x108c, 0x1fcc]));
+        assert!(!op3.contains(&0x1002));
+        assert_eq!(op3, op3.widen(&op1));
+
+        let op1 = Polynomial::new(16, 1, &[(30, 30)]);
+        assert_eq!(Polynomial::null(), op1.set_lower_bound(902));
+        assert_eq!(Polynomial::new(16, 31, &[(30, 29)]), op1.set_lower_bound(2));
+        assert_eq!(Polynomial::null(), op1.set_upper_bound(-1));
+        assert_eq!(Polynomial::new(16, 1, &[(30, 1)]), op1.set_upper_bound(33));
+
+        let op1 = Polynomial::new(16, 0x100, &[(1, 3)]);
+        assert_eq!(Polynomial::new(8, 0, &[(1, 3)]), op1.narrow(8));
+        let op1 = Polynomial::new(8, -2, &[(1, 3)]);
+        assert_eq!(Polynomial::new(16, -2, &[(1, 3)]), op1.sign_extend(16));
+        let op1 = Polynomial::new(8, -4, &[(1, 2)]);
+        assert_eq!(Polynomial::new(16, 0xfc, &[(1, 2)]), op1.zero_extend(16));
+    }
+
+    #[quickcheck]
+    fn qc_polynomial_members(base: i8, terms: Vec<(u8, u8)>) -> bool {
+        let (p, values) = arbitrary_polynomial(base, terms);
+        let (min, max) = (p.min().unwrap(), p.max().unwrap());
+        (min..max + 1).all(|v| p.contains(&v) == values.contains(&v)) &&
+        p.capacity() >= values.len() as inum &&
+        p.to_strided_interval().contains(&values)
+    }
+
+    #[quickcheck]
+    fn qc_polynomial_arith(base1: i8, terms1: Vec<(u8, u8)>, base2: i8, terms2: Vec<(u8, u8)>) -> bool {
+        let (p1, v1) = arbitrary_polynomial(base1, terms1);
+        let (p2, v2) = arbitrary_polynomial(base2, terms2);
+        let mut res = sound(p1 + p2, &v1, &v2, |x, y| x + y) &&
+                      sound(p1 - p2, &v1, &v2, |x, y| x - y) &&
+                      sound(p1 * p2, &v1, &v2, |x, y| x * y) &&
+                      sound(p1 & p2, &v1, &v2, |x, y| x & y) &&
+                      sound(p1 | p2, &v1, &v2, |x, y| x | y) &&
+                      sound(p1 ^ p2, &v1, &v2, |x, y| x ^ y) &&
+                      sound(-p1, &v1, &[0], |x, _| -x) &&
+                      sound(!p1, &v1, &[0], |x, _| !x);
+        if !v2.contains(&0) {
+            res = res && sound(p1 / p2, &v1, &v2, |x, y| x / y) &&
+                  sound(p1 % p2, &v1, &v2, |x, y| x % y);
+        }
+        res
+    }
 
+    #[quickcheck]
+    fn qc_polynomial_constant(base: i8, terms: Vec<(u8, u8)>, c: i8) -> TestResult {
+        let (p, v) = arbitrary_polynomial(base, terms);
+        let c = c as inum;
+        let q = Polynomial::from((16, c));
+        let mut res = sound(p + q, &v, &[c], |x, y| x + y) &&
+                      sound(p * q, &v, &[c], |x, y| x * y) &&
+                      sound(p << Polynomial::from((16, c & 0xf)), &v, &[c & 0xf], |x, y| x << y);
+        if c != 0 {
+            res = res && sound(p / q, &v, &[c], |x, y| x / y) &&
+                  sound(p % q, &v, &[c], |x, y| x % y);
+        }
+        TestResult::from_bool(res)
+    }
 
-// TODO
+    #[quickcheck]
+    fn qc_polynomial_setop(base1: i8, terms1: Vec<(u8, u8)>, base2: i8, terms2: Vec<(u8, u8)>) -> bool {
+        let (p1, v1) = arbitrary_polynomial(base1, terms1);
+        let (p2, v2) = arbitrary_polynomial(base2, terms2);
+        let (join, meet, widen) = (p1.join(&p2), p1.meet(&p2), p1.widen(&p2));
+        join.contains(&v1) && join.contains(&v2) &&
+        widen.contains(&v1) && widen.contains(&v2) &&
+        v1.iter().filter(|v| v2.contains(v)).all(|v| meet.contains(v)) &&
+        p1.sign_extend(32).contains(&v1) &&
+        p1.narrow(8).contains(&v1.iter().map(|v| *v as i8 as inum).collect::<Vec<_>>()) &&
+        p1.zero_extend(32).contains(&v1.iter().map(|v| *v as u16 as inum).collect::<Vec<_>>())
+    }
+}
@@ -182,7 +182,7 @@ impl fmt::Display for StridedInterval {
 //        --> periodNr(-5, 4) = -2
 //            periodNr(3, 4) = 0
 fn periodNr<T>(x: T, period: T) -> T 
-        where T: Clone + PartialEq + Eq + Ord + From<inum> + 
+        where T: Clone + PartialEq + Eq + Ord + From<i64> + 
                     Add<Output=T> + Sub<Output=T> + Rem<Output=T> + Div<Output=T>
 {
     // period must be positive
@@ -206,6 +206,11 @@ fn periodNr<T>(x: T, period: T) -> T
     _x.clone() / period.clone()
 }
 
+/// Returns x as a BigInt, which could not be converted from i128 directly.
+fn big(x: inum) -> BigInt {
+    (BigInt::from((x >> 64) as i64) << 64) + BigInt::from(x as u64)
+}
+
 /// Returns the number of trailing zeroes of x
 fn ntz(_x: inum) -> u8 {
     let x = _x as unum;
@@ -621,15 +626,15 @@ impl Mul for StridedInterval {
                 StridedInterval::from((self.k, 0))
             } else {
                 let (_lb, _ub) = if n > 0 {
-                    (BigInt::from(set_si.lb), BigInt::from(set_si.ub))
+                    (big(set_si.lb), big(set_si.ub))
                 } else {
-                    (BigInt::from(set_si.ub), BigInt::from(set_si.lb))
+                    (big(set_si.ub), big(set_si.lb))
                 };
-                let _n = BigInt::from(n);
+                let _n = big(n);
                 // e.g.
                 //      4 bits: k_period = 8
                 //      5 bits: k_period = 16
-                let k_period: BigInt = BigInt::from(max_in_k_bits!(self.k)) + 1;
+                let k_period: BigInt = big(max_in_k_bits!(self.k)) + 1;
 
                 // New bounds for Mul
                 let __u = _lb.clone() * _n.clone();
@@ -655,10 +660,10 @@ impl Mul for StridedInterval {
         } else {
             // Both are set
             let _poles = vec![
-                (BigInt::from(self.lb) * BigInt::from(other.lb), self.lb, other.lb),
-                (BigInt::from(self.lb) * BigInt::from(other.ub), self.lb, other.ub),
-                (BigInt::from(self.ub) * BigInt::from(other.lb), self.ub, other.lb),
-                (BigInt::from(self.ub) * BigInt::from(other.ub), self.ub, other.ub),
+                (big(self.lb) * big(other.lb), self.lb, other.lb),
+                (big(self.lb) * big(other.ub), self.lb, other.ub),
+                (big(self.ub) * big(other.lb), self.ub, other.lb),
+                (big(self.ub) * big(other.ub), self.ub, other.ub),
             ];
             
             // Similar with above code
@@ -671,7 +676,7 @@ impl Mul for StridedInterval {
                 Some(y) => Some(if x.0 < y.0 { x } else { y }),
             }).expect("No elements in _poles");
 
-            let k_period: BigInt = BigInt::from(max_in_k_bits!(self.k)) + 1;
+            let k_period: BigInt = big(max_in_k_bits!(self.k)) + 1;
 
             let u = periodNr(_min.0.clone(), k_period.clone());
             let v = periodNr(_max.0.clone(), k_period.clone());
@@ -1583,14 +1588,16 @@ impl AbstractSet for StridedInterval {
         }
     }
 
-    // XXX: overflow when capacity > inum::max_value()
+    // XXX: saturated when capacity > inum::max_value()
     fn capacity(&self) -> inum {
         if self.is_empty() {
             0
         } else if self.s == 0 {
             1
         } else {
-            (self.ub - self.lb) / self.s + 1
+            // ub - lb overflows inum when k equals to _bits
+            let n = (self.ub as unum).wrapping_sub(self.lb as unum) / (self.s as unum);
+            cmp::min(n, inum::max_value() as unum - 1) as inum + 1
         }
     }
 
@@ -1610,20 +1617,22 @@ mod test {
         assert_eq!(-9223372036854775808, min_in_k_bits!(64));
         assert_eq!(9223372036854775807, max_in_k_bits!(64));
         assert_eq!(0xffff, mask_in_k_bits!(16));
-        assert_eq!(-1i64, mask_in_k_bits!(64));
+        assert_eq!(0xffffffffffffffff, mask_in_k_bits!(64));
+        assert_eq!(-1, mask_in_k_bits!(128));
         assert_eq!(0xdeadbeef, n_in_k_bits!(0xdeadbeef, 64));
         assert_eq!(1, n_in_k_bits!(0xf0001, 16));
         assert_eq!(-32767, n_in_k_bits!(0xf8001, 16));
         assert_eq!(false, check_overflow!((0 as inum).checked_add(0xdeadbeef), 64));
-        assert_eq!(true, check_overflow!(inum::max_value().checked_add(1), 64));
+        assert_eq!(true, check_overflow!((0 as inum).checked_add(0x8000000000000000), 64));
+        assert_eq!(true, check_overflow!(inum::max_value().checked_add(1), 128));
         assert_eq!(true, check_overflow!((100 as inum).checked_add(100), 8));
     }
 
     #[test]
     fn strided_interval_test_basicfn() {
         assert_eq!(ntz(0x0001000), 12);
-        assert_eq!(ntz(0x0), 64);
-        assert_eq!(ntz(inum::min_value()), 63);
+        assert_eq!(ntz(0x0), 128);
+        assert_eq!(ntz(inum::min_value()), 127);
         assert_eq!(minOr(0x0000101, 0x0001001, 0x0010011, 0x0101001), 0x0010101);
         assert_eq!(maxOr(0x0000101, 0x0001001, 0x0010011, 0x0101001), 0x0101fff);
         let (x, y) = (3, 8);
@@ -1638,7 +1647,9 @@ mod test {
         let (x, y) = (3, 0);
         let (s, t) = exgcd(x, y);
         assert_eq!(x * s + y * t, gcd(x, y));
-        assert_eq!(StridedInterval{k: _bits, s: 1, lb: -9223372036854775808, ub: 9223372036854775807}, 
+        assert_eq!(StridedInterval{k: 64, s: 1, lb: -9223372036854775808, ub: 9223372036854775807}, 
+                   StridedInterval::default_k(64));
+        assert_eq!(StridedInterval{k: _bits, s: 1, lb: inum::min_value(), ub: inum::max_value()}, 
                    StridedInterval::default());
         assert_eq!(StridedInterval{k: _bits, s: 1, lb: inum::min_value(), ub: inum::max_value()}, 
                    StridedInterval::default_k(_bits));
@@ -1766,9 +1777,14 @@ mod test {
         let op2 = StridedInterval::from(0);
         assert_eq!(StridedInterval::from(0), op1 * op2);
 
+        let op1 = StridedInterval::new(64, 1, 0, b);
+        let op2 = StridedInterval::from((64, a));
+        assert_eq!(StridedInterval::default_k(64), op1 * op2);
+
+        // No overflow in 128 bits
         let op1 = StridedInterval::new(_bits, 1, 0, b);
         let op2 = StridedInterval::from(a);
-        assert_eq!(StridedInterval::default(), op1 * op2);
+        assert_eq!(StridedInterval::new(_bits, a, 0, a * b), op1 * op2);
 
         let op1 = StridedInterval::new(_bits, 2, inum::max_value() - 2, inum::max_value());
         let op2 = StridedInterval::from(2);
@@ -1971,7 +1987,7 @@ mod test {
         let op2 = StridedInterval::from((4, 0x6));
         assert_eq!(StridedInterval::from((4, 0x7)), op1|op2);
 
-        let op1 = StridedInterval::from(0);
+        let op1 = StridedInterval::from((64, 0));
         let op2 = StridedInterval::new(64, 1, 45, 59);
         assert_eq!(op2, op1|op2);
         assert_eq!(op2, op2|op1);
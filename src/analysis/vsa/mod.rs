@@ -16,3 +16,6 @@ pub mod abstract_set {
     pub mod BDD;
     pub mod polynomial;
 }
+
+pub mod value_set;
+pub mod vsa;
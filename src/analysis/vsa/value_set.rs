@@ -0,0 +1,528 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Module that implements value sets, a-locs and abstract stores used by
+//! value set analysis.
+//!
+//! A value set maps every memory region to an abstract set of offsets inside
+//! the region. Numbers are regarded as offsets in the global region, thus a
+//! value set only holding global offsets is a scalar.
+//!
+//! An a-loc (abstract location) is roughly a variable in C, which is a range
+//! of bytes accessed directly in some memory region.
+
+use std::collections::{BTreeMap, HashMap};
+
+use petgraph::graph::NodeIndex;
+
+use super::abstract_set::abstract_set::{AbstractSet, Container, inum};
+
+/// Memory regions of value set analysis.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MemRegion {
+    /// Global region, offsets inside are absolute addresses.
+    Global,
+    /// Stack frame of the function, offsets inside are relative to the stack
+    /// pointer at the entry of the function.
+    Stack,
+    /// Heap region allocated by the call at the node.
+    Heap(NodeIndex),
+}
+
+/// Abstract location, i.e. `size` bytes at `offset` in `region`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ALoc {
+    pub region: MemRegion,
+    pub offset: inum,
+    /// Size in bytes
+    pub size: u16,
+}
+
+impl ALoc {
+    pub fn new(region: MemRegion, offset: inum, size: u16) -> ALoc {
+        ALoc {
+            region: region,
+            offset: offset,
+            size: size,
+        }
+    }
+
+    /// Returns true if `self` and `other` share some bytes.
+    pub fn overlaps(&self, other: &ALoc) -> bool {
+        (self.region == other.region) &&
+        (self.offset < other.offset.saturating_add(other.size as inum)) &&
+        (other.offset < self.offset.saturating_add(self.size as inum))
+    }
+
+    /// Heap a-locs are summaries of all memories allocated at the same site,
+    /// thus they could not be updated strongly.
+    pub fn is_summary(&self) -> bool {
+        match self.region {
+            MemRegion::Heap(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Value set of a k-bits value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueSet<S: AbstractSet> {
+    /// Width of the value in bits
+    pub k: u8,
+    // Offsets in every region, regions which are not in the map are empty
+    sets: BTreeMap<MemRegion, S>,
+    // The value may be anything, including pointers into any region
+    unknown: bool,
+}
+
+impl<S: AbstractSet> ValueSet<S> {
+    /// Returns the empty value set.
+    pub fn empty(k: u8) -> Self {
+        ValueSet {
+            k: k,
+            sets: BTreeMap::new(),
+            unknown: false,
+        }
+    }
+
+    /// Returns the value set containing everything.
+    pub fn top(k: u8) -> Self {
+        ValueSet {
+            k: k,
+            sets: BTreeMap::new(),
+            unknown: true,
+        }
+    }
+
+    /// Returns the value set of a number.
+    pub fn constant(k: u8, n: inum) -> Self {
+        ValueSet::from_set(k, MemRegion::Global, S::from((k, n)))
+    }
+
+    /// Returns the value set of a pointer at `offset` in `region`.
+    pub fn pointer(k: u8, region: MemRegion, offset: inum) -> Self {
+        ValueSet::from_set(k, region, S::from((k, offset)))
+    }
+
+    /// Returns the value set of pointers at `offsets` in `region`.
+    pub fn from_set(k: u8, region: MemRegion, offsets: S) -> Self {
+        if (k > S::max_k()) && !offsets.is_empty() {
+            // Too wide for the abstract set
+            return ValueSet::top(k);
+        }
+        let mut vs = ValueSet::empty(k);
+        if !offsets.is_empty() {
+            vs.sets.insert(region, offsets);
+        }
+        vs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.unknown && self.sets.is_empty()
+    }
+
+    pub fn is_top(&self) -> bool {
+        self.unknown
+    }
+
+    /// Returns offsets in `region`.
+    pub fn get(&self, region: &MemRegion) -> Option<&S> {
+        self.sets.get(region)
+    }
+
+    /// Returns all regions `self` may point into, None if unknown.
+    pub fn regions(&self) -> Option<Vec<MemRegion>> {
+        if self.unknown {
+            None
+        } else {
+            Some(self.sets.keys().cloned().collect())
+        }
+    }
+
+    /// Returns the set of numbers if `self` is a scalar.
+    pub fn scalar(&self) -> Option<S> {
+        if self.unknown || (self.sets.len() != 1) {
+            None
+        } else {
+            self.sets.get(&MemRegion::Global).cloned()
+        }
+    }
+
+    /// Returns Some(n) if `self` is the number n.
+    pub fn constant_value(&self) -> Option<inum> {
+        self.scalar().and_then(|s| s.constant())
+    }
+
+    /// Returns a-locs of `size` bytes pointed by `self`, None if some offsets
+    /// are not constant.
+    pub fn a_locs(&self, size: u16) -> Option<Vec<ALoc>> {
+        if self.unknown {
+            return None;
+        }
+        let mut a_locs = Vec::new();
+        for (region, offsets) in &self.sets {
+            a_locs.push(ALoc::new(*region, offsets.constant()?, size));
+        }
+        Some(a_locs)
+    }
+
+    /// Returns members of `a_locs` which may overlap with `size` bytes pointed
+    /// by `self`.
+    pub fn overlapped_a_locs<'a, I>(&self, size: u16, a_locs: I) -> Vec<ALoc>
+        where I: Iterator<Item = &'a ALoc>
+    {
+        a_locs.filter(|a_loc| {
+                if self.unknown {
+                    return true;
+                }
+                match self.sets.get(&a_loc.region) {
+                    // Accessing from any offset in (offset - size, offset + a_loc.size)
+                    // overlaps with a_loc.
+                    Some(offsets) => {
+                        let lo = a_loc.offset.saturating_sub(size as inum - 1);
+                        let hi = a_loc.offset.saturating_add(a_loc.size as inum - 1);
+                        (lo..hi.saturating_add(1)).any(|o| offsets.contains(&o))
+                    }
+                    None => false,
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the value set in `k` bits, extended by sign if `signed`.
+    pub fn resize(&self, k: u8, signed: bool) -> Self {
+        if (k == self.k) || (k == 0) {
+            return self.clone();
+        } else if (k > S::max_k()) && !self.is_empty() {
+            return ValueSet::top(k);
+        }
+        let sets = self.sets
+            .iter()
+            .map(|(region, offsets)| {
+                let offsets = if k < self.k {
+                    offsets.narrow(k)
+                } else if signed {
+                    offsets.sign_extend(k)
+                } else {
+                    offsets.zero_extend(k)
+                };
+                (*region, offsets)
+            })
+            .collect();
+        ValueSet {
+            k: k,
+            sets: sets,
+            unknown: self.unknown,
+        }
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        self.merge(other, |x, y| x.join(y))
+    }
+
+    pub fn widen(&self, other: &Self) -> Self {
+        self.merge(other, |x, y| x.widen(y))
+    }
+
+    // Combines offsets of the same region by `f`.
+    fn merge<F>(&self, other: &Self, f: F) -> Self
+        where F: Fn(&S, &S) -> S
+    {
+        if self.unknown || other.unknown {
+            return ValueSet::top(self.k);
+        }
+        let other = other.resize(self.k, true);
+        let mut vs = self.clone();
+        for (region, offsets) in other.sets {
+            let merged = match vs.sets.get(&region) {
+                Some(old) => f(old, &offsets),
+                None => offsets,
+            };
+            vs.sets.insert(region, merged);
+        }
+        vs
+    }
+
+    // Adds a number to offsets of every region
+    fn shift<F>(&self, n: &S, f: F) -> Self
+        where F: Fn(S, S) -> S
+    {
+        let mut vs = ValueSet::empty(self.k);
+        for (region, offsets) in &self.sets {
+            vs.sets.insert(*region, f(*offsets, *n));
+        }
+        vs
+    }
+
+    /// Returns {x + y | x in self, y in other}.
+    ///     global + global ==> global
+    ///     region + global ==> region
+    ///     region + region ==> top
+    pub fn add(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return ValueSet::empty(self.k);
+        } else if self.unknown || other.unknown {
+            return ValueSet::top(self.k);
+        }
+        let other = other.resize(self.k, true);
+        match (self.scalar(), other.scalar()) {
+            (_, Some(n)) => self.shift(&n, |x, y| x + y),
+            (Some(n), _) => other.shift(&n, |x, y| x + y),
+            _ => ValueSet::top(self.k),
+        }
+    }
+
+    /// Returns {x - y | x in self, y in other}.
+    ///     region - global ==> region
+    ///     region - (the same) region ==> global
+    ///     otherwise ==> top
+    pub fn sub(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return ValueSet::empty(self.k);
+        } else if self.unknown || other.unknown {
+            return ValueSet::top(self.k);
+        }
+        let other = other.resize(self.k, true);
+        if let Some(n) = other.scalar() {
+            return self.shift(&n, |x, y| x - y);
+        }
+        if (self.sets.len() == 1) && (other.sets.len() == 1) {
+            let (r1, o1) = self.sets.iter().next().unwrap();
+            let (r2, o2) = other.sets.iter().next().unwrap();
+            if r1 == r2 {
+                // Distance between two pointers
+                return ValueSet::from_set(self.k, MemRegion::Global, *o1 - *o2);
+            }
+        }
+        ValueSet::top(self.k)
+    }
+
+    /// Applies `f` on numbers, pointers are not supported.
+    pub fn scalar_op<F>(&self, other: &Self, f: F) -> Self
+        where F: Fn(S, S) -> S
+    {
+        if self.is_empty() || other.is_empty() {
+            return ValueSet::empty(self.k);
+        }
+        match (self.scalar(), other.resize(self.k, true).scalar()) {
+            (Some(x), Some(y)) => ValueSet::from_set(self.k, MemRegion::Global, f(x, y)),
+            _ => ValueSet::top(self.k),
+        }
+    }
+
+    /// Applies `f` on numbers, pointers are not supported.
+    pub fn map_scalar<F>(&self, f: F) -> Self
+        where F: Fn(S) -> S
+    {
+        if self.is_empty() {
+            return ValueSet::empty(self.k);
+        }
+        match self.scalar() {
+            Some(x) => ValueSet::from_set(self.k, MemRegion::Global, f(x)),
+            None => ValueSet::top(self.k),
+        }
+    }
+}
+
+/// Abstract store, mapping a-locs to their value sets. A-locs which are not
+/// in the store could be anything.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbstractStore<S: AbstractSet> {
+    a_locs: HashMap<ALoc, ValueSet<S>>,
+}
+
+impl<S: AbstractSet> AbstractStore<S> {
+    pub fn new() -> Self {
+        AbstractStore { a_locs: HashMap::new() }
+    }
+
+    /// Returns the value set held in `a_loc`.
+    pub fn get(&self, a_loc: &ALoc) -> Option<&ValueSet<S>> {
+        self.a_locs.get(a_loc)
+    }
+
+    /// Returns all a-locs with known values.
+    pub fn a_locs(&self) -> Vec<ALoc> {
+        self.a_locs.keys().cloned().collect()
+    }
+
+    /// Returns the k-bits value set loaded from `size` bytes at `addr`.
+    pub fn load(&self, addr: &ValueSet<S>, size: u16, k: u8) -> ValueSet<S> {
+        if addr.is_empty() {
+            return ValueSet::empty(k);
+        }
+        match addr.a_locs(size) {
+            Some(a_locs) => {
+                let mut vs = ValueSet::empty(k);
+                for a_loc in &a_locs {
+                    match self.a_locs.get(a_loc) {
+                        Some(v) => vs = vs.join(&v.resize(k, true)),
+                        None => return ValueSet::top(k),
+                    }
+                }
+                vs
+            }
+            None => ValueSet::top(k),
+        }
+    }
+
+    /// Stores `value` into `size` bytes at `addr`.
+    pub fn store(&mut self, addr: &ValueSet<S>, size: u16, value: &ValueSet<S>) {
+        if addr.is_empty() {
+            return;
+        }
+        match addr.a_locs(size) {
+            Some(ref a_locs) if (a_locs.len() == 1) && !a_locs[0].is_summary() => {
+                // Strong update
+                self.invalidate(&a_locs[0]);
+                self.a_locs.insert(a_locs[0], value.clone());
+            }
+            Some(a_locs) => {
+                // Weak update
+                for a_loc in &a_locs {
+                    let old = self.a_locs.get(a_loc).cloned();
+                    self.invalidate(a_loc);
+                    if let Some(old) = old {
+                        self.a_locs.insert(*a_loc, old.join(value));
+                    }
+                }
+            }
+            None => {
+                let overlapped = addr.overlapped_a_locs(size, self.a_locs.keys());
+                for a_loc in &overlapped {
+                    self.a_locs.remove(a_loc);
+                }
+            }
+        }
+    }
+
+    // Forgets all a-locs overlapping with `a_loc`.
+    fn invalidate(&mut self, a_loc: &ALoc) {
+        let overlapped: Vec<ALoc> = self.a_locs
+            .keys()
+            .filter(|a| a.overlaps(a_loc))
+            .cloned()
+            .collect();
+        for a in &overlapped {
+            self.a_locs.remove(a);
+        }
+    }
+
+    /// Forgets all a-locs not satisfying `f`.
+    pub fn retain<F>(&mut self, f: F)
+        where F: Fn(&ALoc) -> bool
+    {
+        let removed: Vec<ALoc> = self.a_locs.keys().filter(|a| !f(a)).cloned().collect();
+        for a in &removed {
+            self.a_locs.remove(a);
+        }
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        self.merge(other, |x, y| x.join(y))
+    }
+
+    pub fn widen(&self, other: &Self) -> Self {
+        self.merge(other, |x, y| x.widen(y))
+    }
+
+    // Only a-locs known by both stores are kept
+    fn merge<F>(&self, other: &Self, f: F) -> Self
+        where F: Fn(&ValueSet<S>, &ValueSet<S>) -> ValueSet<S>
+    {
+        let mut store = AbstractStore::new();
+        for (a_loc, vs) in &self.a_locs {
+            if let Some(other_vs) = other.a_locs.get(a_loc) {
+                store.a_locs.insert(*a_loc, f(vs, other_vs));
+            }
+        }
+        store
+    }
+
+    /// Returns the number of a-locs with known values.
+    pub fn len(&self) -> usize {
+        self.a_locs.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use analysis::vsa::abstract_set::polynomial::Polynomial;
+    use analysis::vsa::abstract_set::strided_interval::StridedInterval;
+
+    type VS = ValueSet<StridedInterval>;
+
+    #[test]
+    fn value_set_test_arith() {
+        let sp = VS::pointer(64, MemRegion::Stack, 0);
+        let n = VS::constant(64, 8);
+        assert_eq!(VS::pointer(64, MemRegion::Stack, 8), sp.add(&n));
+        assert_eq!(VS::pointer(64, MemRegion::Stack, 8), n.add(&sp));
+        assert_eq!(VS::pointer(64, MemRegion::Stack, -8), sp.sub(&n));
+        assert_eq!(VS::constant(64, 8), sp.add(&n).sub(&sp));
+        assert!(sp.add(&sp).is_top());
+        assert!(n.sub(&sp).is_top());
+        assert!(sp.scalar_op(&n, |x, y| x * y).is_top());
+        assert_eq!(VS::constant(64, 64), n.scalar_op(&n, |x, y| x * y));
+        assert!(VS::empty(64).add(&VS::top(64)).is_empty());
+        assert_eq!(Some(-1), VS::constant(8, 0xff).resize(64, true).constant_value());
+        assert_eq!(Some(0xff), VS::constant(8, 0xff).resize(64, false).constant_value());
+
+        // 128-bit values, e.g. the result of a 64-bit multiplication
+        let wide = VS::constant(64, -1).resize(128, false);
+        assert_eq!(Some(0xffffffffffffffff), wide.constant_value());
+        assert_eq!(Some(0xfffffffffffffffe0000000000000001u128 as inum),
+                   wide.scalar_op(&wide, |x, y| x * y).constant_value());
+        assert!(ValueSet::<Polynomial>::constant(64, -1).resize(128, false).is_top());
+
+        let g = VS::pointer(64, MemRegion::Global, 0x1000);
+        let both = sp.join(&g);
+        assert_eq!(None, both.scalar());
+        assert_eq!(Some(vec![MemRegion::Global, MemRegion::Stack]), both.regions());
+        assert_eq!(Some(vec![ALoc::new(MemRegion::Global, 0x1000, 4),
+                             ALoc::new(MemRegion::Stack, 0, 4)]),
+                   both.a_locs(4));
+        assert!(both.join(&VS::top(64)).is_top());
+    }
+
+    #[test]
+    fn value_set_test_store() {
+        let mut store = AbstractStore::new();
+        let local = VS::pointer(64, MemRegion::Stack, -8);
+        let global = VS::pointer(64, MemRegion::Global, 0x1000);
+        store.store(&local, 8, &VS::constant(64, 1));
+        store.store(&global, 4, &VS::constant(32, 2));
+        assert_eq!(Some(1), store.load(&local, 8, 64).constant_value());
+        assert_eq!(Some(2), store.load(&global, 4, 32).constant_value());
+        assert!(store.load(&VS::pointer(64, MemRegion::Stack, -16), 8, 64).is_top());
+
+        // Weak update
+        let mut weak = store.clone();
+        weak.store(&local.join(&global), 4, &VS::constant(32, 3));
+        assert_eq!(1, weak.len());
+        assert_eq!(VS::constant(32, 2).join(&VS::constant(32, 3)),
+                   weak.load(&global, 4, 32));
+
+        // Partially overwritten
+        let mut partial = store.clone();
+        partial.store(&VS::pointer(64, MemRegion::Stack, -4), 4, &VS::constant(32, 0));
+        assert!(partial.load(&local, 8, 64).is_top());
+
+        // Unknown offsets in stack
+        let mut unknown = store.clone();
+        let offsets = StridedInterval::new(64, 8, -64, 0);
+        unknown.store(&VS::from_set(64, MemRegion::Stack, offsets), 8, &VS::constant(64, 0));
+        assert!(unknown.load(&local, 8, 64).is_top());
+        assert_eq!(Some(2), unknown.load(&global, 4, 32).constant_value());
+
+        let joined = store.join(&partial);
+        assert_eq!(1, joined.len());
+        assert_eq!(Some(2), joined.load(&global, 4, 32).constant_value());
+    }
+}
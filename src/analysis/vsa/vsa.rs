@@ -0,0 +1,622 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Module that implements value set analysis over the SSA of a function.
+//!
+//! Value sets of value nodes are computed sparsely by a worklist over def-use
+//! chains. Memory is modeled by abstract stores attached to memory state nodes
+//! (the "mem" comments, OpStores and memory phis), thus every OpLoad reads
+//! the store of its memory operand.
+//!
+//! Three kinds of memory regions are modeled:
+//!     * Global: offsets are absolute addresses;
+//!     * Stack: offsets are relative to the stack pointer at function entry;
+//!     * Heap: one region for every allocation site (call to *alloc).
+//!
+//! Widening is used on phi nodes which have been visited for several times,
+//! so that the analysis terminates on loops.
+
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use petgraph::graph::NodeIndex;
+
+use frontend::radeco_containers::RadecoFunction;
+use middle::intrinsics;
+use middle::ir::MOpcode;
+use middle::ssa::ssa_traits::{NodeData, NodeType, SSA};
+use middle::ssa::ssastorage::SSAStorage;
+
+use super::abstract_set::abstract_set::{AbstractSet, Container, inum, _bits};
+use super::value_set::{ALoc, AbstractStore, MemRegion, ValueSet};
+
+// Phi nodes visited more times than this will be widened
+const WIDEN_THRESHOLD: usize = 3;
+// Max number of nodes to visit, in case that fixpoint is not reached
+const VISIT_LIMIT: usize = 0x100000;
+// Functions whose return values are regarded as new heap regions
+const ALLOC_FUNCTIONS: [&'static str; 3] = ["malloc", "calloc", "realloc"];
+
+/// Value set analyzer of a function, generic over the abstract set of
+/// offsets.
+pub struct ValueSetAnalyzer<'a, S: AbstractSet> {
+    ssa: &'a SSAStorage,
+    /// Value sets of value nodes
+    value_sets: HashMap<NodeIndex, ValueSet<S>>,
+    /// Abstract stores of memory state nodes
+    stores: HashMap<NodeIndex, AbstractStore<S>>,
+    /// A-locs directly accessed by OpLoad/OpStore
+    a_locs: HashSet<ALoc>,
+    visits: HashMap<NodeIndex, usize>,
+    worklist: VecDeque<NodeIndex>,
+    in_worklist: HashSet<NodeIndex>,
+    // Name of stack pointer
+    sp_name: Option<String>,
+    // Name of register holding return values
+    ret_name: Option<String>,
+}
+
+impl<'a, S: AbstractSet> ValueSetAnalyzer<'a, S> {
+    pub fn new(rfn: &'a RadecoFunction) -> ValueSetAnalyzer<'a, S> {
+        let ssa = rfn.ssa();
+        let alias = &ssa.regfile.alias_info;
+        ValueSetAnalyzer {
+            ssa: ssa,
+            value_sets: HashMap::new(),
+            stores: HashMap::new(),
+            a_locs: HashSet::new(),
+            visits: HashMap::new(),
+            worklist: VecDeque::new(),
+            in_worklist: HashSet::new(),
+            sp_name: alias.get("SP").cloned(),
+            ret_name: alias.get("R0").or_else(|| alias.get("SN")).cloned(),
+        }
+    }
+
+    /// Run value set analysis until fixpoint.
+    pub fn analyze(&mut self) {
+        for node in self.ssa.values() {
+            self.push(node);
+        }
+
+        let mut visited = 0;
+        while let Some(node) = self.worklist.pop_front() {
+            self.in_worklist.remove(&node);
+            visited += 1;
+            if visited > VISIT_LIMIT {
+                radeco_warn!("VSA|Fixpoint is not reached after {} visits", VISIT_LIMIT);
+                break;
+            }
+            if self.visit(node) {
+                for use_ in self.ssa.uses_of(node) {
+                    self.push(use_);
+                }
+            }
+        }
+        radeco_trace!("VSA|Value sets: {:?}", self.value_sets);
+        radeco_trace!("VSA|A-locs: {:?}", self.a_locs);
+    }
+
+    /// Returns the value set of a value node.
+    pub fn value_set(&self, node: &NodeIndex) -> Option<&ValueSet<S>> {
+        self.value_sets.get(node)
+    }
+
+    /// Returns the abstract store of a memory state node, i.e. "mem" comment,
+    /// OpStore or memory phi.
+    pub fn store(&self, node: &NodeIndex) -> Option<&AbstractStore<S>> {
+        self.stores.get(node)
+    }
+
+    /// Returns all a-locs directly accessed in the function.
+    pub fn a_locs(&self) -> &HashSet<ALoc> {
+        &self.a_locs
+    }
+
+    /// Returns the a-locs accessed by OpLoad/OpStore `node`, None if some
+    /// accessed locations are not a-locs.
+    pub fn accessed_a_locs(&self, node: &NodeIndex) -> Option<Vec<ALoc>> {
+        let (addr, size) = self.access_of(node)?;
+        self.value_sets.get(&addr)?.a_locs(size)
+    }
+
+    /// Returns the only a-loc accessed by OpLoad/OpStore `node`, None if it
+    /// may access other locations.
+    pub fn accessed_a_loc(&self, node: &NodeIndex) -> Option<ALoc> {
+        match self.accessed_a_locs(node) {
+            Some(ref a_locs) if a_locs.len() == 1 => Some(a_locs[0]),
+            _ => None,
+        }
+    }
+
+    /// Returns the lowest stack offset whose address escapes, i.e. is passed
+    /// as an argument to a call or stored into memory, None if no stack
+    /// address escapes. The stack pointer at a call escapes as well, since
+    /// arguments may be passed on the stack. Callees could access the memory
+    /// above an escaped address, so all of it should be regarded as escaped.
+    pub fn escaped_stack(&self) -> Option<inum> {
+        let mut lowest: Option<inum> = None;
+        for node in self.ssa.values() {
+            let escaping: Vec<NodeIndex> = match self.ssa.opcode(node) {
+                Some(MOpcode::OpCall) => self.call_arguments(node),
+                Some(MOpcode::OpStore) => self.ssa.operands_of(node).into_iter().skip(2).collect(),
+                // Intrinsics that may access memory, e.g. system calls
+                Some(MOpcode::OpIntrinsic(ref name)) if intrinsics::may_access_memory(name) => {
+                    self.ssa.operands_of(node)
+                }
+                _ => Vec::new(),
+            };
+            for value in escaping {
+                let offsets = match self.value_sets.get(&value).and_then(|vs| vs.get(&MemRegion::Stack)) {
+                    Some(offsets) => offsets,
+                    None => continue,
+                };
+                if offsets.is_empty() {
+                    continue;
+                }
+                // Abstract sets have no lower bound in general, only a
+                // constant could be told precisely.
+                let low = offsets.constant().unwrap_or(inum::min_value());
+                lowest = Some(lowest.map_or(low, |l| l.min(low)));
+            }
+        }
+        lowest
+    }
+
+    // Operands of the call in argument registers and the stack pointer, which
+    // locates the arguments passed on the stack. Calls are passed every
+    // register, but the others, e.g. the frame pointer, are not handed to the
+    // callee.
+    fn call_arguments(&self, call: NodeIndex) -> Vec<NodeIndex> {
+        let regfile = &self.ssa.regfile;
+        let mut args = regfile.iter_args().map(|(id, _)| id + 1).collect::<HashSet<_>>();
+        if let Some(sp) = regfile.register_id_by_alias("SP") {
+            args.insert(sp.to_usize() + 1);
+        }
+        self.ssa
+            .sparse_operands_of(call)
+            .into_iter()
+            .filter(|&(idx, _)| args.contains(&(idx as usize)))
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// Returns the regions accessed by OpLoad/OpStore `node`, None if unknown.
+    pub fn accessed_regions(&self, node: &NodeIndex) -> Option<Vec<MemRegion>> {
+        let (addr, _) = self.access_of(node)?;
+        self.value_sets.get(&addr)?.regions()
+    }
+
+    // Returns the address node and the size in bytes of a memory access
+    fn access_of(&self, node: &NodeIndex) -> Option<(NodeIndex, u16)> {
+        let operands = self.ssa.operands_of(*node);
+        match self.ssa.opcode(*node) {
+            Some(MOpcode::OpLoad) if operands.len() > 1 => {
+                Some((operands[1], self.size_of(node)))
+            }
+            Some(MOpcode::OpStore) if operands.len() > 2 => {
+                Some((operands[1], self.size_of(&operands[2])))
+            }
+            _ => None,
+        }
+    }
+
+    // Size of a value in bytes
+    fn size_of(&self, node: &NodeIndex) -> u16 {
+        match self.ssa.node_data(*node).ok().and_then(|nd| nd.vt.width().get_width()) {
+            Some(w) if w > 0 => cmp::max(w / 8, 1),
+            _ => 8,
+        }
+    }
+
+    fn push(&mut self, node: NodeIndex) {
+        if self.in_worklist.insert(node) {
+            self.worklist.push_back(node);
+        }
+    }
+
+    // Returns true if the result of node changes
+    fn visit(&mut self, node: NodeIndex) -> bool {
+        let ndata = match self.ssa.node_data(node) {
+            Ok(ndata) => ndata,
+            Err(_) => return false,
+        };
+        let count = {
+            let count = self.visits.entry(node).or_insert(0);
+            *count += 1;
+            *count
+        };
+        let widen = self.ssa.is_phi(node) && count > WIDEN_THRESHOLD;
+
+        if self.is_memory(&ndata) {
+            let store = match self.eval_memory(node, &ndata) {
+                Some(store) => store,
+                None => return false,
+            };
+            let store = match self.stores.get(&node) {
+                Some(old) if widen => old.widen(&store),
+                _ => store,
+            };
+            if self.stores.get(&node) == Some(&store) {
+                return false;
+            }
+            self.stores.insert(node, store);
+        } else {
+            let vs = self.eval_value(node, &ndata);
+            let vs = match self.value_sets.get(&node) {
+                Some(old) if widen => old.widen(&vs),
+                _ => vs,
+            };
+            if self.value_sets.get(&node) == Some(&vs) {
+                return false;
+            }
+            self.value_sets.insert(node, vs);
+        }
+        true
+    }
+
+    // Memory states have a width of 0
+    fn is_memory(&self, ndata: &NodeData) -> bool {
+        if ndata.vt.width().get_width() != Some(0) {
+            return false;
+        }
+        match ndata.nt {
+            NodeType::Op(MOpcode::OpStore) | NodeType::Phi => true,
+            NodeType::Comment(ref c) => c.starts_with("mem"),
+            _ => false,
+        }
+    }
+
+    fn is_memory_node(&self, node: &NodeIndex) -> bool {
+        self.ssa.node_data(*node).map(|nd| self.is_memory(&nd)).unwrap_or(false)
+    }
+
+    fn has_register(&self, node: &NodeIndex, name: &Option<String>) -> bool {
+        match *name {
+            Some(ref name) => self.ssa.registers(*node).contains(name),
+            None => false,
+        }
+    }
+
+    fn is_alloc_site(&self, call: &NodeIndex) -> bool {
+        self.ssa
+            .operands_of(*call)
+            .first()
+            .and_then(|target| self.ssa.comment(*target))
+            .map_or(false, |name| ALLOC_FUNCTIONS.iter().any(|f| name.contains(f)))
+    }
+
+    fn value_of(&self, node: &NodeIndex, k: u8) -> ValueSet<S> {
+        self.value_sets.get(node).cloned().unwrap_or_else(|| ValueSet::empty(k))
+    }
+
+    fn eval_memory(&mut self, node: NodeIndex, ndata: &NodeData) -> Option<AbstractStore<S>> {
+        let operands = self.ssa.operands_of(node);
+        match ndata.nt {
+            NodeType::Comment(_) => {
+                let call = match operands.first() {
+                    Some(call) => *call,
+                    // Memory at function entry
+                    None => return Some(AbstractStore::new()),
+                };
+                let mem = self.ssa
+                    .operands_of(call)
+                    .into_iter()
+                    .find(|op| self.is_memory_node(op))?;
+                let mut store = self.stores.get(&mem)?.clone();
+                // Callees may write to globals, heap and the stack above the
+                // lowest escaped address. An unknown bound is the minimum
+                // offset, which drops every stack a-loc.
+                // XXX: Callees are assumed not to write to the rest of the
+                // stack frame of caller.
+                let escaped = self.escaped_stack();
+                store.retain(|a_loc| {
+                    a_loc.region == MemRegion::Stack &&
+                    escaped.map_or(true, |lowest| {
+                        a_loc.offset.saturating_add(a_loc.size as inum) <= lowest
+                    })
+                });
+                Some(store)
+            }
+            NodeType::Phi => {
+                // Operands not visited yet are skipped
+                operands.iter()
+                    .filter_map(|op| self.stores.get(op))
+                    .fold(None, |acc: Option<AbstractStore<S>>, store| match acc {
+                        Some(acc) => Some(acc.join(store)),
+                        None => Some(store.clone()),
+                    })
+            }
+            NodeType::Op(MOpcode::OpStore) if operands.len() > 2 => {
+                let mut store = self.stores.get(&operands[0])?.clone();
+                let value = self.value_sets.get(&operands[2])?.clone();
+                let addr = self.value_of(&operands[1], value.k);
+                let size = self.size_of(&operands[2]);
+                if let Some(a_locs) = addr.a_locs(size) {
+                    self.a_locs.extend(a_locs);
+                }
+                store.store(&addr, size, &value);
+                Some(store)
+            }
+            _ => None,
+        }
+    }
+
+    fn eval_value(&mut self, node: NodeIndex, ndata: &NodeData) -> ValueSet<S> {
+        let width = ndata.vt.width().get_width();
+        let k = match width {
+            Some(w) if (w > 0) && (w <= _bits as u16) => w as u8,
+            // Values wider than abstract sets are not supported
+            Some(w) if w > _bits as u16 => return ValueSet::top(_bits),
+            _ => 64,
+        };
+        let operands = self.ssa.operands_of(node);
+
+        match ndata.nt {
+            NodeType::Op(MOpcode::OpConst(c)) => {
+                // Constants are 64 bits, which are zero extended to wider values
+                let n = if k <= 64 { c as i64 as inum } else { c as inum };
+                ValueSet::constant(k, n)
+            }
+            NodeType::Op(MOpcode::OpLoad) if operands.len() > 1 => {
+                let addr = self.value_of(&operands[1], k);
+                let size = self.size_of(&node);
+                if let Some(a_locs) = addr.a_locs(size) {
+                    self.a_locs.extend(a_locs);
+                }
+                match self.stores.get(&operands[0]) {
+                    Some(store) => store.load(&addr, size, k),
+                    None => ValueSet::empty(k),
+                }
+            }
+            NodeType::Op(MOpcode::OpNarrow(w)) |
+            NodeType::Op(MOpcode::OpSignExt(w)) if !operands.is_empty() => {
+                self.value_of(&operands[0], k).resize(cmp::min(w, _bits as u16) as u8, true)
+            }
+            NodeType::Op(MOpcode::OpZeroExt(w)) if !operands.is_empty() => {
+                self.value_of(&operands[0], k).resize(cmp::min(w, _bits as u16) as u8, false)
+            }
+            NodeType::Op(MOpcode::OpNot) if !operands.is_empty() => {
+                self.value_of(&operands[0], k).resize(k, true).map_scalar(|x| !x)
+            }
+            NodeType::Op(ref opc) if opc.is_binary() && (operands.len() > 1) => {
+                let lhs = self.value_of(&operands[0], k).resize(k, true);
+                let rhs = self.value_of(&operands[1], k);
+                self.eval_binary_op(opc, &lhs, &rhs, k)
+            }
+            NodeType::Op(_) => ValueSet::top(k),
+            NodeType::Phi => {
+                // Operands not visited yet are skipped
+                operands.iter()
+                    .filter_map(|op| self.value_sets.get(op))
+                    .fold(ValueSet::empty(k), |acc, vs| acc.join(vs))
+            }
+            NodeType::Comment(_) => {
+                match operands.first().cloned() {
+                    // Registers at function entry
+                    None if self.has_register(&node, &self.sp_name) => {
+                        ValueSet::pointer(k, MemRegion::Stack, 0)
+                    }
+                    None => ValueSet::top(k),
+                    // Registers after function call
+                    Some(call) if self.ssa.opcode(call) == Some(MOpcode::OpCall) => {
+                        self.eval_call_result(node, call, k)
+                    }
+                    Some(_) => ValueSet::top(k),
+                }
+            }
+            NodeType::Undefined => ValueSet::top(k),
+        }
+    }
+
+    fn eval_binary_op(&self,
+                      opc: &MOpcode,
+                      lhs: &ValueSet<S>,
+                      rhs: &ValueSet<S>,
+                      k: u8)
+                      -> ValueSet<S> {
+        let divided_by_zero = || rhs.resize(k, true).scalar().map_or(true, |s| s.contains(&0));
+        match *opc {
+            MOpcode::OpAdd => lhs.add(rhs),
+            MOpcode::OpSub => lhs.sub(rhs),
+            MOpcode::OpMul => lhs.scalar_op(rhs, |x, y| x * y),
+            MOpcode::OpDiv if !divided_by_zero() => lhs.scalar_op(rhs, |x, y| x / y),
+            MOpcode::OpMod if !divided_by_zero() => lhs.scalar_op(rhs, |x, y| x % y),
+            MOpcode::OpAnd => lhs.scalar_op(rhs, |x, y| x & y),
+            MOpcode::OpOr => lhs.scalar_op(rhs, |x, y| x | y),
+            MOpcode::OpXor => lhs.scalar_op(rhs, |x, y| x ^ y),
+            MOpcode::OpLsl => lhs.scalar_op(rhs, |x, y| x << y),
+            MOpcode::OpLsr => lhs.scalar_op(rhs, |x, y| x >> y),
+            MOpcode::OpEq | MOpcode::OpGt | MOpcode::OpLt => {
+                ValueSet::constant(k, 0).join(&ValueSet::constant(k, 1))
+            }
+            _ => ValueSet::top(k),
+        }
+    }
+
+    fn eval_call_result(&self, node: NodeIndex, call: NodeIndex, k: u8) -> ValueSet<S> {
+        if self.has_register(&node, &self.sp_name) {
+            // XXX: Callees are assumed to keep the stack pointer
+            let sp = self.ssa
+                .operands_of(call)
+                .into_iter()
+                .find(|op| self.has_register(op, &self.sp_name));
+            match sp {
+                Some(sp) => self.value_of(&sp, k).resize(k, true),
+                None => ValueSet::top(k),
+            }
+        } else if self.has_register(&node, &self.ret_name) && self.is_alloc_site(&call) {
+            ValueSet::pointer(k, MemRegion::Heap(call), 0)
+        } else {
+            ValueSet::top(k)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use analysis::vsa::abstract_set::strided_interval::StridedInterval;
+    use utils::test_utils::{function, il_function, REGISTER_PROFILE};
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const STORE_LOAD_TXT: &str = "\
+define-fun sym.store_load(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rsp;
+        %2: $Unknown0 = $mem;
+    bb_0x001000.0000(sz 0x9):
+        [@0x001000.0001] %3: $Unknown64 = %1 - #x8;
+        [@0x001000.0002] %4: $Unknown0 = Store(%2, %3, #x2a);
+        [@0x001004.0001] %5: $Unknown64 = Load(%4, %3);
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %5;
+        $rsp = %1;
+        $mem = %4;
+}
+";
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const LOOP_TXT: &str = "\
+define-fun sym.fill(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rsp;
+        %2: $Unknown0 = $mem;
+    bb_0x001000.0000(sz 0x4):
+        JMP 0x001004.0000
+    bb_0x001004.0000(sz 0x4):
+        %3: $Unknown64 = Phi(#x0, %4);
+        %5: $Unknown0 = Phi(%2, %6);
+        [@0x001004.0001] %7: $Unknown1 = %3 < #x10;
+        JMP IF %7 0x001008.0000 ELSE 0x001010.0000
+    bb_0x001008.0000(sz 0x8):
+        [@0x001008.0001] %8: $Unknown64 = %3 * #x8;
+        [@0x001008.0002] %9: $Unknown64 = %8 + #x3000;
+        [@0x001008.0003] %6: $Unknown0 = Store(%5, %9, %3);
+        [@0x00100C.0001] %4: $Unknown64 = %3 + #x1;
+        JMP 0x001004.0000
+    bb_0x001010.0000(sz 0x1):
+        RETURN
+    exit-node:
+    final-register-state:
+        $rsp = %1;
+        $mem = %5;
+}
+";
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const WIDE_TXT: &str = "\
+define-fun sym.wide(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rax;
+    bb_0x001000.0000(sz 0x4):
+        [@0x001000.0001] %2: $Unknown128 = ZeroExt128(#xffffffffffffffff);
+        [@0x001000.0002] %3: $Unknown128 = %2 * %2;
+        [@0x001000.0003] %4: $Unknown256 = ZeroExt256(%3);
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %1;
+}
+";
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const ESCAPED_OPS: &str = r#"[{"offset":4096,"size":4,"opcode":"sub rsp, 0x10","esil":"16,rsp,-=","type":"sub"},
+                      {"offset":4100,"size":5,"opcode":"mov qword [rsp + 8], 0x2a","esil":"42,8,rsp,+,=[8]","type":"mov"},
+                      {"offset":4105,"size":5,"opcode":"lea rsi, [rsp + 8]","esil":"8,rsp,+,rsi,=","type":"lea"},
+                      {"offset":4110,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4115,"size":5,"opcode":"mov rax, qword [rsp + 8]","esil":"8,rsp,+,[8],rax,=","type":"mov"},
+                      {"offset":4120,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4124,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+
+    fn nodes(rfn: &RadecoFunction, opcode: MOpcode) -> Vec<NodeIndex> {
+        rfn.ssa()
+            .values()
+            .into_iter()
+            .filter(|&n| rfn.ssa().opcode(n) == Some(opcode.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn store_load_test() {
+        let rfn = il_function(STORE_LOAD_TXT);
+        let mut vsa = ValueSetAnalyzer::<StridedInterval>::new(&rfn);
+        vsa.analyze();
+
+        // The slot below the return address is written, then read back.
+        let slot = ALoc::new(MemRegion::Stack, -8, 8);
+        let store = nodes(&rfn, MOpcode::OpStore)[0];
+        assert_eq!(vsa.accessed_a_locs(&store), Some(vec![slot]));
+        assert_eq!(vsa.store(&store).and_then(|s| s.get(&slot)).and_then(|vs| vs.constant_value()),
+                   Some(0x2a));
+        let load = nodes(&rfn, MOpcode::OpLoad)[0];
+        assert_eq!(vsa.accessed_a_locs(&load), Some(vec![slot]));
+        assert_eq!(vsa.value_set(&load).and_then(|vs| vs.constant_value()), Some(0x2a));
+        assert_eq!(vsa.a_locs().len(), 1);
+    }
+
+    #[test]
+    fn loop_index_test() {
+        let rfn = il_function(LOOP_TXT);
+        let mut vsa = ValueSetAnalyzer::<StridedInterval>::new(&rfn);
+        vsa.analyze();
+
+        let store = nodes(&rfn, MOpcode::OpStore)[0];
+        let operands = rfn.ssa().operands_of(store);
+
+        // The index is widened upwards, its lower bound is kept.
+        let index = vsa.value_set(&operands[2]).and_then(|vs| vs.scalar()).unwrap();
+        assert_eq!(index.constant(), None);
+        assert!(index.contains(&0) && index.contains(&0x10) && index.contains(&0x1000));
+        assert!(!index.contains(&-1));
+
+        // Every element of the array is written, no a-loc in particular.
+        let addr = vsa.value_set(&operands[1]).and_then(|vs| vs.get(&MemRegion::Global)).unwrap();
+        assert!(addr.contains(&0x3000) && addr.contains(&0x3008));
+        assert_eq!(vsa.accessed_regions(&store), Some(vec![MemRegion::Global]));
+        assert_eq!(vsa.accessed_a_locs(&store), None);
+        assert!(vsa.a_locs().is_empty());
+        assert!(vsa.store(&operands[0]).is_some());
+    }
+
+    #[test]
+    fn escaped_call_test() {
+        // The address of the slot is passed in `rsi`, the callee may write
+        // to it. The stack pointer at the call escapes as well.
+        let rfn = function(REGISTER_PROFILE, ESCAPED_OPS, true);
+        let mut vsa = ValueSetAnalyzer::<StridedInterval>::new(&rfn);
+        vsa.analyze();
+
+        assert_eq!(vsa.escaped_stack(), Some(-0x10));
+        let store = nodes(&rfn, MOpcode::OpStore)[0];
+        assert_eq!(vsa.store(&store).and_then(|s| s.get(&ALoc::new(MemRegion::Stack, -8, 8)))
+                       .and_then(|vs| vs.constant_value()),
+                   Some(0x2a));
+        let load = nodes(&rfn, MOpcode::OpLoad)[0];
+        assert_eq!(vsa.accessed_a_locs(&load), Some(vec![ALoc::new(MemRegion::Stack, -8, 8)]));
+        assert!(vsa.value_set(&load).unwrap().is_top());
+    }
+
+    #[test]
+    fn wide_values_test() {
+        let rfn = il_function(WIDE_TXT);
+        let mut vsa = ValueSetAnalyzer::<StridedInterval>::new(&rfn);
+        vsa.analyze();
+
+        let ext = nodes(&rfn, MOpcode::OpZeroExt(128))[0];
+        assert_eq!(vsa.value_set(&ext).and_then(|vs| vs.constant_value()),
+                   Some(0xffffffffffffffff));
+        let mul = nodes(&rfn, MOpcode::OpMul)[0];
+        assert_eq!(vsa.value_set(&mul).and_then(|vs| vs.constant_value()),
+                   Some(0xfffffffffffffffe0000000000000001u128 as inum));
+        // Wider than abstract sets, only SCCP folds 256-bit constants
+        let wide = nodes(&rfn, MOpcode::OpZeroExt(256))[0];
+        assert!(vsa.value_set(&wide).unwrap().is_top());
+    }
+}
@@ -0,0 +1,91 @@
+//! This module contains common ARM and AArch64 idioms as grep and replace
+//! patterns which maybe used for further reduction of the SSA form.
+//!
+//! The NZCV flags are set by comparisons as in `x1,x0,==,$z,zf,=,$s,nf,=,...`
+//! and conditions are tested on them, e.g. `nf,vf,^` for `lt`. Unlike x86,
+//! the carry flag is set if there was no borrow.
+//!
+//! NOTE: This module is not stable.
+
+use analysis::matcher::gmatch;
+use middle::ssa::ssa_traits::{SSAMod, SSAWalk, SSA};
+
+mod patterns {
+    pub const N: &'static str = "(OpNarrow1 (OpLsr (OpSub %2, %3), (OpSub #x40, #x1)))";
+    pub const N_32: &'static str = "(OpNarrow1 (OpLsr (OpSub %2, %3), (OpSub #x20, #x1)))";
+    pub const Z: &'static str = "(OpNarrow1 (OpXor #x1, (OpAnd (OpSub %2, %3), #xffffffffffffffff)))";
+    pub const Z_32: &'static str = "(OpNarrow1 (OpXor #x1, (OpAnd (OpSub %2, %3), #xffffffff)))";
+
+    pub const PATTERNS: &'static [(&'static str, &'static str)] = &[
+        ("(OpXor %1, %1)", "#x0"),
+        ("(OpXor %1, #x0)", "%1"),
+        ("(OpAnd %1, #x0)", "#x0"),
+        ("(OpOr %1, #x0)", "%1"),
+        ("(OpSub %1, %1)", "#x0"),
+        ("(OpNot (OpNot %1))", "%1"),
+    ];
+
+    lazy_static! {
+        pub static ref COMPARE_PATTERNS: Vec<(String, &'static str)> = {
+            let mut v = Vec::new();
+            for &(z, n) in &[(Z, N), (Z_32, N_32)] {
+                // The overflow flag is zero once constants are propagated.
+                // LE - zf,nf,vf,^,|
+                v.push((format!("(OpOr {}, (OpXor {}, (OpNarrow1 #x0)))", z, n),
+                        "(OpNot (OpGt %2, %3))"));
+                // GE - nf,vf,^,!
+                v.push((format!("(OpNot (OpXor {}, (OpNarrow1 #x0)))", n), "(OpNot (OpLt %2, %3))"));
+                // LT - nf,vf,^
+                v.push((format!("(OpXor {}, (OpNarrow1 #x0))", n), "(OpLt %2, %3)"));
+                // EQ - zf
+                v.push((format!("{}", z), "(OpEq %2, %3)"));
+            }
+            v
+        };
+    }
+}
+
+pub fn replace<I, S>(ssa: &mut S)
+where I: Iterator<Item=S::ValueRef>,
+      S: SSA + SSAMod + SSAWalk<I> {
+    for pat in patterns::PATTERNS {
+        grep_and_replace!(ssa, pat.0 => pat.1)
+    }
+    for &(ref find, replace) in patterns::COMPARE_PATTERNS.iter() {
+        grep_and_replace!(ssa, find => replace)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use analysis::sccp;
+    use frontend::radeco_containers::ProjectLoader;
+    use frontend::radeco_source::FileSource;
+    use middle::dce;
+    use middle::ir::MOpcode;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    #[test]
+    fn arm32_max_test() {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_files/arm32_filesource/arm32");
+        let source = FileSource::open(path.to_str().unwrap());
+        let rproj = ProjectLoader::new().source(Rc::new(source)).load();
+        let rmod = rproj.nth_module(0).unwrap();
+        let max = rmod.functions.values().find(|rfn| rfn.name == "sym.max").unwrap();
+
+        // `movlt r0, r1` tests `nf,vf,^`, which is `r0 < r1` once the
+        // overflow flag of the compare is propagated.
+        let mut ssa = max.ssa().clone();
+        let mut ssa = {
+            let mut analyzer = sccp::Analyzer::new(&mut ssa);
+            analyzer.analyze();
+            analyzer.emit_ssa()
+        };
+        replace(&mut ssa);
+        dce::collect(&mut ssa);
+        assert!(ssa.values().into_iter().any(|n| ssa.opcode(n) == Some(MOpcode::OpLt)));
+    }
+}
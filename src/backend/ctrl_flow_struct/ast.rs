@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 // B: basic block
 // C: condition
 // V: variable
@@ -10,7 +12,7 @@ pub enum AstNode<B, C, V> {
         Box<AstNode<B, C, V>>,
         Option<Box<AstNode<B, C, V>>>,
     ),
-    Loop(LoopType<C>, Box<AstNode<B, C, V>>),
+    Loop(LoopType<B, C, V>, Box<AstNode<B, C, V>>),
     Break,
     Switch(
         V,
@@ -20,13 +22,16 @@ pub enum AstNode<B, C, V> {
 }
 
 #[derive(Debug, Eq, PartialEq)]
-pub enum LoopType<C> {
+pub enum LoopType<B, C, V> {
     PreChecked(C),
     PostChecked(C),
     Endless,
+    /// `for (init; cond; step)`; `step` is not part of the loop body.
+    For(Box<AstNode<B, C, V>>, C, Box<AstNode<B, C, V>>),
 }
 
-pub type ValueSet = (); // XXX
+/// The values a `switch` case is taken for.
+pub type ValueSet = BTreeSet<u64>;
 
 impl<B, C, V> Default for AstNode<B, C, V> {
     /// Creates a no-op node.
@@ -34,3 +34,32 @@ pub trait AstContextMut: AstContext {
         cond: &Self::Condition,
     ) -> Self::Block;
 }
+
+/// Inspects the statements of `Block`s. Only used to make the output more
+/// readable, so implementations may answer conservatively.
+pub trait AstContextQuery: AstContext {
+    /// Returns the variable assigned by the last statement of `block`, if that
+    /// statement is an assignment to a variable.
+    fn last_assigned_var(&self, block: &Self::Block) -> Option<Self::Variable>;
+
+    /// Returns true if the last statement of `block` reads the value of `var`.
+    fn last_stmt_reads_var(&self, block: &Self::Block, var: &Self::Variable) -> bool;
+
+    /// Returns true if `cond` reads the value of `var`.
+    fn cond_reads_var(&self, cond: &Self::Condition, var: &Self::Variable) -> bool;
+
+    /// Returns the variable and the constant it is compared with, if `cond`
+    /// tests a variable for equality with a constant.
+    fn cond_equals(&self, cond: &Self::Condition) -> Option<(Self::Variable, u64)>;
+
+    /// Returns true if `var` holds the counter of a loop, i.e. the induction
+    /// variable compared by its exit test, see `analysis::loops`.
+    fn is_induction_var(&self, var: &Self::Variable) -> bool;
+
+    /// Returns true if `var1` and `var2` refer to the same variable.
+    fn is_same_var(&self, var1: &Self::Variable, var2: &Self::Variable) -> bool;
+
+    /// Splits `block` into the statements before its last statement, or
+    /// `None` if there are none, and its last statement.
+    fn split_last_stmt(&self, block: Self::Block) -> (Option<Self::Block>, Self::Block);
+}
@@ -298,3 +298,41 @@ fn nested_complementation() {
     let a_and_b = cctx.mk_and(a, b);
     assert_eq!(cctx.mk_and(cctx.mk_or(a_and_b, c), cctx.mk_or(a_and_b, nc)), a_and_b);
 }
+
+// Collects the literals of a condition, with `!` prefixed to negated ones.
+struct Literals;
+
+impl Folder<&'static str> for Literals {
+    type Output = Vec<String>;
+    fn var(&mut self, negated: bool, var: &&'static str) -> Vec<String> {
+        vec![format!("{}{}", if negated { "!" } else { "" }, var)]
+    }
+    fn and<'a, I>(&mut self, operands: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = Condition<'a, &'static str>>,
+        &'static str: 'a,
+    {
+        operands.into_iter().flat_map(|c| c.fold(Literals)).collect()
+    }
+    fn or<'a, I>(&mut self, operands: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = Condition<'a, &'static str>>,
+        &'static str: 'a,
+    {
+        operands.into_iter().flat_map(|c| c.fold(Literals)).collect()
+    }
+}
+
+#[test]
+fn fold_negation() {
+    let cstore = Storage::new();
+    let cctx = cstore.cctx();
+    let a = cctx.mk_var(cctx.new_var("a"));
+    let b = cctx.mk_var(cctx.new_var("b"));
+
+    assert_eq!(a.fold(Literals), vec!["a"]);
+    assert_eq!(cctx.mk_not(a).fold(Literals), vec!["!a"]);
+    let mut literals = cctx.mk_or(a, cctx.mk_not(b)).fold(Literals);
+    literals.sort();
+    assert_eq!(literals, vec!["!b", "a"]);
+}
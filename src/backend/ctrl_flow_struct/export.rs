@@ -58,13 +58,40 @@ impl<'a> Exporter<'a> {
                 let b = self.go(*b)?;
                 Ok(vec![self.conv.ast_mut().new_do_while(c, b)])
             }
+            Loop(For(i, c, s), b) => {
+                let i = self.go(*i)?;
+                let c = c.fold(&mut *self)?;
+                let s = self.go(*s)?;
+                let mut b = self.go(*b)?;
+                if i.len() == 1 && s.len() == 1 {
+                    Ok(vec![self.conv.ast_mut().new_for(i[0], c, s[0], b)])
+                } else {
+                    // `init; while (c) { body; step }`
+                    b.extend(s);
+                    let mut ret = i;
+                    ret.push(self.conv.ast_mut().new_while(c, b));
+                    Ok(ret)
+                }
+            }
             Loop(Endless, b) => {
                 let c = self.const_one;
                 let b = self.go(*b)?;
                 Ok(vec![self.conv.ast_mut().new_while(c, b)])
             }
             Break => Ok(vec![self.conv.ast_mut().insert_break()]),
-            Switch(_, _, _) => unimplemented!(), // TODO
+            Switch(v, cases, default) => {
+                let v = self
+                    .conv
+                    .get_node(v)
+                    .ok_or("unconverted switch variable")?;
+                let cases = cases
+                    .into_iter()
+                    // Case values are printed signed.
+                    .map(|(vs, a)| Ok((vs.into_iter().map(|v| v as i64).collect(), self.go(a)?)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let default = self.go(*default)?;
+                Ok(vec![self.conv.ast_mut().new_switch(v, cases, default)])
+            }
         }
     }
 
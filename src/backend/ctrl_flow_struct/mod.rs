@@ -16,6 +16,7 @@ mod refinement;
 mod test;
 
 use self::ast::AstNode as AstNodeC;
+use self::ast::LoopType;
 use self::ast_context::*;
 use self::graph_utils::ix_bit_set::IxBitSet;
 
@@ -62,7 +63,7 @@ type CondContext<'cd, A> = condition::Context<'cd, <A as AstContext>::Condition>
 type AstNode<'cd, A> =
     ast::AstNode<<A as AstContext>::Block, Condition<'cd, A>, <A as AstContext>::Variable>;
 
-impl<'cd, A: AstContextMut> ControlFlowGraph<'cd, A> {
+impl<'cd, A: AstContextMut + AstContextQuery> ControlFlowGraph<'cd, A> {
     /// Preconditions:
     /// - `entry` must be a source
     /// - all nodes must be reachable from `entry`
@@ -188,6 +189,8 @@ impl<'cd, A: AstContextMut> ControlFlowGraph<'cd, A> {
         debug_assert!(self.graph.node_count() == 0);
 
         if let CfgNode::Code(ret) = ret {
+            let ret = refinement::refine_for_loops(&self.actx, ret);
+            let ret = refinement::refine_switches(&self.actx, ret);
             (ret, self.actx)
         } else {
             panic!("last node wasn't a Code node")
@@ -491,7 +494,19 @@ impl<'cd, A: AstContext> RegionAstContext<'cd, A> {
                 Box::new(Self::export(*t)),
                 oe.map(|e| Box::new(Self::export(*e))),
             ),
-            Loop(t, b) => Loop(t, Box::new(Self::export(*b))),
+            Loop(t, b) => {
+                let t = match t {
+                    LoopType::PreChecked(c) => LoopType::PreChecked(c),
+                    LoopType::PostChecked(c) => LoopType::PostChecked(c),
+                    LoopType::Endless => LoopType::Endless,
+                    LoopType::For(i, c, s) => LoopType::For(
+                        Box::new(Self::export(*i)),
+                        c,
+                        Box::new(Self::export(*s)),
+                    ),
+                };
+                Loop(t, Box::new(Self::export(*b)))
+            }
             Break => Break,
             Switch(v, cases, default) => Switch(
                 v,
@@ -2,8 +2,9 @@
 //!
 //! Everything in this module does not impact correctness, only readability.
 
-use super::ast::LoopType;
-use super::ast_context::AstContext;
+use super::ast::{LoopType, ValueSet};
+use super::ast_context::{AstContext, AstContextQuery};
+use super::condition;
 use super::graph_utils;
 use super::{AstNode, AstNodeC, CondContext, Condition, NodeSet};
 
@@ -12,7 +13,7 @@ use petgraph::prelude::*;
 use petgraph::visit::{IntoNodeReferences, Topo, Walker};
 
 use std::collections::HashMap;
-use std::iter::FromIterator;
+use std::iter::{self, FromIterator};
 
 pub(super) struct Refiner<'cd, A: AstContext> {
     pub cctx: CondContext<'cd, A>,
@@ -36,7 +37,7 @@ impl<'cd, A: AstContext> Refiner<'cd, A> {
     fn refine(mut self) -> AstNode<'cd, A> {
         self.try_find_if_else_pair();
         self.try_find_if();
-        // TODO: find switch
+        // switches are built from the resulting cascades in `refine_switches`
         self.try_find_if_else_cascade();
 
         // move all nodes into a vec in topological order
@@ -595,6 +596,401 @@ impl<'cd, A: AstContext> LoopRefiner<'cd, A> {
     }}
 }
 
+/// Turns `init; while (c) { body; step }` into `for (init; c; step) { body }`
+/// if `init` and `step` assign the same variable, which the loop analysis found
+/// to be the counter of the loop, and both `c` and `step` read it. This is
+/// always sound since the AST has no `continue`.
+pub(super) fn refine_for_loops<'cd, A: AstContextQuery>(
+    actx: &A,
+    ast: AstNode<'cd, A>,
+) -> AstNode<'cd, A> {
+    use self::AstNodeC::*;
+    match ast {
+        BasicBlock(b) => BasicBlock(b),
+        Seq(seq) => {
+            let mut new_seq = Vec::with_capacity(seq.len());
+            for a in seq {
+                let a = refine_for_loops(actx, a);
+                match (new_seq.pop(), a) {
+                    (Some(BasicBlock(init)), Loop(LoopType::PreChecked(c), body)) => {
+                        let (opt_rest, loop_ast) = mk_for_loop(actx, init, c, *body);
+                        if let Some(rest) = opt_rest {
+                            new_seq.push(BasicBlock(rest));
+                        }
+                        new_seq.push(loop_ast);
+                    }
+                    (opt_prev, a) => {
+                        new_seq.extend(opt_prev);
+                        new_seq.push(a);
+                    }
+                }
+            }
+            Seq(new_seq)
+        }
+        Cond(c, t, oe) => Cond(
+            c,
+            Box::new(refine_for_loops(actx, *t)),
+            oe.map(|e| Box::new(refine_for_loops(actx, *e))),
+        ),
+        Loop(t, b) => Loop(t, Box::new(refine_for_loops(actx, *b))),
+        Break => Break,
+        Switch(v, cases, default) => Switch(
+            v,
+            cases
+                .into_iter()
+                .map(|(vs, a)| (vs, refine_for_loops(actx, a)))
+                .collect(),
+            Box::new(refine_for_loops(actx, *default)),
+        ),
+    }
+}
+
+/// Builds a `for` loop out of the `while` loop with condition `cond` and body
+/// `body` that is preceded by `init`. Returns the remainder of `init` and the
+/// new loop, or `init` and the unchanged `while` loop if it doesn't match.
+fn mk_for_loop<'cd, A: AstContextQuery>(
+    actx: &A,
+    init: A::Block,
+    cond: Condition<'cd, A>,
+    body: AstNode<'cd, A>,
+) -> (Option<A::Block>, AstNode<'cd, A>) {
+    use self::AstNodeC::*;
+    let var = match actx.last_assigned_var(&init) {
+        Some(var) => var,
+        None => return (Some(init), Loop(LoopType::PreChecked(cond), Box::new(body))),
+    };
+    if !actx.is_induction_var(&var) || !cond.fold(CondReadsVar { actx, var: &var }) {
+        return (Some(init), Loop(LoopType::PreChecked(cond), Box::new(body)));
+    }
+    match split_step(actx, &var, body) {
+        Ok((body, step)) => {
+            let (opt_rest, init) = actx.split_last_stmt(init);
+            let for_loop = LoopType::For(
+                Box::new(BasicBlock(init)),
+                cond,
+                Box::new(BasicBlock(step)),
+            );
+            (opt_rest, Loop(for_loop, Box::new(body)))
+        }
+        Err(body) => (Some(init), Loop(LoopType::PreChecked(cond), Box::new(body))),
+    }
+}
+
+/// Splits the last statement off `body` if it assigns `var` from a value that
+/// reads `var`.
+fn split_step<'cd, A: AstContextQuery>(
+    actx: &A,
+    var: &A::Variable,
+    body: AstNode<'cd, A>,
+) -> Result<(AstNode<'cd, A>, A::Block), AstNode<'cd, A>> {
+    use self::AstNodeC::*;
+    let is_step = |b: &A::Block| {
+        actx.last_assigned_var(b).map_or(false, |v| actx.is_same_var(&v, var))
+            && actx.last_stmt_reads_var(b, var)
+    };
+    match body {
+        BasicBlock(b) => if is_step(&b) {
+            let (opt_rest, step) = actx.split_last_stmt(b);
+            Ok((opt_rest.map(BasicBlock).unwrap_or_default(), step))
+        } else {
+            Err(BasicBlock(b))
+        },
+        Seq(mut seq) => match seq.pop() {
+            Some(BasicBlock(b)) => if is_step(&b) {
+                let (opt_rest, step) = actx.split_last_stmt(b);
+                seq.extend(opt_rest.map(BasicBlock));
+                Ok((mk_seq_vec(seq), step))
+            } else {
+                seq.push(BasicBlock(b));
+                Err(Seq(seq))
+            },
+            opt_last => {
+                seq.extend(opt_last);
+                Err(Seq(seq))
+            }
+        },
+        _ => Err(body),
+    }
+}
+
+struct CondReadsVar<'a, A: AstContextQuery + 'a>
+where
+    A::Variable: 'a,
+{
+    actx: &'a A,
+    var: &'a A::Variable,
+}
+impl<'a, A: AstContextQuery> Copy for CondReadsVar<'a, A> where A::Variable: 'a {}
+impl<'a, A: AstContextQuery> Clone for CondReadsVar<'a, A>
+where
+    A::Variable: 'a,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, A: AstContextQuery> condition::Folder<A::Condition> for CondReadsVar<'a, A>
+where
+    A::Variable: 'a,
+{
+    type Output = bool;
+    fn var(&mut self, _negated: bool, cond: &A::Condition) -> bool {
+        self.actx.cond_reads_var(cond, self.var)
+    }
+
+    fn and<'c, I>(&mut self, operands: I) -> bool
+    where
+        I: IntoIterator<Item = condition::Condition<'c, A::Condition>>,
+        A::Condition: 'c,
+    {
+        operands.into_iter().any(|c| c.fold(*self))
+    }
+
+    fn or<'c, I>(&mut self, operands: I) -> bool
+    where
+        I: IntoIterator<Item = condition::Condition<'c, A::Condition>>,
+        A::Condition: 'c,
+    {
+        operands.into_iter().any(|c| c.fold(*self))
+    }
+}
+
+/// Turns `if`-`else` cascades that compare a single variable against
+/// constants into `switch`es.
+pub(super) fn refine_switches<'cd, A: AstContextQuery>(
+    actx: &A,
+    ast: AstNode<'cd, A>,
+) -> AstNode<'cd, A> {
+    use self::AstNodeC::*;
+    match ast {
+        BasicBlock(b) => BasicBlock(b),
+        Seq(seq) => Seq(seq.into_iter().map(|a| refine_switches(actx, a)).collect()),
+        Cond(c, t, oe) => {
+            let cond = Cond(c, t, oe);
+            // a `break` in a case would leave the `switch` instead of the loop
+            if contains_break(&cond) {
+                refine_cond_children(actx, cond)
+            } else {
+                mk_switch(actx, cond)
+            }
+        }
+        Loop(t, b) => Loop(t, Box::new(refine_switches(actx, *b))),
+        Break => Break,
+        Switch(v, cases, default) => Switch(
+            v,
+            cases
+                .into_iter()
+                .map(|(vs, a)| (vs, refine_switches(actx, a)))
+                .collect(),
+            Box::new(refine_switches(actx, *default)),
+        ),
+    }
+}
+
+fn refine_cond_children<'cd, A: AstContextQuery>(
+    actx: &A,
+    ast: AstNode<'cd, A>,
+) -> AstNode<'cd, A> {
+    match ast {
+        AstNodeC::Cond(c, t, oe) => AstNodeC::Cond(
+            c,
+            Box::new(refine_switches(actx, *t)),
+            oe.map(|e| Box::new(refine_switches(actx, *e))),
+        ),
+        ast => refine_switches(actx, ast),
+    }
+}
+
+/// Collects the cases of the cascade starting at `cascade` and builds a
+/// `switch` out of them if there are at least two.
+fn mk_switch<'cd, A: AstContextQuery>(actx: &A, cascade: AstNode<'cd, A>) -> AstNode<'cd, A> {
+    use self::AstNodeC::*;
+    let mut var = None;
+    let mut seen = ValueSet::new();
+    // (condition, values, case, whether the case is the else branch)
+    let mut levels = Vec::new();
+    let mut next = cascade;
+    let default = loop {
+        let (c, t, oe) = match next {
+            Cond(c, t, oe) => (c, t, oe),
+            ast => break Some(ast),
+        };
+        let opt_case = c.fold(CaseCondFolder { actx }).and_then(|case| {
+            let same_var = var.as_ref().map_or(true, |v| actx.is_same_var(v, &case.var));
+            // values tested before can't reach this case
+            let values: ValueSet = case.values.difference(&seen).cloned().collect();
+            if !same_var || values.is_empty() {
+                return None;
+            }
+            if var.is_none() {
+                var = Some(case.var);
+            }
+            Some((values, case.negated))
+        });
+        let (values, negated) = match opt_case {
+            Some(case) => case,
+            None => break Some(Cond(c, t, oe)),
+        };
+        seen.extend(values.iter().cloned());
+        // `if (var not in values) t else e` has the case in the else branch
+        let (case, rest) = if negated {
+            (oe.map(|e| *e), Some(*t))
+        } else {
+            (Some(*t), oe.map(|e| *e))
+        };
+        levels.push((c, values, case, negated));
+        match rest {
+            Some(rest) => next = rest,
+            None => break None,
+        }
+    };
+
+    if levels.len() < 2 {
+        var = None;
+    }
+    match var {
+        Some(var) => {
+            let mut cases: Vec<_> = levels
+                .into_iter()
+                .map(|(_, vs, a, _)| (vs, refine_switches(actx, a.unwrap_or_default())))
+                .collect();
+            cases.sort_by_key(|&(ref vs, _)| vs.iter().next().cloned());
+            let default = refine_switches(actx, default.unwrap_or_default());
+            Switch(var, cases, Box::new(default))
+        }
+        None => {
+            // rebuild the cascade
+            let mut ast = default;
+            for (c, _, a, negated) in levels.into_iter().rev() {
+                ast = Some(if negated {
+                    Cond(c, Box::new(ast.unwrap_or_default()), a.map(Box::new))
+                } else {
+                    Cond(c, Box::new(a.unwrap_or_default()), ast.map(Box::new))
+                });
+            }
+            refine_cond_children(actx, ast.unwrap_or_default())
+        }
+    }
+}
+
+/// A condition that holds iff `var` is in `values`, or iff it is not if
+/// `negated` is set.
+struct CaseCond<V> {
+    var: V,
+    values: ValueSet,
+    negated: bool,
+}
+
+struct CaseCondFolder<'a, A: AstContextQuery + 'a> {
+    actx: &'a A,
+}
+impl<'a, A: AstContextQuery> Copy for CaseCondFolder<'a, A> {}
+impl<'a, A: AstContextQuery> Clone for CaseCondFolder<'a, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, A: AstContextQuery> CaseCondFolder<'a, A> {
+    /// Folds all `operands`, which must compare the same variable.
+    fn fold_all<'c, I>(&self, operands: I) -> Option<(A::Variable, Vec<(ValueSet, bool)>)>
+    where
+        I: IntoIterator<Item = condition::Condition<'c, A::Condition>>,
+        A::Condition: 'c,
+    {
+        let mut var = None;
+        let mut sets = Vec::new();
+        for opn in operands {
+            let case = opn.fold(*self)?;
+            if !var.as_ref().map_or(true, |v| self.actx.is_same_var(v, &case.var)) {
+                return None;
+            }
+            if var.is_none() {
+                var = Some(case.var);
+            }
+            sets.push((case.values, case.negated));
+        }
+        var.map(|v| (v, sets))
+    }
+}
+
+impl<'a, A: AstContextQuery> condition::Folder<A::Condition> for CaseCondFolder<'a, A> {
+    type Output = Option<CaseCond<A::Variable>>;
+    fn var(&mut self, negated: bool, cond: &A::Condition) -> Self::Output {
+        let (var, val) = self.actx.cond_equals(cond)?;
+        Some(CaseCond {
+            var,
+            values: iter::once(val).collect(),
+            negated,
+        })
+    }
+
+    fn and<'c, I>(&mut self, operands: I) -> Self::Output
+    where
+        I: IntoIterator<Item = condition::Condition<'c, A::Condition>>,
+        A::Condition: 'c,
+    {
+        // in A and in B = in (A & B); not in A and not in B = not in (A | B)
+        let (var, sets) = self.fold_all(operands)?;
+        let mut included: Option<ValueSet> = None;
+        let mut excluded = ValueSet::new();
+        for (vs, negated) in sets {
+            if negated {
+                excluded.extend(vs);
+            } else {
+                included = Some(match included {
+                    Some(inc) => inc.intersection(&vs).cloned().collect(),
+                    None => vs,
+                });
+            }
+        }
+        match included {
+            Some(inc) => {
+                let values: ValueSet = inc.difference(&excluded).cloned().collect();
+                if values.is_empty() {
+                    None
+                } else {
+                    Some(CaseCond { var, values, negated: false })
+                }
+            }
+            None => Some(CaseCond { var, values: excluded, negated: true }),
+        }
+    }
+
+    fn or<'c, I>(&mut self, operands: I) -> Self::Output
+    where
+        I: IntoIterator<Item = condition::Condition<'c, A::Condition>>,
+        A::Condition: 'c,
+    {
+        // in A or in B = in (A | B); not in A or not in B = not in (A & B)
+        let (var, sets) = self.fold_all(operands)?;
+        let mut included = ValueSet::new();
+        let mut excluded: Option<ValueSet> = None;
+        for (vs, negated) in sets {
+            if negated {
+                excluded = Some(match excluded {
+                    Some(exc) => exc.intersection(&vs).cloned().collect(),
+                    None => vs,
+                });
+            } else {
+                included.extend(vs);
+            }
+        }
+        match excluded {
+            Some(exc) => {
+                let values: ValueSet = exc.difference(&included).cloned().collect();
+                if values.is_empty() {
+                    None
+                } else {
+                    Some(CaseCond { var, values, negated: true })
+                }
+            }
+            None => Some(CaseCond { var, values: included, negated: false }),
+        }
+    }
+}
+
 fn contains_break<B, C, V>(ast: &AstNodeC<B, C, V>) -> bool {
     use self::AstNodeC::*;
     match ast {
@@ -5,6 +5,8 @@ use super::*;
 use super::CfgEdge::False as CEFalse;
 use super::CfgEdge::True as CETrue;
 
+use std::iter;
+
 // NOTE: If a loop dominates the exit node, the algorithm tends to "suck" the
 // `return` up into the loop body, which may end up not testing what you wanted.
 // To work around this, simply add an additional branch at the entry node that
@@ -13,6 +15,7 @@ use super::CfgEdge::True as CETrue;
 #[derive(Default, Debug)]
 struct StringAst {
     vars: Vec<Option<u64>>,
+    induction_vars: Vec<String>,
 }
 
 impl AstContext for StringAst {
@@ -58,6 +61,52 @@ impl AstContextMut for StringAst {
     }
 }
 
+// Statements of a block are separated by "; ". An assignment is written as
+// "var = value" and reads every word of `value`. A condition "var == 3"
+// compares `var` with a constant.
+impl AstContextQuery for StringAst {
+    fn last_assigned_var(&self, block: &String) -> Option<String> {
+        let stmt = block.rsplit("; ").next()?;
+        let mut parts = stmt.splitn(2, " = ");
+        let var = parts.next()?;
+        parts.next().map(|_| var.to_owned())
+    }
+
+    fn last_stmt_reads_var(&self, block: &String, var: &String) -> bool {
+        block
+            .rsplit("; ")
+            .next()
+            .and_then(|stmt| stmt.splitn(2, " = ").nth(1))
+            .map_or(false, |value| value.split_whitespace().any(|w| w == var))
+    }
+
+    fn cond_reads_var(&self, cond: &String, var: &String) -> bool {
+        cond.split_whitespace().any(|w| w == var)
+    }
+
+    fn cond_equals(&self, cond: &String) -> Option<(String, u64)> {
+        let mut parts = cond.splitn(2, " == ");
+        let var = parts.next()?;
+        let val = parts.next()?.parse().ok()?;
+        Some((var.to_owned(), val))
+    }
+
+    fn is_induction_var(&self, var: &String) -> bool {
+        self.induction_vars.contains(var)
+    }
+
+    fn is_same_var(&self, var1: &String, var2: &String) -> bool {
+        var1 == var2
+    }
+
+    fn split_last_stmt(&self, block: String) -> (Option<String>, String) {
+        match block.rfind("; ") {
+            Some(i) => (Some(block[..i].to_owned()), block[i + 2..].to_owned()),
+            None => (None, block),
+        }
+    }
+}
+
 #[test]
 fn ast_nmg_example() {
     let cstore = condition::Storage::new();
@@ -432,14 +481,13 @@ fn ast_switchy() {
     println!("{:#?}", ast);
 
     use self::AstNodeC::*;
-    // XXX: need actual value set impl
     assert_eq!(
         Seq(vec![
             Switch(
                 "n".to_owned(),
                 vec![
-                    ((), BasicBlock("n1".to_owned())),
-                    ((), BasicBlock("n2".to_owned())),
+                    (vec![1, 4, 7, 98].into_iter().collect(), BasicBlock("n1".to_owned())),
+                    (vec![34, 88].into_iter().collect(), BasicBlock("n2".to_owned())),
                 ],
                 Box::new(BasicBlock("n3".to_owned())),
             ),
@@ -449,6 +497,65 @@ fn ast_switchy() {
     );
 }
 
+#[test]
+fn ast_switch_cascade() {
+    /*
+     * switch (x) {
+     * case 0:
+     *   puts("n0");
+     *   break;
+     * case 1:
+     *   puts("n1");
+     *   break;
+     * default:
+     *   puts("n2");
+     *   break;
+     * }
+     */
+    let cstore = condition::Storage::new();
+    let cctx = cstore.cctx();
+
+    let v_c0 = cond_s(cctx, "x == 0");
+    let v_c1 = cond_s(cctx, "x == 1");
+
+    let mut graph = StableDiGraph::new();
+    let c0 = graph.add_node(cnode(v_c0));
+    let c1 = graph.add_node(cnode(v_c1));
+    let n0 = graph.add_node(node("n0"));
+    let n1 = graph.add_node(node("n1"));
+    let n2 = graph.add_node(node("n2"));
+    let exit = graph.add_node(node("return"));
+
+    graph.add_edge(c0, n0, CETrue);
+    graph.add_edge(c0, c1, CEFalse);
+    graph.add_edge(c1, n1, CETrue);
+    graph.add_edge(c1, n2, CEFalse);
+    graph.add_edge(n0, exit, CETrue);
+    graph.add_edge(n1, exit, CETrue);
+    graph.add_edge(n2, exit, CETrue);
+
+    let actx = StringAst::default();
+    let cfg = ControlFlowGraph::new(graph, c0, cctx, actx);
+    let ast = cfg.structure_whole().0;
+    println!("{:#?}", ast);
+
+    use self::AstNodeC::*;
+    assert_eq!(
+        Seq(vec![
+            Switch(
+                "x".to_owned(),
+                vec![
+                    (iter::once(0).collect(), BasicBlock("n0".to_owned())),
+                    (iter::once(1).collect(), BasicBlock("n1".to_owned())),
+                ],
+                Box::new(BasicBlock("n2".to_owned())),
+            ),
+            BasicBlock("return".to_owned()),
+        ]),
+        ast
+    );
+}
+
 #[test]
 fn ast_ifelse_cascade() {
     let cstore = condition::Storage::new();
@@ -564,6 +671,132 @@ fn ast_while() {
     );
 }
 
+#[test]
+fn ast_for() {
+    /*
+     * if (ce) {
+     *   for (i = 0; i < n; i = i + 1) {
+     *     puts("n");
+     *   }
+     * }
+     * return;
+     */
+    let cstore = condition::Storage::new();
+    let cctx = cstore.cctx();
+
+    let v_ce = cond_s(cctx, "ce");
+    let v_c1 = cond_s(cctx, "i < n");
+
+    let mut graph = StableDiGraph::new();
+    let entry = graph.add_node(cnode(v_ce));
+    let init = graph.add_node(node("m; i = 0"));
+    let c = graph.add_node(cnode(v_c1));
+    let n = graph.add_node(node("n; i = i + 1"));
+    let exit = graph.add_node(node("return"));
+
+    graph.add_edge(entry, init, CETrue);
+    graph.add_edge(entry, exit, CEFalse);
+    graph.add_edge(init, c, CETrue);
+    graph.add_edge(c, n, CETrue);
+    graph.add_edge(c, exit, CEFalse);
+    graph.add_edge(n, c, CETrue);
+
+    let actx = StringAst {
+        induction_vars: vec!["i".to_owned()],
+        ..StringAst::default()
+    };
+    let cfg = ControlFlowGraph::new(graph, entry, cctx, actx);
+    let ast = cfg.structure_whole().0;
+    println!("{:#?}", ast);
+
+    let c_ce = cctx.mk_var(v_ce);
+    let c_c1 = cctx.mk_var(v_c1);
+
+    use self::AstNodeC::*;
+    assert_eq!(
+        Seq(vec![
+            Cond(
+                c_ce,
+                Box::new(Seq(vec![
+                    BasicBlock("m".to_owned()),
+                    Loop(
+                        LoopType::For(
+                            Box::new(BasicBlock("i = 0".to_owned())),
+                            c_c1,
+                            Box::new(BasicBlock("i = i + 1".to_owned())),
+                        ),
+                        Box::new(BasicBlock("n".to_owned())),
+                    ),
+                ])),
+                None,
+            ),
+            BasicBlock("return".to_owned()),
+        ]),
+        ast
+    );
+}
+
+#[test]
+fn ast_for_not_counter() {
+    /*
+     * if (ce) {
+     *   m; i = 0;
+     *   while (i < n) {
+     *     n; i = i + 1;
+     *   }
+     * }
+     * return;
+     *
+     * The loop analysis did not find `i` to be the counter of the loop.
+     */
+    let cstore = condition::Storage::new();
+    let cctx = cstore.cctx();
+
+    let v_ce = cond_s(cctx, "ce");
+    let v_c1 = cond_s(cctx, "i < n");
+
+    let mut graph = StableDiGraph::new();
+    let entry = graph.add_node(cnode(v_ce));
+    let init = graph.add_node(node("m; i = 0"));
+    let c = graph.add_node(cnode(v_c1));
+    let n = graph.add_node(node("n; i = i + 1"));
+    let exit = graph.add_node(node("return"));
+
+    graph.add_edge(entry, init, CETrue);
+    graph.add_edge(entry, exit, CEFalse);
+    graph.add_edge(init, c, CETrue);
+    graph.add_edge(c, n, CETrue);
+    graph.add_edge(c, exit, CEFalse);
+    graph.add_edge(n, c, CETrue);
+
+    let actx = StringAst::default();
+    let cfg = ControlFlowGraph::new(graph, entry, cctx, actx);
+    let ast = cfg.structure_whole().0;
+    println!("{:#?}", ast);
+
+    let c_ce = cctx.mk_var(v_ce);
+    let c_c1 = cctx.mk_var(v_c1);
+
+    use self::AstNodeC::*;
+    assert_eq!(
+        Seq(vec![
+            Cond(
+                c_ce,
+                Box::new(Seq(vec![
+                    BasicBlock("m; i = 0".to_owned()),
+                    Loop(
+                        LoopType::PreChecked(c_c1),
+                        Box::new(BasicBlock("n; i = i + 1".to_owned())),
+                    ),
+                ])),
+                None,
+            ),
+            BasicBlock("return".to_owned()),
+        ]),
+        ast
+    );
+}
+
 #[test]
 fn ast_do_while() {
     /*
@@ -1047,6 +1280,14 @@ fn stringify_conds<'cd>(ast: AstNode<'cd, StringAst>) -> AstNodeC<String, String
             Box::new(stringify_conds(*b)),
         ),
         Loop(Endless, b) => Loop(Endless, Box::new(stringify_conds(*b))),
+        Loop(For(i, c, s), b) => Loop(
+            For(
+                Box::new(stringify_conds(*i)),
+                format!("{:?}", c),
+                Box::new(stringify_conds(*s)),
+            ),
+            Box::new(stringify_conds(*b)),
+        ),
         Break => Break,
         Switch(v, cases, default) => Switch(
             v,
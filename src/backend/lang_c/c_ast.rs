@@ -104,6 +104,10 @@ pub enum CASTNode {
     Declaration(Ty),
     While,
     DoWhile,
+    For,
+    Switch,
+    // `case` labels of a `switch`, `default` if empty
+    Case(Vec<i64>),
     Goto(String),
     Label(String),
     Break,
@@ -123,6 +127,8 @@ pub enum Expr {
     Add,
     //Add field `SignExt`, `ZeroExt`, `Narrow`
     Cast(usize),
+    // Conversion to a C type, e.g. `(double) x`
+    TypeCast(Ty),
     Sub,
     Mul,
     DeRef,
@@ -226,6 +232,19 @@ impl CAST {
         }
     }
 
+    fn get_block_ordered(&self, node: &NodeIndex) -> Vec<NodeIndex> {
+        let mut ns = self.ast.edges_directed(*node, Direction::Outgoing)
+            .into_iter()
+            .filter_map(|x| {
+                match x.weight() {
+                    CASTEdge::BlockOrd(i) => Some((i, x.target())),
+                    _ => None
+                }
+            }).collect::<Vec<_>>();
+        ns.sort_by_key(|k| k.0);
+        ns.into_iter().map(|(_, n)| n).collect()
+    }
+
     fn get_args_ordered(&self, node: &NodeIndex) -> Vec<NodeIndex> {
         let mut args = self.ast
             .edges_directed(*node, EdgeDirection::Outgoing)
@@ -311,6 +330,68 @@ impl CAST {
         while_h
     }
 
+    pub fn new_for(&mut self,
+                   init: NodeIndex,
+                   condition: NodeIndex,
+                   step: NodeIndex,
+                   body: Vec<NodeIndex>)
+                   -> NodeIndex {
+        let idx = if let Some(e1) = self.ast.find_edge(self.fn_head, init) {
+            let idx = self.get_statement_ord(e1);
+            self.ast.remove_edge(e1);
+            idx
+        } else {
+            self.next_edge_idx()
+        };
+        for n in &[condition, step] {
+            if let Some(e) = self.ast.find_edge(self.fn_head, *n) {
+                self.ast.remove_edge(e);
+            }
+        }
+        let for_h = self.ast.add_node(CASTNode::For);
+        self.ast.add_edge(self.fn_head, for_h, CASTEdge::StatementOrd(idx));
+        self.ast.add_edge(for_h, init, CASTEdge::OpOrd(0));
+        self.ast.add_edge(for_h, condition, CASTEdge::OpOrd(1));
+        self.ast.add_edge(for_h, step, CASTEdge::OpOrd(2));
+        let node = self.ast.add_node(CASTNode::Block);
+        self.ast.add_edge(for_h, node, CASTEdge::OpOrd(3));
+        for (i, n) in body.iter().enumerate() {
+            let e = self.ast.find_edge(self.fn_head, *n).expect("This cannot be `None`");
+            self.ast.remove_edge(e);
+            self.ast.add_edge(node, *n, CASTEdge::BlockOrd(i as u64));
+        }
+        for_h
+    }
+
+    pub fn new_switch(&mut self,
+                      var: NodeIndex,
+                      cases: Vec<(Vec<i64>, Vec<NodeIndex>)>,
+                      default: Vec<NodeIndex>)
+                      -> NodeIndex {
+        let idx = self.next_edge_idx();
+        if let Some(e) = self.ast.find_edge(self.fn_head, var) {
+            self.ast.remove_edge(e);
+        }
+        let switch_h = self.ast.add_node(CASTNode::Switch);
+        self.ast.add_edge(self.fn_head, switch_h, CASTEdge::StatementOrd(idx));
+        self.ast.add_edge(switch_h, var, CASTEdge::OpOrd(0));
+        let default_case = if default.is_empty() {
+            None
+        } else {
+            Some((Vec::new(), default))
+        };
+        for (i, (values, body)) in cases.into_iter().chain(default_case).enumerate() {
+            let case = self.ast.add_node(CASTNode::Case(values));
+            self.ast.add_edge(switch_h, case, CASTEdge::BlockOrd(i as u64));
+            for (j, n) in body.iter().enumerate() {
+                let e = self.ast.find_edge(self.fn_head, *n).expect("This cannot be `None`");
+                self.ast.remove_edge(e);
+                self.ast.add_edge(case, *n, CASTEdge::BlockOrd(j as u64));
+            }
+        }
+        switch_h
+    }
+
     pub fn new_if(&mut self,
                            condition: NodeIndex,
                            body: Vec<NodeIndex>,
@@ -347,9 +428,8 @@ impl CAST {
         if_h
     }
 
-    pub fn call_func(&mut self, func_name: &str, args: Vec<Option<NodeIndex>>) -> NodeIndex {
-        let args_str = args
-            .into_iter()
+    fn args_str(&self, args: Vec<Option<NodeIndex>>) -> Vec<String> {
+        args.into_iter()
             .map(|arg_opt| {
                 if let Some(arg) = arg_opt {
                     match self.ast.node_weight(arg) {
@@ -360,7 +440,11 @@ impl CAST {
                 } else {
                     "unknown".to_string()
                 }
-            }).collect::<Vec<_>>();
+            }).collect()
+    }
+
+    pub fn call_func(&mut self, func_name: &str, args: Vec<Option<NodeIndex>>) -> NodeIndex {
+        let args_str = self.args_str(args);
         let call_node = self.ast.add_node(CASTNode::Call(func_name.to_string(), args_str));
         let idx = self.next_edge_idx();
         self.ast.add_edge(self.fn_head, call_node, CASTEdge::StatementOrd(idx));
@@ -383,6 +467,15 @@ impl CAST {
         ret_node
     }
 
+    /// Returns the value of a function call, i.e. `return func_name(args)`
+    pub fn ret_call(&mut self, func_name: &str, args: Vec<Option<NodeIndex>>) -> NodeIndex {
+        let value_str = format!("{}({})", func_name, self.args_str(args).join(", "));
+        let ret_node = self.ast.add_node(CASTNode::Return(value_str));
+        let idx = self.next_edge_idx();
+        self.ast.add_edge(self.fn_head, ret_node, CASTEdge::StatementOrd(idx));
+        ret_node
+    }
+
     pub fn goto(&mut self, label: &str) -> NodeIndex {
         let goto_n = self.ast.add_node(CASTNode::Goto(label.to_string()));
         let idx = self.next_edge_idx();
@@ -501,6 +594,48 @@ impl CAST {
                         format_with_indent("", indent),
                         condition)
             }
+            CASTNode::For => {
+                // Get the arguments -> initialization, condition, step, for body.
+                let args = self.get_args_ordered(node);
+                let init = self.emit_c(&args[0], 0, true);
+                let condition = self.emit_c(&args[1], 0, true);
+                let step = self.emit_c(&args[2], 0, true);
+                let for_body = self.emit_c(&args[3], indent + 1, false);
+                format!("{} ({}; {}; {}) {{\n{}\n{}",
+                        format_with_indent("for", indent),
+                        init,
+                        condition,
+                        step,
+                        for_body,
+                        format_with_indent("}", indent))
+            }
+            CASTNode::Switch => {
+                // Get the arguments -> switch variable, cases in order.
+                let var = self.emit_c(&self.get_args_ordered(node)[0], 0, true);
+                let cases = self.get_block_ordered(node)
+                    .into_iter()
+                    .map(|n| self.emit_c(&n, indent + 1, false))
+                    .collect::<Vec<_>>();
+                format!("{} ({}) {{\n{}\n{}",
+                        format_with_indent("switch", indent),
+                        var,
+                        cases.join("\n"),
+                        format_with_indent("}", indent))
+            }
+            CASTNode::Case(ref values) => {
+                let mut labels = values.iter()
+                    .map(|v| format_with_indent(&format!("case {}:", v), indent))
+                    .collect::<Vec<_>>();
+                if labels.is_empty() {
+                    labels.push(format_with_indent("default:", indent));
+                }
+                let mut body = self.get_block_ordered(node)
+                    .into_iter()
+                    .map(|n| self.emit_c(&n, indent + 1, false))
+                    .collect::<Vec<_>>();
+                body.push(format_with_indent("break;", indent + 1));
+                format!("{}\n{}", labels.join("\n"), body.join("\n"))
+            }
             CASTNode::Goto(ref label) => {
                 format_with_indent(&format!("goto {}", label), indent)
             }
@@ -523,6 +658,9 @@ impl CAST {
                     Expr::Cast(size) => format!("({} as {})",
                                          format_with_indent(&op_str[0], indent),
                                          &size.to_string()),
+                    Expr::TypeCast(ref ty) => format!("(({}) {})",
+                                         format_with_indent(&ty.to_string(), indent),
+                                         &op_str[0]),
                     Expr::Sub => format!("({} - {})",
                                          format_with_indent(&op_str[0], indent),
                                          &op_str[1]),
@@ -583,17 +721,9 @@ impl CAST {
                 format!("{}({})", format_with_indent(&func, indent), args.join(", "))
             }
             CASTNode::Block => {
-                let mut ns = self.ast.edges_directed(*node, Direction::Outgoing)
+                self.get_block_ordered(node)
                     .into_iter()
-                    .filter_map(|x| {
-                        match x.weight() {
-                            CASTEdge::BlockOrd(i) => Some((i, x.target())),
-                            _ => None
-                        }
-                    }).collect::<Vec<_>>();
-                ns.sort_by_key(|k| k.0);
-                ns.into_iter()
-                    .map(|(_, n)| self.emit_c(&n, indent, false))
+                    .map(|n| self.emit_c(&n, indent, false))
                     .collect::<Vec<_>>()
                     .join("\n")
             }
@@ -622,6 +752,9 @@ impl CAST {
             &CASTNode::FunctionHeader(_)
             | &CASTNode::While
             | &CASTNode::DoWhile
+            | &CASTNode::For
+            | &CASTNode::Switch
+            | &CASTNode::Case(_)
             | &CASTNode::If
             | &CASTNode::Label(_)
             | &CASTNode::Var(_)
@@ -756,4 +889,38 @@ mod test {
         let _ = c_ast.ret(None);
         println!("{}", c_ast.print());
     }
+
+    #[test]
+    fn c_ast_for_test() {
+        let mut c_ast = CAST::new("main");
+        let vars = c_ast.declare_vars(Ty::new(BTy::Int, false, 0), &["i".to_owned(), "n".to_owned()], false);
+        let zero = c_ast.declare_vars(Ty::new(BTy::Int, false, 0), &["0".to_owned()], true)[0];
+        let one = c_ast.declare_vars(Ty::new(BTy::Int, false, 0), &["1".to_owned()], true)[0];
+        let init = c_ast.expr(Expr::Assign, &[vars[0], zero], false);
+        let lt = c_ast.expr(Expr::Lt, &vars, true);
+        let sum = c_ast.expr(Expr::Add, &[vars[1], vars[0]], true);
+        let assignment = c_ast.expr(Expr::Assign, &[vars[1], sum], false);
+        let increment = c_ast.expr(Expr::Add, &[vars[0], one], true);
+        let step = c_ast.expr(Expr::Assign, &[vars[0], increment], false);
+        c_ast.new_for(init, lt, step, vec![assignment]);
+        let _ = c_ast.ret(None);
+        let output = c_ast.print();
+        println!("{}", output);
+        assert!(output.contains("for (i = 0; (i < n); i = (i + 1)) {\n        n = (n + i);\n    }"));
+    }
+
+    #[test]
+    fn c_ast_switch_test() {
+        let mut c_ast = CAST::new("main");
+        let vars = c_ast.declare_vars(Ty::new(BTy::Int, false, 0), &["x".to_owned(), "y".to_owned()], false);
+        let one = c_ast.declare_vars(Ty::new(BTy::Int, false, 0), &["1".to_owned()], true)[0];
+        let two = c_ast.declare_vars(Ty::new(BTy::Int, false, 0), &["2".to_owned()], true)[0];
+        let assign1 = c_ast.expr(Expr::Assign, &[vars[1], one], false);
+        let assign2 = c_ast.expr(Expr::Assign, &[vars[1], two], false);
+        c_ast.new_switch(vars[0], vec![(vec![-1, 4], vec![assign1])], vec![assign2]);
+        let _ = c_ast.ret(None);
+        let output = c_ast.print();
+        println!("{}", output);
+        assert!(output.contains("switch (x) {\n        case -1:\n        case 4:\n            y = 1;\n            break;\n        default:\n            y = 2;\n            break;\n    }"));
+    }
 }
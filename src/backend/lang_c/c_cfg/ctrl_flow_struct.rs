@@ -1,6 +1,6 @@
-use super::{ActionEdge, ActionNode, CCFGEdge, CCFGNode, CCFGRef, ValueEdge, CCFG};
+use super::{ActionEdge, ActionNode, CCFGEdge, CCFGNode, CCFGRef, ValueEdge, ValueNode, CCFG};
 use backend::ctrl_flow_struct as flstr;
-use backend::ctrl_flow_struct::ast_context::{AstContext, AstContextMut};
+use backend::ctrl_flow_struct::ast_context::{AstContext, AstContextMut, AstContextQuery};
 use backend::lang_c::c_ast::{self, CAST};
 
 use petgraph::stable_graph::StableDiGraph;
@@ -228,3 +228,105 @@ impl AstContextMut for CCFG {
         vec![self.assign(var, cond, unk)]
     }
 }
+
+impl AstContextQuery for CCFG {
+    fn last_assigned_var(&self, block: &Self::Block) -> Option<Self::Variable> {
+        let (dst, _) = self.assignment(*block.last()?)?;
+        var_name(self, dst).map(|_| dst)
+    }
+
+    fn last_stmt_reads_var(&self, block: &Self::Block, &var: &Self::Variable) -> bool {
+        block
+            .last()
+            .and_then(|&stmt| self.assignment(stmt))
+            .map_or(false, |(_, src)| reads_var(self, src, var))
+    }
+
+    fn cond_reads_var(&self, &cond: &Self::Condition, &var: &Self::Variable) -> bool {
+        reads_var(self, cond, var)
+    }
+
+    fn cond_equals(&self, &cond: &Self::Condition) -> Option<(Self::Variable, u64)> {
+        match self.g.node_weight(cond) {
+            Some(&CCFGNode::Value(ValueNode::Expression(c_ast::Expr::Eq))) => (),
+            _ => return None,
+        }
+        let operands = self.operands_from_expr(cond);
+        if operands.len() != 2 {
+            return None;
+        }
+        match (constant_value(self, operands[0]), constant_value(self, operands[1])) {
+            (None, Some(val)) => Some((operands[0], val)),
+            (Some(val), None) => Some((operands[1], val)),
+            _ => None,
+        }
+    }
+
+    fn is_induction_var(&self, &var: &Self::Variable) -> bool {
+        var_name(self, var).map_or(false, |name| self.induction_vars.contains(name))
+    }
+
+    fn is_same_var(&self, &var1: &Self::Variable, &var2: &Self::Variable) -> bool {
+        // Each use of a local gets its own node, compare the names instead.
+        // Switches may also be on expressions, these are compared by structure.
+        if var1 == var2 {
+            return true;
+        }
+        match (self.g.node_weight(var1), self.g.node_weight(var2)) {
+            (Some(&CCFGNode::Value(ValueNode::Variable(_, ref name1))),
+             Some(&CCFGNode::Value(ValueNode::Variable(_, ref name2)))) => name1 == name2,
+            (Some(&CCFGNode::Value(ValueNode::Constant(_, ref name1))),
+             Some(&CCFGNode::Value(ValueNode::Constant(_, ref name2)))) => name1 == name2,
+            (Some(&CCFGNode::Value(ValueNode::Expression(ref op1))),
+             Some(&CCFGNode::Value(ValueNode::Expression(ref op2)))) => {
+                let operands1 = self.operands_from_expr(var1);
+                let operands2 = self.operands_from_expr(var2);
+                op1 == op2 && operands1.len() == operands2.len()
+                    && operands1
+                        .iter()
+                        .zip(operands2.iter())
+                        .all(|(opn1, opn2)| self.is_same_var(opn1, opn2))
+            }
+            _ => false,
+        }
+    }
+
+    fn split_last_stmt(&self, mut block: Self::Block) -> (Option<Self::Block>, Self::Block) {
+        let last = block.pop().into_iter().collect();
+        if block.is_empty() {
+            (None, last)
+        } else {
+            (Some(block), last)
+        }
+    }
+}
+
+fn var_name(ccfg: &CCFG, node: CCFGRef) -> Option<&str> {
+    match ccfg.g.node_weight(node) {
+        Some(&CCFGNode::Value(ValueNode::Variable(_, ref name))) => Some(name.as_str()),
+        _ => None,
+    }
+}
+
+fn constant_value(ccfg: &CCFG, node: CCFGRef) -> Option<u64> {
+    match ccfg.g.node_weight(node) {
+        Some(&CCFGNode::Value(ValueNode::Constant(_, ref name))) => {
+            if name.starts_with("0x") {
+                u64::from_str_radix(&name[2..], 16).ok()
+            } else {
+                name.parse().ok()
+            }
+        }
+        _ => None,
+    }
+}
+
+// Returns true if the value `node` reads the variable `var`.
+fn reads_var(ccfg: &CCFG, node: CCFGRef, var: CCFGRef) -> bool {
+    if ccfg.is_same_var(&node, &var) {
+        return true;
+    }
+    ccfg.operands_from_expr(node)
+        .into_iter()
+        .any(|opn| reads_var(ccfg, opn, var))
+}
@@ -54,6 +54,8 @@ pub enum ActionEdge {
     Normal,
     /// Destination of Goto statement
     GotoDst,
+    /// Function call whose value is returned by a Return action
+    RetCall,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -90,6 +92,8 @@ pub struct CCFG {
     g: Graph<CCFGNode, CCFGEdge>,
     /// Variables declared in this function, bool value is `is_implicit` flag
     vars: HashSet<(bool, CCFGRef)>,
+    /// Parameters of this function, in order
+    params: Vec<CCFGRef>,
     /// Constants declared in this function, bool value is `is_implicit` flag
     consts: HashSet<(bool, CCFGRef)>,
     /// Expressions declared in this function, bool value is `is_implicit` flag
@@ -98,6 +102,8 @@ pub struct CCFG {
     label_map: HashMap<CCFGRef, String>,
     /// Debug information retrieved from SSA
     debug_info: HashMap<CCFGRef, String>,
+    /// Names of the variables holding the counters of loops
+    induction_vars: HashSet<String>,
 }
 
 
@@ -128,10 +134,12 @@ impl CCFG {
             unknown: unknown,
             g: g,
             vars: HashSet::new(),
+            params: Vec::new(),
             consts: HashSet::new(),
             exprs: Vec::new(),
             label_map: HashMap::new(),
             debug_info: HashMap::new(),
+            induction_vars: HashSet::new(),
         }
     }
 
@@ -156,6 +164,26 @@ impl CCFG {
         node
     }
 
+    /// Mark the variables named `name` as holding the counter of a loop
+    pub fn mark_induction_var(&mut self, name: &str) {
+        self.induction_vars.insert(name.to_string());
+    }
+
+    /// Add ValueNode of parameter, which is declared by the function header
+    pub fn param(&mut self, name: &str, ty: Option<Ty>) -> CCFGRef {
+        let node = self.g.add_node(CCFGNode::Value(ValueNode::Variable(ty, name.to_string())));
+        self.params.push(node);
+        node
+    }
+
+    /// Set type of ValueNode of variable
+    pub fn set_var_type(&mut self, var: CCFGRef, ty: Ty) {
+        if let Some(&mut CCFGNode::Value(ValueNode::Variable(ref mut ty_opt, _))) =
+            self.g.node_weight_mut(var) {
+            *ty_opt = Some(ty);
+        }
+    }
+
     /// Add ValueNode of constant value
     pub fn constant(&mut self, name: &str, ty: Option<Ty>) -> CCFGRef {
         let node = self.g.add_node(CCFGNode::Value(ValueNode::Constant(ty, name.to_string())));
@@ -295,6 +323,18 @@ impl CCFG {
         node
     }
 
+    /// Add ActionNode of return statement returning the value of a function
+    /// call, i.e. `return fname(args)`
+    pub fn return_call(&mut self, fname: &str, args: &[CCFGRef], prev_action: CCFGRef) -> CCFGRef {
+        let call_node = self.g.add_node(CCFGNode::Action(ActionNode::Call(fname.to_string())));
+        for (i, arg) in args.iter().enumerate() {
+            self.g.add_edge(call_node, *arg, CCFGEdge::Value(ValueEdge::Arg(i as u8)));
+        }
+        let node = self.add_return(None, prev_action);
+        self.g.add_edge(node, call_node, CCFGEdge::Action(ActionEdge::RetCall));
+        node
+    }
+
     pub fn insert_goto_before(&mut self, next: CCFGRef, dst: CCFGRef, label_str: &str) -> CCFGRef {
         let es = self.g.edges_directed(next, Direction::Incoming)
             .into_iter()
@@ -501,6 +541,22 @@ impl CCFG {
         ret_val.into_iter().next()
     }
 
+    // Returns function call whose value is returned by a return statement
+    fn ret_call(&self, idx: CCFGRef) -> Option<CCFGRef> {
+        match self.g.node_weight(idx) {
+            Some(&CCFGNode::Action(ActionNode::Return)) => {},
+            _ => {return None;}
+        };
+        self.g.edges_directed(idx, Direction::Outgoing)
+            .into_iter()
+            .filter_map(|e| {
+                match e.weight() {
+                    &CCFGEdge::Action(ActionEdge::RetCall) => Some(e.target()),
+                    _ => None,
+                }
+            }).next()
+    }
+
     // Returns operands used by a given expression
     fn operands_from_expr(&self, expr: CCFGRef) -> Vec<CCFGRef> {
         let mut operands = self.g.edges_directed(expr, Direction::Outgoing)
@@ -537,6 +593,13 @@ impl CCFG {
         }
     }
 
+    pub fn is_return_node(&self, node: CCFGRef) -> bool {
+        match self.g.node_weight(node) {
+            Some(&CCFGNode::Action(ActionNode::Return)) => true,
+            _ => false
+        }
+    }
+
     pub fn is_call_node(&self, node: CCFGRef) -> bool {
         match self.g.node_weight(node) {
             Some(&CCFGNode::Action(ActionNode::Call(_))) => true,
@@ -730,6 +793,21 @@ impl<'a> CASTConverter<'a> {
         let unknown_node = self.ast.declare_vars(Ty::new(c_ast::BTy::Int, false, 0), &["unknown".to_string()], true)
             .first().cloned().expect("This can not be None");
         self.node_map.insert(self.cfg.unknown, unknown_node);
+        let params = self.cfg.params
+            .iter()
+            .filter_map(|&param| match self.cfg.g.node_weight(param) {
+                Some(&CCFGNode::Value(ValueNode::Variable(ref ty_opt, ref param_name))) => {
+                    let ty = ty_opt.clone().unwrap_or(Ty::new(c_ast::BTy::Int, false, 0));
+                    Some((param, (ty, param_name.to_string())))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let args = params.iter().map(|&(_, ref arg)| arg.clone()).collect::<Vec<_>>();
+        let arg_nodes = self.ast.function_args(&args);
+        for (&(param, _), n) in params.iter().zip(arg_nodes) {
+            self.node_map.insert(param, n);
+        }
         for &(is_implicit, con) in self.cfg.consts.iter() {
             if let Some(&CCFGNode::Value(ValueNode::Constant(ref ty_opt, ref value_name))) = self.cfg.g.node_weight(con) {
                 let ty = ty_opt.clone().unwrap_or(Ty::new(c_ast::BTy::Int, false, 0));
@@ -842,8 +920,8 @@ impl<'a> CASTConverter<'a> {
         }
     }
 
-    fn to_c_ast_call(&mut self, node: CCFGRef, name: &str) -> Result<CASTRef, &'static str> {
-        let args = self.cfg.args_call(node)
+    fn call_args(&self, node: CCFGRef) -> Vec<Option<CASTRef>> {
+        self.cfg.args_call(node)
             .unwrap_or(Vec::new())
             .into_iter()
             .map(|arg| {
@@ -852,7 +930,11 @@ impl<'a> CASTConverter<'a> {
                     radeco_warn!("Error args_call");
                 }
                 ret
-            }).collect();
+            }).collect()
+    }
+
+    fn to_c_ast_call(&mut self, node: CCFGRef, name: &str) -> Result<CASTRef, &'static str> {
+        let args = self.call_args(node);
         let ret_node_opt = self.cfg.func_val(node)
             .and_then(|x| self.node_map.get(&x).map(|a| *a));
         let mut node = self.ast.call_func(name, args);
@@ -863,6 +945,12 @@ impl<'a> CASTConverter<'a> {
     }
 
     fn to_c_ast_return(&mut self, node: CCFGRef) -> Result<CASTRef, &'static str> {
+        if let Some(call) = self.cfg.ret_call(node) {
+            if let Some(CCFGNode::Action(ActionNode::Call(name))) = self.cfg.g.node_weight(call).cloned() {
+                let args = self.call_args(call);
+                return Ok(self.ast.ret_call(&name, args));
+            }
+        }
         let opt = self.cfg.ret_val(node)
             .and_then(|n| self.node_map.get(&n))
             .map(|n| *n);
@@ -1041,6 +1129,22 @@ mod test {
         println!("{}", output);
     }
 
+    // fn main () {
+    //     unsigned int x;
+    //     return func(x)
+    // }
+    #[test]
+    fn c_cfg_return_call_test() {
+        let mut cfg = CCFG::new("main");
+        let entry = cfg.entry;
+        let x = cfg.var("x", None);
+        let _ = cfg.return_call("func", &[x], entry);
+        CCFGVerifier::verify(&cfg).expect("CCFG verification failed");
+        let output = cfg.to_c_ast().print();
+        println!("{}", output);
+        assert!(output.contains("return func(x)"));
+    }
+
     // fn main () {
     //     unsigned int y;
     //     unsigned int x;
@@ -1163,4 +1267,23 @@ mod test {
         let output = cfg.to_c_ast().print();
         println!("{}", output);
     }
+
+    // fn main (int arg_8, unsigned int arg_c) {
+    //     unsigned int x;
+    //     x = (arg_8 + arg_c)
+    // }
+    #[test]
+    fn c_cfg_param_test() {
+        let mut cfg = CCFG::new("main");
+        let entry = cfg.entry;
+        let a = cfg.param("arg_8", Some(Ty::new(BTy::Int, true, 0)));
+        let b = cfg.param("arg_c", None);
+        let x = cfg.var("x", None);
+        let expr = cfg.expr(&[a, b], c_ast::Expr::Add);
+        let _ = cfg.assign(x, expr, entry);
+        CCFGVerifier::verify(&cfg).expect("CCFG verification failed");
+        let output = cfg.to_c_ast().print();
+        println!("{}", output);
+        assert!(output.starts_with("fn main (int arg_8, unsigned int arg_c) {"));
+    }
 }
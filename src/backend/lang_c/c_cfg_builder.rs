@@ -7,8 +7,12 @@
 use super::c_ast;
 use super::c_ast::Ty;
 use super::c_cfg::{CCFG, CCFGRef};
-use frontend::radeco_containers::RadecoFunction;
+use analysis::loops::{InductionVar, LoopForest};
+use analysis::stack_args;
+use frontend::radeco_containers::{BindingType, RadecoFunction};
 use middle::ir::{MOpcode, MAddress};
+use middle::prototypes::{self, CType};
+use middle::syscalls;
 use middle::ssa::cfg_traits::CFG;
 use middle::ssa::ssa_traits::{SSA, SSAWalk};
 use middle::ssa::ssastorage::{NodeData, SSAStorage};
@@ -29,6 +33,7 @@ pub fn recover_c_cfg(
     let mut builder = CCFGBuilder::new(rfn, fname_map);
     let data_graph = CCFGDataMap::recover_data(rfn, &mut builder.cfg, strings);
     builder.datamap = data_graph;
+    builder.mark_induction_vars();
     builder.cfg_from_ssa();
     builder.insert_jumps();
     builder.cfg
@@ -53,11 +58,18 @@ struct CCFGBuilder<'a> {
     fname_map: &'a HashMap<u64, String>,
     action_map: HashMap<SSARef, CCFGRef>,
     datamap: CCFGDataMap<'a>,
+    // Stack offsets of SSA, to find the stack arguments of calls
+    stack_offsets: HashMap<SSARef, i64>,
 }
 
 impl<'a> CCFGBuilder<'a> {
     fn new(rfn: &'a RadecoFunction, fname_map: &'a HashMap<u64, String>) -> CCFGBuilder<'a> {
         let cfg = CCFG::new(rfn.name.as_ref());
+        let stack_offsets = if rfn.callee_stack_args().is_empty() {
+            HashMap::new()
+        } else {
+            stack_args::stack_offsets(rfn.ssa())
+        };
         CCFGBuilder {
             last_action: cfg.entry,
             cfg: cfg,
@@ -66,6 +78,7 @@ impl<'a> CCFGBuilder<'a> {
             fname_map: fname_map,
             action_map: HashMap::new(),
             datamap: CCFGDataMap::new(rfn),
+            stack_offsets: stack_offsets,
         }
     }
 
@@ -124,6 +137,29 @@ impl<'a> CCFGBuilder<'a> {
         args.into_iter().map(|(_, n)| n).collect()
     }
 
+    // Values stored to the stack arguments of the callee, see `analysis::stack_args`.
+    fn stack_args(&self, call_node: SSARef) -> Vec<Option<SSARef>> {
+        let offsets = self.ssa
+            .address(call_node)
+            .and_then(|addr| self.rfn.callee_stack_args().get(&addr.address));
+        match offsets {
+            Some(offsets) => stack_args::call_args(self.ssa, &self.stack_offsets, call_node, offsets),
+            None => Vec::new(),
+        }
+    }
+
+    // Tail calls are lifted from jumps to the functions tail called.
+    fn is_tail_call(&self, call_node: SSARef) -> bool {
+        let addr = match self.ssa.address(call_node) {
+            Some(addr) => addr.address,
+            None => return false,
+        };
+        self.rfn.instructions().iter().any(|op| {
+            op.offset == Some(addr) && op.optype.as_ref().map_or(false, |ty| ty == "jmp") &&
+                op.jump.map_or(false, |target| self.rfn.tail_calls().contains(&target))
+        })
+    }
+
     fn call_action(&mut self, call_node: SSARef) -> CCFGRef {
         let call_info = utils::call_info(call_node, self.ssa).expect("This should not be `None`");
         let callee_node = call_info.target;
@@ -137,7 +173,7 @@ impl<'a> CCFGBuilder<'a> {
                 "unknown".to_string()
             }
         };
-        let args = self.args_inorder(call_node)
+        let mut args = self.args_inorder(call_node)
             .into_iter()
             .map(|n| {
                 self.datamap.var_map.get(&n).cloned().unwrap_or(
@@ -145,7 +181,32 @@ impl<'a> CCFGBuilder<'a> {
                 )
             })
             .collect::<Vec<_>>();
-        let ret_val_node = self.return_node(call_node);
+        for value in self.stack_args(call_node) {
+            let arg = value.and_then(|n| self.datamap.var_map.get(&n).cloned());
+            args.push(arg.unwrap_or(self.cfg.unknown));
+        }
+        // Known prototypes tell the arguments and the type of the return value.
+        let proto = prototypes::lookup(&func_name);
+        if let Some(proto) = proto {
+            if proto.fixed_args() {
+                args.truncate(proto.args.len());
+            }
+        }
+        if self.is_tail_call(call_node) {
+            self.last_action = self.cfg.return_call(&func_name, args.as_slice(), self.last_action);
+            return self.last_action;
+        }
+        let ret_val_node = match proto {
+            Some(proto) if proto.ret.is_void() => None,
+            Some(proto) => {
+                let ret_val_node = self.return_node(call_node);
+                if let Some(node) = ret_val_node {
+                    self.cfg.set_var_type(node, CCFGDataMap::type_from_ctype(&proto.ret));
+                }
+                ret_val_node
+            }
+            None => self.return_node(call_node),
+        };
         self.last_action = self.cfg.call_func(
             &func_name,
             args.as_slice(),
@@ -155,6 +216,63 @@ impl<'a> CCFGBuilder<'a> {
         self.last_action
     }
 
+    fn intrinsic_action(&mut self, node: SSARef) -> CCFGRef {
+        let info = utils::intrinsic_info(node, self.ssa).expect("This should not be `None`");
+        let ssa = self.ssa;
+        // Memory is not an argument of the C call
+        let args = info.args
+            .iter()
+            .filter(|&&n| ssa.node_data(n).ok().map_or(true, |d| d.vt.width().get_width() != Some(0)))
+            .map(|n| self.datamap.var_map.get(n).cloned().unwrap_or(self.cfg.unknown))
+            .collect::<Vec<_>>();
+        if syscalls::is_syscall(&info.name) {
+            return self.syscall_action(&info, args);
+        }
+        // A single output is the value of the call, otherwise the outputs are
+        // the registers they are named after.
+        let outputs = info.outputs
+            .iter()
+            .filter_map(|n| self.datamap.var_map.get(n).cloned())
+            .collect::<Vec<_>>();
+        let ret_val = if outputs.len() == 1 { Some(outputs[0]) } else { None };
+        let func_name = format!("__{}", info.name);
+        self.last_action = self.cfg.call_func(
+            &func_name,
+            args.as_slice(),
+            self.last_action,
+            ret_val,
+        );
+        self.last_action
+    }
+
+    /// Emits a system call as a call to the function named after it, e.g.
+    /// `write(1, buf, n)`, when its number is constant. `args` are the
+    /// number followed by the argument registers of the system call ABI.
+    fn syscall_action(&mut self, info: &utils::IntrinsicInfo, args: Vec<CCFGRef>) -> CCFGRef {
+        let syscall = info.args
+            .first()
+            .and_then(|&n| self.ssa.constant_value(n))
+            .and_then(|number| syscalls::lookup(&info.name, number));
+        let (func_name, args) = match syscall {
+            Some(syscall) => {
+                let nargs = (syscall.nargs as usize + 1).min(args.len());
+                (syscall.name.to_string(), args[1..nargs].to_vec())
+            }
+            None => ("syscall".to_string(), args),
+        };
+        // The return value is the first register written
+        let ret_val = info.outputs
+            .first()
+            .and_then(|n| self.datamap.var_map.get(n).cloned());
+        self.last_action = self.cfg.call_func(
+            &func_name,
+            args.as_slice(),
+            self.last_action,
+            ret_val,
+        );
+        self.last_action
+    }
+
     fn addr_str(&self, node: SSARef) -> String {
         self.ssa.address(node).map(|a| format!("{}", a)).unwrap_or(
             "unknown".to_string(),
@@ -178,6 +296,18 @@ impl<'a> CCFGBuilder<'a> {
                 }
                 ret
             }
+            MOpcode::OpIntrinsic(_) => {
+                let ret = self.intrinsic_action(node);
+                if is_debug() {
+                    let addr = self.addr_str(node);
+                    let ops_dbg = self.ssa.operands_of(node);
+                    self.cfg.debug_info_at(
+                        ret,
+                        format!("Intrinsic {:?} @ {}", ops_dbg, addr),
+                    );
+                }
+                ret
+            }
             MOpcode::OpStore => {
                 let ops = self.ssa.operands_of(node);
                 let dst = self.datamap
@@ -205,7 +335,7 @@ impl<'a> CCFGBuilder<'a> {
     fn is_recover_action(&self, node: SSARef) -> bool {
         let op = self.ssa.opcode(node).unwrap_or(MOpcode::OpInvalid);
         match op {
-            MOpcode::OpCall | MOpcode::OpStore => true,
+            MOpcode::OpCall | MOpcode::OpIntrinsic(_) | MOpcode::OpStore => true,
             _ => false,
         }
     }
@@ -291,6 +421,10 @@ impl<'a> CCFGBuilder<'a> {
         let prev = self.action_map.get(&block)
             .and_then(|&n| self.cfg.preds_of(n).first().cloned())
             .expect("This should not be `None`");
+        // Tail calls already return
+        if self.cfg.is_return_node(prev) {
+            return;
+        }
         // TODO specify return value if it exists
         self.cfg.add_return(None, prev);
     }
@@ -343,6 +477,37 @@ impl<'a> CCFGBuilder<'a> {
         }
     }
 
+    // Marks the locals the counters of loops are stored to, so that their
+    // loops are turned into `for` loops.
+    fn mark_induction_vars(&mut self) {
+        let forest = LoopForest::new(self.ssa);
+        for lp in forest.loops() {
+            let counter = match lp.counter {
+                Some(counter) => counter,
+                None => continue,
+            };
+            // The counter and the values derived from it, e.g. `i + 1`.
+            let values = lp.induction_vars
+                .iter()
+                .filter(|&(&node, iv)| match *iv {
+                    InductionVar::Basic { .. } => node == counter,
+                    InductionVar::Derived { base, .. } => base == counter,
+                })
+                .map(|(&node, _)| node)
+                .collect::<HashSet<_>>();
+            for node in self.ssa.values() {
+                let ops = self.ssa.operands_of(node);
+                if self.ssa.opcode(node) != Some(MOpcode::OpStore) || ops.len() != 3 ||
+                   !values.contains(&ops[2]) {
+                    continue;
+                }
+                for vb in self.rfn.local_at(ops[1], true).unwrap_or_default() {
+                    self.cfg.mark_induction_var(vb.name());
+                }
+            }
+        }
+    }
+
     fn cfg_from_ssa(&mut self) {
         for node in self.ssa.inorder_walk() {
             if self.is_recover_action(node) {
@@ -365,6 +530,8 @@ struct CCFGDataMap<'a> {
     // a map from node of data_graph to one of CCFG's register
     pub reg_map: HashMap<String, CCFGRef>,
     pub const_nodes: HashSet<SSARef>,
+    // a map from name of stack argument to CCFG's parameter
+    params: HashMap<String, CCFGRef>,
     seen: HashSet<SSARef>,
 }
 
@@ -376,6 +543,7 @@ impl<'a> CCFGDataMap<'a> {
             var_map: HashMap::new(),
             reg_map: HashMap::new(),
             const_nodes: HashSet::new(),
+            params: HashMap::new(),
             seen: HashSet::new(),
         }
     }
@@ -388,6 +556,7 @@ impl<'a> CCFGDataMap<'a> {
         let mut s = Self::new(rfn);
         s.prepare_consts(ast, strings);
         s.prepare_regs(ast);
+        s.prepare_args(ast);
         for node in s.ssa.inorder_walk() {
             if s.ssa.is_phi(node) {
                 s.handle_phi(node);
@@ -469,10 +638,46 @@ impl<'a> CCFGDataMap<'a> {
         // TODO More types
         match type_str {
             "int" => Some(Ty::new(c_ast::BTy::Int, true, 0)),
+            "float" => Some(Ty::new(c_ast::BTy::Float, true, 0)),
+            "double" => Some(Ty::new(c_ast::BTy::Double, true, 0)),
             _ => None,
         }
     }
 
+    fn type_from_ctype(ty: &CType) -> Ty {
+        let words = ty.base.split_whitespace().collect::<Vec<_>>();
+        let mut base = if words.contains(&"char") {
+            c_ast::BTy::Char
+        } else if words.contains(&"float") {
+            c_ast::BTy::Float
+        } else if words.contains(&"double") {
+            c_ast::BTy::Double
+        } else if words.contains(&"void") || words.contains(&"struct") ||
+                  words.contains(&"union") {
+            c_ast::BTy::Void
+        } else {
+            c_ast::BTy::Int
+        };
+        for _ in 0..ty.pointers {
+            base = c_ast::BTy::Ptr(Box::new(base));
+        }
+        let signed = !words.contains(&"unsigned");
+        let long = words.iter().filter(|&&w| w == "long").count() as u8;
+        Ty::new(base, signed, long)
+    }
+
+    fn float_type(width: u16) -> Ty {
+        if width <= 32 {
+            Ty::new(c_ast::BTy::Float, true, 0)
+        } else {
+            Ty::new(c_ast::BTy::Double, true, 0)
+        }
+    }
+
+    fn int_type(width: u16) -> Ty {
+        Ty::new(c_ast::BTy::Int, true, if width > 32 { 1 } else { 0 })
+    }
+
     fn update_values(&mut self, ret_node: SSARef, ast: &mut CCFG) {
         debug_assert!(self.ssa.is_expr(ret_node));
         radeco_trace!("CCFGBuilder::update_values {:?}", ret_node);
@@ -484,7 +689,10 @@ impl<'a> CCFGDataMap<'a> {
         if let Some(bindings) = self.rfn.local_at(ret_node, true) {
             // TODO add type
             let type_info = Self::type_from_str(&bindings[0].type_str);
-            let cfg_node = ast.var(bindings[0].name(), type_info);
+            let cfg_node = match self.params.get(bindings[0].name()) {
+                Some(&param) if bindings[0].btype.is_argument() => param,
+                _ => ast.var(bindings[0].name(), type_info),
+            };
             self.var_map.insert(ret_node, cfg_node);
             return;
         }
@@ -542,13 +750,54 @@ impl<'a> CCFGDataMap<'a> {
             MOpcode::OpZeroExt(size) => {
                 self.handle_cast(ret_node, ops[0], c_ast::Expr::Cast(size as usize), ast)
             }
+            // Floating point operations use the same operators in C.
+            MOpcode::OpFAdd => self.handle_binop(ret_node, ops, c_ast::Expr::Add, ast),
+            MOpcode::OpFSub => self.handle_binop(ret_node, ops, c_ast::Expr::Sub, ast),
+            MOpcode::OpFMul => self.handle_binop(ret_node, ops, c_ast::Expr::Mul, ast),
+            MOpcode::OpFDiv => self.handle_binop(ret_node, ops, c_ast::Expr::Div, ast),
+            MOpcode::OpFEq => self.handle_binop(ret_node, ops, c_ast::Expr::Eq, ast),
+            MOpcode::OpFLt => self.handle_binop(ret_node, ops, c_ast::Expr::Lt, ast),
+            MOpcode::OpIntToFloat(size) | MOpcode::OpFloatConv(size) => {
+                let expr = c_ast::Expr::TypeCast(Self::float_type(size));
+                self.handle_uniop(ret_node, ops[0], expr, ast)
+            }
+            MOpcode::OpFloatToInt(size) => {
+                let expr = c_ast::Expr::TypeCast(Self::int_type(size));
+                self.handle_uniop(ret_node, ops[0], expr, ast)
+            }
             MOpcode::OpCall => {
                 self.update_data_graph_by_call(ret_node, ast);
             }
+            MOpcode::OpIntrinsic(_) => {
+                self.update_data_graph_by_intrinsic(ret_node, ast);
+            }
             _ => {}
         }
     }
 
+    fn update_data_graph_by_intrinsic(&mut self, node: SSARef, ast: &mut CCFG) {
+        radeco_trace!("CCFGBuilder::update_data_graph_by_intrinsic {:?}", node);
+        let info = match utils::intrinsic_info(node, self.ssa) {
+            Some(info) => info,
+            None => return,
+        };
+        for output in info.outputs {
+            if self.var_map.contains_key(&output) {
+                continue;
+            }
+            // Outputs are commented with the register they define, e.g. `eax@0x1000`
+            let name = match self.ssa.g[output] {
+                NodeData::Comment(_, ref s) => s.split('@').next().unwrap_or("tmp").to_string(),
+                _ => continue,
+            };
+            if name == "mem" {
+                continue;
+            }
+            let cfg_node = ast.var(&name, None);
+            self.var_map.insert(output, cfg_node);
+        }
+    }
+
     fn update_data_graph_by_call(&mut self, call_node: SSARef, ast: &mut CCFG) {
         radeco_trace!("CCFGBuilder::update_data_graph_by_call {:?}", call_node);
         let ret_reg_name_opt = ret_value_string(self.rfn);
@@ -603,6 +852,17 @@ impl<'a> CCFGDataMap<'a> {
             }
         }
     }
+
+    // Stack arguments are the parameters of the function, register arguments
+    // are still read from the registers.
+    fn prepare_args(&mut self, ast: &mut CCFG) {
+        for vb in self.rfn.args() {
+            if let BindingType::StackArgument(_) = vb.btype {
+                let param = ast.param(vb.name(), Self::type_from_str(&vb.type_str));
+                self.params.insert(vb.name().to_string(), param);
+            }
+        }
+    }
 }
 
 struct CCFGBuilderVerifier {}
@@ -11,4 +11,7 @@ pub mod lang_c;
 pub mod x86 {
     pub mod x86_idioms;
 }
+pub mod arm {
+    pub mod arm_idioms;
+}
 pub mod ctrl_flow_struct;
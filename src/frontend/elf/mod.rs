@@ -0,0 +1,521 @@
+//! Native `Source` for ELF files, which does not need radare2.
+//!
+//! `ElfSource` reads the binary metadata (sections, symbols, imports, exports,
+//! relocations, libraries, entrypoint and strings) directly from the file.
+//! Everything that requires a disassembler, such as `functions` and
+//! `instructions_at`, is forwarded to another `Source` set with
+//! `ElfSource::code`, e.g. a `FileSource` or a live r2 instance.
+//!
+//! ```rust,no_run
+//! # extern crate radeco_lib;
+//! # use radeco_lib::frontend::elf::ElfSource;
+//! # use radeco_lib::frontend::radeco_source::Source;
+//! # fn main() {
+//! let elf = ElfSource::open("./test_files/file").expect("Failed to load ELF");
+//! for lib in elf.libraries().expect("Failed to read libraries") {
+//!     println!("{}", lib);
+//! }
+//! # }
+//! ```
+
+pub mod reader;
+
+use base64;
+use serde_json::{self, Value};
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+
+use r2api::structs::{FunctionInfo, LCCInfo, LEntryInfo, LExportInfo, LFlagInfo, LImportInfo, LOpInfo,
+                     LRegInfo, LRelocInfo, LSectionInfo, LStringInfo, LSymbolInfo, LVarInfo};
+
+use frontend::radeco_source::{Source, SourceErr};
+use self::reader::{Elf, Reloc, Section, Symbol};
+use self::reader::{EM_386, EM_AARCH64, EM_ARM, EM_X86_64};
+use self::reader::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE, SHT_DYNSYM, SHT_NOBITS, SHT_REL, SHT_RELA, SHT_SYMTAB};
+use self::reader::{STB_GLOBAL, STB_LOCAL, STB_WEAK, STT_FILE, STT_FUNC, STT_NOTYPE, STT_SECTION};
+
+/// Minimum length of the strings reported by `Source::strings`.
+const MIN_STRING_LENGTH: usize = 4;
+
+pub struct ElfSource {
+    elf: Elf,
+    code: Option<Rc<Source>>,
+}
+
+impl ElfSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ElfSource, SourceErr> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        ElfSource::from_bytes(data)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<ElfSource, SourceErr> {
+        let elf = Elf::parse(data).map_err(SourceErr::SrcErr)?;
+        Ok(ElfSource {
+            elf: elf,
+            code: None,
+        })
+    }
+
+    /// Sets the `Source` used to retrieve functions, instructions and
+    /// register profile, which can not be read from the file itself.
+    pub fn code(mut self, code: Rc<Source>) -> ElfSource {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn elf(&self) -> &Elf {
+        &self.elf
+    }
+
+    fn code_source(&self) -> Result<&Rc<Source>, SourceErr> {
+        self.code.as_ref().ok_or(SourceErr::SrcErr("No source for the code of the ELF file"))
+    }
+
+    // Symbols from `.symtab`, or from `.dynsym` for stripped binaries.
+    fn elf_symbols(&self) -> Result<Vec<Symbol>, SourceErr> {
+        let table = self.elf.sections.iter().find(|s| s.stype == SHT_SYMTAB)
+            .or_else(|| self.elf.sections.iter().find(|s| s.stype == SHT_DYNSYM));
+        match table {
+            Some(table) => Ok(self.elf.symbols(table).map_err(SourceErr::SrcErr)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn dynamic_symbols(&self) -> Result<Vec<Symbol>, SourceErr> {
+        match self.elf.sections.iter().find(|s| s.stype == SHT_DYNSYM) {
+            Some(table) => Ok(self.elf.symbols(table).map_err(SourceErr::SrcErr)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // Relocations of every relocation section, with the name of their symbol.
+    fn elf_relocs(&self) -> Result<Vec<(&Section, Reloc, Option<String>)>, SourceErr> {
+        let mut relocs = Vec::new();
+        for section in &self.elf.sections {
+            if section.stype != SHT_REL && section.stype != SHT_RELA {
+                continue;
+            }
+            let symbols = match self.elf.sections.get(section.link as usize) {
+                Some(table) if section.link != 0 => self.elf.symbols(table).map_err(SourceErr::SrcErr)?,
+                _ => Vec::new(),
+            };
+            for reloc in self.elf.relocs(section).map_err(SourceErr::SrcErr)? {
+                let name = match symbols.get(reloc.sym as usize) {
+                    Some(s) if reloc.sym != 0 && !s.name.is_empty() => Some(s.name.clone()),
+                    _ => None,
+                };
+                relocs.push((section, reloc, name));
+            }
+        }
+        Ok(relocs)
+    }
+
+    // Address of the PLT stub of every function imported through `.rela.plt`
+    // (or `.rel.plt`), by name.
+    fn plt_entries(&self) -> Result<Vec<(String, u64)>, SourceErr> {
+        let plt = match self.elf.section_named(".plt") {
+            Some(plt) => plt.addr,
+            None => return Ok(Vec::new()),
+        };
+        let (header, entry) = match self.elf.machine {
+            EM_386 | EM_X86_64 => (16, 16),
+            EM_ARM => (20, 12),
+            EM_AARCH64 => (32, 16),
+            _ => return Ok(Vec::new()),
+        };
+        let relocs = self.elf_relocs()?;
+        let entries = relocs.into_iter()
+                            .filter(|&(s, _, _)| s.name == ".rela.plt" || s.name == ".rel.plt")
+                            .enumerate()
+                            .filter_map(|(i, (_, _, name))| name.map(|n| (n, plt + header + i as u64 * entry)))
+                            .collect();
+        Ok(entries)
+    }
+}
+
+fn bind_name(bind: u8) -> &'static str {
+    match bind {
+        STB_LOCAL => "LOCAL",
+        STB_GLOBAL => "GLOBAL",
+        STB_WEAK => "WEAK",
+        _ => "UNKNOWN",
+    }
+}
+
+fn type_name(stype: u8) -> Option<&'static str> {
+    match stype {
+        STT_NOTYPE => Some("NOTYPE"),
+        STT_FUNC => Some("FUNC"),
+        _ => None,
+    }
+}
+
+fn is_named(symbol: &Symbol) -> bool {
+    match symbol.stype {
+        STT_SECTION | STT_FILE => false,
+        _ => !symbol.name.is_empty(),
+    }
+}
+
+// Permissions of the section, in the format used by r2, e.g. "-r-x".
+fn section_flags(section: &Section) -> String {
+    let flag = |f, c| if section.flags & f != 0 { c } else { '-' };
+    ['-', flag(SHF_ALLOC, 'r'), flag(SHF_WRITE, 'w'), flag(SHF_EXECINSTR, 'x')].iter().collect()
+}
+
+fn is_relative(machine: u16, rtype: u32) -> bool {
+    match (machine, rtype) {
+        (EM_X86_64, 8) | (EM_386, 8) | (EM_ARM, 23) | (EM_AARCH64, 1027) => true,
+        _ => false,
+    }
+}
+
+fn is_ifunc(machine: u16, rtype: u32) -> bool {
+    match (machine, rtype) {
+        (EM_X86_64, 37) | (EM_386, 42) | (EM_ARM, 160) | (EM_AARCH64, 1032) => true,
+        _ => false,
+    }
+}
+
+impl Source for ElfSource {
+    fn functions(&self) -> Result<Vec<FunctionInfo>, SourceErr> {
+        self.code_source()?.functions()
+    }
+
+    fn instructions_at(&self, address: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+        self.code_source()?.instructions_at(address)
+    }
+
+    fn register_profile(&self) -> Result<LRegInfo, SourceErr> {
+        self.code_source()?.register_profile()
+    }
+
+    fn flags(&self) -> Result<Vec<LFlagInfo>, SourceErr> {
+        let records = self.elf_symbols()?
+                          .iter()
+                          .filter(|s| is_named(s) && s.is_defined())
+                          .map(|s| {
+                              json!({
+                                  "name": format!("sym.{}", s.name),
+                                  "offset": s.value,
+                                  "size": s.size,
+                              })
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn sections(&self) -> Result<Vec<LSectionInfo>, SourceErr> {
+        let records = self.elf
+                          .sections
+                          .iter()
+                          .map(|s| {
+                              let size = if s.stype == SHT_NOBITS { 0 } else { s.size };
+                              json!({
+                                  "flags": section_flags(s),
+                                  "name": s.name,
+                                  "paddr": s.offset,
+                                  "size": size,
+                                  "vaddr": s.addr,
+                                  "vsize": s.size,
+                              })
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn symbols(&self) -> Result<Vec<LSymbolInfo>, SourceErr> {
+        let records = self.elf_symbols()?
+                          .iter()
+                          .filter(|s| is_named(s))
+                          .enumerate()
+                          .map(|(i, s)| {
+                              let mut record = json!({
+                                  "name": s.name,
+                                  "flagname": format!("sym.{}", s.name),
+                                  "ordinal": i,
+                                  "bind": bind_name(s.bind),
+                                  "size": s.size,
+                                  "vaddr": s.value,
+                                  "paddr": self.elf.file_offset(s.value).unwrap_or(0),
+                              });
+                              if let Some(t) = type_name(s.stype) {
+                                  record["type"] = json!(t);
+                              }
+                              record
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn imports(&self) -> Result<Vec<LImportInfo>, SourceErr> {
+        let plt = self.plt_entries()?;
+        let records = self.dynamic_symbols()?
+                          .iter()
+                          .filter(|s| is_named(s) && !s.is_defined())
+                          .enumerate()
+                          .map(|(i, s)| {
+                              let mut record = json!({
+                                  "bind": bind_name(s.bind),
+                                  "name": s.name,
+                                  "ordinal": i + 1,
+                                  "plt": plt.iter().find(|&&(ref n, _)| n == &s.name).map_or(0, |&(_, a)| a),
+                              });
+                              if let Some(t) = type_name(s.stype) {
+                                  record["type"] = json!(t);
+                              }
+                              record
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn exports(&self) -> Result<Vec<LExportInfo>, SourceErr> {
+        let records = self.elf_symbols()?
+                          .iter()
+                          .filter(|s| {
+                              is_named(s) && s.is_defined() && (s.bind == STB_GLOBAL || s.bind == STB_WEAK)
+                          })
+                          .enumerate()
+                          .map(|(i, s)| {
+                              let mut record = json!({
+                                  "name": s.name,
+                                  "flagname": format!("sym.{}", s.name),
+                                  "ordinal": i,
+                                  "bind": bind_name(s.bind),
+                                  "size": s.size,
+                                  "vaddr": s.value,
+                                  "paddr": self.elf.file_offset(s.value).unwrap_or(0),
+                              });
+                              if let Some(t) = type_name(s.stype) {
+                                  record["type"] = json!(t);
+                              }
+                              record
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn relocs(&self) -> Result<Vec<LRelocInfo>, SourceErr> {
+        let machine = self.elf.machine;
+        let bits = self.elf.word_size() * 8;
+        let records = self.elf_relocs()?
+                          .into_iter()
+                          .map(|(_, r, name)| {
+                              let kind = if is_relative(machine, r.rtype) { "ADD" } else { "SET" };
+                              let mut record = json!({
+                                  "is_ifunc": is_ifunc(machine, r.rtype),
+                                  "paddr": self.elf.file_offset(r.offset).unwrap_or(0),
+                                  "type": format!("{}_{}", kind, bits),
+                                  "vaddr": r.offset,
+                              });
+                              if let Some(name) = name {
+                                  record["name"] = json!(name);
+                              }
+                              record
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn libraries(&self) -> Result<Vec<String>, SourceErr> {
+        self.elf.needed().map_err(SourceErr::SrcErr)
+    }
+
+    fn entrypoint(&self) -> Result<Vec<LEntryInfo>, SourceErr> {
+        let entry = self.elf.entry;
+        let record = json!({
+            "vaddr": entry,
+            "paddr": self.elf.file_offset(entry).unwrap_or(0),
+            "baddr": self.elf.segments.iter().map(|s| s.vaddr).min().unwrap_or(0),
+            "laddr": 0,
+            "haddr": 24,
+            "etype": Value::Null,
+        });
+        Ok(serde_json::from_value(Value::Array(vec![record]))?)
+    }
+
+    fn disassemble_function(&self, name: &str) -> Result<Vec<LOpInfo>, SourceErr> {
+        self.code_source()?.disassemble_function(name)
+    }
+
+    fn disassemble_n_bytes(&self, n: u64, at: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+        self.code_source()?.disassemble_n_bytes(n, at)
+    }
+
+    fn disassemble_n_insts(&self, n: u64, at: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+        self.code_source()?.disassemble_n_insts(n, at)
+    }
+
+    fn locals_of(&self, start_addr: u64) -> Result<Vec<LVarInfo>, SourceErr> {
+        self.code_source()?.locals_of(start_addr)
+    }
+
+    fn cc_info_of(&self, start_addr: u64) -> Result<LCCInfo, SourceErr> {
+        self.code_source()?.cc_info_of(start_addr)
+    }
+
+    /// Printable ASCII runs terminated by a NUL byte. With `data_only`, only
+    /// the sections holding data are searched, otherwise every section loaded
+    /// from the file is.
+    fn strings(&self, data_only: bool) -> Result<Vec<LStringInfo>, SourceErr> {
+        let mut records = Vec::new();
+        for section in &self.elf.sections {
+            if section.flags & SHF_ALLOC == 0 || section.stype == SHT_NOBITS {
+                continue;
+            }
+            if data_only && section.flags & SHF_EXECINSTR != 0 {
+                continue;
+            }
+            let data = self.elf.section_data(section).map_err(SourceErr::SrcErr)?;
+            let mut start = 0;
+            for (i, &b) in data.iter().enumerate() {
+                if (b >= 0x20 && b < 0x7f) || b == b'\t' || b == b'\n' {
+                    continue;
+                }
+                if b == 0 && i - start >= MIN_STRING_LENGTH {
+                    let ordinal = records.len();
+                    records.push(json!({
+                        "length": i - start,
+                        "ordinal": ordinal,
+                        "paddr": section.offset + start as u64,
+                        "section": section.name,
+                        "size": i - start + 1,
+                        "string": base64::encode(&data[start..i]),
+                        "vaddr": section.addr + start as u64,
+                        "type": "ascii",
+                    }));
+                }
+                start = i + 1;
+            }
+        }
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn raw(&self, cmd: String) -> Result<String, SourceErr> {
+        self.code_source()?.raw(cmd)
+    }
+
+    fn read_bytes(&self, addr: u64, size: u64) -> Result<Vec<u8>, SourceErr> {
+        self.elf.read(addr, size).map_err(SourceErr::SrcErr)
+    }
+
+    fn send(&self, s: String) -> Result<(), SourceErr> {
+        match self.code {
+            Some(ref code) => code.send(s),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FILE: &'static str = "./test_files/file";
+    const FILE_32: &'static str = "./test_files/elf32";
+
+    #[test]
+    fn elf_source_metadata_test() {
+        let elf = ElfSource::open(FILE).expect("Failed to load ELF");
+        let entry = elf.entrypoint().expect("No entrypoint");
+        assert_eq!(entry.len(), 1);
+        assert_eq!(entry[0].vaddr, Some(0x2640));
+
+        let libraries = elf.libraries().expect("No libraries");
+        assert_eq!(libraries, vec!["libmagic.so.1".to_owned(), "libc.so.6".to_owned()]);
+
+        let sections = elf.sections().expect("No sections");
+        assert_eq!(sections.len(), 30);
+        let text = sections.iter()
+                           .find(|s| s.name.as_ref().map_or(false, |n| n == ".text"))
+                           .expect("No .text section");
+        assert_eq!(text.vaddr, Some(0x1890));
+        assert_eq!(text.size, Some(0x1502));
+    }
+
+    #[test]
+    fn elf_source_symbols_test() {
+        let elf = ElfSource::open(FILE).expect("Failed to load ELF");
+        let imports = elf.imports().expect("No imports");
+        let getenv = imports.iter()
+                            .find(|i| i.name.as_ref().map_or(false, |n| n == "getenv"))
+                            .expect("No import of getenv");
+        assert_eq!(getenv.plt, Some(0x1610));
+
+        let exports = elf.exports().expect("No exports");
+        let init = exports.iter()
+                          .find(|e| e.name.as_ref().map_or(false, |n| n == "_init"))
+                          .expect("No export of _init");
+        assert_eq!(init.vaddr, Some(0x15e8));
+        assert!(elf.functions().is_err());
+    }
+
+    #[test]
+    fn elf_source_elf32_test() {
+        let elf = ElfSource::open(FILE_32).expect("Failed to load ELF");
+        assert!(!elf.elf().is_64());
+        assert_eq!(elf.elf().machine, EM_386);
+
+        let imports = elf.imports().expect("No imports");
+        let puts = imports.iter()
+                          .find(|i| i.name.as_ref().map_or(false, |n| n == "puts"))
+                          .expect("No import of puts");
+        assert_eq!(puts.plt, Some(0x170));
+
+        let exports = elf.exports().expect("No exports");
+        let greet = exports.iter()
+                           .find(|e| e.name.as_ref().map_or(false, |n| n == "greet"))
+                           .expect("No export of greet");
+        assert_eq!(greet.vaddr, Some(0x180));
+    }
+
+    #[test]
+    fn elf_source_relocs_test() {
+        let elf = ElfSource::open(FILE_32).expect("Failed to load ELF");
+        let relocs = elf.relocs().expect("No relocs");
+        assert_eq!(relocs.len(), 1);
+        assert_eq!(relocs[0].name, Some("puts".to_owned()));
+        assert_eq!(relocs[0].vaddr, Some(0x2000));
+        assert_eq!(relocs[0].paddr, Some(0x1000));
+    }
+
+    #[test]
+    fn elf_source_strings_test() {
+        let elf = ElfSource::open(FILE_32).expect("Failed to load ELF");
+        let strings = elf.strings(true).expect("No strings");
+        let hello = strings.iter()
+                           .find(|s| s.vaddr == Some(0x188))
+                           .expect("No string in .rodata");
+        assert_eq!(hello.string, Some(base64::encode("hello, elf32")));
+        assert_eq!(hello.section, Some(".rodata".to_owned()));
+        // Names of the dynamic symbols.
+        assert!(strings.iter().any(|s| s.string == Some(base64::encode("libelf32.so"))));
+    }
+
+    #[test]
+    fn elf_source_read_bytes_test() {
+        let elf = ElfSource::open(FILE_32).expect("Failed to load ELF");
+        // `counter` in `.data`, followed by `buffer` in `.bss`.
+        let bytes = elf.read_bytes(0x2004, 8).expect("Failed to read bytes");
+        assert_eq!(bytes, [0x44, 0x33, 0x22, 0x11, 0, 0, 0, 0]);
+        assert!(elf.read_bytes(0x2010, 0x10).is_err());
+        assert!(elf.read_bytes(u64::max_value() - 1, 4).is_err());
+        assert_eq!(elf.elf().file_offset(u64::max_value()), None);
+    }
+
+    #[test]
+    fn elf_header_overflow_test() {
+        let mut data = Vec::new();
+        File::open(FILE).unwrap().read_to_end(&mut data).unwrap();
+        // `e_phoff` at the end of the address space.
+        for b in &mut data[32..40] {
+            *b = 0xff;
+        }
+        assert!(Elf::parse(data).is_err());
+    }
+}
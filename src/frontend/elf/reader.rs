@@ -0,0 +1,372 @@
+//! Reads the headers, symbols, relocations and dynamic entries of ELF files.
+//!
+//! Both classes, ELF32 and ELF64, and both byte orders are understood. Only the
+//! parts needed by [`ElfSource`] are decoded.
+//!
+//! [`ElfSource`]: ::frontend::elf::ElfSource
+
+use std::result;
+
+pub type Result<T> = result::Result<T, &'static str>;
+
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_DYNAMIC: u32 = 6;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_REL: u32 = 9;
+pub const SHT_DYNSYM: u32 = 11;
+
+pub const SHF_WRITE: u64 = 0x1;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+
+pub const PT_LOAD: u32 = 1;
+
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+pub const STT_FILE: u8 = 4;
+
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
+
+pub const SHN_UNDEF: u16 = 0;
+
+pub const EM_386: u16 = 3;
+pub const EM_ARM: u16 = 40;
+pub const EM_X86_64: u16 = 62;
+pub const EM_AARCH64: u16 = 183;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+
+/// Section header.
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub name: String,
+    pub stype: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+}
+
+/// Program header.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub ptype: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub filesz: u64,
+    pub memsz: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub stype: u8,
+    pub bind: u8,
+    pub shndx: u16,
+}
+
+impl Symbol {
+    pub fn is_defined(&self) -> bool {
+        self.shndx != SHN_UNDEF
+    }
+}
+
+/// Relocation, with the index of its symbol in the linked symbol table.
+#[derive(Clone, Debug)]
+pub struct Reloc {
+    pub offset: u64,
+    pub rtype: u32,
+    pub sym: u32,
+}
+
+/// A parsed ELF file, holding its contents.
+#[derive(Clone, Debug)]
+pub struct Elf {
+    data: Vec<u8>,
+    is_64: bool,
+    big_endian: bool,
+    pub machine: u16,
+    pub entry: u64,
+    pub sections: Vec<Section>,
+    pub segments: Vec<Segment>,
+}
+
+impl Elf {
+    pub fn parse(data: Vec<u8>) -> Result<Elf> {
+        if data.len() < 16 || &data[..4] != b"\x7fELF" {
+            return Err("Not an ELF file");
+        }
+        let is_64 = match data[4] {
+            1 => false,
+            2 => true,
+            _ => return Err("Invalid ELF class"),
+        };
+        let big_endian = match data[5] {
+            1 => false,
+            2 => true,
+            _ => return Err("Invalid ELF data encoding"),
+        };
+        let mut elf = Elf {
+            data: data,
+            is_64: is_64,
+            big_endian: big_endian,
+            machine: 0,
+            entry: 0,
+            sections: Vec::new(),
+            segments: Vec::new(),
+        };
+
+        // The fields following `e_entry` are shifted by the size of the words.
+        let w = elf.word_size();
+        elf.machine = elf.u16_at(18)?;
+        elf.entry = elf.word_at(24)?;
+        let phoff = elf.word_at(24 + w)?;
+        let shoff = elf.word_at(24 + 2 * w)?;
+        let phentsize = elf.u16_at(30 + 3 * w)? as u64;
+        let phnum = elf.u16_at(32 + 3 * w)? as u64;
+        let shentsize = elf.u16_at(34 + 3 * w)? as u64;
+        let shnum = elf.u16_at(36 + 3 * w)? as u64;
+        let shstrndx = elf.u16_at(38 + 3 * w)? as usize;
+
+        for i in 0..phnum {
+            let segment = elf.segment_at(entry_at(phoff, i, phentsize)?)?;
+            elf.segments.push(segment);
+        }
+        let mut name_offsets = Vec::new();
+        for i in 0..shnum {
+            let at = entry_at(shoff, i, shentsize)?;
+            name_offsets.push(elf.u32_at(at)? as u64);
+            let section = elf.section_at(at)?;
+            elf.sections.push(section);
+        }
+        // Name the sections, now that the table of their names is known.
+        if let Some(names) = elf.sections.get(shstrndx).cloned() {
+            for (i, offset) in name_offsets.into_iter().enumerate() {
+                let name = elf.string_at(&names, offset)?;
+                elf.sections[i].name = name;
+            }
+        }
+        Ok(elf)
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    /// Size of addresses, in bytes.
+    pub fn word_size(&self) -> u64 {
+        if self.is_64 { 8 } else { 4 }
+    }
+
+    pub fn section_named(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Contents of the section, empty if it takes no space in the file.
+    pub fn section_data(&self, section: &Section) -> Result<&[u8]> {
+        if section.stype == SHT_NOBITS {
+            return Ok(&[]);
+        }
+        self.bytes(section.offset, section.size)
+    }
+
+    /// Symbols of a symbol table, `.symtab` or `.dynsym`.
+    pub fn symbols(&self, table: &Section) -> Result<Vec<Symbol>> {
+        let strtab = self.linked(table)?;
+        let entsize = if self.is_64 { 24 } else { 16 };
+        let mut symbols = Vec::new();
+        for i in 0..table.size / entsize {
+            let at = entry_at(table.offset, i, entsize)?;
+            let name = self.u32_at(at)? as u64;
+            let (info, shndx, value, size) = if self.is_64 {
+                (self.u8_at(at + 4)?, self.u16_at(at + 6)?, self.u64_at(at + 8)?, self.u64_at(at + 16)?)
+            } else {
+                (self.u8_at(at + 12)?,
+                 self.u16_at(at + 14)?,
+                 self.u32_at(at + 4)? as u64,
+                 self.u32_at(at + 8)? as u64)
+            };
+            symbols.push(Symbol {
+                name: self.string_at(strtab, name)?,
+                value: value,
+                size: size,
+                stype: info & 0xf,
+                bind: info >> 4,
+                shndx: shndx,
+            });
+        }
+        Ok(symbols)
+    }
+
+    /// Relocations of a `SHT_REL` or `SHT_RELA` section.
+    pub fn relocs(&self, section: &Section) -> Result<Vec<Reloc>> {
+        let w = self.word_size();
+        let entsize = if section.stype == SHT_RELA { 3 * w } else { 2 * w };
+        let mut relocs = Vec::new();
+        for i in 0..section.size / entsize {
+            let at = entry_at(section.offset, i, entsize)?;
+            let offset = self.word_at(at)?;
+            let info = self.word_at(at + w)?;
+            let (sym, rtype) = if self.is_64 {
+                (info >> 32, info & 0xffff_ffff)
+            } else {
+                (info >> 8, info & 0xff)
+            };
+            relocs.push(Reloc {
+                offset: offset,
+                rtype: rtype as u32,
+                sym: sym as u32,
+            });
+        }
+        Ok(relocs)
+    }
+
+    /// Names of the libraries needed, from the `.dynamic` section.
+    pub fn needed(&self) -> Result<Vec<String>> {
+        let dynamic = match self.sections.iter().find(|s| s.stype == SHT_DYNAMIC) {
+            Some(dynamic) => dynamic,
+            None => return Ok(Vec::new()),
+        };
+        let strtab = self.linked(dynamic)?;
+        let w = self.word_size();
+        let mut needed = Vec::new();
+        for i in 0..dynamic.size / (2 * w) {
+            let at = entry_at(dynamic.offset, i, 2 * w)?;
+            match self.word_at(at)? {
+                DT_NULL => break,
+                DT_NEEDED => needed.push(self.string_at(strtab, self.word_at(at + w)?)?),
+                _ => {}
+            }
+        }
+        Ok(needed)
+    }
+
+    /// Offset in the file of the virtual address, if it is loaded from it.
+    pub fn file_offset(&self, vaddr: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .filter(|s| s.ptype == PT_LOAD)
+            .find(|s| {
+                vaddr >= s.vaddr && s.vaddr.checked_add(s.filesz).map_or(false, |end| vaddr < end)
+            })
+            .and_then(|s| (vaddr - s.vaddr).checked_add(s.offset))
+    }
+
+    /// Bytes loaded at the virtual address. Bytes past the end of the file
+    /// contents of a segment, as in `.bss`, are zero.
+    pub fn read(&self, vaddr: u64, size: u64) -> Result<Vec<u8>> {
+        let end = vaddr.checked_add(size).ok_or("Address is not mapped")?;
+        let segment = self.segments
+            .iter()
+            .filter(|s| s.ptype == PT_LOAD)
+            .find(|s| vaddr >= s.vaddr && s.vaddr.checked_add(s.memsz).map_or(false, |e| end <= e))
+            .ok_or("Address is not mapped")?;
+        let start = vaddr - segment.vaddr;
+        let in_file = segment.filesz.saturating_sub(start).min(size);
+        let at = segment.offset.checked_add(start).ok_or("Truncated ELF file")?;
+        let mut bytes = self.bytes(at, in_file)?.to_vec();
+        bytes.resize(size as usize, 0);
+        Ok(bytes)
+    }
+
+    fn segment_at(&self, at: u64) -> Result<Segment> {
+        let ptype = self.u32_at(at)?;
+        if self.is_64 {
+            Ok(Segment {
+                ptype: ptype,
+                offset: self.u64_at(at + 8)?,
+                vaddr: self.u64_at(at + 16)?,
+                filesz: self.u64_at(at + 32)?,
+                memsz: self.u64_at(at + 40)?,
+            })
+        } else {
+            Ok(Segment {
+                ptype: ptype,
+                offset: self.u32_at(at + 4)? as u64,
+                vaddr: self.u32_at(at + 8)? as u64,
+                filesz: self.u32_at(at + 16)? as u64,
+                memsz: self.u32_at(at + 20)? as u64,
+            })
+        }
+    }
+
+    fn section_at(&self, at: u64) -> Result<Section> {
+        let w = self.word_size();
+        Ok(Section {
+            name: String::new(),
+            stype: self.u32_at(at + 4)?,
+            flags: self.word_at(at + 8)?,
+            addr: self.word_at(at + 8 + w)?,
+            offset: self.word_at(at + 8 + 2 * w)?,
+            size: self.word_at(at + 8 + 3 * w)?,
+            link: self.u32_at(at + 8 + 4 * w)?,
+            info: self.u32_at(at + 12 + 4 * w)?,
+        })
+    }
+
+    // The section `link` of `section` refers to, e.g. the string table of a
+    // symbol table.
+    fn linked(&self, section: &Section) -> Result<&Section> {
+        self.sections.get(section.link as usize).ok_or("Invalid section link")
+    }
+
+    fn string_at(&self, strtab: &Section, offset: u64) -> Result<String> {
+        let data = self.section_data(strtab)?;
+        let start = data.get(offset as usize..).ok_or("Invalid string offset")?;
+        let end = start.iter().position(|&b| b == 0).unwrap_or(start.len());
+        Ok(String::from_utf8_lossy(&start[..end]).into_owned())
+    }
+
+    fn bytes(&self, at: u64, size: u64) -> Result<&[u8]> {
+        let end = at.checked_add(size).ok_or("Truncated ELF file")?;
+        self.data.get(at as usize..end as usize).ok_or("Truncated ELF file")
+    }
+
+    fn uint_at(&self, at: u64, size: u64) -> Result<u64> {
+        let bytes = self.bytes(at, size)?;
+        let mut value = 0;
+        for i in 0..bytes.len() {
+            let b = if self.big_endian { bytes[i] } else { bytes[bytes.len() - 1 - i] };
+            value = value << 8 | b as u64;
+        }
+        Ok(value)
+    }
+
+    fn u8_at(&self, at: u64) -> Result<u8> {
+        self.uint_at(at, 1).map(|v| v as u8)
+    }
+
+    fn u16_at(&self, at: u64) -> Result<u16> {
+        self.uint_at(at, 2).map(|v| v as u16)
+    }
+
+    fn u32_at(&self, at: u64) -> Result<u32> {
+        self.uint_at(at, 4).map(|v| v as u32)
+    }
+
+    fn u64_at(&self, at: u64) -> Result<u64> {
+        self.uint_at(at, 8)
+    }
+
+    fn word_at(&self, at: u64) -> Result<u64> {
+        self.uint_at(at, self.word_size())
+    }
+}
+
+// Offset of the entry `i` of the table at `offset`, with entries of `entsize`
+// bytes.
+fn entry_at(offset: u64, i: u64, entsize: u64) -> Result<u64> {
+    i.checked_mul(entsize)
+        .and_then(|at| offset.checked_add(at))
+        .ok_or("Truncated ELF file")
+}
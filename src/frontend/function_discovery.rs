@@ -0,0 +1,250 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Discovers functions by recursive descent.
+//!
+//! Starting from a set of seed addresses, the instructions of every function
+//! are found by following its jumps, and the targets of its calls become new
+//! functions. This is repeated until no new function is found.
+//!
+//! An unconditional jump to the start of another function is a tail call: it
+//! is recorded, but not followed. Conditional jumps are never tail calls. As
+//! functions are found, a function previously explored may turn out to jump
+//! into one of them, in which case it is explored again.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use frontend::radeco_source::{Source, SourceErr};
+use r2api::structs::LOpInfo;
+
+/// Maximum number of instructions of a single function.
+pub const MAX_INSTRUCTIONS: usize = 0x4000;
+
+// Number of instructions disassembled at once.
+const CHUNK_SIZE: u64 = 32;
+
+#[derive(Clone, Debug, Default)]
+/// A function found by `discover`.
+pub struct DiscoveredFunction {
+    /// Start address of the function
+    pub offset: u64,
+    /// Size of the function in bytes, from its start to its last instruction
+    pub size: u64,
+    /// Instructions of the function, sorted by address
+    pub instructions: Vec<LOpInfo>,
+    /// Targets of the direct calls made by the function
+    pub calls: Vec<u64>,
+    /// Targets of the tail calls made by the function
+    pub tail_calls: Vec<u64>,
+}
+
+impl DiscoveredFunction {
+    // Returns true if the function has an instruction at `address`, which is
+    // not its start.
+    fn contains(&self, address: u64) -> bool {
+        address != self.offset &&
+            self.instructions.binary_search_by_key(&Some(address), |op| op.offset).is_ok()
+    }
+}
+
+/// Discovers the functions reachable from `seeds`, using the instructions
+/// disassembled by `source`. `external` are the addresses of functions which
+/// are not part of the binary, like imports. They are never explored, but
+/// jumps to them are tail calls.
+pub fn discover(source: &Source,
+                seeds: &[u64],
+                external: &HashSet<u64>)
+                -> Result<BTreeMap<u64, DiscoveredFunction>, SourceErr> {
+    let mut starts = seeds.iter()
+        .cloned()
+        .filter(|addr| !external.contains(addr))
+        .collect::<BTreeSet<_>>();
+    let mut functions = BTreeMap::<u64, DiscoveredFunction>::new();
+
+    loop {
+        // Explore new functions, and functions which jump into other ones.
+        let pending = starts.iter()
+            .cloned()
+            .filter(|entry| match functions.get(entry) {
+                Some(f) => starts.iter().any(|&s| f.contains(s)),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            break;
+        }
+        for entry in pending {
+            let f = explore(source, entry, &starts, external)?;
+            radeco_trace!("function_discovery|{:#x}|{} instructions|calls {:?}|tail calls {:?}",
+                          entry, f.instructions.len(), f.calls, f.tail_calls);
+            functions.insert(entry, f);
+        }
+
+        let targets = functions.values()
+            .flat_map(|f| f.calls.iter().chain(f.tail_calls.iter()))
+            .cloned()
+            .filter(|addr| !external.contains(addr))
+            .collect::<Vec<_>>();
+        starts.extend(targets);
+    }
+
+    Ok(functions)
+}
+
+// Explores the function starting at `entry`. The exploration stops at the
+// start of other functions, `starts`.
+fn explore(source: &Source,
+           entry: u64,
+           starts: &BTreeSet<u64>,
+           external: &HashSet<u64>)
+           -> Result<DiscoveredFunction, SourceErr> {
+    let mut f = DiscoveredFunction::default();
+    f.offset = entry;
+    let is_function = |addr: u64| addr != entry && (starts.contains(&addr) || external.contains(&addr));
+
+    let mut ops = BTreeMap::new();
+    let mut worklist = vec![entry];
+    while let Some(start) = worklist.pop() {
+        let mut addr = start;
+        'block: loop {
+            if ops.contains_key(&addr) || is_function(addr) || ops.len() >= MAX_INSTRUCTIONS {
+                break;
+            }
+            let chunk = source.disassemble_n_insts(CHUNK_SIZE, addr)?;
+            if chunk.is_empty() {
+                break;
+            }
+            for op in chunk {
+                let size = match (op.offset, op.size) {
+                    (Some(offset), Some(size)) if offset == addr && size > 0 => size,
+                    _ => break 'block,
+                };
+                let optype = op.optype.clone().unwrap_or_default();
+                let jump = op.jump;
+                ops.insert(addr, op);
+
+                match optype.as_str() {
+                    "call" => f.calls.extend(jump),
+                    "jmp" => {
+                        match jump {
+                            Some(target) if is_function(target) => f.tail_calls.push(target),
+                            Some(target) => worklist.push(target),
+                            None => {}
+                        }
+                        break 'block;
+                    }
+                    // Exploration stops at the start of other functions.
+                    "cjmp" => worklist.extend(jump),
+                    "ret" | "ujmp" | "rjmp" | "ijmp" | "irjmp" | "mjmp" | "trap" | "ill" |
+                    "invalid" => break 'block,
+                    _ => {}
+                }
+
+                addr += size;
+                if ops.contains_key(&addr) || is_function(addr) {
+                    break 'block;
+                }
+            }
+        }
+    }
+
+    if let Some((&last, op)) = ops.iter().next_back() {
+        f.size = last + op.size.unwrap_or(0) - entry;
+    }
+    f.instructions = ops.into_iter().map(|(_, op)| op).collect();
+    f.calls.sort();
+    f.calls.dedup();
+    f.tail_calls.sort();
+    f.tail_calls.dedup();
+    Ok(f)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use r2api::structs::{FunctionInfo, LFlagInfo, LRegInfo, LSectionInfo};
+    use serde_json;
+
+    // Disassembles a fixed listing.
+    struct Listing(Vec<LOpInfo>);
+
+    impl Source for Listing {
+        fn functions(&self) -> Result<Vec<FunctionInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn instructions_at(&self, _: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+            Err(SourceErr::SrcErr("No Instructions found"))
+        }
+
+        fn register_profile(&self) -> Result<LRegInfo, SourceErr> {
+            Err(SourceErr::SrcErr("No register profile"))
+        }
+
+        fn flags(&self) -> Result<Vec<LFlagInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn sections(&self) -> Result<Vec<LSectionInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn disassemble_n_insts(&self, n: u64, at: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+            Ok(self.0
+                .iter()
+                .skip_while(|op| op.offset != Some(at))
+                .take(n as usize)
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[test]
+    fn discover_test() {
+        // 0x10 calls 0x30, which calls 0x40, which calls 0x20. 0x10 and 0x30
+        // jump to 0x20 before it is known to be a function. 0x50 is an import.
+        let ops = r#"[{"offset":16,"size":5,"opcode":"call 0x30","type":"call","jump":48},
+                      {"offset":21,"size":2,"opcode":"jne 0x17","type":"cjmp","jump":23},
+                      {"offset":23,"size":5,"opcode":"jmp 0x20","type":"jmp","jump":32},
+                      {"offset":28,"size":4,"opcode":"nop","type":"nop"},
+                      {"offset":32,"size":1,"opcode":"ret","type":"ret"},
+                      {"offset":48,"size":5,"opcode":"call 0x40","type":"call","jump":64},
+                      {"offset":53,"size":5,"opcode":"jmp 0x20","type":"jmp","jump":32},
+                      {"offset":64,"size":5,"opcode":"call 0x20","type":"call","jump":32},
+                      {"offset":69,"size":5,"opcode":"jmp 0x50","type":"jmp","jump":80},
+                      {"offset":80,"size":1,"opcode":"ret","type":"ret"}]"#;
+        let source = Listing(serde_json::from_str(ops).unwrap());
+        let external = [80].iter().cloned().collect();
+        let functions = discover(&source, &[16], &external).unwrap();
+
+        assert_eq!(functions.keys().cloned().collect::<Vec<_>>(), vec![16, 32, 48, 64]);
+        assert_eq!(functions[&16].calls, vec![48]);
+        assert_eq!(functions[&16].tail_calls, vec![32]);
+        assert_eq!(functions[&16].instructions.len(), 3);
+        assert_eq!(functions[&16].size, 12);
+        assert_eq!(functions[&48].tail_calls, vec![32]);
+        assert_eq!(functions[&64].calls, vec![32]);
+        assert_eq!(functions[&64].tail_calls, vec![80]);
+        assert_eq!(functions[&32].instructions.len(), 1);
+    }
+
+    #[test]
+    fn conditional_jump_test() {
+        // 0x10 jumps to 0x20 conditionally, and later calls it.
+        let ops = r#"[{"offset":16,"size":2,"opcode":"jne 0x20","type":"cjmp","jump":32},
+                      {"offset":18,"size":5,"opcode":"call 0x20","type":"call","jump":32},
+                      {"offset":23,"size":1,"opcode":"ret","type":"ret"},
+                      {"offset":32,"size":1,"opcode":"ret","type":"ret"}]"#;
+        let source = Listing(serde_json::from_str(ops).unwrap());
+        let functions = discover(&source, &[16], &HashSet::new()).unwrap();
+
+        assert_eq!(functions.keys().cloned().collect::<Vec<_>>(), vec![16, 32]);
+        assert_eq!(functions[&16].calls, vec![32]);
+        assert!(functions[&16].tail_calls.is_empty());
+        assert_eq!(functions[&16].instructions.len(), 3);
+    }
+}
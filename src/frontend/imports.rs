@@ -1,6 +1,7 @@
 //! Defines structs and methods to deal with imports and dynamic linking
 
 use frontend::radeco_containers::RadecoFunction;
+use middle::prototypes;
 use std::borrow::Cow;
 use std::cell::RefCell;
 
@@ -15,9 +16,14 @@ pub struct ImportInfo {
 }
 
 impl ImportInfo {
+    /// Creates the stub of an import, with the prototype of the libc function
+    /// of the same name if any.
     pub fn new_stub(plt: u64, name: Cow<'static, str>) -> ImportInfo {
         let mut rfn = RadecoFunction::default() ;
         rfn.name = name.clone();
+        if let Some(proto) = prototypes::lookup(&name) {
+            rfn.set_prototype(proto.clone());
+        }
         ImportInfo {
             plt: plt,
             name: name,
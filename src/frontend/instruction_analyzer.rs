@@ -4,7 +4,8 @@
 //! Instruction analysis may be abbreviated as `IA`
 use std::borrow::Cow;
 
-use capstone_rust::capstone as cs;
+use middle::intrinsics;
+use r2api::structs::LOpInfo;
 
 // TODO: Register must be replaced by Register information from arch-rs.
 // This will be a part of a bigger rewrite/refactor.
@@ -38,7 +39,7 @@ pub type IAError = Cow<'static, str>;
 /// Trait for a struct to be an Instruction-level analyzer
 pub trait InstructionAnalyzer: Sized {
     /// Construct the IA
-    fn new(bytes: Vec<u8>) -> Result<Self, IAError>;
+    fn new(op: &LOpInfo) -> Result<Self, IAError>;
 
     /// Return analyzed information
     fn info(&self) -> Result<&InstructionInfo, IAError>;
@@ -100,95 +101,170 @@ pub trait InstructionAnalyzer: Sized {
     }
 }
 
-/// Capstone-based x86 instruction analyzer
-#[allow(non_camel_case_types)]
-pub struct X86_CS_IA {
-    bytes: Vec<u8>,
-    cs: cs::Capstone,
-    info: InstructionInfo,
-}
+// Prefixes that are disassembled before the mnemonic
+const PREFIXES: &[&str] = &["lock", "rep", "repe", "repne", "repz", "repnz"];
 
-//impl From<cs::CsErr> for IAError {
-    //fn from(err: cs::CsErr) -> IAError {
-        //Cow::from(err.to_string())
-    //}
-//}
+/// Returns the mnemonic of the instruction disassembled by radare2, without
+/// prefixes.
+pub fn mnemonic_of(op: &LOpInfo) -> Option<String> {
+    op.opcode.as_ref().and_then(|disasm| {
+        disasm.split_whitespace()
+            .find(|w| !PREFIXES.contains(w))
+            .map(|w| w.to_lowercase())
+    })
+}
 
-impl X86_CS_IA {
+// Returns the explicit operands of the instruction disassembled by radare2.
+fn operands_of(op: &LOpInfo) -> Vec<IOperand> {
+    let disasm = match op.opcode {
+        Some(ref disasm) => disasm.to_lowercase(),
+        None => return Vec::new(),
+    };
+    // Skip the prefixes and the mnemonic
+    let operands = disasm.split_whitespace()
+        .skip_while(|w| PREFIXES.contains(w))
+        .skip(1)
+        .collect::<Vec<_>>()
+        .join(" ");
+    operands.split(',')
+        .map(|o| o.trim())
+        .filter(|o| !o.is_empty())
+        .map(parse_operand)
+        .collect()
+}
 
-    fn reg_name(&self, reg: u32) -> Option<String> {
-        self.cs.reg_name(reg).map(|x| x.to_owned())
+// Parses an operand in intel syntax, such as `rax`, `0x10` or
+// `qword [rax + rbx*8 - 0x10]`.
+fn parse_operand(operand: &str) -> IOperand {
+    if let (Some(open), Some(close)) = (operand.find('['), operand.rfind(']')) {
+        if open >= close {
+            return IOperand::Invalid;
+        }
+        let (mut base, mut index, mut scale, mut disp) = (None, None, 1, 0);
+        let addr = operand[open + 1..close].replace('-', "+-");
+        for term in addr.split('+').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if let Some(n) = parse_number(term) {
+                disp += n;
+            } else if let Some(star) = term.find('*') {
+                index = Some(term[..star].trim().to_owned());
+                scale = parse_number(term[star + 1..].trim()).unwrap_or(1) as i32;
+            } else if base.is_none() {
+                base = Some(term.to_owned());
+            } else {
+                index = Some(term.to_owned());
+            }
+        }
+        IOperand::Memory {
+            base: base,
+            index: index,
+            scale: scale,
+            disp: disp,
+        }
+    } else if let Some(n) = parse_number(operand) {
+        IOperand::Immediate(n)
+    } else if operand.chars().all(|c| c.is_alphanumeric()) {
+        IOperand::Register(operand.to_owned())
+    } else {
+        IOperand::Other
     }
+}
+
+fn parse_number(s: &str) -> Option<i64> {
+    let (neg, s) = if s.starts_with('-') {
+        (true, s[1..].trim())
+    } else {
+        (false, s)
+    };
+    let n = if s.starts_with("0x") {
+        u64::from_str_radix(&s[2..], 16).ok()? as i64
+    } else {
+        s.parse::<i64>().ok()?
+    };
+    Some(if neg { n.wrapping_neg() } else { n })
+}
+
+/// x86 instruction analyzer, for the instructions described in
+/// `middle::intrinsics`.
+#[allow(non_camel_case_types)]
+pub struct X86_IA {
+    info: InstructionInfo,
+}
 
-    fn analyze(&mut self) -> Result<(), IAError> {
-        // Disassembly exactly one instruction
-        let buf = match self.cs.disasm(self.bytes.as_slice(), 0x0, 1) {
-            Ok(d) => d,
-            Err(e) => return Err(Cow::from(e.to_string())),
+impl InstructionAnalyzer for X86_IA {
+    fn new(op: &LOpInfo) -> Result<X86_IA, IAError> {
+        let mut mnemonic = mnemonic_of(op).ok_or(Cow::from("no disassembly"))?;
+        let operands = operands_of(op);
+        // Software interrupts are told apart by their vector, e.g. `int80`
+        let vector = match (mnemonic.as_str(), operands.first()) {
+            ("int", Some(&IOperand::Immediate(n))) => Some(n),
+            _ => None,
+        };
+        if let Some(n) = vector {
+            mnemonic = format!("int{:x}", n);
+        }
+        let intrinsic = match intrinsics::lookup(&mnemonic) {
+            Some(intrinsic) => intrinsic,
+            None => return Err(Cow::from(format!("unknown instruction: {}", mnemonic))),
         };
 
-        for instr in buf.iter() {
-            // TODO: Look into prefixes for opcode.
-            // See how this information is set in capstone.
-            self.info.mnemonic = Cow::from(instr.mnemonic);
-            let details = instr.detail.unwrap();
-            if let cs::DetailsArch::X86(arch) = details.arch {
-                for i in 0..arch.op_count {
-                    // Get the current operand
-                    let op: cs::cs_x86_op = arch.operands[i as usize];
-                    let iop: IOperand = match op.type_ {
-                        cs::x86_op_type::X86_OP_REG => {
-                            let reg: cs::x86_reg = op.reg();
-                            if let Some(reg_name) = self.reg_name(reg.as_int()) {
-                                IOperand::Register(reg_name)
-                            } else {
-                                IOperand::Invalid
-                            }
-                        },
-                        cs::x86_op_type::X86_OP_MEM => {
-                            let mem: &cs::x86_op_mem = op.mem();
-                            let base = self.reg_name(mem.base);
-                            let index = self.reg_name(mem.index);
-                            let scale = mem.scale;
-                            let disp = mem.disp;
-                            IOperand::Memory {
-                                base: base,
-                                index: index,
-                                scale: scale,
-                                disp: disp,
-                            }
-                        },
-                        cs::x86_op_type::X86_OP_IMM => IOperand::Immediate(op.imm()),
-                        // FP/XMM/etc. We can handle these later. Right now, we only
-                        // bother about registers (gpr) and memory operands.
-                        _ => IOperand::Other,
-                    };
-
-                    if i == 0 {
-                        self.info.reads.push(iop);
-                    } else {
-                        self.info.writes.push(iop);
+        let mut info = InstructionInfo::default();
+        info.reads = intrinsic.reads.iter().map(|r| IOperand::Register(r.to_string())).collect();
+        info.writes = intrinsic.writes.iter().map(|r| IOperand::Register(r.to_string())).collect();
+        if intrinsic.memory {
+            // Memory anywhere
+            let anywhere = IOperand::Memory {
+                base: None,
+                index: None,
+                scale: 1,
+                disp: 0,
+            };
+            info.reads.push(anywhere.clone());
+            info.writes.push(anywhere);
+        }
+        if intrinsic.explicit {
+            for operand in operands {
+                // Registers used in the address are read as well
+                if let IOperand::Memory { ref base, ref index, .. } = operand {
+                    for reg in base.iter().chain(index.iter()) {
+                        info.reads.push(IOperand::Register(reg.clone()));
                     }
                 }
+                info.reads.push(operand.clone());
+                info.writes.push(operand);
             }
         }
-        Ok(())
-    }
-}
-
-impl InstructionAnalyzer for X86_CS_IA {
-    fn new(bytes: Vec<u8>) -> Result<X86_CS_IA, IAError> {
-        let dis = cs::Capstone::new(cs::cs_arch::CS_ARCH_X86, cs::CS_MODE_32).unwrap();
-        dis.option(cs::cs_opt_type::CS_OPT_DETAIL, cs::cs_opt_value::CS_OPT_ON).unwrap();
-
-        let mut ia = X86_CS_IA { bytes: bytes, cs: dis, info: InstructionInfo::default() };
-
-        ia.analyze()?;
+        info.mnemonic = Cow::from(mnemonic);
 
-        Ok(ia)
+        Ok(X86_IA { info: info })
     }
 
     fn info(&self) -> Result<&InstructionInfo, IAError> {
         Ok(&self.info)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn operand_parsing() {
+        match parse_operand("qword [rax + rbx*8 - 0x10]") {
+            IOperand::Memory { base, index, scale, disp } => {
+                assert_eq!(base, Some("rax".to_owned()));
+                assert_eq!(index, Some("rbx".to_owned()));
+                assert_eq!(scale, 8);
+                assert_eq!(disp, -0x10);
+            }
+            o => panic!("{:?} is not a memory operand", o),
+        }
+        match parse_operand("ecx") {
+            IOperand::Register(ref r) if r == "ecx" => {}
+            o => panic!("{:?} is not ecx", o),
+        }
+        match parse_operand("0x2a") {
+            IOperand::Immediate(42) => {}
+            o => panic!("{:?} is not 42", o),
+        }
+    }
+}
@@ -0,0 +1,504 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Resolves jump tables.
+//!
+//! SSA construction leaves indirect jumps unresolved, the usual lowering of a
+//! `switch` is such a jump through a table of code addresses. This module
+//! matches the selector of indirect jumps against the common table patterns,
+//! reads the tables from the binary and records their targets in the
+//! `RadecoFunction`. Constructing the SSA again then turns each resolved jump
+//! into real control flow, see `PhiPlacer::add_jump_table`.
+//!
+//! The number of entries is taken from the bound check guarding the jump, an
+//! unsigned compare of the table index against a constant in a dominating
+//! conditional block. A table without such a check, or with an entry which is
+//! not the start of an instruction of the function, is left unresolved.
+
+use std::cmp;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use frontend::radeco_containers::RadecoFunction;
+use frontend::radeco_source::Source;
+use middle::ir::MOpcode;
+use middle::ssa::cfg_traits::CFG;
+use middle::ssa::ssa_traits::SSA;
+
+/// Maximum number of entries read for a single table.
+pub const MAX_ENTRIES: usize = 512;
+
+// Number of blocks walked up from the indirect jump to its bound check.
+const MAX_GUARD_DISTANCE: usize = 4;
+// Number of nodes of the guarding condition searched for the bound.
+const MAX_GUARD_NODES: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A table lookup computing the target of an indirect jump.
+pub struct TableRef<V> {
+    /// Value used to index the table.
+    pub index: V,
+    /// Address of the first entry.
+    pub base: u64,
+    /// Size of a single entry in bytes.
+    pub entry_size: u64,
+    /// Set if entries are signed offsets relative to this address, as
+    /// emitted for position independent code.
+    pub relative_to: Option<u64>,
+}
+
+/// Matches the target of an indirect jump, `selector`, against a table
+/// lookup. Recognized are `[base + index * size]` and
+/// `rel + ext([base + index * size])`, where `base` and `rel` are constant.
+pub fn table_ref<T: SSA>(ssa: &T, selector: T::ValueRef) -> Option<TableRef<T::ValueRef>> {
+    if ssa.opcode(selector) == Some(MOpcode::OpAdd) {
+        let operands = ssa.operands_of(selector);
+        if operands.len() != 2 {
+            return None;
+        }
+        for &(load, rel) in &[(operands[0], operands[1]), (operands[1], operands[0])] {
+            if let (Some(tref), Some(rel)) = (table_load(ssa, strip_casts(ssa, load)),
+                                              const_value(ssa, rel)) {
+                return Some(TableRef { relative_to: Some(rel), ..tref });
+            }
+        }
+        None
+    } else {
+        table_load(ssa, strip_casts(ssa, selector))
+    }
+}
+
+// Matches `[base + index * size]`.
+fn table_load<T: SSA>(ssa: &T, load: T::ValueRef) -> Option<TableRef<T::ValueRef>> {
+    if ssa.opcode(load) != Some(MOpcode::OpLoad) {
+        return None;
+    }
+    let entry_size = ssa.node_data(load).ok()?.vt.width().get_width()? as u64 / 8;
+    if ![1, 2, 4, 8].contains(&entry_size) {
+        return None;
+    }
+
+    let addr = *ssa.operands_of(load).get(1)?;
+    if ssa.opcode(addr) != Some(MOpcode::OpAdd) {
+        return None;
+    }
+    let operands = ssa.operands_of(addr);
+    if operands.len() != 2 {
+        return None;
+    }
+    for &(base, scaled) in &[(operands[0], operands[1]), (operands[1], operands[0])] {
+        if let (Some(base), Some(index)) = (const_value(ssa, base),
+                                            scaled_index(ssa, scaled, entry_size)) {
+            return Some(TableRef {
+                index: index,
+                base: base,
+                entry_size: entry_size,
+                relative_to: None,
+            });
+        }
+    }
+    None
+}
+
+// Matches `index * size` or `index << log2(size)` and returns `index`.
+fn scaled_index<T: SSA>(ssa: &T, node: T::ValueRef, size: u64) -> Option<T::ValueRef> {
+    let operands = ssa.operands_of(node);
+    match ssa.opcode(node) {
+        Some(MOpcode::OpMul) if operands.len() == 2 => {
+            if const_value(ssa, operands[1]) == Some(size) {
+                Some(operands[0])
+            } else if const_value(ssa, operands[0]) == Some(size) {
+                Some(operands[1])
+            } else {
+                None
+            }
+        }
+        Some(MOpcode::OpLsl) if operands.len() == 2 => {
+            if const_value(ssa, operands[1]).map(|s| 1u64.checked_shl(s as u32)) == Some(Some(size)) {
+                Some(operands[0])
+            } else {
+                None
+            }
+        }
+        // Byte tables are indexed directly.
+        _ if size == 1 && const_value(ssa, node).is_none() => Some(node),
+        _ => None,
+    }
+}
+
+fn strip_casts<T: SSA>(ssa: &T, node: T::ValueRef) -> T::ValueRef {
+    match ssa.opcode(node) {
+        Some(MOpcode::OpSignExt(_)) |
+        Some(MOpcode::OpZeroExt(_)) |
+        Some(MOpcode::OpNarrow(_)) => {
+            match ssa.operands_of(node).get(0) {
+                Some(&operand) => strip_casts(ssa, operand),
+                None => node,
+            }
+        }
+        _ => node,
+    }
+}
+
+// Constants are not folded during SSA construction, thus addresses computed
+// from the program counter are still a tree of operations.
+fn const_value<T: SSA>(ssa: &T, node: T::ValueRef) -> Option<u64> {
+    if let Some(value) = ssa.constant(node) {
+        return Some(value);
+    }
+    let operands = ssa.operands_of(node);
+    let operand = |i: usize| operands.get(i).and_then(|&op| const_value(ssa, op));
+    match ssa.opcode(node)? {
+        MOpcode::OpAdd => Some(operand(0)?.wrapping_add(operand(1)?)),
+        MOpcode::OpSub => Some(operand(0)?.wrapping_sub(operand(1)?)),
+        MOpcode::OpMul => Some(operand(0)?.wrapping_mul(operand(1)?)),
+        MOpcode::OpLsl => Some(operand(0)?.checked_shl(operand(1)? as u32)?),
+        MOpcode::OpAnd => Some(operand(0)? & operand(1)?),
+        MOpcode::OpZeroExt(_) => operand(0),
+        MOpcode::OpNarrow(width) if width < 64 => Some(operand(0)? & ((1 << width) - 1)),
+        MOpcode::OpNarrow(_) => operand(0),
+        _ => None,
+    }
+}
+
+/// Resolves the jump tables of `rfn` with the data read from `source`.
+/// Returns the number of resolved tables.
+pub fn resolve(rfn: &mut RadecoFunction, source: &Source) -> usize {
+    let offsets = rfn.instructions()
+        .iter()
+        .filter_map(|op| op.offset)
+        .collect::<BTreeSet<_>>();
+
+    let tables = {
+        let ssa = rfn.ssa();
+        let block_starts = ssa.blocks()
+            .into_iter()
+            .filter_map(|block| ssa.starting_address(block))
+            .map(|addr| addr.address)
+            .collect::<BTreeSet<_>>();
+        let mut tables = HashMap::new();
+        for block in ssa.blocks() {
+            // Indirect jumps have a selector, but only a single successor.
+            let selector = match ssa.selector_in(block) {
+                Some(selector) if ssa.unconditional_block(block).is_some() => selector,
+                _ => continue,
+            };
+            let tref = match table_ref(ssa, selector) {
+                Some(tref) => tref,
+                None => continue,
+            };
+            // SSA construction looks the table up by the address of the jump,
+            // the last instruction of the block.
+            let jump_addr = match jump_address(ssa, block, &block_starts, &offsets) {
+                Some(addr) => addr,
+                None => continue,
+            };
+            let entries = match table_size(ssa, block, tref.index) {
+                Some(entries) => entries,
+                None => {
+                    radeco_warn!("No bound check found for jump table @ {:#x}", tref.base);
+                    continue;
+                }
+            };
+            let targets = read_targets(source, &tref, entries, &offsets);
+            if targets.is_empty() {
+                radeco_warn!("No targets found for jump table @ {:#x}", tref.base);
+                continue;
+            }
+            radeco_trace!("jump_tables|{:#x}|{:#x}|{:?}", jump_addr, tref.base, targets);
+            tables.insert(jump_addr, targets);
+        }
+        tables
+    };
+
+    let resolved = tables.len();
+    rfn.jump_tables_mut().extend(tables);
+    resolved
+}
+
+// Address of the last instruction of `block`.
+fn jump_address<T: SSA>(ssa: &T, block: T::ActionRef, block_starts: &BTreeSet<u64>,
+                        offsets: &BTreeSet<u64>) -> Option<u64> {
+    let start = ssa.starting_address(block)?.address;
+    let last = match block_starts.range(start.saturating_add(1)..).next() {
+        Some(&end) => offsets.range(start..end).next_back(),
+        None => offsets.range(start..).next_back(),
+    };
+    last.cloned()
+}
+
+// Number of entries of a table indexed by `index`, from the bound check
+// guarding `block`. The check is searched for in the closest conditional
+// block reached through single predecessors.
+fn table_size<T: SSA>(ssa: &T, block: T::ActionRef, index: T::ValueRef) -> Option<u64> {
+    let index = index_root(ssa, index);
+    let mut current = block;
+    for _ in 0..MAX_GUARD_DISTANCE {
+        let preds = ssa.preds_of(current);
+        if preds.len() != 1 {
+            return None;
+        }
+        current = preds[0];
+        if ssa.conditional_blocks(current).is_some() {
+            let condition = ssa.selector_in(current)?;
+            return guard_bound(ssa, condition, index);
+        }
+    }
+    None
+}
+
+// Searches `condition` for unsigned compares of `index` against a constant
+// `c`. The borrow flag, `index < c`, admits `c` entries (`jae`, `jb`). Also
+// testing the zero flag, `(index - c) & mask`, unlike the sign flag,
+// `(index - c) >> 63`, admits `c + 1` entries (`ja`, `jbe`).
+fn guard_bound<T: SSA>(ssa: &T, condition: T::ValueRef, index: T::ValueRef) -> Option<u64> {
+    let mut bound = None;
+    let mut zero_test = false;
+    let mut seen = HashSet::new();
+    let mut stack = vec![(condition, false)];
+    while let Some((node, shifted)) = stack.pop() {
+        if seen.len() >= MAX_GUARD_NODES || !seen.insert(node) {
+            continue;
+        }
+        let operands = ssa.operands_of(node);
+        let opcode = ssa.opcode(node);
+        let compare = match opcode {
+            Some(MOpcode::OpLt) | Some(MOpcode::OpGt) | Some(MOpcode::OpSub) |
+            Some(MOpcode::OpEq) if operands.len() == 2 => compared_constant(ssa, &operands, index),
+            _ => None,
+        };
+        if let Some(c) = compare {
+            if bound.map_or(false, |b| b != c) {
+                radeco_warn!("Conflicting bounds {:?} and {:#x} of jump table index", bound, c);
+                return None;
+            }
+            bound = Some(c);
+            match opcode {
+                Some(MOpcode::OpSub) if !shifted => zero_test = true,
+                Some(MOpcode::OpEq) => zero_test = true,
+                _ => {}
+            }
+            continue;
+        }
+        let shift = opcode == Some(MOpcode::OpLsr);
+        stack.extend(operands.into_iter().map(|op| (op, shift)));
+    }
+    bound.map(|c| if zero_test { c.saturating_add(1) } else { c })
+}
+
+// The constant `index` is compared against by `operands`, in either order.
+fn compared_constant<T: SSA>(ssa: &T, operands: &[T::ValueRef], index: T::ValueRef) -> Option<u64> {
+    for &(value, bound) in &[(operands[0], operands[1]), (operands[1], operands[0])] {
+        if let Some(c) = const_value(ssa, bound) {
+            if index_root(ssa, value) == index {
+                return Some(c);
+            }
+        }
+    }
+    None
+}
+
+// Strips what the compare and the table lookup may apply differently to the
+// same index: casts, masks and writes of it into a sub-register, which are
+// `value | (old & mask)`.
+fn index_root<T: SSA>(ssa: &T, node: T::ValueRef) -> T::ValueRef {
+    let operands = ssa.operands_of(node);
+    let masked = |op: T::ValueRef| {
+        ssa.opcode(op) == Some(MOpcode::OpAnd) &&
+        ssa.operands_of(op).iter().any(|&o| const_value(ssa, o).is_some())
+    };
+    match ssa.opcode(node) {
+        Some(MOpcode::OpSignExt(_)) |
+        Some(MOpcode::OpZeroExt(_)) |
+        Some(MOpcode::OpNarrow(_)) if operands.len() == 1 => index_root(ssa, operands[0]),
+        Some(MOpcode::OpAnd) if operands.len() == 2 => {
+            if const_value(ssa, operands[1]).is_some() {
+                index_root(ssa, operands[0])
+            } else if const_value(ssa, operands[0]).is_some() {
+                index_root(ssa, operands[1])
+            } else {
+                node
+            }
+        }
+        Some(MOpcode::OpOr) if operands.len() == 2 => {
+            match (masked(operands[0]), masked(operands[1])) {
+                (false, true) => index_root(ssa, operands[0]),
+                (true, false) => index_root(ssa, operands[1]),
+                _ => node,
+            }
+        }
+        _ => node,
+    }
+}
+
+fn read_targets<V>(source: &Source, tref: &TableRef<V>, entries: u64,
+                   starts: &BTreeSet<u64>) -> Vec<u64> {
+    if entries > MAX_ENTRIES as u64 {
+        radeco_warn!("Jump table @ {:#x} has too many entries: {}", tref.base, entries);
+        return Vec::new();
+    }
+    let table_size = entries * tref.entry_size;
+    // Do not read past the section holding the table.
+    let size = match source.section_of(tref.base) {
+        Ok(section) => {
+            let end = section.vaddr.unwrap_or(0) + section.size.unwrap_or(0);
+            cmp::min(table_size, end.saturating_sub(tref.base))
+        }
+        Err(_) => table_size,
+    };
+    let bytes = match source.read_bytes(tref.base, size) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            radeco_warn!(e);
+            return Vec::new();
+        }
+    };
+
+    let targets = bytes.chunks(tref.entry_size as usize)
+        .take_while(|entry| entry.len() as u64 == tref.entry_size)
+        .map(|entry| entry_target(entry, tref))
+        .collect::<Vec<_>>();
+    if targets.len() as u64 != entries {
+        radeco_warn!("Jump table @ {:#x} is truncated", tref.base);
+        return Vec::new();
+    }
+    if targets.iter().any(|t| !starts.contains(t)) {
+        radeco_warn!("Jump table @ {:#x} has targets outside the function", tref.base);
+        return Vec::new();
+    }
+    targets
+}
+
+// Entries are read as little endian.
+fn entry_target<V>(entry: &[u8], tref: &TableRef<V>) -> u64 {
+    let value = entry.iter().rev().fold(0, |acc, &b| (acc << 8) | b as u64);
+    match tref.relative_to {
+        Some(rel) => {
+            let shift = 64 - 8 * entry.len() as u32;
+            let offset = ((value << shift) as i64 >> shift) as u64;
+            rel.wrapping_add(offset)
+        }
+        None => value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use frontend::radeco_source::SourceErr;
+    use r2api::structs::{FunctionInfo, LFlagInfo, LOpInfo, LRegInfo, LSectionInfo};
+    use utils::test_utils::{self, REGISTER_PROFILE};
+
+    // Memory holding a single table.
+    struct TableSource {
+        base: u64,
+        data: Vec<u8>,
+    }
+
+    impl Source for TableSource {
+        fn functions(&self) -> Result<Vec<FunctionInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn instructions_at(&self, _: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn register_profile(&self) -> Result<LRegInfo, SourceErr> {
+            Err(SourceErr::SrcErr("No register profile"))
+        }
+
+        fn flags(&self) -> Result<Vec<LFlagInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn sections(&self) -> Result<Vec<LSectionInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn read_bytes(&self, addr: u64, size: u64) -> Result<Vec<u8>, SourceErr> {
+            let start = addr.wrapping_sub(self.base) as usize;
+            match self.data.get(start..start.saturating_add(size as usize)) {
+                Some(bytes) => Ok(bytes.to_vec()),
+                None => Err(SourceErr::SrcErr("Address is not mapped")),
+            }
+        }
+    }
+
+    fn function(ops: &str) -> RadecoFunction {
+        test_utils::function(REGISTER_PROFILE, ops, false)
+    }
+
+    fn table(entries: &[u64], entry_size: usize) -> Vec<u8> {
+        entries.iter()
+            .flat_map(|&e| (0..entry_size).map(move |i| (e >> (8 * i)) as u8))
+            .collect()
+    }
+
+    #[test]
+    fn absolute_table_test() {
+        // switch (edi) with the cases 0 to 5 at 0x1010 to 0x1015, and the
+        // default at 0x1030. The entry following the table is the address of
+        // an instruction, too.
+        let ops = r#"[{"offset":4096,"size":3,"opcode":"cmp edi, 5","esil":"5,edi,==,$z,zf,=,$b32,cf,=,$p,pf,=,$s,sf,=,$o,of,=","type":"cmp"},
+                      {"offset":4099,"size":2,"opcode":"ja 0x1030","esil":"cf,zf,|,!,?{,4144,rip,=,}","type":"cjmp","jump":4144,"fail":4101},
+                      {"offset":4101,"size":2,"opcode":"mov edi, edi","esil":"edi,edi,=","type":"mov"},
+                      {"offset":4103,"size":7,"opcode":"jmp qword [rdi*8 + 0x2000]","esil":"8192,rdi,8,*,+,[8],rip,=","type":"ujmp"},
+                      {"offset":4112,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4113,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4114,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4115,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4116,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4117,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4144,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let cases = vec![0x1010, 0x1011, 0x1012, 0x1013, 0x1014, 0x1015];
+        let mut entries = cases.clone();
+        entries.push(0x1030);
+        let source = TableSource { base: 0x2000, data: table(&entries, 8) };
+
+        let mut rfn = function(ops);
+        assert_eq!(resolve(&mut rfn, &source), 1);
+        assert_eq!(rfn.jump_tables().get(&0x1007), Some(&cases));
+    }
+
+    #[test]
+    fn relative_table_test() {
+        // The PIC lowering of switch (edi) with the cases 0 to 3 at 0x1020 to
+        // 0x1023, and the default at 0x1040. The table at 0x2000 holds offsets
+        // from its own address, and the jump is not where its target is
+        // computed.
+        let ops = r#"[{"offset":4096,"size":3,"opcode":"cmp edi, 4","esil":"4,edi,==,$z,zf,=,$b32,cf,=,$p,pf,=,$s,sf,=,$o,of,=","type":"cmp"},
+                      {"offset":4099,"size":2,"opcode":"jae 0x1040","esil":"cf,!,?{,4160,rip,=,}","type":"cjmp","jump":4160,"fail":4101},
+                      {"offset":4101,"size":7,"opcode":"lea rdx, [rip + 0xff4]","esil":"4084,rip,+,rdx,=","type":"lea"},
+                      {"offset":4108,"size":4,"opcode":"movsxd rax, dword [rdx + rdi*4]","esil":"rdx,rdi,4,*,+,[4],rax,=","type":"mov"},
+                      {"offset":4112,"size":3,"opcode":"add rax, rdx","esil":"rdx,rax,+=","type":"add"},
+                      {"offset":4115,"size":2,"opcode":"jmp rax","esil":"rax,rip,=","type":"ujmp"},
+                      {"offset":4128,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4129,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4130,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4131,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"},
+                      {"offset":4160,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let cases = vec![0x1020, 0x1021, 0x1022, 0x1023];
+        let mut entries = cases.iter().map(|c| c.wrapping_sub(0x2000)).collect::<Vec<u64>>();
+        entries.push(0x1040u64.wrapping_sub(0x2000));
+        let source = TableSource { base: 0x2000, data: table(&entries, 4) };
+
+        let mut rfn = function(ops);
+        assert_eq!(resolve(&mut rfn, &source), 1);
+        assert_eq!(rfn.jump_tables().get(&0x1013), Some(&cases));
+    }
+
+    #[test]
+    fn unguarded_table_test() {
+        let ops = r#"[{"offset":4096,"size":7,"opcode":"jmp qword [rdi*8 + 0x2000]","esil":"8192,rdi,8,*,+,[8],rip,=","type":"ujmp"},
+                      {"offset":4112,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let source = TableSource { base: 0x2000, data: table(&[0x1010], 8) };
+
+        let mut rfn = function(ops);
+        assert_eq!(resolve(&mut rfn, &source), 0);
+        assert!(rfn.jump_tables().is_empty());
+    }
+}
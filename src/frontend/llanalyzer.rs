@@ -1,6 +1,8 @@
 //! Implements some low-level analysis as a part of frontend
 
-use frontend::radeco_containers::{RadecoModule, CallGraph, CallContextInfo, RadecoFunction};
+use analysis::stack_args;
+use frontend::radeco_containers::{RadecoModule, CallGraph, CallContextInfo, RadecoFunction,
+                                  BindingType};
 use middle::ir::MOpcode;
 use middle::ssa::ssa_traits::{SSAWalk, SSA, NodeType};
 use petgraph::Direction;
@@ -41,6 +43,26 @@ pub fn load_call_graph(finfos: &[FunctionInfo], rmod: &RadecoModule) -> CallGrap
         }
     }
 
+    // Tail calls are jumps for the source, add the ones lifted as calls.
+    for (offset, fnode) in &node_map {
+        if let Some(rfn) = rmod.functions.get(offset) {
+            for op in rfn.instructions() {
+                let is_jmp = op.optype.as_ref().map_or(false, |ty| ty == "jmp");
+                let target = match (op.offset, op.jump) {
+                    (Some(csite), Some(target)) if is_jmp && rfn.tail_calls().contains(&target) => {
+                        node_map.get(&target).map(|tn| (csite, tn))
+                    }
+                    _ => None,
+                };
+                if let Some((csite, tn)) = target {
+                    let mut cctx = CallContextInfo::default();
+                    cctx.csite = csite;
+                    cg.add_edge(*fnode, *tn, cctx);
+                }
+            }
+        }
+    }
+
     // Initial callsite analysis to fix up CallContextInfo on the edges.
     for (offset, node) in &node_map {
         if let Some(rfn) = rmod.functions.get(offset) {
@@ -90,10 +112,22 @@ fn analyze_callsite_initial(rfn: &RadecoFunction) -> HashMap<u64, CallContextInf
     cctxs
 }
 
+// Returns the stack arguments of `rfn` with their offset.
+fn stack_bindings(rfn: &RadecoFunction) -> Vec<(u64, NodeIndex)> {
+    rfn.args()
+        .into_iter()
+        .filter_map(|vb| match vb.btype {
+            BindingType::StackArgument(offset) => Some((offset as u64, vb.idx)),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn init_call_ctx(rmod: &mut RadecoModule) {
     for wrapper in rmod.functions.iter() {
         let rfn = wrapper.1;
         let mut csites: HashMap<u64, CallContextInfo> = analyze_callsite_initial(rfn);
+        let offsets = stack_args::stack_offsets(rfn.ssa());
         // Iterate through callsites
         let mut cgwalker =
             rmod.callgraph.neighbors_directed(rfn.cgid(), Direction::Outgoing).detach();
@@ -105,34 +139,26 @@ pub fn init_call_ctx(rmod: &mut RadecoModule) {
             let callee_info = if let Some(calleefn) = rmod.functions.get(&callee_off) {
                 let mut args = calleefn.bindings()
                     .into_iter()
+                    .filter(|x| x.ridx.is_some())
                     .filter(|x| x.btype.is_argument() || x.btype.is_return())
                     .cloned()
                     .collect::<Vec<_>>();
-                args.sort_by(|x, y| {
-                    match (x.ridx, y.ridx) {
-                        (Some(xidx), Some(ref yidx)) => xidx.cmp(yidx),
-                        (_, _) => unreachable!(),
-                    }
-                });
-                Some((calleefn.cgid(), args))
+                args.sort_by_key(|x| x.ridx);
+                Some((calleefn.cgid(), args, stack_bindings(&calleefn)))
             } else if let Some(calleefn) = rmod.imports.get(&callee_off).map(|ifn| ifn.rfn.borrow()) {
                 let mut args = calleefn.bindings()
                     .into_iter()
+                    .filter(|x| x.ridx.is_some())
                     .filter(|x| x.btype.is_argument() || x.btype.is_return())
                     .cloned()
                     .collect::<Vec<_>>();
-                args.sort_by(|x, y| {
-                    match (x.ridx, y.ridx) {
-                        (Some(xidx), Some(ref yidx)) => xidx.cmp(yidx),
-                        (_, _) => unreachable!(),
-                    }
-                });
-                Some((calleefn.cgid(), args))
+                args.sort_by_key(|x| x.ridx);
+                Some((calleefn.cgid(), args, stack_bindings(&calleefn)))
             } else {
                 None
             };
 
-            if let Some((callee_cgid, args)) = callee_info {
+            if let Some((callee_cgid, args, stack)) = callee_info {
                 // Access the actual callsite in rfn.
                 if let Some(mut cctx) = csites.remove(&csite) {
                     cctx.map = cctx.map
@@ -140,6 +166,14 @@ pub fn init_call_ctx(rmod: &mut RadecoModule) {
                         .map(|&(k, _)| k)
                         .zip(args.into_iter().map(|v| v.idx))
                         .collect();
+                    // Stack arguments are mapped from the values stored to the
+                    // stack before the call.
+                    let arg_offsets = stack.iter().map(|&(offset, _)| offset).collect::<Vec<_>>();
+                    let values =
+                        stack_args::call_args(rfn.ssa(), &offsets, cctx.csite_node, &arg_offsets);
+                    cctx.map.extend(values.into_iter()
+                        .zip(stack)
+                        .filter_map(|(value, (_, idx))| value.map(|value| (value, idx))));
                     // Update callsite information in the callgraph.
                     rmod.callgraph.update_edge(rfn.cgid(), callee_cgid, cctx);
                 }
@@ -13,8 +13,13 @@ pub mod containers;
 // New replacements
 pub mod radeco_source;
 pub mod radeco_containers;
+pub mod elf;
+pub mod text_source;
 
 pub mod bindings;
-// pub mod instruction_analyzer;
+pub mod instruction_analyzer;
 pub mod llanalyzer;
+pub mod jump_tables;
+pub mod tail_calls;
+pub mod function_discovery;
 pub mod imports;
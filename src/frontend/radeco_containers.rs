@@ -31,22 +31,32 @@
 //! For more examples of loading, check the `examples/` directory of this project.
 
 
+use backend::arm::arm_idioms;
+use frontend::jump_tables;
+use frontend::tail_calls;
 use frontend::llanalyzer;
 use frontend::radeco_source::Source;
 use frontend::ssaconstructor::SSAConstruct;
 use frontend::imports::ImportInfo;
 
+use middle::c_header;
+use middle::prototypes::{self, Prototype};
 use middle::regfile::{SubRegisterFile, RegisterUsage};
 use middle::ssa::cfg_traits::CFG;
 use middle::ssa::ssa_traits::{SSA, NodeType};
 
 use middle::ssa::ssastorage::SSAStorage;
+use middle::ssa::memoryssa::{MemorySSA, MemorySSAInfo};
+use analysis::vsa::abstract_set::strided_interval::StridedInterval;
+use analysis::vsa::vsa::ValueSetAnalyzer;
+use analysis::noreturn;
+use analysis::stack_args;
 use petgraph::Direction;
 
 use petgraph::graph::{NodeIndex, Graph};
 use petgraph::visit::EdgeRef;
 use r2api::api_trait::R2Api;
-use r2api::structs::{LVarInfo, LOpInfo, LSymbolInfo, LRelocInfo, LExportInfo,
+use r2api::structs::{FunctionInfo, LVarInfo, LOpInfo, LSymbolInfo, LRelocInfo, LExportInfo,
                      LStringInfo, LSectionInfo, LEntryInfo, LSymbolType, LCCInfo};
 
 use r2pipe::r2::R2;
@@ -65,9 +75,14 @@ use std::sync::Arc;
 
 /// Defines sane defaults for the loading process.
 pub mod loader_defaults {
+    use frontend::function_discovery;
     use frontend::radeco_source::Source;
+    use middle::ir::MOpcode;
+    use middle::ssa::ssa_traits::{SSA, SSAWalk};
+    use middle::ssa::utils;
     use r2api::structs::LSymbolType;
     use std::borrow::Cow;
+    use std::collections::HashSet;
     use std::rc::Rc;
     use super::FLResult;
     use super::{RadecoModule, RadecoFunction};
@@ -120,6 +135,110 @@ pub mod loader_defaults {
             fl.clone()
         }
     }
+
+    /// Discover functions by recursive descent from the entrypoints, exports,
+    /// functions identified so far and the call targets in already lifted
+    /// SSA. Records the tail calls of identified functions.
+    ///
+    /// The SSA is lifted after the function loader first runs, the loader is
+    /// then run again by `ModuleLoader::load` to follow the call targets.
+    pub fn strat_recursive_descent(source: Option<&Rc<Source>>,
+                                   fl: &FLResult,
+                                   rmod: &RadecoModule)
+                                   -> FLResult {
+        let src = match source {
+            Some(src) => src,
+            None => return FLResult::default(),
+        };
+
+        let mut seeds = rmod.entrypoint
+            .iter()
+            .filter_map(|e| e.vaddr)
+            .chain(rmod.exports.iter().filter_map(|e| e.vaddr))
+            .chain(fl.functions.keys().cloned())
+            .chain(rmod.functions.keys().cloned())
+            .collect::<Vec<_>>();
+        for rfn in rmod.functions.values() {
+            seeds.extend(call_targets(rfn));
+        }
+        let external = rmod.imports.keys().cloned().collect::<HashSet<_>>();
+
+        let discovered = match function_discovery::discover(&**src, &seeds, &external) {
+            Ok(discovered) => discovered,
+            Err(e) => {
+                radeco_warn!(e);
+                return FLResult::default();
+            }
+        };
+
+        let names = rmod.exports
+            .iter()
+            .filter_map(|e| Some((e.vaddr?, e.name.clone()?)))
+            .collect::<Vec<_>>();
+        let mut new_fl = FLResult::default();
+        for (offset, df) in discovered {
+            // Functions already lifted are not found again.
+            if df.instructions.is_empty() ||
+               (!fl.functions.contains_key(&offset) && rmod.functions.contains_key(&offset)) {
+                continue;
+            }
+            let rfn = match fl.functions.get(&offset) {
+                // Keep what is known, and let the instructions be loaded as usual
+                Some(known) => {
+                    let mut rfn = known.clone();
+                    rfn.tail_calls = df.tail_calls;
+                    rfn
+                }
+                None => {
+                    let mut rfn = RadecoFunction::default();
+                    rfn.name = match names.iter().find(|&&(addr, _)| addr == offset) {
+                        Some(&(_, ref name)) => Cow::from(name.clone()),
+                        None => Cow::from(format!("fcn.{:08x}", offset)),
+                    };
+                    rfn.offset = offset;
+                    rfn.size = df.size;
+                    rfn.instructions = df.instructions;
+                    rfn.tail_calls = df.tail_calls;
+                    new_fl.new += 1;
+                    rfn
+                }
+            };
+            new_fl.functions.insert(offset, rfn);
+        }
+        new_fl
+    }
+
+    // Returns the constant targets of the calls in the lifted SSA of `rfn`.
+    // The target of an indirect call is the value of the register it jumps
+    // through, if that register is an operand of the call.
+    fn call_targets(rfn: &RadecoFunction) -> Vec<u64> {
+        let ssa = rfn.ssa();
+        let mut targets = Vec::new();
+        for node in ssa.inorder_walk() {
+            if ssa.opcode(node) != Some(MOpcode::OpCall) {
+                continue;
+            }
+            let info = match utils::call_info(node, ssa) {
+                Some(info) => info,
+                None => continue,
+            };
+            let target = ssa.constant_value(info.target).or_else(|| {
+                let address = ssa.address(node)?.address;
+                let op = rfn.instructions().iter().find(|op| op.offset == Some(address))?;
+                // The ESIL of an indirect call ends with `<reg>,<pc>,=`.
+                let tokens = op.esil.as_ref()?.split(',').collect::<Vec<_>>();
+                let n = tokens.len();
+                if n < 3 || tokens[n - 1] != "=" ||
+                   ssa.regfile.alias_info.get("PC").map(|pc| pc.as_str()) != Some(tokens[n - 2]) {
+                    return None;
+                }
+                let reg = ssa.regfile.register_id_by_name(tokens[n - 3])?;
+                ssa.constant_value(*info.register_args.get(reg)?)
+            });
+            targets.extend(target);
+        }
+        targets
+    }
 }
 
 /// Top level container used to hold all analysis
@@ -195,6 +314,7 @@ pub enum FunctionType {
 pub enum BindingType {
     // Arguments - ith argument
     RegisterArgument(usize),
+    // Stack offset (from "SP" at the entry)
     StackArgument(usize),
     // Local variables - base register, offset
     RegisterLocal(String, i64),
@@ -337,6 +457,23 @@ pub struct RadecoFunction {
     /// Name of the calling convention of this function (e.g. amd64, ms, arm64, etc.)
     // see https://github.com/radare/radare2/tree/9e08da0fa6b6c36edf04db72d22e065ccc90d381/libr/anal/d
     pub callconv_name: String,
+    /// MemorySSA of the function, None if it is not built or out of date
+    mssa: Option<MemorySSAInfo<NodeIndex, NodeIndex>>,
+    /// Targets of the resolved jump tables, keyed by the address of the
+    /// indirect jump. The n-th target is taken for index n.
+    jump_tables: HashMap<u64, Vec<u64>>,
+    /// Start addresses of the functions this function tail calls, by jumping
+    /// to them with a balanced stack
+    tail_calls: Vec<u64>,
+    /// Function never returns to its caller
+    noreturn: bool,
+    /// Addresses of the calls to functions which never return
+    noreturn_calls: HashSet<u64>,
+    /// Prototype of this function, if known
+    prototype: Option<Prototype>,
+    /// Offsets of the stack arguments of the callees, keyed by the address of
+    /// the call
+    callee_stack_args: HashMap<u64, Vec<u64>>,
 }
 
 #[derive(Default)]
@@ -348,6 +485,7 @@ pub struct ProjectLoader<'a> {
     filter_modules: Option<fn(&RadecoModule) -> bool>,
     source: Option<Rc<Source>>,
     mloader: Option<ModuleLoader<'a>>,
+    header: Option<Cow<'static, str>>,
 }
 
 impl<'a> ProjectLoader<'a> {
@@ -360,6 +498,7 @@ impl<'a> ProjectLoader<'a> {
             filter_modules: None,
             source: None,
             mloader: None,
+            header: None,
         }
     }
     // TODO:
@@ -407,6 +546,14 @@ impl<'a> ProjectLoader<'a> {
         self
     }
 
+    /// Read function prototypes from the C header at `path`. They override the
+    /// argument and return bindings of the imports and functions of the same
+    /// name, see `ModuleLoader::prototypes`.
+    pub fn header<T: AsRef<str>>(mut self, path: T) -> ProjectLoader<'a> {
+        self.header = Some(Cow::from(path.as_ref().to_owned()));
+        self
+    }
+
     /// Kick everything off based on the config/defaults
     pub fn load(mut self) -> RadecoProject {
         if self.source.is_none() {
@@ -426,7 +573,11 @@ impl<'a> ProjectLoader<'a> {
         if self.mloader.is_none() {
             self.mloader = Some(ModuleLoader::default().source(Rc::clone(source))
                 .build_ssa()
+                .resolve_jump_tables()
+                .detect_tail_calls()
                 .build_callgraph()
+                .detect_noreturn()
+                .detect_stack_args()
                 .load_datarefs()
                 .load_locals()
                 .parallel()
@@ -438,6 +589,12 @@ impl<'a> ProjectLoader<'a> {
 
         {
             let mod_loader = self.mloader.as_mut().unwrap();
+            if let Some(ref path) = self.header {
+                match c_header::parse_header_file(&**path) {
+                    Ok(header) => mod_loader.add_prototypes(header.prototypes),
+                    Err(e) => radeco_err!("Error parsing {}: {}", path, e),
+                }
+            }
             // TODO: Set name correctly
             mod_map.push(mod_loader.load(Rc::clone(source)));
         }
@@ -560,6 +717,11 @@ pub struct ModuleLoader<'a> {
     parallel: bool,
     assume_cc: bool,
     stub_imports: bool,
+    resolve_jump_tables: bool,
+    detect_tail_calls: bool,
+    detect_noreturn: bool,
+    detect_stack_args: bool,
+    prototypes: HashMap<String, Prototype>,
 }
 
 impl<'a> ModuleLoader<'a> {
@@ -623,6 +785,64 @@ impl<'a> ModuleLoader<'a> {
         self
     }
 
+    /// Resolves jump tables and rebuilds the SSA of functions that have any.
+    /// Needs `build_ssa` and support for `read_bytes` from the `Source`.
+    pub fn resolve_jump_tables(mut self) -> ModuleLoader<'a> {
+        self.resolve_jump_tables = true;
+        self
+    }
+
+    /// Lifts jumps to the start of other functions as tail calls, if the
+    /// stack is balanced at the jump. Needs `build_ssa`.
+    pub fn detect_tail_calls(mut self) -> ModuleLoader<'a> {
+        self.detect_tail_calls = true;
+        self
+    }
+
+    /// Detects functions which never return and rebuilds the SSA of their
+    /// callers, ending blocks at the calls. Needs `build_ssa` and
+    /// `build_callgraph`.
+    pub fn detect_noreturn(mut self) -> ModuleLoader<'a> {
+        self.detect_noreturn = true;
+        self
+    }
+
+    /// Binds the arguments functions read from the stack, and the ones they
+    /// pop on return. Needs `build_callgraph` and `assume_cc`.
+    pub fn detect_stack_args(mut self) -> ModuleLoader<'a> {
+        self.detect_stack_args = true;
+        self
+    }
+
+    /// Overrides the prototypes of the imports and functions with `prototypes`,
+    /// matched by symbol name. The argument and return bindings then follow
+    /// them, see `assume_cc`.
+    pub fn prototypes(mut self, prototypes: Vec<Prototype>) -> ModuleLoader<'a> {
+        self.add_prototypes(prototypes);
+        self
+    }
+
+    fn add_prototypes(&mut self, prototypes: Vec<Prototype>) {
+        self.prototypes.extend(prototypes.into_iter().map(|p| (p.name.clone(), p)));
+    }
+
+    fn load_call_graph(rmod: &mut RadecoModule, aux_info: &[FunctionInfo]) {
+        rmod.callgraph = llanalyzer::load_call_graph(aux_info, &*rmod);
+        // Iterate through nodes and associate nodes with the correct functions
+        for nidx in rmod.callgraph.node_indices() {
+            if let Some(cg_addr) = rmod.callgraph.node_weight(nidx) {
+                if let Some(rfn) = rmod.functions.get_mut(cg_addr) {
+                    // Functions defined in this binary
+                    rfn.cgid = nidx;
+                }
+                if let Some(ifn) = rmod.imports.get_mut(cg_addr) {
+                    // Handle imports
+                    ifn.rfn.borrow_mut().cgid = nidx;
+                }
+            }
+        }
+    }
+
     fn init_fn_bindings(rfn: &mut RadecoFunction, sub_reg_f: &SubRegisterFile) {
         // Setup binding information for functions based on reg_p. Note that this essential
         // marks the "potential" arguments without worrying about if they're ever used. Future
@@ -639,11 +859,12 @@ impl<'a> ModuleLoader<'a> {
             (ssa.operands_of(entry_state), ssa.operands_of(exit_state))
         };
 
+        let ret_alias = sub_reg_f.return_alias();
         let mut tbindings: Vec<VarBinding> = sub_reg_f.alias_info
             .iter()
             .filter_map(|reg| {
                 let alias = reg.0;
-                if let &Some(idx) = &["A0", "A1", "A2", "A3", "A4", "A5", "SN"]
+                if let &Some(idx) = &["A0", "A1", "A2", "A3", "A4", "A5", ret_alias]
                     .iter()
                     .position(|f| f == alias) {
                     let mut vb = VarBinding::default();
@@ -692,6 +913,7 @@ impl<'a> ModuleLoader<'a> {
         });
 
         rfn.bindings = tbindings;
+        rfn.bind_prototype();
     }
 
     /// Kick everything off and load module information based on config and defaults
@@ -775,10 +997,35 @@ impl<'a> ModuleLoader<'a> {
 
         rmod.functions = flresult.functions;
 
-        // Load instructions into functions
+        // Load instructions into functions, unless discovered with them
         for rfn in rmod.functions.values_mut() {
-            rfn.instructions = source.disassemble_function(&rfn.name)
-                .unwrap_or(Vec::new());
+            if rfn.instructions.is_empty() {
+                rfn.instructions = source.disassemble_function(&rfn.name)
+                    .unwrap_or(Vec::new());
+            }
+        }
+
+        for rfn in rmod.functions.values_mut() {
+            let proto = self.prototypes.get(prototypes::symbol_name(&rfn.name)).cloned();
+            if let Some(proto) = proto {
+                rfn.set_prototype(proto);
+            }
+        }
+        for ifn in rmod.imports.values() {
+            if let Some(proto) = self.prototypes.get(prototypes::symbol_name(&ifn.name)) {
+                ifn.rfn.borrow_mut().set_prototype(proto.clone());
+            }
+        }
+
+        if self.detect_tail_calls {
+            let starts = rmod.functions
+                .keys()
+                .chain(rmod.imports.keys())
+                .cloned()
+                .collect::<HashSet<_>>();
+            for rfn in rmod.functions.values_mut() {
+                tail_calls::record(rfn, &starts);
+            }
         }
 
         // Load calling conventions for all functions and imports
@@ -807,6 +1054,74 @@ impl<'a> ModuleLoader<'a> {
                     SSAConstruct::<SSAStorage>::construct(rfn, &reg_p, self.assume_cc, true);
                 }
             }
+
+            // Reading the tables goes through `source`, which cannot be shared
+            // between threads.
+            if self.resolve_jump_tables {
+                for rfn in rmod.functions.values_mut() {
+                    if jump_tables::resolve(rfn, &**source) > 0 {
+                        *rfn.ssa_mut() = SSAStorage::new();
+                        SSAConstruct::<SSAStorage>::construct(rfn, &reg_p, self.assume_cc, true);
+                    }
+                }
+            }
+
+            // Tail calls, recorded above or by the function loader, are only
+            // kept if the stack is balanced at the jump.
+            for rfn in rmod.functions.values_mut() {
+                if rfn.tail_calls().is_empty() {
+                    continue;
+                }
+                if tail_calls::drop_unbalanced(rfn) > 0 {
+                    *rfn.ssa_mut() = SSAStorage::new();
+                    SSAConstruct::<SSAStorage>::construct(rfn, &reg_p, self.assume_cc, true);
+                }
+            }
+
+            // The call targets in the lifted SSA may be functions the loader
+            // missed, see `strat_recursive_descent`. The loader runs again,
+            // lifting the functions it finds, until no new one is found.
+            let filter = self.filter;
+            loop {
+                let mut found = floader.load(&rmod)
+                    .functions
+                    .into_iter()
+                    .filter(|&(offset, ref rfn)| {
+                        !rmod.functions.contains_key(&offset) && filter.map_or(true, |f| f(rfn))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                if found.is_empty() {
+                    break;
+                }
+                let starts = rmod.functions
+                    .keys()
+                    .chain(found.keys())
+                    .chain(rmod.imports.keys())
+                    .cloned()
+                    .collect::<HashSet<_>>();
+                for (&offset, rfn) in &mut found {
+                    radeco_trace!("module_loader|found {} at {:#x} in the lifted SSA",
+                                  rfn.name, offset);
+                    if rfn.instructions.is_empty() {
+                        rfn.instructions = source.disassemble_function(&rfn.name)
+                            .unwrap_or(Vec::new());
+                    }
+                    let proto = self.prototypes.get(prototypes::symbol_name(&rfn.name)).cloned();
+                    if let Some(proto) = proto {
+                        rfn.set_prototype(proto);
+                    }
+                    if self.detect_tail_calls {
+                        tail_calls::record(rfn, &starts);
+                    }
+                    rfn.callconv = source.cc_info_of(offset).ok();
+                    SSAConstruct::<SSAStorage>::construct(rfn, &reg_p, self.assume_cc, true);
+                    if !rfn.tail_calls().is_empty() && tail_calls::drop_unbalanced(rfn) > 0 {
+                        *rfn.ssa_mut() = SSAStorage::new();
+                        SSAConstruct::<SSAStorage>::construct(rfn, &reg_p, self.assume_cc, true);
+                    }
+                }
+                rmod.functions.extend(found);
+            }
         }
 
         if self.stub_imports {
@@ -826,20 +1141,24 @@ impl<'a> ModuleLoader<'a> {
             };
 
             if self.build_callgraph {
-                rmod.callgraph = llanalyzer::load_call_graph(aux_info.as_slice(), &rmod);
-                // Iterate through nodes and associate nodes with the correct functions
-                for nidx in rmod.callgraph.node_indices() {
-                    if let Some(cg_addr) = rmod.callgraph.node_weight(nidx) {
-                        if let Some(rfn) = rmod.functions.get_mut(cg_addr) {
-                            // Functions defined in this binary
-                            rfn.cgid = nidx;
-                        }
-                        if let Some(ifn) = rmod.imports.get_mut(cg_addr) {
-                            // Handle imports
-                            ifn.rfn.borrow_mut().cgid = nidx;
-                        }
+                ModuleLoader::load_call_graph(&mut rmod, aux_info.as_slice());
+            }
+
+            // The call graph holds nodes of the SSA, so it is loaded again
+            // after rebuilding any.
+            if self.build_callgraph && self.build_ssa && self.detect_noreturn {
+                noreturn::analyze(&mut rmod);
+                let mut rebuilt = false;
+                for rfn in rmod.functions.values_mut() {
+                    if !rfn.noreturn_calls().is_empty() {
+                        *rfn.ssa_mut() = SSAStorage::new();
+                        SSAConstruct::<SSAStorage>::construct(rfn, &reg_p, self.assume_cc, true);
+                        rebuilt = true;
                     }
                 }
+                if rebuilt {
+                    ModuleLoader::load_call_graph(&mut rmod, aux_info.as_slice());
+                }
             }
 
             if self.load_datarefs {
@@ -876,6 +1195,14 @@ impl<'a> ModuleLoader<'a> {
             }
         }
 
+        // Conditions tested on the NZCV flags of ARM are turned back into
+        // compares of their operands.
+        if self.build_ssa && sub_reg_f.alias_info.contains_key("LR") {
+            for rfn in rmod.functions.values_mut() {
+                arm_idioms::replace(rfn.ssa_mut());
+            }
+        }
+
         if self.build_callgraph && self.assume_cc {
             for rfn in rmod.functions.values_mut() {
                 ModuleLoader::init_fn_bindings(rfn, &sub_reg_f);
@@ -885,6 +1212,14 @@ impl<'a> ModuleLoader<'a> {
                 ModuleLoader::init_fn_bindings(&mut ifn.rfn.borrow_mut(), &sub_reg_f);
             }
 
+            // Stack arguments are found once the register arguments are bound.
+            if self.detect_stack_args {
+                for rfn in rmod.functions.values_mut() {
+                    stack_args::analyze(rfn);
+                }
+                stack_args::record_calls(&mut rmod);
+            }
+
             llanalyzer::init_call_ctx(&mut rmod);
         }
 
@@ -979,6 +1314,7 @@ impl<'a> FunctionLoader<'a> {
         // TODO: Append these to the front
         self.strategies.push(&loader_defaults::strat_use_symbols);
         self.strategies.push(&loader_defaults::strat_use_source);
+        self.strategies.push(&loader_defaults::strat_recursive_descent);
         self
     }
 }
@@ -1085,7 +1421,10 @@ impl RadecoFunction {
         &self.ssa
     }
 
+    /// Also drops the attached MemorySSA, which may not hold once the SSA
+    /// is modified.
     pub fn ssa_mut(&mut self) -> &mut SSAStorage {
+        self.mssa = None;
         &mut self.ssa
     }
 
@@ -1117,6 +1456,115 @@ impl RadecoFunction {
         &self.datarefs
     }
 
+    /// Returns the resolved jump tables of this function.
+    pub fn jump_tables(&self) -> &HashMap<u64, Vec<u64>> {
+        &self.jump_tables
+    }
+
+    pub fn jump_tables_mut(&mut self) -> &mut HashMap<u64, Vec<u64>> {
+        &mut self.jump_tables
+    }
+
+    /// Returns the start addresses of the functions this function tail calls.
+    pub fn tail_calls(&self) -> &Vec<u64> {
+        &self.tail_calls
+    }
+
+    pub fn tail_calls_mut(&mut self) -> &mut Vec<u64> {
+        &mut self.tail_calls
+    }
+
+    pub fn is_noreturn(&self) -> bool {
+        self.noreturn
+    }
+
+    pub fn mark_noreturn(&mut self) {
+        self.noreturn = true;
+    }
+
+    /// Returns the addresses of the calls to functions which never return.
+    pub fn noreturn_calls(&self) -> &HashSet<u64> {
+        &self.noreturn_calls
+    }
+
+    pub fn noreturn_calls_mut(&mut self) -> &mut HashSet<u64> {
+        &mut self.noreturn_calls
+    }
+
+    /// Returns the offsets of the stack arguments of the functions this
+    /// function calls, see `analysis::stack_args`.
+    pub fn callee_stack_args(&self) -> &HashMap<u64, Vec<u64>> {
+        &self.callee_stack_args
+    }
+
+    pub fn callee_stack_args_mut(&mut self) -> &mut HashMap<u64, Vec<u64>> {
+        &mut self.callee_stack_args
+    }
+
+    pub fn prototype(&self) -> Option<&Prototype> {
+        self.prototype.as_ref()
+    }
+
+    /// Sets the prototype of this function, its bindings then follow it.
+    pub fn set_prototype(&mut self, prototype: Prototype) {
+        self.prototype = Some(prototype);
+        self.bind_prototype();
+    }
+
+    // Keeps the argument and return bindings which are part of the prototype,
+    // and types them.
+    fn bind_prototype(&mut self) {
+        let proto = match self.prototype {
+            Some(ref proto) => proto,
+            None => return,
+        };
+        self.bindings.retain(|vb| match vb.btype {
+            BindingType::RegisterArgument(i) => !proto.fixed_args() || i < proto.args.len(),
+            BindingType::Return => !proto.ret.is_void(),
+            _ => true,
+        });
+        for vb in &mut self.bindings {
+            match vb.btype {
+                BindingType::RegisterArgument(i) => {
+                    if let Some(ty) = proto.args.get(i) {
+                        vb.type_str = ty.to_string();
+                    }
+                }
+                BindingType::Return => vb.type_str = proto.ret.to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the MemorySSA attached to this function.
+    pub fn memory_ssa(&self) -> Option<&MemorySSAInfo<NodeIndex, NodeIndex>> {
+        self.mssa.as_ref()
+    }
+
+    pub fn set_memory_ssa(&mut self, mssa: MemorySSAInfo<NodeIndex, NodeIndex>) {
+        self.mssa = Some(mssa);
+    }
+
+    /// Drops the attached MemorySSA, should be called by passes which
+    /// add/remove OpLoads/OpStores without updating it.
+    pub fn clear_memory_ssa(&mut self) {
+        self.mssa = None;
+    }
+
+    /// Builds MemorySSA with may_alias sets partitioned by value set analysis,
+    /// and attaches it to this function.
+    pub fn build_memory_ssa(&mut self) {
+        let mssa = {
+            let mut vsa = ValueSetAnalyzer::<StridedInterval>::new(self);
+            vsa.analyze();
+            let mut mssa = MemorySSA::new(&self.ssa);
+            mssa.gather_a_locs(&vsa);
+            mssa.run();
+            mssa.into_info()
+        };
+        self.mssa = Some(mssa);
+    }
+
     pub fn locals(&self) -> VarBindings {
         self.bindings.iter()
             .filter(|vb| vb.btype.is_local())
@@ -1124,12 +1572,25 @@ impl RadecoFunction {
             .collect::<Vec<_>>()
     }
 
+    /// Returns the argument bindings, the register arguments in order followed
+    /// by the stack arguments by offset.
     pub fn args(&self) -> VarBindings {
-        unimplemented!()
+        let mut args = self.bindings.iter()
+            .filter(|vb| vb.btype.is_argument())
+            .cloned()
+            .collect::<Vec<_>>();
+        args.sort_by_key(|vb| match vb.btype {
+            BindingType::RegisterArgument(i) => (0, i),
+            BindingType::StackArgument(offset) => (1, offset),
+            _ => unreachable!(),
+        });
+        args
     }
 
-    pub fn set_args(&mut self, _args: &Vec<usize>) {
-        unimplemented!()
+    /// Replaces the argument bindings with `args`.
+    pub fn set_args(&mut self, args: VarBindings) {
+        self.bindings.retain(|vb| !vb.btype.is_argument());
+        self.bindings.extend(args);
     }
 
     pub fn set_modifides(&mut self, _locals: &Vec<usize>) {
@@ -1178,8 +1639,23 @@ impl RadecoFunction {
         }
     }
 
+    // Binds the addresses the stack arguments are loaded from, so that they
+    // are found by `local_at`.
     pub fn mark_args(&mut self) {
-        // TODO
+        let loads = stack_args::stack_args(&self.ssa);
+        for vb in self.args() {
+            let offset = match vb.btype {
+                BindingType::StackArgument(offset) => offset as u64,
+                _ => continue,
+            };
+            for &load in loads.get(&offset).into_iter().flat_map(|loads| loads) {
+                let addr = self.ssa.operands_of(load)[1];
+                // Values of the stack pointer are not the argument.
+                if self.ssa.is_expr(addr) && self.ssa.registers(addr).is_empty() {
+                    self.binding_map.insert(addr, vec![vb.clone()]);
+                }
+            }
+        }
     }
 
     pub fn mark_locals(&mut self) {
@@ -1223,6 +1699,10 @@ pub struct CallContextInfo {
 #[cfg(test)]
 mod test {
     use super::*;
+    use frontend::radeco_source::SourceErr;
+    use r2api::structs::{LFlagInfo, LRegInfo};
+    use serde_json;
+    use utils::test_utils::{function, REGISTER_PROFILE};
 
     #[test]
     fn test_fn_loader() {
@@ -1231,4 +1711,66 @@ mod test {
         // let mut fl = FunctionLoader::default();
         // fl.strategy(&ld);
     }
+
+    // Disassembles a fixed listing.
+    struct Listing(Vec<LOpInfo>);
+
+    impl Source for Listing {
+        fn functions(&self) -> Result<Vec<FunctionInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn instructions_at(&self, _: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+            Err(SourceErr::SrcErr("No Instructions found"))
+        }
+
+        fn register_profile(&self) -> Result<LRegInfo, SourceErr> {
+            Err(SourceErr::SrcErr("No register profile"))
+        }
+
+        fn flags(&self) -> Result<Vec<LFlagInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn sections(&self) -> Result<Vec<LSectionInfo>, SourceErr> {
+            Ok(Vec::new())
+        }
+
+        fn disassemble_n_insts(&self, n: u64, at: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+            Ok(self.0
+                .iter()
+                .skip_while(|op| op.offset != Some(at))
+                .take(n as usize)
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[test]
+    fn recursive_descent_ssa_test() {
+        // 0x10 calls 0x20 through rax, which only the lifted SSA tells.
+        let ops = r#"[{"offset":16,"size":7,"opcode":"mov rax, 0x20","esil":"32,rax,=","type":"mov"},
+                      {"offset":23,"size":2,"opcode":"call rax","esil":"rip,8,rsp,-=,rsp,=[],rax,rip,=","type":"ucall"},
+                      {"offset":25,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let ret = r#"[{"offset":32,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut rfn = function(REGISTER_PROFILE, ops, false);
+        rfn.offset = 16;
+        let mut listing = rfn.instructions().to_vec();
+        listing.extend(serde_json::from_str::<Vec<LOpInfo>>(ret).unwrap());
+        let source: Rc<Source> = Rc::new(Listing(listing));
+        let mut rmod = RadecoModule::default();
+
+        // Before lifting, the instructions alone do not tell the target.
+        let mut known = FLResult::default();
+        known.functions.insert(16, RadecoFunction::default());
+        let fl = loader_defaults::strat_recursive_descent(Some(&source), &known, &rmod);
+        assert_eq!(fl.functions.keys().cloned().collect::<Vec<_>>(), vec![16]);
+        assert_eq!(fl.new, 0);
+
+        rmod.functions.insert(16, rfn);
+        let fl = loader_defaults::strat_recursive_descent(Some(&source), &FLResult::default(), &rmod);
+        assert_eq!(fl.functions.keys().cloned().collect::<Vec<_>>(), vec![32]);
+        assert_eq!(fl.new, 1);
+        assert_eq!(fl.functions[&32].name, "fcn.00000020");
+    }
 }
@@ -56,6 +56,9 @@ pub trait Source {
     fn cc_info_of(&self, _start_addr: u64) -> Result<LCCInfo, SourceErr> { unimplemented!() }
     fn strings(&self, data_only: bool) -> Result<Vec<LStringInfo>, SourceErr> { unimplemented!() }
     fn raw(&self, _cmd: String) -> Result<String, SourceErr> { unimplemented!() }
+    fn read_bytes(&self, _addr: u64, _size: u64) -> Result<Vec<u8>, SourceErr> {
+        Err(SourceErr::SrcErr("`Source::read_bytes` is not implemented"))
+    }
 
     fn send(&self, _: String) -> Result<(), SourceErr> { Ok(()) }
 
@@ -209,6 +212,11 @@ impl<R: R2Api> Source for WrappedR2Api<R> {
         let _ = self.try_borrow_mut()?.raw(s);
         Ok(())
     }
+
+    fn read_bytes(&self, addr: u64, size: u64) -> Result<Vec<u8>, SourceErr> {
+        let json_str = self.try_borrow_mut()?.raw(format!("pxj {} @ {}", size, addr));
+        Ok(serde_json::from_str(&json_str)?)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -483,6 +491,10 @@ mod test {
     use r2pipe::r2::R2;
     use frontend::radeco_containers::*;
     use frontend::radeco_source::*;
+    use middle::ir::MOpcode;
+    use middle::ssa::cfg_traits::CFG;
+    use middle::ssa::ssa_traits::{SSA, SSAWalk};
+    use middle::ssa::utils;
 
     #[test]
     fn file_source_test() {
@@ -491,4 +503,79 @@ mod test {
         let source = FileSource::open(path.to_str().unwrap());
         ProjectLoader::new().source(Rc::new(source)).load();
     }
+
+    fn load_fixture(base: &str) -> RadecoProject {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_files");
+        path.push(base);
+        let source = FileSource::open(path.to_str().unwrap());
+        ProjectLoader::new().source(Rc::new(source)).load()
+    }
+
+    fn function<'a>(rmod: &'a RadecoModule, name: &str) -> &'a RadecoFunction {
+        rmod.functions.values().find(|rfn| rfn.name == name).expect("Function not found")
+    }
+
+    // Targets of the calls and whether any conditional execution is lifted.
+    fn calls_and_ite(rfn: &RadecoFunction) -> (Vec<u64>, bool) {
+        let ssa = rfn.ssa();
+        let mut calls = Vec::new();
+        let mut ite = false;
+        for node in ssa.inorder_walk() {
+            match ssa.opcode(node) {
+                Some(MOpcode::OpCall) => {
+                    let info = utils::call_info(node, ssa).unwrap();
+                    calls.extend(ssa.constant_value(info.target));
+                }
+                Some(MOpcode::OpITE) => ite = true,
+                _ => {}
+            }
+        }
+        (calls, ite)
+    }
+
+    // Whether the function returns, i.e. its exit is reached.
+    fn returns(rfn: &RadecoFunction) -> bool {
+        let ssa = rfn.ssa();
+        !ssa.preds_of(ssa.exit_node().unwrap()).is_empty()
+    }
+
+    #[test]
+    fn arm64_file_source_test() {
+        let rproj = load_fixture("arm64_filesource/arm64");
+        let rmod = rproj.nth_module(0).unwrap();
+
+        // `ret` returns through the link register.
+        let add = function(rmod, "sym.add");
+        assert!(returns(add));
+        // `w0` is the low half of `x0`.
+        let regs = &add.ssa().regfile;
+        assert_eq!(regs.get_subregister("w0").unwrap().base,
+                   regs.get_subregister("x0").unwrap().base);
+
+        let main = function(rmod, "main");
+        assert!(returns(main));
+        assert_eq!(calls_and_ite(main), (vec![0x400], true));
+    }
+
+    #[test]
+    fn arm32_file_source_test() {
+        let rproj = load_fixture("arm32_filesource/arm32");
+        let rmod = rproj.nth_module(0).unwrap();
+
+        // `movlt r0, r1` followed by `bx lr`.
+        let max = function(rmod, "sym.max");
+        assert!(returns(max));
+        assert_eq!(calls_and_ite(max), (Vec::new(), true));
+
+        // Thumb, returning by `pop {r4, pc}`.
+        let twice = function(rmod, "sym.twice");
+        assert!(returns(twice));
+        assert_eq!(calls_and_ite(twice), (vec![0x10000], false));
+
+        // The flags are lifted on their own, not as a part of `cpsr`.
+        let regs = &twice.ssa().regfile;
+        assert!(regs.register_id_by_name("cpsr").is_none());
+        assert!(regs.register_id_by_name("zf").is_some());
+    }
 }
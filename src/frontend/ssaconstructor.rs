@@ -6,18 +6,23 @@
 // There are some limitations to the current ESIL parser and these may/must be
 // improved in the
 // further commits.
-// 1. We only parse ESIL that is "well formed", that is, it cannot have any
-// statements after an if.
+// 1. ESIL is parsed in a single pass only if it is "well formed", that is, it
+// cannot have any statements after an if.
 // For example: "zf,?{,0x80,rip,=,}" is a valid esil statement as it does not
 // have any
 // instructions after "}" in the same instruction.
+// Instructions with statements after an if or with ESIL control flow ("GOTO",
+// "BREAK", "REPEAT") are split into several basic blocks at sub-addresses of the
+// instruction, see `lift_intra_cf`.
 
 use esil::lexer::{Token, Tokenizer};
 
 use esil::parser::{Parse, Parser};
-// use frontend::instruction_analyzer::{InstructionAnalyzer, X86_CS_IA, IOperand};
+use frontend::instruction_analyzer::{mnemonic_of, InstructionAnalyzer, IOperand, X86_IA};
+use frontend::jump_tables;
 use frontend::radeco_containers::RadecoFunction;
 
+use middle::intrinsics;
 use middle::ir::{self, MAddress, MOpcode};
 use middle::phiplacement::PhiPlacer;
 use middle::regfile::SubRegisterFile;
@@ -26,10 +31,9 @@ use middle::ssa::ssa_traits::{SSAExtra, SSAMod, ValueInfo};
 
 use r2api::structs::{LOpInfo, LRegInfo};
 
-// use regex::Regex;
 use std::{fmt, cmp, u64};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::borrow::Cow;
 
 pub type VarId = usize;
 
@@ -54,6 +58,15 @@ pub struct SSAConstruct<'a, T>
     mem_id: u64,
     assume_cc: bool,
     replace_pc: bool,
+    // Resolved jump tables of the function, keyed by the address of the indirect jump.
+    jump_tables: HashMap<u64, Vec<u64>>,
+    // Addresses of the calls to functions which never return.
+    noreturn_calls: HashSet<u64>,
+    // Targets of the jumps lifted as tail calls.
+    tail_calls: HashSet<u64>,
+    // Whether the instruction being lifted returns, in which case its write to
+    // PC is the return.
+    returning: bool,
 }
 
 impl<'a, T> SSAConstruct<'a, T>
@@ -72,6 +85,10 @@ impl<'a, T> SSAConstruct<'a, T>
             mem_id: 0,
             assume_cc: false,
             replace_pc: true,
+            jump_tables: HashMap::new(),
+            noreturn_calls: HashSet::new(),
+            tail_calls: HashSet::new(),
+            returning: false,
         };
 
         // Add all the registers to the variable list.
@@ -84,11 +101,17 @@ impl<'a, T> SSAConstruct<'a, T>
     // Helper wrapper.
     pub fn construct(rfn: &mut RadecoFunction, ri: &LRegInfo, assume_cc: bool, replace_pc: bool) {
         let instructions = rfn.instructions().to_vec();
+        let jump_tables = rfn.jump_tables().clone();
+        let noreturn_calls = rfn.noreturn_calls().clone();
+        let tail_calls = rfn.tail_calls().iter().cloned().collect();
         let regfile = Arc::new(SubRegisterFile::new(ri));
         rfn.ssa_mut().regfile = regfile.clone();
         let mut constr = SSAConstruct::new(rfn.ssa_mut(), &regfile);
         constr.assume_cc = assume_cc;
         constr.replace_pc = replace_pc;
+        constr.jump_tables = jump_tables;
+        constr.noreturn_calls = noreturn_calls;
+        constr.tail_calls = tail_calls;
         constr.run(instructions.as_slice());
     }
 
@@ -220,10 +243,27 @@ impl<'a, T> SSAConstruct<'a, T>
                             self.phiplacer
                                 .add_block(target_addr, Some(*address), Some(UNCOND_EDGE));
                             self.needs_new_block = true;
+                        } else if self.returning || self.is_link_register(&operands[1]) {
+                            // Architectures with a link register return by writing
+                            // the return address to PC, either straight from the
+                            // link register or after popping it from the stack.
+                            self.returning = false;
+                            self.phiplacer.add_return(*address, UNCOND_EDGE);
+                            self.needs_new_block = true;
                         } else {
                             // Indirect CF transfer
                             if let Some(ref jump_idx) = rhs {
-                                self.phiplacer.add_indirect_cf(jump_idx, address, UNCOND_EDGE);
+                                // Jumps through a resolved table become a chain of
+                                // compares of the table index.
+                                let table = self.jump_tables.get(&address.address).cloned();
+                                let index = table.as_ref().and_then(|_| {
+                                    jump_tables::table_ref(self.phiplacer.ssa(), *jump_idx)
+                                });
+                                if let (Some(targets), Some(tref)) = (table, index) {
+                                    self.phiplacer.add_jump_table(&tref.index, &targets, address);
+                                } else {
+                                    self.phiplacer.add_indirect_cf(jump_idx, address, UNCOND_EDGE);
+                                }
                                 // Next instruction should begin in a new block
                                 self.needs_new_block = true;
                             } else {
@@ -341,8 +381,11 @@ impl<'a, T> SSAConstruct<'a, T>
                 return Some(op_node);
             }
             Token::EPop => unreachable!(),
-            Token::EGoto => unimplemented!(),
-            Token::EBreak => unimplemented!(),
+            Token::EGoto | Token::EBreak => {
+                // These are lowered by `lift_intra_cf` before reaching the parser.
+                radeco_warn!("Unexpected ESIL control flow @ {}", address);
+                return None;
+            }
             Token::EEndIf | Token::ENop => {
                 return None;
             }
@@ -429,12 +472,26 @@ impl<'a, T> SSAConstruct<'a, T>
     // it into its SSA
     // form.
     pub fn run(&mut self, op_info: &[LOpInfo]) {
+        // Operations default to the width of PC, e.g. 32 bits on ARM.
+        let default_size = self.regfile
+            .alias_info
+            .get("PC")
+            .and_then(|pc| self.regfile.get_subregister(pc))
+            .map_or(64, |pc| pc.width);
         let mut p = Parser::init(Some(self.regfile
                                      .named_registers
                                      .iter()
                                      .map(|(n, v)| (n.clone(), v.width as u64))
                                      .collect()),
-                                 Some(64));
+                                 Some(default_size));
+
+        // Instructions following a call which never returns are lifted only
+        // if they are branched to.
+        let targets = op_info.iter()
+            .filter_map(|op| op.jump)
+            .chain(self.jump_tables.values().flat_map(|t| t.iter().cloned()))
+            .collect::<HashSet<_>>();
+        let mut unreachable = false;
 
         let mut current_address = MAddress::new(0, 0);
         self.init_blocks();
@@ -444,6 +501,12 @@ impl<'a, T> SSAConstruct<'a, T>
             }
 
             let offset = op.offset.unwrap_or(0);
+            if unreachable {
+                if !targets.contains(&offset) {
+                    continue;
+                }
+                unreachable = false;
+            }
 
             // Get ESIL string
             let esil_str = if let Some(ref esil_str_) = op.esil {
@@ -480,17 +543,26 @@ impl<'a, T> SSAConstruct<'a, T>
 
             radeco_trace!("ssa_construct_esil|{}|{:?}", current_address, esil_str);
 
+            // Instructions whose semantics are not described by their ESIL are
+            // kept as intrinsics.
+            {
+                let overrides = &["TRAP", "$", "TODO"];
+                let unsupported = esil_str.split(",").any(|x| overrides.contains(&x));
+                let known = esil_str.is_empty() &&
+                    mnemonic_of(op).map_or(false, |m| intrinsics::lookup(&m).is_some());
+                if unsupported || known {
+                    self.process_intrinsic(op, &mut current_address);
+                    continue;
+                }
+            }
+
             // Handle call separately.
             // NOTE: This is a hack.
             {
-                // also handle unknown ESIL this way
-                let overrides = &["GOTO", "TRAP", "$", "TODO", "REPEAT"];
                 let opt_call_ty =
-                    if esil_str.split(",").any(|x| overrides.contains(&x)) {
-                        Some(Cow::Owned(format!("ESIL: {}", esil_str)))
-                    } else if let Some(ref ty) = op.optype {
-                        if ty == "call" || ty == "ucall" {
-                            Some(Cow::Borrowed(ty))
+                    if let Some(ref ty) = op.optype {
+                        if ty == "call" || ty == "ucall" || ty == "rcall" || ty == "ircall" {
+                            Some(ty)
                         } else {
                             None
                         }
@@ -499,136 +571,60 @@ impl<'a, T> SSAConstruct<'a, T>
                     };
 
                 if let Some(call_ty) = opt_call_ty {
-                    let is_real_call = &*call_ty == "call" || &*call_ty == "ucall";
-
-                    let unknown_str = "unknown".to_owned();
-
-                    let value_type = if &*call_ty == "call" {
+                    let value_type = if call_ty == "call" {
                         scalar!(0)
                     } else {
                         //TODO Specify WidthSpec from esil
                         reference!()
                     };
 
-                    let call_operand =
-                        self.phiplacer.add_comment(current_address,
-                                                   value_type,
-                                                   op.opcode.clone().unwrap_or(unknown_str));
+                    self.process_call(op, value_type, &mut current_address);
 
-                    let opcode = if is_real_call {
-                        MOpcode::OpCall
-                    } else {
-                        MOpcode::OpCustom(call_ty.into_owned())
-                    };
-                    let op_call = self.phiplacer
-                        .add_op(&opcode, &mut current_address, value_type);
-
-
-                    // If `self.assume_cc` is set, then we assume that the callee strictly obeys the
-                    // calling convention.
-                    let (cargs, retr) = if self.assume_cc && is_real_call {
-                        (self.regfile.iter_args(), self.regfile.alias_info.get("SN"))
-                    } else {
-                        // If we cannot make any assumption about the calling convention, then we
-                        // need to be conservative and assume that the callee takes every register
-                        // as an argument and also clobbers every register.
-                        (self.regfile.into_iter(), None)
-                    };
-
-                    for (i, ref reg) in cargs {
-                        let rnode = self.phiplacer.read_register(&mut current_address, reg);
-                        self.phiplacer.op_use(&op_call, (i + 1) as u8, &rnode);
-                        // We don't know which register contains the return value. Assume that all
-                        // registers are clobbered and write to them.
-                        if retr.is_none() {
-                            let new_register_comment = format!("{}@{}", reg, current_address);
-                            let width = self.regfile
-                                .whole_registers
-                                .get(i)
-                                .expect("Unable to find register with index");
-                            let comment_node = self.phiplacer
-                                .add_comment(current_address, *width, new_register_comment);
-                            self.phiplacer.write_register(&mut current_address, reg, comment_node);
-                            self.phiplacer.op_use(&comment_node, i as u8, &op_call);
-                        }
-                    }
-
-                    // Assume every function call reads from and writes to memory.
-                    let mem_id = self.mem_id();
-                    let mem_node = self.phiplacer.read_variable(&mut current_address, mem_id);
-                    self.phiplacer.op_use(&op_call, (mem_id + 1) as u8, &mem_node);
-                    let new_mem_comment = format!("{}@{}", "mem", current_address);
-                    let comment_node = self.phiplacer
-                        .add_comment(current_address, *MEM_VALUEINFO, new_mem_comment);
-                    self.phiplacer.write_variable(current_address, mem_id, comment_node);
-                    self.phiplacer.op_use(&comment_node, mem_id as u8, &op_call);
-
-                    // If we're using CC, we assume that we know the register that corresponds to
-                    // the return value, so we write this register with the output from `OpCall`
-                    if let Some(reg) = retr {
-                        let new_register_comment = format!("{}@{}", reg, current_address);
-                        let idx = self.regfile
-                            .whole_names
-                            .iter()
-                            .position(|r| r == reg)
-                            .expect("Invalid register");
-                        let width = self.regfile
-                            .whole_registers
-                            .get(idx)
-                            .expect("Unable to find register with index");
-                        let comment_node = self.phiplacer
-                            .add_comment(current_address, *width, new_register_comment);
-                        self.phiplacer.write_register(&mut current_address, reg, comment_node);
-                        self.phiplacer.op_use(&comment_node, 0, &op_call);
+                    if self.noreturn_calls.contains(&offset) {
+                        self.phiplacer.add_noreturn(current_address);
+                        self.needs_new_block = true;
+                        unreachable = true;
                     }
-
-                    current_address.offset += 1;
-                    self.phiplacer.set_address(&op_call, current_address);
-                    self.phiplacer.op_use(&op_call, 0, &call_operand);
                     continue;
                 }
             }
 
-            // Handle returns separately
-            if op.optype.as_ref().map_or(false, |ty| ty == "ret") {
+            // Jumps to the start of another function are tail calls, lifted as
+            // a call followed by a return.
+            if op.optype.as_ref().map_or(false, |ty| ty == "jmp") &&
+               op.jump.map_or(false, |target| self.tail_calls.contains(&target)) {
+                self.process_call(op, scalar!(0), &mut current_address);
                 self.phiplacer.add_return(current_address, UNCOND_EDGE);
                 self.needs_new_block = true;
                 continue;
             }
 
-            /*
-            // Some overrides as we do not support all esil and don't want to panic.
-            let overrides = &["GOTO", "TRAP", "$", "TODO", "REPEAT"];
-            if esil_str.split(",").any(|x| overrides.contains(&x)) {
-                // Do something else other than asking the parserS
-                lazy_static! {
-                    static ref OPRE: Regex = Regex::new(r"[[:xdigit:]][[:xdigit:]]")
-                                                .expect("Unable to compile regex");
-                }
-
-                let byte_str = op.bytes.as_ref().expect("Invalid bytes for instruction");
-                let bytes = OPRE.captures_iter(byte_str.as_str())
-                    .map(|cap| u8::from_str_radix(&cap[0], 16).expect("Cannot Fail"))
-                    .collect::<Vec<u8>>();
-
-                // TODO/XXX:
-                // This needs to be selected based on the current arch
-                let ia = X86_CS_IA::new(bytes).expect("Unable to instantiate IA");
-                self.process_custom(&ia, &mut current_address);
+            // Handle returns separately. The return address is popped by the
+            // return on x86, which is left out to keep the stack balanced.
+            // Returns through the link register are lifted in full, as they
+            // may restore registers, e.g. `pop {r4, pc}`.
+            let ret = op.optype.as_ref().map_or(false, |ty| ty == "ret");
+            let cret = op.optype.as_ref().map_or(false, |ty| ty == "cret");
+            let link_register = self.regfile.alias_info.contains_key("LR");
+            if ret && !link_register {
+                self.phiplacer.add_return(current_address, UNCOND_EDGE);
+                self.needs_new_block = true;
                 continue;
             }
-            */
+            self.returning = ret || cret;
 
-            while let Some(ref token) = p.parse::<_, Tokenizer>(esil_str) {
-                radeco_trace!("ssa_construct_token|{}|{:?}", current_address, token);
-                let (lhs, rhs) = p.fetch_operands(token);
-                // Determine what to do with the operands and get the result.
-                let result = self.process_op(token, &mut current_address, &[lhs, rhs], op.size.unwrap_or(0));
-                if let Some(result_) = self.process_out(result, current_address) {
-                    p.push(result_);
-                }
-                current_address.offset += 1;
+            if has_intra_cf(esil_str) {
+                self.lift_intra_cf(&mut p, esil_str, &mut current_address, op.size.unwrap_or(0));
+            } else {
+                self.parse_esil(&mut p, esil_str, &mut current_address, op.size.unwrap_or(0));
+            }
+
+            // Returns whose ESIL does not write PC still end the function.
+            if self.returning && ret {
+                self.phiplacer.add_return(current_address, UNCOND_EDGE);
+                self.needs_new_block = true;
             }
+            self.returning = false;
         }
         // BUG: The last block may not have the biggest address, which means current_address
         // may be not in the last basic block
@@ -637,6 +633,378 @@ impl<'a, T> SSAConstruct<'a, T>
         self.phiplacer.finish(op_info);
     }
 
+    // Whether `operand` is the link register, which holds the return address.
+    fn is_link_register(&self, operand: &Option<Token>) -> bool {
+        match *operand {
+            Some(Token::EIdentifier(ref name)) |
+            Some(Token::ERegister(ref name)) => {
+                let lr = self.regfile.register_id_by_alias("LR");
+                lr.is_some() && self.regfile.register_id_by_name(name) == lr
+            }
+            _ => false,
+        }
+    }
+
+    // Lowers the call `op` into an `OpCall`, returning it.
+    fn process_call(&mut self, op: &LOpInfo, value_type: ValueInfo, address: &mut MAddress)
+                    -> T::ValueRef {
+        let unknown_str = "unknown".to_owned();
+
+        let call_operand =
+            self.phiplacer.add_comment(*address,
+                                       value_type,
+                                       op.opcode.clone().unwrap_or(unknown_str));
+
+        let op_call = self.phiplacer
+            .add_op(&MOpcode::OpCall, address, value_type);
+
+
+        // If `self.assume_cc` is set, then we assume that the callee strictly obeys the
+        // calling convention.
+        let (cargs, retr) = if self.assume_cc {
+            (self.regfile.iter_args(), self.regfile.alias_info.get(self.regfile.return_alias()))
+        } else {
+            // If we cannot make any assumption about the calling convention, then we
+            // need to be conservative and assume that the callee takes every register
+            // as an argument and also clobbers every register.
+            (self.regfile.into_iter(), None)
+        };
+
+        for (i, ref reg) in cargs {
+            let rnode = self.phiplacer.read_register(address, reg);
+            self.phiplacer.op_use(&op_call, (i + 1) as u8, &rnode);
+            // We don't know which register contains the return value. Assume that all
+            // registers are clobbered and write to them.
+            if retr.is_none() {
+                let new_register_comment = format!("{}@{}", reg, *address);
+                let width = self.regfile
+                    .whole_registers
+                    .get(i)
+                    .expect("Unable to find register with index");
+                let comment_node = self.phiplacer
+                    .add_comment(*address, *width, new_register_comment);
+                self.phiplacer.write_register(address, reg, comment_node);
+                self.phiplacer.op_use(&comment_node, i as u8, &op_call);
+            }
+        }
+
+        // The stack pointer locates the arguments passed on the stack, see
+        // `analysis::stack_args`, and checks that the stack is balanced at tail
+        // calls, see `frontend::tail_calls`.
+        let sp = self.regfile.register_id_by_alias("SP");
+        if let (true, Some(sp)) = (self.assume_cc, sp) {
+            let reg = self.regfile.whole_names[sp.to_usize()].clone();
+            let rnode = self.phiplacer.read_register(address, &reg);
+            self.phiplacer.op_use(&op_call, sp.to_u8() + 1, &rnode);
+        }
+
+        // Assume every function call reads from and writes to memory.
+        let mem_id = self.mem_id();
+        let mem_node = self.phiplacer.read_variable(address, mem_id);
+        self.phiplacer.op_use(&op_call, (mem_id + 1) as u8, &mem_node);
+        let new_mem_comment = format!("{}@{}", "mem", *address);
+        let comment_node = self.phiplacer
+            .add_comment(*address, *MEM_VALUEINFO, new_mem_comment);
+        self.phiplacer.write_variable(*address, mem_id, comment_node);
+        self.phiplacer.op_use(&comment_node, mem_id as u8, &op_call);
+
+        // If we're using CC, we assume that we know the register that corresponds to
+        // the return value, so we write this register with the output from `OpCall`
+        if let Some(reg) = retr {
+            let new_register_comment = format!("{}@{}", reg, *address);
+            let idx = self.regfile
+                .whole_names
+                .iter()
+                .position(|r| r == reg)
+                .expect("Invalid register");
+            let width = self.regfile
+                .whole_registers
+                .get(idx)
+                .expect("Unable to find register with index");
+            let comment_node = self.phiplacer
+                .add_comment(*address, *width, new_register_comment);
+            self.phiplacer.write_register(address, reg, comment_node);
+            self.phiplacer.op_use(&comment_node, 0, &op_call);
+        }
+
+        address.offset += 1;
+        self.phiplacer.set_address(&op_call, *address);
+        self.phiplacer.op_use(&op_call, 0, &call_operand);
+        op_call
+    }
+
+    fn parse_esil(&mut self, p: &mut Parser, esil: &str, address: &mut MAddress, op_size: u64) {
+        // The parser does not know floating point operations. They are lifted
+        // here, with their operands taken from the stack of the parser.
+        if !esil.split(',').any(|w| float_opcode(w).is_some()) {
+            self.parse_tokens(p, esil, address, op_size);
+            return;
+        }
+        let mut segment = Vec::new();
+        for word in esil.split(',') {
+            if let Some(opcode) = float_opcode(word) {
+                if !segment.is_empty() {
+                    self.parse_tokens(p, &segment.join(","), address, op_size);
+                    segment.clear();
+                }
+                self.process_float_op(p, opcode, address, op_size);
+            } else {
+                segment.push(word);
+            }
+        }
+        if !segment.is_empty() {
+            self.parse_tokens(p, &segment.join(","), address, op_size);
+        }
+    }
+
+    fn parse_tokens(&mut self, p: &mut Parser, esil: &str, address: &mut MAddress, op_size: u64) {
+        while let Some(ref token) = p.parse::<_, Tokenizer>(esil) {
+            radeco_trace!("ssa_construct_token|{}|{:?}", address, token);
+            let (lhs, rhs) = p.fetch_operands(token);
+            // Determine what to do with the operands and get the result.
+            let result = self.process_op(token, address, &[lhs, rhs], op_size);
+            if let Some(result_) = self.process_out(result, *address) {
+                p.push(result_);
+            }
+            address.offset += 1;
+        }
+    }
+
+    fn process_float_op(&mut self,
+                        p: &mut Parser,
+                        opcode: MOpcode,
+                        address: &mut MAddress,
+                        op_size: u64) {
+        // Operands are fetched like those of an integer operation of the same arity.
+        let arity_token = if opcode.is_binary() { Token::EAdd } else { Token::ENeg };
+        let (lhs, rhs) = p.fetch_operands(&arity_token);
+        let lhs = self.process_in(&lhs, address, Some(op_size));
+        let rhs = self.process_in(&rhs, address, Some(op_size));
+        let lhs = match lhs {
+            Some(lhs) => lhs,
+            None => {
+                radeco_warn!("Missing operand for {} @ {}", opcode, address);
+                return;
+            }
+        };
+
+        let result = if let Some(rhs) = rhs {
+            // Floats are never zero extended, the narrower operand is converted instead.
+            let lhs_size = self.phiplacer.operand_width(&lhs);
+            let rhs_size = self.phiplacer.operand_width(&rhs);
+            let size = cmp::max(lhs_size, rhs_size);
+            let lhs = self.convert_float(lhs, size, address);
+            let rhs = self.convert_float(rhs, size, address);
+            let out_size = match opcode {
+                MOpcode::OpFEq | MOpcode::OpFLt => 1,
+                _ => size,
+            };
+            let op_node = self.phiplacer
+                .add_op(&opcode, address, ValueInfo::new_scalar(ir::WidthSpec::Known(out_size)));
+            self.phiplacer.op_use(&op_node, 0, &lhs);
+            self.phiplacer.op_use(&op_node, 1, &rhs);
+            op_node
+        } else {
+            let out_size = match opcode {
+                MOpcode::OpIntToFloat(w) | MOpcode::OpFloatToInt(w) | MOpcode::OpFloatConv(w) => w,
+                _ => 64,
+            };
+            let op_node = self.phiplacer
+                .add_op(&opcode, address, ValueInfo::new_scalar(ir::WidthSpec::Known(out_size)));
+            self.phiplacer.op_use(&op_node, 0, &lhs);
+            op_node
+        };
+
+        if let Some(result_) = self.process_out(Some(result), *address) {
+            p.push(result_);
+        }
+        address.offset += 1;
+    }
+
+    fn convert_float(&mut self, value: T::ValueRef, size: u16, address: &mut MAddress) -> T::ValueRef {
+        if self.phiplacer.operand_width(&value) == size {
+            return value;
+        }
+        let conv = self.phiplacer.add_op(&MOpcode::OpFloatConv(size),
+                                         address,
+                                         ValueInfo::new_scalar(ir::WidthSpec::Known(size)));
+        self.phiplacer.op_use(&conv, 0, &value);
+        conv
+    }
+
+    // Lifts an instruction whose ESIL has control flow of its own. The words of
+    // the ESIL string are split into basic blocks at the targets of "GOTO" and
+    // "REPEAT" and after every jump and "}". Each block starts at a new
+    // sub-address of the instruction, "BREAK" jumps to a block following all
+    // others. The parts in between are parsed as usual.
+    fn lift_intra_cf(&mut self, p: &mut Parser, esil: &str, address: &mut MAddress, op_size: u64) {
+        let words = esil.split(',').collect::<Vec<_>>();
+        let end = words.len();
+
+        // Word indices which are jumped to.
+        let targets = words.iter()
+            .enumerate()
+            .filter(|&(_, w)| *w == "GOTO" || *w == "REPEAT")
+            .filter_map(|(i, _)| i.checked_sub(1).and_then(|j| esil_number(words[j])))
+            .map(|t| cmp::min(t as usize, end))
+            .collect::<HashSet<_>>();
+
+        // Sub-addresses of the blocks started so far and edges into blocks
+        // which are not started yet, by word index.
+        let mut starts: HashMap<usize, MAddress> = HashMap::new();
+        let mut pending: HashMap<usize, Vec<(MAddress, u8)>> = HashMap::new();
+        let mut segment: Vec<&str> = Vec::new();
+        let mut split = false;
+
+        for (i, word) in words.iter().enumerate() {
+            if split || targets.contains(&i) {
+                self.parse_segment(p, &mut segment, address, op_size);
+                let incoming = pending.remove(&i).unwrap_or_default();
+                starts.insert(i, self.start_intra_block(address, incoming));
+                split = false;
+            }
+
+            match *word {
+                "GOTO" => {
+                    let target = segment.pop().and_then(esil_number);
+                    self.parse_segment(p, &mut segment, address, op_size);
+                    match target {
+                        Some(t) => {
+                            let t = cmp::min(t as usize, end);
+                            if let Some(&start) = starts.get(&t) {
+                                self.phiplacer.add_edge(*address, start, UNCOND_EDGE);
+                            } else {
+                                pending.entry(t).or_insert_with(Vec::new).push((*address, UNCOND_EDGE));
+                            }
+                        }
+                        None => radeco_warn!("Non-constant ESIL GOTO @ {}", address),
+                    }
+                    self.needs_new_block = true;
+                    split = true;
+                }
+                "BREAK" => {
+                    self.parse_segment(p, &mut segment, address, op_size);
+                    pending.entry(end).or_insert_with(Vec::new).push((*address, UNCOND_EDGE));
+                    self.needs_new_block = true;
+                    split = true;
+                }
+                "REPEAT" => {
+                    // `counter,target,REPEAT` jumps to `target` while `counter` is
+                    // greater than one, pushing the decremented counter. The stack
+                    // does not flow along the edges of the blocks, thus a register
+                    // counter is written back instead, on the way to `target`.
+                    let target = segment.pop().and_then(esil_number);
+                    let counter = segment.pop().map(esil_token);
+                    self.parse_segment(p, &mut segment, address, op_size);
+                    let (target, counter) = match (target, counter) {
+                        (Some(t), Some(c)) => (cmp::min(t as usize, end), Some(c)),
+                        _ => {
+                            radeco_warn!("Invalid ESIL REPEAT @ {}", address);
+                            continue;
+                        }
+                    };
+                    let register = match counter {
+                        Some(Token::EIdentifier(ref name)) if self.regfile.get_subregister(name).is_some() => {
+                            Some(name.clone())
+                        }
+                        _ => None,
+                    };
+                    let mut counter = self.process_in(&counter, address, Some(op_size));
+                    let mut one = Some(self.phiplacer.add_const(address, 1, None));
+                    self.phiplacer.narrow_const_operand(address, &mut counter, &mut one);
+                    let counter = counter.expect("counter cannot be `None`");
+                    let one = one.expect("This cannot be `None`");
+                    let cond = self.phiplacer.add_op(&MOpcode::OpGt,
+                                                     address,
+                                                     ValueInfo::new_scalar(ir::WidthSpec::Known(1)));
+                    self.phiplacer.op_use(&cond, 0, &counter);
+                    self.phiplacer.op_use(&cond, 1, &one);
+                    let ite = self.phiplacer.add_op(&MOpcode::OpITE,
+                                                    address,
+                                                    ValueInfo::new_scalar(ir::WidthSpec::Known(1)));
+                    self.phiplacer.op_use(&ite, 0, &cond);
+                    pending.entry(i + 1).or_insert_with(Vec::new).push((*address, FALSE_EDGE));
+
+                    // The jump decrements the counter in a block of its own.
+                    let src = *address;
+                    self.needs_new_block = true;
+                    self.start_intra_block(address, vec![(src, TRUE_EDGE)]);
+                    if let Some(ref name) = register {
+                        let width = self.phiplacer.operand_width(&counter);
+                        let dec = self.phiplacer.add_op(&MOpcode::OpSub,
+                                                        address,
+                                                        ValueInfo::new_scalar(ir::WidthSpec::Known(width)));
+                        self.phiplacer.op_use(&dec, 0, &counter);
+                        self.phiplacer.op_use(&dec, 1, &one);
+                        self.phiplacer.write_register(address, name, dec);
+                        address.offset += 1;
+                    } else {
+                        radeco_warn!("ESIL REPEAT counter is not a register @ {}", address);
+                    }
+                    if let Some(&start) = starts.get(&target) {
+                        self.phiplacer.add_edge(*address, start, UNCOND_EDGE);
+                    } else {
+                        pending.entry(target).or_insert_with(Vec::new).push((*address, UNCOND_EDGE));
+                    }
+                    self.needs_new_block = true;
+                    split = true;
+                }
+                "}" => {
+                    self.parse_segment(p, &mut segment, address, op_size);
+                    // The false edge of the if joins with the end of its body.
+                    if let Some((ite, src_address)) = self.nesting.pop() {
+                        let false_address = MAddress::new(address.address, address.offset + 1);
+                        let false_comment = self.phiplacer
+                            .add_comment(src_address, scalar!(0), format!("F: {}", false_address));
+                        self.phiplacer.op_use(&ite, 2, &false_comment);
+                        pending.entry(i + 1).or_insert_with(Vec::new).push((src_address, FALSE_EDGE));
+                    } else {
+                        radeco_warn!("Unbalanced ESIL if @ {}", address);
+                    }
+                    split = true;
+                }
+                _ => segment.push(*word),
+            }
+        }
+
+        self.parse_segment(p, &mut segment, address, op_size);
+        // Whatever is left jumps past the end of the instruction.
+        if !pending.is_empty() {
+            let incoming = pending.drain().flat_map(|(_, edges)| edges).collect();
+            self.start_intra_block(address, incoming);
+        }
+    }
+
+    fn parse_segment(&mut self,
+                     p: &mut Parser,
+                     segment: &mut Vec<&str>,
+                     address: &mut MAddress,
+                     op_size: u64) {
+        if !segment.is_empty() {
+            let esil = segment.join(",");
+            self.parse_esil(p, &esil, address, op_size);
+            segment.clear();
+        }
+    }
+
+    // Starts a new block at the next sub-address of the instruction, the
+    // previous block falls through to it unless it ended with a jump.
+    fn start_intra_block(&mut self, address: &mut MAddress, incoming: Vec<(MAddress, u8)>) -> MAddress {
+        let prev = *address;
+        address.offset += 1;
+        let start = *address;
+        if self.needs_new_block {
+            self.phiplacer.add_block(start, None, None);
+        } else {
+            self.phiplacer.add_block(start, Some(prev), Some(UNCOND_EDGE));
+        }
+        self.needs_new_block = false;
+        for (src, edge_type) in incoming {
+            self.phiplacer.add_block(start, Some(src), Some(edge_type));
+        }
+        start
+    }
+
     #[allow(dead_code)]
     fn process_memory_op(&mut self,
                          base: &Option<String>,
@@ -696,101 +1064,71 @@ impl<'a, T> SSAConstruct<'a, T>
     }
 
 
-    /*
-    fn process_custom<IA: InstructionAnalyzer>(&mut self, ia: &IA, addr: &mut MAddress) {
-        let mnemonic = ia.mnemonic().clone();
-        let opcode = MOpcode::OpCustom(mnemonic.into_owned());
-        let vt = ValueInfo::new_unresolved(ir::WidthSpec::Unknown);
-
-        let custom_opnode = self.phiplacer.add_op(&opcode, addr, vt);
-
-        let reg_r = ia.registers_read()
-            .iter()
-            .map(|&x| if let &IOperand::Register(ref s) = x {
-                let tok = Token::ERegister(s.clone());
-                self.process_in(&Some(tok), addr, None)
-            } else {
-                None
-            })
-            .filter(|x| x.is_some())
-            .collect::<Vec<_>>();
-
-        // Use read registers as arguments
-        for (i, reg) in reg_r.iter().enumerate() {
-            if let &Some(ref reg_node) = reg {
-                self.phiplacer.op_use(&custom_opnode, i as u8, reg_node);
+    // Lifts an instruction whose semantics are not described by its ESIL into
+    // an `OpIntrinsic`. The operands of the intrinsic are the registers read by
+    // the instruction, followed by memory if it is accessed, and every register
+    // written is redefined by a comment node using the intrinsic. Instructions
+    // the instruction analyzer does not know are assumed to read and clobber
+    // every register and memory.
+    fn process_intrinsic(&mut self, op: &LOpInfo, address: &mut MAddress) {
+        let (name, reads, writes, memory) = match X86_IA::new(op) {
+            Ok(ia) => {
+                let regs = |operands: Vec<&IOperand>| {
+                    operands.into_iter()
+                        .filter_map(|o| match *o {
+                            IOperand::Register(ref r) => Some(r.clone()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                };
+                (ia.mnemonic().to_string(),
+                 regs(ia.registers_read()),
+                 regs(ia.registers_written()),
+                 ia.has_memory_operand())
             }
-        }
-
-        let opidx = reg_r.len() as u8;
+            Err(e) => {
+                radeco_trace!("ssa_construct_intrinsic|{}|{}", address, e);
+                let name = mnemonic_of(op).unwrap_or("unknown".to_owned());
+                let regs = self.regfile.into_iter().map(|(_, r)| r).collect::<Vec<_>>();
+                (name, regs.clone(), regs, true)
+            }
+        };
 
-        let reg_w = ia.registers_written()
-            .iter()
-            .map(|&x| if let &IOperand::Register(ref s) = x {
-                Some(s)
-            } else {
-                None
-            })
-            .filter(|x| x.is_some())
-            .collect::<Vec<_>>();
+        let mut args = Vec::new();
+        for reg in &reads {
+            args.push(self.phiplacer.read_register(address, reg));
+        }
+        if memory {
+            let mem_id = self.mem_id();
+            args.push(self.phiplacer.read_variable(address, mem_id));
+        }
 
-        // Write out modified variables
-        for reg in &reg_w {
-            if let Some(ref rname) = *reg {
-                self.phiplacer.write_register(addr, rname, custom_opnode);
-            }
+        let intrinsic = self.phiplacer.add_op(&MOpcode::OpIntrinsic(name), address, scalar!(0));
+        for (i, arg) in args.iter().enumerate() {
+            self.phiplacer.op_use(&intrinsic, i as u8, arg);
         }
 
-        if ia.has_memory_operand() {
-            // <addr> in represented in mem_op, need to extract the mib.
-            // base + index*scale +- disp
-            let (opcode, maddr) = if let Some(mem_op) = ia.memory_written() {
-                // Insert a store
-                let st = MOpcode::OpStore;
-                let addr =
-                    if let &IOperand::Memory { ref base, ref index, ref scale, ref disp } =
-                        mem_op {
-                        self.process_memory_op(base, index, *scale, *disp, addr)
-                    } else {
-                        unreachable!()
-                    };
-                (st, addr)
-            } else if let Some(mem_op) = ia.memory_read() {
-                // Insert load
-                let ld = MOpcode::OpLoad;
-                let addr =
-                    if let &IOperand::Memory { ref base, ref index, ref scale, ref disp } =
-                        mem_op {
-                        self.process_memory_op(base, index, *scale, *disp, addr)
-                    } else {
-                        unreachable!()
-                    };
-                (ld, addr)
-            } else {
-                // Memory has to be a load or a store operation, cannot be anything else
-                unreachable!()
+        for (i, reg) in writes.iter().enumerate() {
+            let width = match self.regfile.get_subregister(reg) {
+                Some(info) => info.width as u16,
+                None => {
+                    radeco_warn!("Write to unknown register {} is dropped", reg);
+                    continue;
+                }
             };
-
-            let mem_op = self.phiplacer.add_op(&opcode, addr, vt);
-            let mem = self.phiplacer.read_variable(addr, self.mem_id);
-
-            // Op[Load/Store](mem, addr)
-            self.phiplacer.op_use(&mem_op, 0, &mem);
-            self.phiplacer.op_use(&mem_op, 1, &maddr);
-
-            // Do some additional handling, such as associating the written value,
-            // creating new instance of memory etc.
-            if opcode == MOpcode::OpStore {
-                self.phiplacer.op_use(&mem_op, 2, &custom_opnode);
-                // New instance of memory
-                self.phiplacer.write_variable(*addr, self.mem_id, mem_op);
-            } else {
-                // Use memory load in the custom_opnode
-                self.phiplacer.op_use(&custom_opnode, opidx, &mem_op);
-            }
+            let vt = ValueInfo::new_scalar(ir::WidthSpec::Known(width));
+            let comment = self.phiplacer.add_comment(*address, vt, format!("{}@{}", reg, address));
+            self.phiplacer.op_use(&comment, i as u8, &intrinsic);
+            self.phiplacer.write_register(address, reg, comment);
+        }
+        if memory {
+            let mem_id = self.mem_id();
+            let comment = self.phiplacer
+                .add_comment(*address, *MEM_VALUEINFO, format!("mem@{}", address));
+            self.phiplacer.op_use(&comment, writes.len() as u8, &intrinsic);
+            self.phiplacer.write_variable(*address, mem_id, comment);
         }
     }
-    */
 } // end impl SSAConstruct
 
 #[cfg(test)]
@@ -798,7 +1136,10 @@ mod test {
     use analysis::sccp;
     use middle::{dot, dce};
     use middle::ir_writer;
+    use middle::ssa::cfg_traits::CFG;
+    use middle::ssa::ssa_traits::SSA;
     use middle::ssa::ssastorage::SSAStorage;
+    use middle::ssa::utils;
     use r2api::structs::{LFunctionInfo, LRegInfo};
     use serde_json;
     use std::fs::File;
@@ -872,6 +1213,271 @@ mod test {
         f.write_all(tmp.as_bytes()).expect("Write failed!");
     }
 
+    #[test]
+    fn ssa_intra_cf_test() {
+        // `rep movsb` followed by `mov eax, 1` and `ret`.
+        let ops = r#"[{"offset":4096,"size":2,"opcode":"rep movsb byte [rdi], byte ptr [rsi]",
+                       "esil":"rcx,!,?{,BREAK,},rsi,[1],rdi,=[1],df,?{,1,rdi,-=,1,rsi,-=,},df,!,?{,1,rdi,+=,1,rsi,+=,},rcx,--=,rcx,?{,0,GOTO,}",
+                       "type":"mov"},
+                      {"offset":4098,"size":5,"opcode":"mov eax, 1","esil":"1,eax,=","type":"mov"},
+                      {"offset":4103,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut reg_profile = Default::default();
+        let mut instructions = Default::default();
+        before_test(&mut reg_profile,
+                    &mut instructions,
+                    "test_files/tiny_sccp_test_instructions.json");
+        let ops: Vec<LOpInfo> = serde_json::from_str(ops).unwrap();
+        let mut ssa = SSAStorage::new();
+        {
+            let regfile = SubRegisterFile::new(&reg_profile);
+            let mut constructor = SSAConstruct::new(&mut ssa, &regfile);
+            constructor.run(ops.as_slice());
+        }
+        // The instruction loops back to its own start.
+        let back_edge = ssa.blocks().into_iter().any(|b| {
+            let addr = ssa.starting_address(b).unwrap();
+            ssa.succs_of(b).into_iter().any(|s| {
+                let succ = ssa.starting_address(s).unwrap();
+                succ.address == 4096 && addr.address == 4096 && succ < addr
+            })
+        });
+        assert!(back_edge);
+
+        // The loop goes on while the decremented counter is not zero.
+        let ites = ssa.values()
+            .into_iter()
+            .filter(|&v| ssa.opcode(v) == Some(MOpcode::OpITE))
+            .collect::<Vec<_>>();
+        assert_eq!(ites.len(), 4);
+        let loop_ite = ites.into_iter()
+            .find(|&ite| ssa.opcode(ssa.operands_of(ite)[0]) == Some(MOpcode::OpSub))
+            .expect("Loop condition is not lifted");
+
+        // The end of the loop falls through to `mov eax, 1`.
+        let mov = ssa.values()
+            .into_iter()
+            .find(|&v| ssa.address(v).map_or(false, |a| a.address == 4098))
+            .expect("mov eax, 1 is not lifted");
+        let mov_block = ssa.block_for(mov).unwrap();
+        assert!(ssa.preds_of(mov_block).contains(&ssa.block_for(loop_ite).unwrap()));
+    }
+
+    #[test]
+    fn ssa_repeat_test() {
+        // `counter,target,REPEAT` with `rcx` as the counter, which starts at 3.
+        let ops = r#"[{"offset":4096,"size":7,"opcode":"mov rcx, 3","esil":"3,rcx,=","type":"mov"},
+                      {"offset":4103,"size":2,"opcode":"rep lodsb al, byte [rsi]","esil":"rsi,[1],al,=,1,rsi,+=,rcx,0,REPEAT","type":"mov"},
+                      {"offset":4105,"size":5,"opcode":"mov eax, 1","esil":"1,eax,=","type":"mov"},
+                      {"offset":4110,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut reg_profile = Default::default();
+        let mut instructions = Default::default();
+        before_test(&mut reg_profile,
+                    &mut instructions,
+                    "test_files/tiny_sccp_test_instructions.json");
+        let ops: Vec<LOpInfo> = serde_json::from_str(ops).unwrap();
+        let mut ssa = SSAStorage::new();
+        {
+            let regfile = Arc::new(SubRegisterFile::new(&reg_profile));
+            ssa.regfile = regfile.clone();
+            let mut constructor = SSAConstruct::new(&mut ssa, &*regfile);
+            constructor.run(ops.as_slice());
+        }
+
+        // The loop goes on while the counter is greater than one, and the
+        // counter is decremented on the way back.
+        let gt = ssa.values()
+            .into_iter()
+            .find(|&v| ssa.opcode(v) == Some(MOpcode::OpGt))
+            .expect("Condition is not lifted");
+        let operands = ssa.operands_of(gt);
+        assert_eq!(ssa.constant(operands[1]), Some(1));
+        let counter = operands[0];
+        assert!(ssa.is_phi(counter));
+        let dec = ssa.values()
+            .into_iter()
+            .find(|&v| ssa.opcode(v) == Some(MOpcode::OpSub) && ssa.operands_of(v)[0] == counter)
+            .expect("Counter is not decremented");
+        assert!(ssa.operands_of(counter).contains(&dec));
+        assert_ne!(ssa.block_for(dec), ssa.block_for(gt));
+
+        // The counter is not a constant, the fall-through is kept.
+        let ssa = {
+            let mut analyzer = sccp::Analyzer::new(&mut ssa);
+            analyzer.analyze();
+            analyzer.emit_ssa()
+        };
+        assert!(ssa.values().into_iter().any(|v| ssa.address(v).map_or(false, |a| a.address == 4105)));
+    }
+
+    #[test]
+    fn ssa_noreturn_test() {
+        // The call at 0x1002 does not return, the `xor` after it is only
+        // reached by the jump.
+        let ops = r#"[{"offset":4096,"size":2,"opcode":"je 0x100b","esil":"zf,?{,4107,rip,=,}","type":"cjmp","jump":4107,"fail":4098},
+                      {"offset":4098,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4103,"size":4,"opcode":"mov eax, 1","esil":"1,eax,=","type":"mov"},
+                      {"offset":4107,"size":2,"opcode":"xor eax, eax","esil":"eax,eax,^=","type":"xor"},
+                      {"offset":4109,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut reg_profile = Default::default();
+        let mut instructions = Default::default();
+        before_test(&mut reg_profile,
+                    &mut instructions,
+                    "test_files/tiny_sccp_test_instructions.json");
+        let ops: Vec<LOpInfo> = serde_json::from_str(ops).unwrap();
+        let mut ssa = SSAStorage::new();
+        let regfile = SubRegisterFile::new(&reg_profile);
+        {
+            let mut constructor = SSAConstruct::new(&mut ssa, &regfile);
+            constructor.noreturn_calls.insert(4098);
+            constructor.run(ops.as_slice());
+        }
+        let addresses = ssa.values()
+            .into_iter()
+            .filter_map(|v| ssa.address(v))
+            .map(|a| a.address)
+            .collect::<HashSet<_>>();
+        assert!(addresses.contains(&4098));
+        assert!(!addresses.contains(&4103));
+        assert!(addresses.contains(&4107));
+        // The block of the call only leads to the exit.
+        let call = ssa.values()
+            .into_iter()
+            .find(|&v| ssa.opcode(v) == Some(MOpcode::OpCall))
+            .unwrap();
+        let block = ssa.block_for(call).unwrap();
+        assert_eq!(ssa.succs_of(block), vec![ssa.exit_node().unwrap()]);
+    }
+
+    #[test]
+    fn ssa_tail_call_test() {
+        // The jump at 0x1002 is a tail call to 0x2000, the one at 0x1007 is not.
+        let ops = r#"[{"offset":4096,"size":2,"opcode":"je 0x1007","esil":"zf,?{,4103,rip,=,}","type":"cjmp","jump":4103,"fail":4098},
+                      {"offset":4098,"size":5,"opcode":"jmp 0x2000","esil":"8192,rip,=","type":"jmp","jump":8192},
+                      {"offset":4103,"size":2,"opcode":"jmp 0x100b","esil":"4107,rip,=","type":"jmp","jump":4107},
+                      {"offset":4107,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut reg_profile = Default::default();
+        let mut instructions = Default::default();
+        before_test(&mut reg_profile,
+                    &mut instructions,
+                    "test_files/tiny_sccp_test_instructions.json");
+        let ops: Vec<LOpInfo> = serde_json::from_str(ops).unwrap();
+        let mut ssa = SSAStorage::new();
+        let regfile = SubRegisterFile::new(&reg_profile);
+        {
+            let mut constructor = SSAConstruct::new(&mut ssa, &regfile);
+            constructor.assume_cc = true;
+            constructor.tail_calls.insert(8192);
+            constructor.run(ops.as_slice());
+        }
+        let calls = ssa.values()
+            .into_iter()
+            .filter(|&v| ssa.opcode(v) == Some(MOpcode::OpCall))
+            .collect::<Vec<_>>();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(ssa.address(calls[0]).unwrap().address, 4098);
+        // The stack pointer is an operand of the call.
+        let sp = regfile.register_id_by_alias("SP").unwrap();
+        assert!(ssa.sparse_operands_of(calls[0]).iter().any(|&(i, _)| i == sp.to_u8() + 1));
+        // The block of the call only leads to the exit.
+        let block = ssa.block_for(calls[0]).unwrap();
+        assert_eq!(ssa.succs_of(block), vec![ssa.exit_node().unwrap()]);
+    }
+
+    #[test]
+    fn ssa_intrinsic_test() {
+        // `cpuid` has no ESIL, `ud2` is not known to the instruction analyzer.
+        let ops = r#"[{"offset":4096,"size":2,"opcode":"cpuid","esil":"","type":"unk"},
+                      {"offset":4098,"size":2,"opcode":"syscall","esil":"0,$","type":"swi"},
+                      {"offset":4100,"size":2,"opcode":"int 0x80","esil":"128,$","type":"swi"},
+                      {"offset":4102,"size":2,"opcode":"ud2","esil":"TRAP","type":"trap"},
+                      {"offset":4104,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut reg_profile = Default::default();
+        let mut instructions = Default::default();
+        before_test(&mut reg_profile,
+                    &mut instructions,
+                    "test_files/tiny_sccp_test_instructions.json");
+        let ops: Vec<LOpInfo> = serde_json::from_str(ops).unwrap();
+        let mut ssa = SSAStorage::new();
+        let regfile = SubRegisterFile::new(&reg_profile);
+        {
+            let mut constructor = SSAConstruct::new(&mut ssa, &regfile);
+            constructor.run(ops.as_slice());
+        }
+        let intrinsics = ssa.values()
+            .into_iter()
+            .filter_map(|v| utils::intrinsic_info(v, &ssa))
+            .collect::<Vec<_>>();
+        assert_eq!(intrinsics.len(), 4);
+        let cpuid = intrinsics.iter().find(|i| i.name == "cpuid").unwrap();
+        assert_eq!(cpuid.args.len(), 2);
+        assert_eq!(cpuid.outputs.len(), 4);
+        // System calls read the ABI registers and memory.
+        let syscall = intrinsics.iter().find(|i| i.name == "syscall").unwrap();
+        assert_eq!(syscall.args.len(), 8);
+        assert_eq!(syscall.outputs.len(), 4);
+        let int80 = intrinsics.iter().find(|i| i.name == "int80").unwrap();
+        assert_eq!(int80.args.len(), 8);
+        assert_eq!(int80.outputs.len(), 2);
+        // Registers and memory are all read and written by unknown instructions.
+        let ud2 = intrinsics.iter().find(|i| i.name == "ud2").unwrap();
+        assert_eq!(ud2.args.len(), regfile.whole_names.len() + 1);
+        assert_eq!(ud2.args.len(), ud2.outputs.len());
+    }
+
+    #[test]
+    fn ssa_float_test() {
+        // Scalar double operations on the low halves of `xmm` registers, then
+        // a write to the high half of `xmm3`.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"addsd xmm0, xmm1","esil":"xmm1l,xmm0l,F+,xmm0l,=","type":"add"},
+                      {"offset":4100,"size":5,"opcode":"cvtsi2sd xmm2, rax","esil":"rax,I2D,xmm2l,=","type":"mov"},
+                      {"offset":4105,"size":4,"opcode":"comisd xmm0, xmm1","esil":"xmm1l,xmm0l,F<,cf,=","type":"cmp"},
+                      {"offset":4109,"size":5,"opcode":"movq xmm3h, rax","esil":"rax,xmm3h,=","type":"mov"},
+                      {"offset":4114,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut reg_profile = Default::default();
+        let mut instructions = Default::default();
+        before_test(&mut reg_profile,
+                    &mut instructions,
+                    "test_files/tiny_sccp_test_instructions.json");
+        let ops: Vec<LOpInfo> = serde_json::from_str(ops).unwrap();
+        let mut ssa = SSAStorage::new();
+        {
+            let regfile = SubRegisterFile::new(&reg_profile);
+            let mut constructor = SSAConstruct::new(&mut ssa, &regfile);
+            constructor.run(ops.as_slice());
+        }
+        let width = |v| ssa.node_data(v).unwrap().vt.width().get_width();
+        let with_opcode = |opcode: MOpcode| {
+            ssa.values()
+                .into_iter()
+                .filter(|&v| ssa.opcode(v) == Some(opcode.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let fadd = with_opcode(MOpcode::OpFAdd);
+        assert_eq!(fadd.len(), 1);
+        assert_eq!(width(fadd[0]), Some(64));
+        assert!(ssa.operands_of(fadd[0]).iter().all(|&v| width(v) == Some(64)));
+        assert_eq!(with_opcode(MOpcode::OpIntToFloat(64)).len(), 1);
+        let flt = with_opcode(MOpcode::OpFLt);
+        assert_eq!(flt.len(), 1);
+        assert_eq!(width(flt[0]), Some(1));
+
+        // The sum is written to the low half of `xmm0`, zero extended to the
+        // register. Its high half is kept by shifting, masks would not fit.
+        assert!(with_opcode(MOpcode::OpZeroExt(128))
+            .into_iter()
+            .any(|v| ssa.operands_of(v) == vec![fadd[0]]));
+        assert!(with_opcode(MOpcode::OpAnd).into_iter().all(|v| width(v) != Some(128)));
+        // Keeping the high half takes a right and a left shift for each of
+        // `xmm0` and `xmm2`. Writing the high half of `xmm3` shifts the value
+        // left, and keeps its low half by a left and a right shift.
+        let shifts = |opcode| {
+            with_opcode(opcode).into_iter().filter(|&v| width(v) == Some(128)).count()
+        };
+        assert_eq!(shifts(MOpcode::OpLsr), 3);
+        assert_eq!(shifts(MOpcode::OpLsl), 4);
+    }
+
     #[test]
     fn ssa_bfs_walk() {
         let mut reg_profile = Default::default();
@@ -908,6 +1514,48 @@ mod test {
     }
 }
 
+// Returns true if `esil` has control flow within the instruction, that is a
+// jump or statements following an if.
+fn has_intra_cf(esil: &str) -> bool {
+    let words = esil.split(',').collect::<Vec<_>>();
+    let last = words.len().saturating_sub(1);
+    words.iter().enumerate().any(|(i, w)| {
+        *w == "GOTO" || *w == "BREAK" || *w == "REPEAT" || (*w == "}" && i < last)
+    })
+}
+
+// Floating point operations of ESIL, these work on doubles.
+fn float_opcode(word: &str) -> Option<MOpcode> {
+    Some(match word {
+        "F+" => MOpcode::OpFAdd,
+        "F-" => MOpcode::OpFSub,
+        "F*" => MOpcode::OpFMul,
+        "F/" => MOpcode::OpFDiv,
+        "F==" => MOpcode::OpFEq,
+        "F<" => MOpcode::OpFLt,
+        "I2D" | "S2D" => MOpcode::OpIntToFloat(64),
+        "D2I" => MOpcode::OpFloatToInt(64),
+        "F2D" => MOpcode::OpFloatConv(64),
+        "D2F" => MOpcode::OpFloatConv(32),
+        _ => return None,
+    })
+}
+
+fn esil_number(word: &str) -> Option<u64> {
+    if word.starts_with("0x") {
+        u64::from_str_radix(&word[2..], 16).ok()
+    } else {
+        word.parse().ok()
+    }
+}
+
+fn esil_token(word: &str) -> Token {
+    match esil_number(word) {
+        Some(n) => Token::EConstant(n),
+        None => Token::EIdentifier(word.to_owned()),
+    }
+}
+
 lazy_static! {
     /// A `ValueInfo` for `{mem}` comments
     static ref MEM_VALUEINFO: ValueInfo = scalar!(0);
@@ -0,0 +1,200 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Detects tail calls.
+//!
+//! A jump to the start of another function is a tail call if the stack is
+//! balanced at the jump, i.e. the stack pointer is the one the function was
+//! entered with, so that the callee returns to the caller of the function.
+//!
+//! Jumps to function starts are first recorded as tail calls of the
+//! `RadecoFunction`, which SSA construction lifts as a call followed by a
+//! return. The stack pointer passed to each of these calls is then checked
+//! by tracking its offset from the entry, and the targets of the unbalanced
+//! jumps are dropped before constructing the SSA again.
+
+use std::collections::{HashMap, HashSet};
+
+use frontend::radeco_containers::RadecoFunction;
+use middle::ir::MOpcode;
+use middle::regfile::RegisterId;
+use middle::ssa::cfg_traits::CFG;
+use middle::ssa::ssa_traits::SSA;
+use middle::ssa::ssastorage::SSAStorage;
+use middle::ssa::utils;
+
+use petgraph::prelude::NodeIndex;
+
+/// Records the jumps of `rfn` to one of `starts`, the start addresses of
+/// functions, as tail calls. Returns the number of tail called functions.
+pub fn record(rfn: &mut RadecoFunction, starts: &HashSet<u64>) -> usize {
+    let offset = rfn.offset;
+    let targets = rfn.instructions()
+        .iter()
+        .filter(|op| op.optype.as_ref().map_or(false, |ty| ty == "jmp"))
+        .filter_map(|op| op.jump)
+        .filter(|target| *target != offset && starts.contains(target))
+        .collect::<Vec<_>>();
+
+    let tail_calls = rfn.tail_calls_mut();
+    tail_calls.extend(targets);
+    tail_calls.sort();
+    tail_calls.dedup();
+    tail_calls.len()
+}
+
+/// Drops the tail calls of `rfn` made with an unbalanced stack, using its
+/// SSA. A function jumped to with an unbalanced stack once is not tail called
+/// at all. Returns the number of dropped tail calls.
+pub fn drop_unbalanced(rfn: &mut RadecoFunction) -> usize {
+    let jumps = {
+        let tail_calls = rfn.tail_calls();
+        rfn.instructions()
+            .iter()
+            .filter(|op| op.optype.as_ref().map_or(false, |ty| ty == "jmp"))
+            .filter_map(|op| match (op.offset, op.jump) {
+                (Some(offset), Some(target)) if tail_calls.contains(&target) => {
+                    Some((offset, target))
+                }
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let unbalanced = unbalanced_calls(rfn.ssa(), &jumps);
+    if !unbalanced.is_empty() {
+        radeco_trace!("tail_calls|{:#x}|unbalanced {:?}", rfn.offset, unbalanced);
+    }
+
+    let tail_calls = rfn.tail_calls_mut();
+    let before = tail_calls.len();
+    tail_calls.retain(|target| !unbalanced.contains(target));
+    before - tail_calls.len()
+}
+
+// Returns the targets of the calls at the addresses of `jumps`, which are not
+// passed the stack pointer of the entry.
+fn unbalanced_calls(ssa: &SSAStorage, jumps: &HashMap<u64, u64>) -> HashSet<u64> {
+    let sp = match ssa.regfile.register_id_by_alias("SP") {
+        Some(sp) => sp,
+        None => return jumps.values().cloned().collect(),
+    };
+    let offsets = stack_offsets(ssa, sp);
+
+    let mut unbalanced = HashSet::new();
+    for node in ssa.values() {
+        if ssa.opcode(node) != Some(MOpcode::OpCall) {
+            continue;
+        }
+        let target = match ssa.address(node).and_then(|addr| jumps.get(&addr.address)) {
+            Some(&target) => target,
+            None => continue,
+        };
+        let offset = utils::call_info(node, ssa)
+            .and_then(|info| info.register_args.get(sp).cloned())
+            .and_then(|sp_value| offsets.get(&sp_value).cloned());
+        if offset != Some(Some(0)) {
+            unbalanced.insert(target);
+        }
+    }
+    unbalanced
+}
+
+// Computes the offset of the values of the stack pointer from its value at
+// the entry. The offset of a value is `None` if it depends on the path taken.
+fn stack_offsets(ssa: &SSAStorage, sp: RegisterId) -> HashMap<NodeIndex, Option<u64>> {
+    let mut offsets = HashMap::new();
+    let entry_sp = ssa.entry_node()
+        .and_then(|entry| ssa.registers_in(entry))
+        .and_then(|regstate| utils::register_state_info(regstate, ssa).get(sp).cloned());
+    let mut worklist = match entry_sp {
+        Some((node, _)) => {
+            offsets.insert(node, Some(0));
+            vec![node]
+        }
+        None => return offsets,
+    };
+
+    while let Some(node) = worklist.pop() {
+        let offset = offsets[&node];
+        for user in ssa.uses_of(node) {
+            let user_offset = match user_offset(ssa, sp, node, offset, user) {
+                Some(user_offset) => user_offset,
+                None => continue,
+            };
+            // Calls preserve the stack pointer, which they define again.
+            let defined = if ssa.opcode(user) == Some(MOpcode::OpCall) {
+                match utils::call_rets(user, ssa).get(sp) {
+                    Some(&(output, _)) => output,
+                    None => continue,
+                }
+            } else {
+                user
+            };
+            let merged = match offsets.get(&defined) {
+                None => user_offset,
+                Some(&known) if known == user_offset => continue,
+                Some(&None) => continue,
+                Some(_) => None,
+            };
+            offsets.insert(defined, merged);
+            worklist.push(defined);
+        }
+    }
+    offsets
+}
+
+// Returns the offset of `user` following from the one of its operand `node`,
+// or `None` if `user` is not a value of the stack pointer.
+fn user_offset(ssa: &SSAStorage,
+               sp: RegisterId,
+               node: NodeIndex,
+               offset: Option<u64>,
+               user: NodeIndex)
+               -> Option<Option<u64>> {
+    if ssa.is_phi(user) {
+        return Some(offset);
+    }
+    let operands = ssa.operands_of(user);
+    let constant = |i: usize| operands.get(i).and_then(|&o| ssa.constant_value(o));
+    match ssa.opcode(user)? {
+        MOpcode::OpAdd if operands.len() == 2 && operands[0] != operands[1] => {
+            let c = if operands[0] == node { constant(1) } else { constant(0) };
+            Some(offset.and_then(|offset| c.map(|c| offset.wrapping_add(c))))
+        }
+        MOpcode::OpSub if operands.len() == 2 && operands[0] == node => {
+            let c = constant(1);
+            Some(offset.and_then(|offset| c.map(|c| offset.wrapping_sub(c))))
+        }
+        MOpcode::OpNarrow(_) | MOpcode::OpZeroExt(_) => Some(offset),
+        MOpcode::OpCall => {
+            let is_sp = utils::call_info(user, ssa)
+                .map_or(false, |info| info.register_args.get(sp) == Some(&node));
+            if is_sp { Some(offset) } else { None }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::construct;
+
+    #[test]
+    fn unbalanced_calls_test() {
+        // The call at 0x1001 is made with `rbp` still pushed, the one at 0x1007
+        // after popping it.
+        let ops = r#"[{"offset":4096,"size":1,"opcode":"push rbp","esil":"rbp,8,rsp,-=,rsp,=[8]","type":"upush"},
+                      {"offset":4097,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4102,"size":1,"opcode":"pop rbp","esil":"rsp,[8],rbp,=,8,rsp,+=","type":"pop"},
+                      {"offset":4103,"size":5,"opcode":"call 0x3000","esil":"rip,8,rsp,-=,rsp,=[],12288,rip,=","type":"call","jump":12288},
+                      {"offset":4108,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let ssa = construct(ops);
+        let jumps = [(4097, 8192), (4103, 12288)].iter().cloned().collect();
+        assert_eq!(unbalanced_calls(&ssa, &jumps), [8192].iter().cloned().collect());
+    }
+}
@@ -0,0 +1,501 @@
+//! `Source` reading a compact, hand-written listing of ESIL instructions.
+//!
+//! This allows to exercise lifting, SSA construction and the analyses on
+//! small snippets, without capturing JSON from radare2. A listing looks like:
+//!
+//! ```text
+//! # Full line comments start with '#'.
+//! registers ./test_files/bin1_filesource/bin1_register_profile.json
+//! section .text 0x1000 0x20 -r-x
+//! function main 0x1000 amd64
+//! symbol sym.helper 0x1010 0x2 FUNC
+//!
+//! 0x1000 push 1 rbp,8,rsp,-=,rsp,=[8]              ; push rbp
+//! 0x1001 call 5 rip,8,rsp,-=,rsp,=[],0x1010,rip,=  ; call 0x1010
+//! 0x1006 pop  1 rsp,[8],rbp,=,8,rsp,+=             ; pop rbp
+//! 0x1007 ret  1 rsp,[8],rip,=,8,rsp,+=
+//! ```
+//!
+//! Every instruction line holds the address, the mnemonic, the size and the
+//! ESIL of the instruction, optionally followed by its full text after a ';'.
+//! The kind of the instruction (call, return, jump, ...) is derived from the
+//! mnemonic, and the target of calls and jumps from the constant assigned by
+//! the ESIL. Functions span the instructions from their address to the next
+//! function. The register profile, which is required for lifting, is read from
+//! a JSON file as saved by `FileSource`. A relative path to it is resolved
+//! against the directory of the listing by `TextSource::open`.
+
+use serde_json::{self, Value};
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use r2api::structs::{FunctionInfo, LCCInfo, LEntryInfo, LExportInfo, LFlagInfo, LImportInfo, LOpInfo,
+                     LRegInfo, LRelocInfo, LSectionInfo, LStringInfo, LSymbolInfo, LVarInfo};
+
+use frontend::radeco_source::{Source, SourceErr};
+
+/// Error in a listing, with the line (starting from 1) it occurs on.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        self.reason
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+struct Instruction {
+    offset: u64,
+    size: u64,
+    opcode: String,
+    esil: String,
+    optype: String,
+    jump: Option<u64>,
+}
+
+struct Function {
+    name: String,
+    offset: u64,
+    calltype: String,
+}
+
+struct Symbol {
+    name: String,
+    vaddr: u64,
+    size: u64,
+    stype: String,
+}
+
+struct Section {
+    name: String,
+    vaddr: u64,
+    size: u64,
+    flags: String,
+}
+
+#[derive(Default)]
+pub struct TextSource {
+    regs: Option<Value>,
+    instructions: BTreeMap<u64, Instruction>,
+    functions: Vec<Function>,
+    symbols: Vec<Symbol>,
+    sections: Vec<Section>,
+}
+
+fn parse_number(s: &str) -> Option<u64> {
+    if s.starts_with("0x") {
+        u64::from_str_radix(&s[2..], 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+// Kind of the instruction, as named by r2, e.g. "call" or "cjmp".
+fn op_type(mnemonic: &str, jump: Option<u64>) -> String {
+    let optype = match mnemonic {
+        "call" | "bl" | "blx" | "blr" | "jal" | "jalr" => "call",
+        "ret" | "retn" | "retq" | "eret" => "ret",
+        "jmp" | "b" | "br" | "bx" => "jmp",
+        "cbz" | "cbnz" | "tbz" | "tbnz" => "cjmp",
+        "push" => "upush",
+        m if m.starts_with("b.") || m.starts_with('j') => "cjmp",
+        m => m,
+    };
+    // Without a constant target, calls and jumps are indirect.
+    match (optype, jump) {
+        ("call", None) => "ucall".to_owned(),
+        ("jmp", None) => "ujmp".to_owned(),
+        (optype, _) => optype.to_owned(),
+    }
+}
+
+// Last constant assigned by the ESIL, as in "0x1010,rip,=". This is the
+// target of calls and jumps, which only assign the program counter a value.
+fn jump_target(esil: &str) -> Option<u64> {
+    let tokens = esil.split(',').collect::<Vec<_>>();
+    tokens.windows(3)
+          .filter(|w| w[2] == "=")
+          .filter_map(|w| parse_number(w[0]))
+          .last()
+}
+
+impl TextSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<TextSource, SourceErr> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        TextSource::parse_in(&text, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parses a listing, with relative paths resolved against the current
+    /// directory.
+    pub fn parse(text: &str) -> Result<TextSource, SourceErr> {
+        TextSource::parse_in(text, Path::new(""))
+    }
+
+    // Parses a listing, with relative paths resolved against `dir`.
+    fn parse_in(text: &str, dir: &Path) -> Result<TextSource, SourceErr> {
+        let mut source = TextSource::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |reason| ParseError { line: i + 1, reason: reason };
+            let (fields, disasm) = match line.find(';') {
+                Some(idx) => (&line[..idx], Some(line[idx + 1..].trim())),
+                None => (line, None),
+            };
+            let fields = fields.split_whitespace().collect::<Vec<_>>();
+            let number = |idx: usize| fields.get(idx).and_then(|s| parse_number(s));
+            if fields.is_empty() {
+                return Err(err("Missing address of the instruction").into());
+            }
+            match fields[0] {
+                "registers" => {
+                    let path = fields.get(1).ok_or(err("Missing path of the register profile"))?;
+                    let mut json = String::new();
+                    File::open(dir.join(path))?.read_to_string(&mut json)?;
+                    source.regs = Some(serde_json::from_str(&json)?);
+                }
+                "section" => {
+                    let name = fields.get(1).ok_or(err("Missing name of the section"))?;
+                    source.sections.push(Section {
+                        name: name.to_string(),
+                        vaddr: number(2).ok_or(err("Invalid address of the section"))?,
+                        size: number(3).ok_or(err("Invalid size of the section"))?,
+                        flags: fields.get(4).unwrap_or(&"-r-x").to_string(),
+                    });
+                }
+                "function" => {
+                    let name = fields.get(1).ok_or(err("Missing name of the function"))?;
+                    source.functions.push(Function {
+                        name: name.to_string(),
+                        offset: number(2).ok_or(err("Invalid address of the function"))?,
+                        calltype: fields.get(3).unwrap_or(&"").to_string(),
+                    });
+                }
+                "symbol" => {
+                    let name = fields.get(1).ok_or(err("Missing name of the symbol"))?;
+                    source.symbols.push(Symbol {
+                        name: name.to_string(),
+                        vaddr: number(2).ok_or(err("Invalid address of the symbol"))?,
+                        size: number(3).unwrap_or(0),
+                        stype: fields.get(4).unwrap_or(&"NOTYPE").to_string(),
+                    });
+                }
+                _ => {
+                    let offset = number(0).ok_or(err("Unknown directive"))?;
+                    let mnemonic = fields.get(1).ok_or(err("Missing mnemonic"))?;
+                    let size = number(2).ok_or(err("Invalid size of the instruction"))?;
+                    let esil = fields.get(3).unwrap_or(&"").to_string();
+                    if fields.len() > 4 {
+                        return Err(err("Unexpected text after the ESIL").into());
+                    }
+                    let jump = match op_type(mnemonic, None).as_str() {
+                        "ucall" | "ujmp" | "cjmp" => jump_target(&esil),
+                        _ => None,
+                    };
+                    let inst = Instruction {
+                        offset: offset,
+                        size: size,
+                        opcode: disasm.unwrap_or(*mnemonic).to_owned(),
+                        optype: op_type(mnemonic, jump),
+                        esil: esil,
+                        jump: jump,
+                    };
+                    if source.instructions.insert(offset, inst).is_some() {
+                        return Err(err("Duplicate instruction address").into());
+                    }
+                }
+            }
+        }
+        Ok(source)
+    }
+
+    // Instructions of the function, which end at the next function.
+    fn instructions_of(&self, function: &Function) -> Vec<&Instruction> {
+        let end = self.functions
+                      .iter()
+                      .map(|f| f.offset)
+                      .filter(|&offset| offset > function.offset)
+                      .min()
+                      .unwrap_or(u64::max_value());
+        self.instructions.range(function.offset..end).map(|(_, inst)| inst).collect()
+    }
+
+    fn op_record(&self, inst: &Instruction) -> Value {
+        let mut record = json!({
+            "offset": inst.offset,
+            "size": inst.size,
+            "opcode": inst.opcode,
+            "esil": inst.esil,
+            "type": inst.optype,
+        });
+        if let Some(jump) = inst.jump {
+            record["jump"] = json!(jump);
+        }
+        if inst.optype == "cjmp" {
+            record["fail"] = json!(inst.offset + inst.size);
+        }
+        record
+    }
+
+    fn ops(&self, insts: Vec<&Instruction>) -> Result<Vec<LOpInfo>, SourceErr> {
+        let records = insts.into_iter().map(|inst| self.op_record(inst)).collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn function_of(&self, offset: u64) -> Option<&Function> {
+        self.functions.iter().find(|f| f.offset == offset)
+    }
+}
+
+impl Source for TextSource {
+    fn functions(&self) -> Result<Vec<FunctionInfo>, SourceErr> {
+        let records = self.functions
+                          .iter()
+                          .map(|f| {
+                              let insts = self.instructions_of(f);
+                              let size = insts.last().map_or(0, |i| i.offset + i.size - f.offset);
+                              let callrefs = insts.iter()
+                                                  .filter(|i| i.optype == "call")
+                                                  .filter_map(|i| {
+                                                      i.jump.map(|jump| {
+                                                          json!({"addr": jump, "type": "C", "at": i.offset})
+                                                      })
+                                                  })
+                                                  .collect::<Vec<_>>();
+                              json!({
+                                  "name": f.name,
+                                  "offset": f.offset,
+                                  "size": size,
+                                  "realsz": size,
+                                  "calltype": f.calltype,
+                                  "callrefs": callrefs,
+                                  "type": "fcn",
+                              })
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn instructions_at(&self, address: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+        let function = self.function_of(address).ok_or(SourceErr::SrcErr("No Instructions found"))?;
+        self.ops(self.instructions_of(function))
+    }
+
+    fn register_profile(&self) -> Result<LRegInfo, SourceErr> {
+        let regs = self.regs.clone().ok_or(SourceErr::SrcErr("No register profile in the listing"))?;
+        Ok(serde_json::from_value(regs)?)
+    }
+
+    fn flags(&self) -> Result<Vec<LFlagInfo>, SourceErr> {
+        let functions = self.functions()?;
+        let records = functions.iter()
+                               .map(|f| json!({"name": f.name, "offset": f.offset, "size": f.size}))
+                               .chain(self.symbols
+                                          .iter()
+                                          .map(|s| json!({"name": s.name, "offset": s.vaddr, "size": s.size})))
+                               .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn sections(&self) -> Result<Vec<LSectionInfo>, SourceErr> {
+        let records = self.sections
+                          .iter()
+                          .map(|s| {
+                              json!({
+                                  "flags": s.flags,
+                                  "name": s.name,
+                                  "paddr": 0,
+                                  "size": s.size,
+                                  "vaddr": s.vaddr,
+                                  "vsize": s.size,
+                              })
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    fn symbols(&self) -> Result<Vec<LSymbolInfo>, SourceErr> {
+        let records = self.symbols
+                          .iter()
+                          .enumerate()
+                          .map(|(i, s)| {
+                              json!({
+                                  "name": s.name,
+                                  "flagname": s.name,
+                                  "ordinal": i,
+                                  "bind": "GLOBAL",
+                                  "size": s.size,
+                                  "type": s.stype,
+                                  "vaddr": s.vaddr,
+                                  "paddr": 0,
+                              })
+                          })
+                          .collect();
+        Ok(serde_json::from_value(Value::Array(records))?)
+    }
+
+    // Listings only describe code, the following are always empty.
+
+    fn imports(&self) -> Result<Vec<LImportInfo>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn exports(&self) -> Result<Vec<LExportInfo>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn relocs(&self) -> Result<Vec<LRelocInfo>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn libraries(&self) -> Result<Vec<String>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn entrypoint(&self) -> Result<Vec<LEntryInfo>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn strings(&self, _data_only: bool) -> Result<Vec<LStringInfo>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn locals_of(&self, _start_addr: u64) -> Result<Vec<LVarInfo>, SourceErr> {
+        Ok(Vec::new())
+    }
+
+    fn disassemble_function(&self, name: &str) -> Result<Vec<LOpInfo>, SourceErr> {
+        let function = self.functions
+                           .iter()
+                           .find(|f| f.name == name)
+                           .ok_or(SourceErr::SrcErr("No known function"))?;
+        self.ops(self.instructions_of(function))
+    }
+
+    fn disassemble_n_bytes(&self, n: u64, at: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+        self.ops(self.instructions.range(at..at.saturating_add(n)).map(|(_, inst)| inst).collect())
+    }
+
+    fn disassemble_n_insts(&self, n: u64, at: u64) -> Result<Vec<LOpInfo>, SourceErr> {
+        self.ops(self.instructions.range(at..).take(n as usize).map(|(_, inst)| inst).collect())
+    }
+
+    /// Calling convention of the functions, from the argument and return
+    /// registers of the register profile.
+    fn cc_info_of(&self, start_addr: u64) -> Result<LCCInfo, SourceErr> {
+        if self.function_of(start_addr).is_none() {
+            return Err(SourceErr::SrcErr("No known function offset"));
+        }
+        let regs = self.register_profile()?;
+        let alias = |role: &str| {
+            regs.alias_info.iter().find(|a| a.role_str == role).map(|a| a.reg.clone())
+        };
+        let args = (0..10).filter_map(|i| alias(&format!("A{}", i))).collect::<Vec<_>>();
+        let ret = alias("R0").or_else(|| alias("SN"));
+        Ok(serde_json::from_value(json!({"ret": ret, "args": args}))?)
+    }
+
+    fn raw(&self, _cmd: String) -> Result<String, SourceErr> {
+        Err(SourceErr::SrcErr("`Source::raw` is not implemented"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+    use frontend::radeco_containers::ProjectLoader;
+    use middle::ir::MOpcode;
+    use middle::ssa::cfg_traits::CFG;
+    use middle::ssa::ssa_traits::{SSA, SSAWalk};
+    use middle::ssa::utils;
+
+    const LISTING: &'static str = r#"
+        # Calls `twice` and returns its result, plus one if it is not zero.
+        registers ./test_files/bin1_filesource/bin1_register_profile.json
+        section .text 0x1000 0x20
+        function main 0x1000 amd64
+        function twice 0x1010 amd64
+
+        0x1000 call 5 rip,8,rsp,-=,rsp,=[],0x1010,rip,=   ; call 0x1010
+        0x1005 test 3 0,rax,rax,&,==,$z,zf,=             ; test rax, rax
+        0x1008 je   2 zf,?{,0x100d,rip,=,}                ; je 0x100d
+        0x100a inc  3 1,rax,+=                            ; inc rax
+        0x100d ret  1 rsp,[8],rip,=,8,rsp,+=
+
+        0x1010 lea  4 rdi,rdi,+,rax,=                     ; lea rax, [rdi + rdi]
+        0x1014 ret  1 rsp,[8],rip,=,8,rsp,+=
+    "#;
+
+    #[test]
+    fn text_source_parse_test() {
+        let source = TextSource::parse(LISTING).expect("Failed to parse listing");
+        let functions = source.functions().unwrap();
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].size, Some(0xe));
+        assert_eq!(functions[1].size, Some(0x5));
+
+        let ops = source.instructions_at(0x1000).unwrap();
+        assert_eq!(ops.len(), 5);
+        assert_eq!(ops[0].optype, Some("call".to_owned()));
+        assert_eq!(ops[0].jump, Some(0x1010));
+        assert_eq!(ops[0].opcode, Some("call 0x1010".to_owned()));
+        assert_eq!(ops[2].optype, Some("cjmp".to_owned()));
+        assert_eq!(ops[2].jump, Some(0x100d));
+        assert_eq!(ops[4].opcode, Some("ret".to_owned()));
+
+        let cc = source.cc_info_of(0x1010).unwrap();
+        assert_eq!(cc.ret, Some("rax".to_owned()));
+        assert!(source.cc_info_of(0x1005).is_err());
+
+        match TextSource::parse("0x1000 nop") {
+            Err(SourceErr::OtherErr(e)) => assert_eq!(e.to_string(), "line 1: Invalid size of the instruction"),
+            _ => panic!("Parsed an instruction without size"),
+        }
+    }
+
+    #[test]
+    fn text_source_load_test() {
+        let source = TextSource::parse(LISTING).expect("Failed to parse listing");
+        let rproj = ProjectLoader::new().source(Rc::new(source)).load();
+        let rmod = rproj.nth_module(0).unwrap();
+
+        let main = rmod.functions.get(&0x1000).expect("No main");
+        let ssa = main.ssa();
+        assert!(!ssa.preds_of(ssa.exit_node().unwrap()).is_empty());
+        let calls = ssa.inorder_walk()
+                       .into_iter()
+                       .filter(|&node| ssa.opcode(node) == Some(MOpcode::OpCall))
+                       .filter_map(|node| ssa.constant_value(utils::call_info(node, ssa).unwrap().target))
+                       .collect::<Vec<_>>();
+        assert_eq!(calls, vec![0x1010]);
+    }
+
+    #[test]
+    fn text_source_open_test() {
+        let path = "./test_files/bin1_filesource/bin1_listing.txt";
+        let source = TextSource::open(path).expect("Failed to open listing");
+        assert!(source.register_profile().is_ok());
+
+        let ops = source.disassemble_n_bytes(u64::max_value(), 0x1010).unwrap();
+        assert_eq!(ops.len(), 2);
+    }
+}
@@ -49,7 +49,7 @@
 
 extern crate regex;
 extern crate petgraph;
-extern crate serde_json;
+#[macro_use] extern crate serde_json;
 #[macro_use] extern crate lazy_static;
 extern crate fixedbitset;
 extern crate either;
@@ -58,6 +58,7 @@ extern crate typed_arena;
 extern crate bit_set;
 extern crate num;
 extern crate linear_map;
+extern crate base64;
 
 #[cfg(test)]
 extern crate quickcheck;
@@ -0,0 +1,87 @@
+//! Defines an AST for C declarations. It follows the declarator syntax of C,
+//! so that the actions in [the parser] are trivial. [`lowering`] then derives
+//! the declared types.
+//!
+//! [the parser]: ::middle::c_header::parser
+//! [`lowering`]: ::middle::c_header::lowering
+
+#[derive(Debug)]
+pub enum Decl {
+    Typedef(Specifiers, Vec<Declarator>),
+    Declare(Specifiers, Vec<Declarator>),
+}
+
+#[derive(Debug)]
+pub struct Specifiers {
+    pub ty: TypeSpec,
+    pub qualifiers: Vec<Qualifier>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Qualifier {
+    Const,
+    Volatile,
+    Restrict,
+    Extern,
+    Static,
+    Inline,
+    Noreturn,
+}
+
+#[derive(Debug)]
+pub enum TypeSpec {
+    /// Builtin type, e.g. `unsigned long`.
+    Builtin(String),
+    /// Type named by a typedef.
+    Name(String),
+    /// `struct` or `union`, with its fields if defined here.
+    Record(RecordKind, Option<String>, Option<Vec<Field>>),
+    /// `enum`, with its enumerators if defined here.
+    Enum(Option<String>, Option<Vec<Enumerator>>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordKind {
+    Struct,
+    Union,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub specifiers: Specifiers,
+    pub declarators: Vec<Declarator>,
+}
+
+#[derive(Debug)]
+pub struct Enumerator {
+    pub name: String,
+    pub value: Option<EnumValue>,
+}
+
+#[derive(Debug)]
+pub enum EnumValue {
+    Const(i64),
+    Name(String),
+}
+
+/// A declarator. The name, if any, is innermost: `int *f(void)` declares a
+/// `Pointer(Function(Name(f), ..))`.
+#[derive(Debug)]
+pub enum Declarator {
+    Name(Option<String>),
+    Pointer(Box<Declarator>),
+    Array(Box<Declarator>, Option<u64>),
+    Function(Box<Declarator>, Params),
+}
+
+#[derive(Debug)]
+pub struct Params {
+    pub params: Vec<Param>,
+    pub variadic: bool,
+}
+
+#[derive(Debug)]
+pub struct Param {
+    pub specifiers: Specifiers,
+    pub declarator: Option<Declarator>,
+}
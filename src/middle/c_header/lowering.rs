@@ -0,0 +1,237 @@
+//! Lowers the declarations of the [AST] to a [`Header`].
+//!
+//! [AST]: ::middle::c_header::ast
+//! [`Header`]: ::middle::c_header::Header
+
+use middle::c_header::ast::*;
+use middle::c_header::{Header, Member};
+use middle::prototypes::{self, CType, Prototype};
+
+pub fn lower_decls(decls: Vec<Decl>) -> Result<Header, String> {
+    let mut lowering = Lowering::default();
+    for decl in decls {
+        lowering.lower_decl(decl)?;
+    }
+    Ok(lowering.header)
+}
+
+// Type derived from a declarator.
+enum Type {
+    Base(CType),
+    Pointer(Box<Type>),
+    Array(Box<Type>, Option<u64>),
+    // Return type, parameters, `None` if unspecified, and variadic.
+    Function(Box<Type>, Option<Vec<CType>>, bool),
+}
+
+impl Type {
+    // The type as it is passed.
+    fn to_ctype(&self) -> CType {
+        match *self {
+            Type::Base(ref ty) => ty.clone(),
+            Type::Pointer(ref ty) | Type::Array(ref ty, _) => {
+                let mut ty = ty.to_ctype();
+                ty.pointers += 1;
+                ty
+            }
+            // Only pointed to, see above.
+            Type::Function(..) => CType::new("void", 0),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Lowering {
+    header: Header,
+    anonymous: usize,
+}
+
+impl Lowering {
+    fn lower_decl(&mut self, decl: Decl) -> Result<(), String> {
+        match decl {
+            Decl::Typedef(specifiers, declarators) => {
+                let hint = declarators.first().and_then(declarator_name).map(|n| n.to_owned());
+                let base = self.lower_specifiers(specifiers, hint)?;
+                for declarator in declarators {
+                    match self.derive(Type::Base(base.clone()), declarator)? {
+                        (Some(name), ty) => {
+                            self.header.typedefs.insert(name, ty.to_ctype());
+                        }
+                        (None, _) => return Err("Typedef without a name".to_owned()),
+                    }
+                }
+            }
+            Decl::Declare(specifiers, declarators) => {
+                let noreturn = specifiers.qualifiers.contains(&Qualifier::Noreturn);
+                let base = self.lower_specifiers(specifiers, None)?;
+                // Only functions are of interest, variables are skipped.
+                for declarator in declarators {
+                    if let (Some(name), Type::Function(ret, args, variadic)) =
+                        self.derive(Type::Base(base.clone()), declarator)? {
+                        self.header.prototypes.push(Prototype {
+                            name: name,
+                            ret: ret.to_ctype(),
+                            unspecified: args.is_none(),
+                            args: args.unwrap_or_default(),
+                            variadic: variadic,
+                            noreturn: noreturn,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Lowers the type of `specifiers`, recording the records and enums it
+    // defines. Anonymous ones are named `hint`, if any.
+    fn lower_specifiers(&mut self,
+                        specifiers: Specifiers,
+                        hint: Option<String>)
+                        -> Result<CType, String> {
+        let ty = match specifiers.ty {
+            TypeSpec::Builtin(words) => CType::new(&builtin(&words), 0),
+            TypeSpec::Name(name) => {
+                self.header
+                    .typedefs
+                    .get(&name)
+                    .cloned()
+                    .or_else(|| prototypes::typedef(&name))
+                    .unwrap_or_else(|| CType::new(&name, 0))
+            }
+            TypeSpec::Record(kind, tag, members) => {
+                let kind = match kind {
+                    RecordKind::Struct => "struct",
+                    RecordKind::Union => "union",
+                };
+                let base = format!("{} {}", kind, self.tag(tag.or(hint)));
+                if let Some(members) = members {
+                    let members = self.lower_members(members)?;
+                    self.header.records.insert(base.clone(), members);
+                }
+                CType::new(&base, 0)
+            }
+            TypeSpec::Enum(tag, enumerators) => {
+                if let Some(enumerators) = enumerators {
+                    self.lower_enumerators(enumerators)?;
+                }
+                CType::new(&format!("enum {}", self.tag(tag.or(hint))), 0)
+            }
+        };
+        Ok(ty)
+    }
+
+    fn lower_members(&mut self, fields: Vec<Field>) -> Result<Vec<Member>, String> {
+        let mut members = Vec::new();
+        for field in fields {
+            let base = self.lower_specifiers(field.specifiers, None)?;
+            for declarator in field.declarators {
+                let (name, ty) = self.derive(Type::Base(base.clone()), declarator)?;
+                let (ty, count) = match ty {
+                    Type::Array(elem, count) => (elem.to_ctype(), count),
+                    ty => (ty.to_ctype(), None),
+                };
+                members.push(Member {
+                    name: name.unwrap_or_default(),
+                    ty: ty,
+                    count: count,
+                });
+            }
+        }
+        Ok(members)
+    }
+
+    fn lower_enumerators(&mut self, enumerators: Vec<Enumerator>) -> Result<(), String> {
+        let mut next = 0;
+        for enumerator in enumerators {
+            let value = match enumerator.value {
+                None => next,
+                Some(EnumValue::Const(value)) => value,
+                Some(EnumValue::Name(ref name)) => {
+                    *self.header
+                        .constants
+                        .get(name)
+                        .ok_or_else(|| format!("Unknown enumerator {}", name))?
+                }
+            };
+            self.header.constants.insert(enumerator.name, value);
+            next = value.wrapping_add(1);
+        }
+        Ok(())
+    }
+
+    fn lower_params(&mut self, params: Vec<Param>) -> Result<Vec<CType>, String> {
+        let mut args = Vec::new();
+        for param in params {
+            let base = self.lower_specifiers(param.specifiers, None)?;
+            let ty = match param.declarator {
+                Some(declarator) => self.derive(Type::Base(base), declarator)?.1,
+                None => Type::Base(base),
+            };
+            args.push(ty.to_ctype());
+        }
+        // `(void)` declares no parameters.
+        if args.len() == 1 && args[0].is_void() {
+            args.clear();
+        }
+        Ok(args)
+    }
+
+    // Derives the type declared by `declarator` from the one of the specifiers,
+    // `base`. Returns it with the declared name, if any.
+    fn derive(&mut self,
+              base: Type,
+              declarator: Declarator)
+              -> Result<(Option<String>, Type), String> {
+        match declarator {
+            Declarator::Name(name) => Ok((name, base)),
+            Declarator::Pointer(inner) => self.derive(Type::Pointer(Box::new(base)), *inner),
+            Declarator::Array(inner, count) => {
+                self.derive(Type::Array(Box::new(base), count), *inner)
+            }
+            Declarator::Function(inner, params) => {
+                // `()` leaves the parameters unspecified, unlike `(void)`.
+                let args = if params.params.is_empty() && !params.variadic {
+                    None
+                } else {
+                    Some(self.lower_params(params.params)?)
+                };
+                self.derive(Type::Function(Box::new(base), args, params.variadic), *inner)
+            }
+        }
+    }
+
+    fn tag(&mut self, tag: Option<String>) -> String {
+        match tag {
+            Some(tag) => tag,
+            None => {
+                self.anonymous += 1;
+                format!("anon{}", self.anonymous)
+            }
+        }
+    }
+}
+
+fn declarator_name(declarator: &Declarator) -> Option<&str> {
+    match *declarator {
+        Declarator::Name(ref name) => name.as_ref().map(|n| n.as_str()),
+        Declarator::Pointer(ref inner) |
+        Declarator::Array(ref inner, _) |
+        Declarator::Function(ref inner, _) => declarator_name(inner),
+    }
+}
+
+// Normalizes the words of a builtin type, e.g. `long int` to `long`, as in
+// the prototypes bundled with radeco.
+fn builtin(words: &str) -> String {
+    let words = words.split(' ').collect::<Vec<_>>();
+    let sized = words.iter().any(|&w| w == "short" || w == "long");
+    let mut base = words.iter()
+        .cloned()
+        .filter(|&w| w != "signed" && !(sized && w == "int"))
+        .collect::<Vec<_>>();
+    if base.is_empty() || base == ["unsigned"] {
+        base.push("int");
+    }
+    base.join(" ")
+}
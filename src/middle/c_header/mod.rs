@@ -0,0 +1,103 @@
+//! Parses C headers into [`Prototype`]s and types.
+//!
+//! A subset of C declarations is understood: typedefs, struct, union and enum
+//! definitions, function pointers and prototypes. Comments and preprocessor
+//! directives are skipped, macros are not expanded. Types are lowered to
+//! [`CType`]s the way they are passed: arrays decay to pointers and function
+//! pointers become `void *`.
+//!
+//! [`Prototype`]: ::middle::prototypes::Prototype
+//! [`CType`]: ::middle::prototypes::CType
+
+mod ast;
+mod lowering;
+mod parser;
+#[cfg(test)]
+mod test;
+
+use middle::prototypes::{CType, Prototype};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Declarations of a C header.
+#[derive(Clone, Debug, Default)]
+pub struct Header {
+    /// Declared functions.
+    pub prototypes: Vec<Prototype>,
+    /// Typedefs, with the type they stand for.
+    pub typedefs: HashMap<String, CType>,
+    /// Members of the defined structs and unions, by type, e.g. `struct stat`.
+    /// Anonymous ones are named after their typedef, if any.
+    pub records: HashMap<String, Vec<Member>>,
+    /// Values of the enumerators.
+    pub constants: HashMap<String, i64>,
+}
+
+/// Member of a struct or union.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub ty: CType,
+    /// Number of elements, if the member is an array.
+    pub count: Option<u64>,
+}
+
+/// Parses the declarations of a C header.
+pub fn parse_header(text: &str) -> Result<Header, String> {
+    let text = strip(text);
+    let decls = parser::HeaderParser::new()
+        .parse(&text)
+        .map_err(|e| e.to_string())?;
+    lowering::lower_decls(decls)
+}
+
+/// Reads and parses the C header at `path`.
+pub fn parse_header_file<P: AsRef<Path>>(path: P) -> Result<Header, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_header(&text)
+}
+
+// Blanks out comments and preprocessor directives.
+fn strip(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                while let Some(c) = chars.next() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                stripped.push(' ');
+            }
+            // Directives continue on the next line after a backslash.
+            '#' if line_start => {
+                let mut prev = c;
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' && prev != '\\' {
+                        break;
+                    }
+                    prev = c;
+                    chars.next();
+                }
+            }
+            _ => {
+                line_start = c == '\n' || (line_start && c.is_whitespace());
+                stripped.push(c);
+            }
+        }
+    }
+    stripped
+}
@@ -0,0 +1,103 @@
+use middle::c_header::*;
+use middle::prototypes::CType;
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const HEADER: &str = "\
+#ifndef EXAMPLE_H
+#define EXAMPLE_H \\
+    1
+#include <stddef.h>
+
+/* Callbacks are
+ * passed a context. */
+typedef int (*callback_t)(void *ctx, int event);
+typedef unsigned long int handle_t;
+
+typedef struct {
+    handle_t handle;
+    char name[16];
+    struct node *next, *prev;
+    callback_t cb;
+} node_t;
+
+union value {
+    long long i;
+    double f;
+};
+
+enum state { IDLE, RUNNING = 4, STOPPED, FAILED = -1, DEFAULT = IDLE, };
+
+node_t *node_new(const char *name, size_t len);
+int node_each(node_t *, int (*)(node_t *, void *), void *);
+extern void node_log(int level, const char *fmt, ...);
+_Noreturn void node_panic(void);
+static inline enum state node_state(const node_t * const node), node_reset();
+char **node_names(int (*filter)(const char *), unsigned n, long *counts[]);
+int count;
+#endif // EXAMPLE_H
+";
+
+#[test]
+fn prototypes_test() {
+    let header = parse_header(HEADER).unwrap();
+    let protos = header.prototypes;
+    let names = protos.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names,
+               ["node_new", "node_each", "node_log", "node_panic", "node_state", "node_reset",
+                "node_names"]);
+
+    assert_eq!(protos[0].ret, CType::new("struct node_t", 1));
+    assert_eq!(protos[0].args, [CType::new("char", 1), CType::new("unsigned long", 0)]);
+    assert!(!protos[0].variadic && !protos[0].noreturn);
+
+    // Function pointers are passed as `void *`.
+    assert_eq!(protos[1].args,
+               [CType::new("struct node_t", 1), CType::new("void", 1), CType::new("void", 1)]);
+    assert_eq!(protos[2].args, [CType::new("int", 0), CType::new("char", 1)]);
+    assert!(protos[2].variadic && protos[2].ret.is_void());
+    assert!(protos[3].noreturn && protos[3].args.is_empty());
+    assert_eq!(protos[4].ret, CType::new("enum state", 0));
+    assert_eq!(protos[4].args, [CType::new("struct node_t", 1)]);
+    // `()` leaves the arguments unspecified, unlike `(void)`.
+    assert!(!protos[3].unspecified);
+    assert!(protos[5].args.is_empty() && protos[5].unspecified && !protos[5].fixed_args());
+
+    // Arrays decay to pointers.
+    assert_eq!(protos[6].ret, CType::new("char", 2));
+    assert_eq!(protos[6].args,
+               [CType::new("void", 1), CType::new("unsigned int", 0), CType::new("long", 2)]);
+}
+
+#[test]
+fn types_test() {
+    let header = parse_header(HEADER).unwrap();
+    assert_eq!(header.typedefs["callback_t"], CType::new("void", 1));
+    assert_eq!(header.typedefs["handle_t"], CType::new("unsigned long", 0));
+    assert_eq!(header.typedefs["node_t"], CType::new("struct node_t", 0));
+
+    let node = &header.records["struct node_t"];
+    let members = node.iter()
+        .map(|m| (m.name.as_str(), m.ty.to_string(), m.count))
+        .collect::<Vec<_>>();
+    assert_eq!(members,
+               [("handle", "unsigned long".to_owned(), None),
+                ("name", "char".to_owned(), Some(16)),
+                ("next", "struct node *".to_owned(), None),
+                ("prev", "struct node *".to_owned(), None),
+                ("cb", "void *".to_owned(), None)]);
+    assert_eq!(header.records["union value"][0].ty, CType::new("long long", 0));
+
+    let constants = ["IDLE", "RUNNING", "STOPPED", "FAILED", "DEFAULT"]
+        .iter()
+        .map(|c| header.constants[*c])
+        .collect::<Vec<_>>();
+    assert_eq!(constants, [0, 4, 5, -1, 0]);
+}
+
+#[test]
+fn errors_test() {
+    assert!(parse_header("int f(int;").is_err());
+    assert!(parse_header("typedef int;").is_err());
+    assert!(parse_header("enum e { A = B };").is_err());
+    assert!(parse_header("").unwrap().prototypes.is_empty());
+}
@@ -7,9 +7,17 @@
 
 //! Dead code elimination
 
+use analysis::vsa::abstract_set::abstract_set::inum;
+use analysis::vsa::value_set::{ALoc, MemRegion};
+use frontend::radeco_containers::RadecoFunction;
+use middle::ir::MOpcode;
 use middle::ssa::graph_traits::Graph;
-use middle::ssa::ssa_traits::{SSAExtra, SSAMod, NodeType};
-use std::collections::VecDeque;
+use middle::ssa::memoryssa::{MemOpcode, MemorySSAInfo};
+use middle::ssa::ssa_traits::{SSA, SSAExtra, SSAMod, NodeType};
+use middle::ssa::ssastorage::SSAStorage;
+use petgraph::EdgeDirection;
+use petgraph::graph::NodeIndex;
+use std::collections::{HashSet, VecDeque};
 
 /// Removes SSA nodes that are not used by any other node.
 /// The algorithm will not consider whether the uses keeping a node alive
@@ -103,3 +111,163 @@ pub fn sweep<T>(ssa: &mut T)
         }
     }
 }
+
+/// Removes OpStores to stack slots of the function which are never loaded
+/// before being overwritten or the function returns, e.g. spills without
+/// reloads. Stores reaching a call which may read the slot, i.e. whose
+/// address escapes or which is above the stack pointer at the call, are
+/// kept, and so are the slots in the frame of the caller. Returns the number of removed stores.
+pub fn collect_stores(rfn: &mut RadecoFunction) -> usize {
+    if rfn.memory_ssa().is_none() {
+        rfn.build_memory_ssa();
+    }
+    let dead = {
+        let mssa = rfn.memory_ssa().expect("MemorySSA not built");
+        dead_stores(rfn.ssa(), mssa)
+    };
+    if dead.is_empty() {
+        return 0;
+    }
+
+    {
+        let ssa = rfn.ssa_mut();
+        for store in &dead {
+            radeco_trace!("dce_rm_store|{:?}", store);
+            // Memory after the store is the same as the one before it.
+            let mem = ssa.operands_of(*store)[0];
+            ssa.replace_value(*store, mem);
+        }
+        collect(ssa);
+    }
+    dead.len()
+}
+
+// Returns OpStores whose memory is never loaded
+fn dead_stores(ssa: &SSAStorage, mssa: &MemorySSAInfo<NodeIndex, NodeIndex>) -> Vec<NodeIndex> {
+    let mut dead = Vec::new();
+    for node in ssa.values() {
+        if ssa.opcode(node) != Some(MOpcode::OpStore) {
+            continue;
+        }
+        let a_loc = match mssa.accessed_a_loc(&node) {
+            Some(a_loc) => a_loc,
+            None => continue,
+        };
+        let end = a_loc.offset.saturating_add(a_loc.size as inum);
+        // Slots at or above the stack pointer at the entry belong to the caller.
+        if a_loc.region != MemRegion::Stack || end > 0 {
+            continue;
+        }
+        if !is_loaded(mssa, &node, &a_loc) {
+            dead.push(node);
+        }
+    }
+    dead
+}
+
+// Returns true if the memory defined by the OpStore may be loaded, or read by
+// a call, before the a-loc is overwritten entirely.
+fn is_loaded(mssa: &MemorySSAInfo<NodeIndex, NodeIndex>, store: &NodeIndex, a_loc: &ALoc) -> bool {
+    let start = match mssa.mem_node(store) {
+        Some(n) => n,
+        None => return true,
+    };
+    let end = a_loc.offset.saturating_add(a_loc.size as inum);
+    let mut visited = HashSet::new();
+    let mut worklist = vec![start];
+    while let Some(n) = worklist.pop() {
+        if !visited.insert(n) {
+            continue;
+        }
+        for user in mssa.g.neighbors_directed(n, EdgeDirection::Incoming) {
+            match mssa.g[user] {
+                MemOpcode::VUse | MemOpcode::Call => return true,
+                MemOpcode::VDef => {
+                    let kills = mssa.associated_nodes
+                        .get(&user)
+                        .and_then(|s| mssa.accessed_a_loc(s))
+                        .map_or(false, |a| {
+                            a.region == a_loc.region && a.offset <= a_loc.offset &&
+                            a.offset.saturating_add(a.size as inum) >= end
+                        });
+                    if !kills {
+                        worklist.push(user);
+                    }
+                }
+                MemOpcode::Phi => worklist.push(user),
+                _ => {}
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::{function, REGISTER_PROFILE};
+
+    fn stores(rfn: &RadecoFunction) -> usize {
+        rfn.ssa()
+            .values()
+            .into_iter()
+            .filter(|&n| rfn.ssa().opcode(n) == Some(MOpcode::OpStore))
+            .count()
+    }
+
+    #[test]
+    fn dead_store_test() {
+        // The store of `rdi` is overwritten before the load and the call,
+        // which may read the slot above `rsp`.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"sub rsp, 0x10","esil":"16,rsp,-=","type":"sub"},
+                      {"offset":4100,"size":5,"opcode":"mov qword [rsp + 8], rdi","esil":"rdi,8,rsp,+,=[8]","type":"mov"},
+                      {"offset":4105,"size":5,"opcode":"mov qword [rsp + 8], rsi","esil":"rsi,8,rsp,+,=[8]","type":"mov"},
+                      {"offset":4110,"size":5,"opcode":"mov rax, qword [rsp + 8]","esil":"8,rsp,+,[8],rax,=","type":"mov"},
+                      {"offset":4115,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4120,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4124,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut rfn = function(REGISTER_PROFILE, ops, false);
+        assert_eq!(stores(&rfn), 2);
+        assert_eq!(collect_stores(&mut rfn), 1);
+        assert_eq!(stores(&rfn), 1);
+    }
+
+    #[test]
+    fn loaded_store_test() {
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"sub rsp, 0x10","esil":"16,rsp,-=","type":"sub"},
+                      {"offset":4100,"size":5,"opcode":"mov qword [rsp + 8], rdi","esil":"rdi,8,rsp,+,=[8]","type":"mov"},
+                      {"offset":4105,"size":5,"opcode":"mov rax, qword [rsp + 8]","esil":"8,rsp,+,[8],rax,=","type":"mov"},
+                      {"offset":4110,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4114,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut rfn = function(REGISTER_PROFILE, ops, false);
+        assert_eq!(collect_stores(&mut rfn), 0);
+        assert_eq!(stores(&rfn), 1);
+    }
+
+    #[test]
+    fn stack_argument_store_test() {
+        // The slot at `rsp` holds an argument passed on the stack.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"sub rsp, 0x10","esil":"16,rsp,-=","type":"sub"},
+                      {"offset":4100,"size":4,"opcode":"mov qword [rsp], rdi","esil":"rdi,rsp,=[8]","type":"mov"},
+                      {"offset":4104,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4109,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4113,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut rfn = function(REGISTER_PROFILE, ops, true);
+        assert_eq!(collect_stores(&mut rfn), 0);
+        assert_eq!(stores(&rfn), 1);
+    }
+
+    #[test]
+    fn escaped_store_test() {
+        // The address of the slot is passed in `rsi`, the callee may read it.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"sub rsp, 0x10","esil":"16,rsp,-=","type":"sub"},
+                      {"offset":4100,"size":5,"opcode":"mov qword [rsp + 8], rdi","esil":"rdi,8,rsp,+,=[8]","type":"mov"},
+                      {"offset":4105,"size":5,"opcode":"lea rsi, [rsp + 8]","esil":"8,rsp,+,rsi,=","type":"lea"},
+                      {"offset":4110,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4115,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4119,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut rfn = function(REGISTER_PROFILE, ops, true);
+        assert_eq!(collect_stores(&mut rfn), 0);
+        assert_eq!(stores(&rfn), 1);
+    }
+}
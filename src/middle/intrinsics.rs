@@ -0,0 +1,194 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Describes the instructions that are lifted into `OpIntrinsic` nodes.
+//!
+//! Instructions whose semantics cannot be expressed in ESIL, like `cpuid` or
+//! `rdtsc`, are kept as opaque intrinsics. An intrinsic node uses the registers
+//! read by the instruction as its operands, in the order given here, followed
+//! by memory if the instruction accesses memory. Every register written by the
+//! instruction is defined by a comment node using the intrinsic, see
+//! `middle::ssa::utils::intrinsic_info`.
+
+/// Register effects of an intrinsic instruction.
+#[derive(Debug)]
+pub struct Intrinsic {
+    /// Mnemonic of the instruction.
+    pub name: &'static str,
+    /// Registers implicitly read by the instruction.
+    pub reads: &'static [&'static str],
+    /// Registers implicitly written by the instruction.
+    pub writes: &'static [&'static str],
+    /// The explicit operands of the instruction are both read and written.
+    pub explicit: bool,
+    /// The instruction may access any memory, not only its explicit operands.
+    pub memory: bool,
+    /// The instruction has no effects other than writing its outputs, and
+    /// always writes the same outputs for the same inputs.
+    pub pure: bool,
+}
+
+static X86_INTRINSICS: &[Intrinsic] = &[
+    Intrinsic {
+        name: "cpuid",
+        reads: &["eax", "ecx"],
+        writes: &["eax", "ebx", "ecx", "edx"],
+        explicit: false,
+        memory: false,
+        pure: true,
+    },
+    Intrinsic {
+        name: "xgetbv",
+        reads: &["ecx"],
+        writes: &["eax", "edx"],
+        explicit: false,
+        memory: false,
+        pure: true,
+    },
+    Intrinsic {
+        name: "rdtsc",
+        reads: &[],
+        writes: &["eax", "edx"],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "rdtscp",
+        reads: &[],
+        writes: &["eax", "ecx", "edx"],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "rdpmc",
+        reads: &["ecx"],
+        writes: &["eax", "edx"],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "rdrand",
+        reads: &[],
+        writes: &["cf"],
+        explicit: true,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "rdseed",
+        reads: &[],
+        writes: &["cf"],
+        explicit: true,
+        memory: false,
+        pure: false,
+    },
+    // An atomic store when an operand is in memory.
+    Intrinsic {
+        name: "xchg",
+        reads: &[],
+        writes: &[],
+        explicit: true,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "pause",
+        reads: &[],
+        writes: &[],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "lfence",
+        reads: &[],
+        writes: &[],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "mfence",
+        reads: &[],
+        writes: &[],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "sfence",
+        reads: &[],
+        writes: &[],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    Intrinsic {
+        name: "hlt",
+        reads: &[],
+        writes: &[],
+        explicit: false,
+        memory: false,
+        pure: false,
+    },
+    // System calls, the number is the first operand, see `middle::syscalls`
+    Intrinsic {
+        name: "syscall",
+        reads: &["rax", "rdi", "rsi", "rdx", "r10", "r8", "r9"],
+        writes: &["rax", "rcx", "r11"],
+        explicit: false,
+        memory: true,
+        pure: false,
+    },
+    Intrinsic {
+        name: "int80",
+        reads: &["eax", "ebx", "ecx", "edx", "esi", "edi", "ebp"],
+        writes: &["eax"],
+        explicit: false,
+        memory: true,
+        pure: false,
+    },
+];
+
+/// Returns the known intrinsic with mnemonic `name`.
+pub fn lookup(name: &str) -> Option<&'static Intrinsic> {
+    X86_INTRINSICS.iter().find(|i| i.name == name)
+}
+
+/// Returns true if the intrinsic `name` may access memory, through its
+/// explicit operands or not. Unknown intrinsics are assumed to access any
+/// memory.
+pub fn may_access_memory(name: &str) -> bool {
+    lookup(name).map_or(true, |i| i.explicit || i.memory)
+}
+
+/// Returns true if the intrinsic `name` is known to be pure. Unknown
+/// intrinsics are assumed to have side effects.
+pub fn is_pure(name: &str) -> bool {
+    lookup(name).map_or(false, |i| i.pure)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intrinsic_lookup() {
+        let cpuid = lookup("cpuid").unwrap();
+        assert_eq!(cpuid.reads, &["eax", "ecx"]);
+        assert_eq!(cpuid.writes.len(), 4);
+        assert!(is_pure("cpuid"));
+        assert!(!is_pure("rdtsc"));
+        assert!(!is_pure("ud2"));
+        assert!(!is_pure("xchg"));
+        assert!(lookup("ud2").is_none());
+        assert!(lookup("syscall").unwrap().memory);
+    }
+}
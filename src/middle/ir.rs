@@ -12,10 +12,13 @@
 use std::fmt;
 use std::borrow::Cow;
 
+use middle::intrinsics;
+use middle::wide::{self, U256};
+
 pub type Address = u64;
 
 /// Used to describe the width of an operand/operation in a SSA Node.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum WidthSpec {
     /// Takes whatever width the other operation takes
     Adaptive,
@@ -32,7 +35,7 @@ impl WidthSpec {
     }
 
     pub fn new_known(w: u16) -> WidthSpec {
-        assert!(&[0, 1, 2, 4, 8, 16, 32, 64, 128].contains(&w));
+        assert!(&[0, 1, 2, 4, 8, 16, 32, 64, 80, 128, 256, 512].contains(&w));
         WidthSpec::Known(w)
     }
 }
@@ -40,7 +43,7 @@ impl WidthSpec {
 impl From<u16> for WidthSpec {
     fn from(other: u16) -> WidthSpec {
         if other < u16::max_value() {
-            assert!(&[0, 1, 2, 4, 8, 16, 32, 64, 128].contains(&other));
+            assert!(&[0, 1, 2, 4, 8, 16, 32, 64, 80, 128, 256, 512].contains(&other));
             WidthSpec::Known(other)
         } else {
             WidthSpec::Unknown
@@ -100,14 +103,15 @@ pub enum MArity {
     Zero,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum MOpcode {
     OpAdd,
     OpAnd,
     OpCJmp,
     OpCall,
     OpConst(u64),
-    OpCustom(String),
+    // Instruction kept opaque, see `middle::intrinsics`
+    OpIntrinsic(String),
     OpDiv,
     OpEq,
     OpGt,
@@ -137,6 +141,20 @@ pub enum MOpcode {
     OpXor,
     // Zero Extend to width
     OpZeroExt(u16),
+    // Floating point arithmetic, operands and result have the same width
+    OpFAdd,
+    OpFSub,
+    OpFMul,
+    OpFDiv,
+    // Floating point compares
+    OpFEq,
+    OpFLt,
+    // Signed integer to float of width
+    OpIntToFloat(u16),
+    // Float to signed integer of width, rounding towards zero
+    OpFloatToInt(u16),
+    // Float to float of width
+    OpFloatConv(u16),
 }
 
 impl MOpcode {
@@ -156,6 +174,22 @@ impl MOpcode {
         self.info().0
     }
 
+    /// Returns true for operations on floating point values.
+    pub fn is_float(&self) -> bool {
+        match *self {
+            MOpcode::OpFAdd |
+            MOpcode::OpFSub |
+            MOpcode::OpFMul |
+            MOpcode::OpFDiv |
+            MOpcode::OpFEq |
+            MOpcode::OpFLt |
+            MOpcode::OpIntToFloat(_) |
+            MOpcode::OpFloatToInt(_) |
+            MOpcode::OpFloatConv(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_commutative(&self) -> bool {
         match *self {
             MOpcode::OpAdd |
@@ -163,7 +197,10 @@ impl MOpcode {
             MOpcode::OpAnd |
             MOpcode::OpOr |
             MOpcode::OpXor |
-            MOpcode::OpEq => true,
+            MOpcode::OpEq |
+            MOpcode::OpFAdd |
+            MOpcode::OpFMul |
+            MOpcode::OpFEq => true,
             _ => false,
         }
     }
@@ -175,6 +212,7 @@ impl MOpcode {
             MOpcode::OpCJmp |
             MOpcode::OpCall |
             MOpcode::OpITE => true,
+            MOpcode::OpIntrinsic(ref name) => !intrinsics::is_pure(name),
             _ => false,
         }
     }
@@ -197,7 +235,7 @@ impl MOpcode {
             MOpcode::OpCJmp => (Cow::from("OpJmpIf"), MArity::Binary),
             MOpcode::OpCall => (Cow::from("OpCall"), MArity::Unary),
             MOpcode::OpConst(c) => (Cow::from(format!("OpCost({})", c)), MArity::Zero),
-            MOpcode::OpCustom(ref s) => (Cow::from(format!("OpCustom({})", s)), MArity::Zero),
+            MOpcode::OpIntrinsic(ref s) => (Cow::from(format!("OpIntrinsic({})", s)), MArity::Zero),
             MOpcode::OpDiv => (Cow::from("OpDiv"), MArity::Binary),
             MOpcode::OpEq => (Cow::from("OpEq"), MArity::Binary),
             MOpcode::OpGt => (Cow::from("OpGt"), MArity::Binary),
@@ -222,6 +260,15 @@ impl MOpcode {
             MOpcode::OpSub => (Cow::from("OpSub"), MArity::Binary),
             MOpcode::OpXor => (Cow::from("OpXor"), MArity::Binary),
             MOpcode::OpZeroExt(_) => (Cow::from("OpZeroExt"), MArity::Unary),
+            MOpcode::OpFAdd => (Cow::from("OpFAdd"), MArity::Binary),
+            MOpcode::OpFSub => (Cow::from("OpFSub"), MArity::Binary),
+            MOpcode::OpFMul => (Cow::from("OpFMul"), MArity::Binary),
+            MOpcode::OpFDiv => (Cow::from("OpFDiv"), MArity::Binary),
+            MOpcode::OpFEq => (Cow::from("OpFEq"), MArity::Binary),
+            MOpcode::OpFLt => (Cow::from("OpFLt"), MArity::Binary),
+            MOpcode::OpIntToFloat(_) => (Cow::from("OpIntToFloat"), MArity::Unary),
+            MOpcode::OpFloatToInt(_) => (Cow::from("OpFloatToInt"), MArity::Unary),
+            MOpcode::OpFloatConv(_) => (Cow::from("OpFloatConv"), MArity::Unary),
         }
     }
 
@@ -232,7 +279,7 @@ impl MOpcode {
             MOpcode::OpCJmp => 2,
             MOpcode::OpCall => 3,
             MOpcode::OpConst(_) => 4,
-            MOpcode::OpCustom(_) => 5,
+            MOpcode::OpIntrinsic(_) => 5,
             MOpcode::OpDiv => 6,
             MOpcode::OpEq => 7,
             MOpcode::OpGt => 8,
@@ -257,45 +304,90 @@ impl MOpcode {
             MOpcode::OpSub => 27,
             MOpcode::OpXor => 28,
             MOpcode::OpZeroExt(_) => 29,
+            MOpcode::OpFAdd => 30,
+            MOpcode::OpFSub => 31,
+            MOpcode::OpFMul => 32,
+            MOpcode::OpFDiv => 33,
+            MOpcode::OpFEq => 34,
+            MOpcode::OpFLt => 35,
+            MOpcode::OpIntToFloat(_) => 36,
+            MOpcode::OpFloatToInt(_) => 37,
+            MOpcode::OpFloatConv(_) => 38,
         }
     }
 
     pub fn eval_binop(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        self.eval_binop_width(U256::from(lhs), U256::from(rhs), 64)
+            .map(|v| v.low_u128() as u64)
+    }
+
+    /// Evaluates the operation on `width` bits wide operands. Returns None if
+    /// it could not be evaluated, e.g. on division by zero or if `width` is
+    /// wider than 256 bits.
+    pub fn eval_binop_width(&self, lhs: U256, rhs: U256, width: u16) -> Option<U256> {
         use self::MOpcode::*;
-        use std::num::Wrapping;
 
-        let lhs = Wrapping(lhs);
-        let rhs = Wrapping(rhs);
-        Some(match self {
-            OpAdd => (lhs + rhs).0,
-            OpSub => (lhs - rhs).0,
-            OpMul => (lhs * rhs).0,
-            OpDiv => (lhs / rhs).0,
-            OpMod => (lhs % rhs).0,
-            OpAnd => (lhs & rhs).0,
-            OpOr => (lhs | rhs).0,
-            OpXor => (lhs ^ rhs).0,
-            OpEq => (lhs == rhs) as u64,
-            OpGt => (lhs > rhs) as u64,
-            OpLt => (lhs < rhs) as u64,
-            OpLsl => (lhs << (rhs.0 as usize)).0,
-            OpLsr => (lhs >> (rhs.0 as usize)).0,
-            OpRol => lhs.0.rotate_left(rhs.0 as u32),
-            OpRor => lhs.0.rotate_right(rhs.0 as u32),
+        if width == 0 || width > 256 {
+            return None;
+        }
+        let mask = width_mask(width);
+        let lhs = lhs & mask;
+        let rhs = rhs & mask;
+        let bool_value = |b: bool| U256::from(b as u64);
+        // Shift amounts are below `width` once out of range ones are handled.
+        let amount = rhs.low_u128() as u32;
+        let rotation = rhs.checked_rem(U256::from(width as u64))?.low_u128() as u32;
+        let res = match self {
+            OpAdd => lhs.wrapping_add(rhs),
+            OpSub => lhs.wrapping_sub(rhs),
+            OpMul => lhs.wrapping_mul(rhs),
+            OpDiv => lhs.checked_div(rhs)?,
+            OpMod => lhs.checked_rem(rhs)?,
+            OpAnd => lhs & rhs,
+            OpOr => lhs | rhs,
+            OpXor => lhs ^ rhs,
+            OpEq => bool_value(lhs == rhs),
+            OpGt => bool_value(lhs > rhs),
+            OpLt => bool_value(lhs < rhs),
+            // Bits shifted out of the operand are lost
+            OpLsl if rhs >= U256::from(width as u64) => U256::zero(),
+            OpLsr if rhs >= U256::from(width as u64) => U256::zero(),
+            OpLsl => lhs << amount,
+            OpLsr => lhs >> amount,
+            OpRol if rotation == 0 => lhs,
+            OpRor if rotation == 0 => lhs,
+            OpRol => (lhs << rotation) | (lhs >> (width as u32 - rotation)),
+            OpRor => (lhs >> rotation) | (lhs << (width as u32 - rotation)),
             _ => return None,
-        })
+        };
+        Some(res & mask)
     }
 
     pub fn eval_unop(&self, val: u64) -> Option<u64> {
+        self.eval_unop_width(U256::from(val), 64).map(|v| v.low_u128() as u64)
+    }
+
+    /// Evaluates the operation on a `width` bits wide operand, see
+    /// `eval_binop_width`.
+    pub fn eval_unop_width(&self, val: U256, width: u16) -> Option<U256> {
         use self::MOpcode::*;
 
+        if width == 0 || width > 256 {
+            return None;
+        }
         Some(match self {
-            OpNot => !val,
+            OpNot => !val & width_mask(width),
             _ => return None,
         })
     }
 }
 
+/// Returns the mask of the low `width` bits, `width` must not be wider than
+/// 256 bits.
+pub fn width_mask(width: u16) -> U256 {
+    wide::width_mask(width)
+}
+
 impl fmt::Display for MOpcode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.to_string())
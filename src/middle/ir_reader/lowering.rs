@@ -333,6 +333,12 @@ fn lower_infix_op(siop: sast::InfixOp) -> IrOpcode {
         sast::InfixOp::Lt => IrOpcode::OpLt,
         sast::InfixOp::Lsl => IrOpcode::OpLsl,
         sast::InfixOp::Lsr => IrOpcode::OpLsr,
+        sast::InfixOp::FAdd => IrOpcode::OpFAdd,
+        sast::InfixOp::FSub => IrOpcode::OpFSub,
+        sast::InfixOp::FMul => IrOpcode::OpFMul,
+        sast::InfixOp::FDiv => IrOpcode::OpFDiv,
+        sast::InfixOp::FEq => IrOpcode::OpFEq,
+        sast::InfixOp::FLt => IrOpcode::OpFLt,
     }
 }
 
@@ -347,6 +353,9 @@ fn lower_resize_op(srst: sast::ResizeType, sws: sast::WidthSpec) -> IrOpcode {
         sast::ResizeType::Narrow => IrOpcode::OpNarrow(sws.0),
         sast::ResizeType::SignExt => IrOpcode::OpSignExt(sws.0),
         sast::ResizeType::ZeroExt => IrOpcode::OpZeroExt(sws.0),
+        sast::ResizeType::IntToFloat => IrOpcode::OpIntToFloat(sws.0),
+        sast::ResizeType::FloatToInt => IrOpcode::OpFloatToInt(sws.0),
+        sast::ResizeType::FloatConv => IrOpcode::OpFloatConv(sws.0),
     }
 }
 
@@ -71,6 +71,9 @@ pub enum ResizeType {
     Narrow,
     SignExt,
     ZeroExt,
+    IntToFloat,
+    FloatToInt,
+    FloatConv,
 }
 
 #[derive(Debug)]
@@ -99,6 +102,12 @@ pub enum InfixOp {
     Lt,
     Lsl,
     Lsr,
+    FAdd,
+    FSub,
+    FMul,
+    FDiv,
+    FEq,
+    FLt,
 }
 
 #[derive(Debug)]
@@ -151,6 +151,18 @@ fn roundtrip_basic_ssa() {
     roundtrip("sym.foo".to_owned(), SSA_TXT);
 }
 
+#[test]
+fn roundtrip_float_ssa() {
+    let ssa_txt = SSA_TXT
+        .replace(
+            "%35: $Unknown64(*?) = #x1 + %14;",
+            "%35: $Unknown64(*?) = IntToFloat64(%14);\n        \
+             [@0x000610.0002] %36: $Unknown64(*?) = %35 *. %35;",
+        )
+        .replace("$rax = %35;", "$rax = %36;");
+    roundtrip("sym.foo".to_owned(), &ssa_txt);
+}
+
 #[test]
 fn roundtrip_bin1_main() {
     roundtrip_file("main".to_owned(), "test_files/bin1_main_ssa");
@@ -215,6 +215,12 @@ impl<'a, O: Write> IRWriter<'a, O> {
             OpLt => self.emit_binop("<", operands),
             OpLsl => self.emit_binop("<<", operands),
             OpLsr => self.emit_binop(">>", operands),
+            OpFAdd => self.emit_binop("+.", operands),
+            OpFSub => self.emit_binop("-.", operands),
+            OpFMul => self.emit_binop("*.", operands),
+            OpFDiv => self.emit_binop("/.", operands),
+            OpFEq => self.emit_binop("==.", operands),
+            OpFLt => self.emit_binop("<.", operands),
             OpNot => {
                 write!(self.output, "!")?;
                 self.emit_opt_operand(operands.get(0).cloned())?;
@@ -256,6 +262,30 @@ impl<'a, O: Write> IRWriter<'a, O> {
                 write!(self.output, ")")?;
                 Ok(())
             }
+            OpIntToFloat(wd) => {
+                write!(self.output, "IntToFloat{}(", wd)?;
+                self.emit_opt_operand(operands.get(0).cloned())?;
+                write!(self.output, ")")?;
+                Ok(())
+            }
+            OpFloatToInt(wd) => {
+                write!(self.output, "FloatToInt{}(", wd)?;
+                self.emit_opt_operand(operands.get(0).cloned())?;
+                write!(self.output, ")")?;
+                Ok(())
+            }
+            OpFloatConv(wd) => {
+                write!(self.output, "FloatConv{}(", wd)?;
+                self.emit_opt_operand(operands.get(0).cloned())?;
+                write!(self.output, ")")?;
+                Ok(())
+            }
+            OpIntrinsic(ref name) => {
+                write!(self.output, "{}(", name)?;
+                self.emit_operand_list(operands)?;
+                write!(self.output, ")")?;
+                Ok(())
+            }
             _ => {
                 radeco_warn!("unknown opcode: {:?}", opcode);
                 write!(self.output, "{}(", opcode)?;
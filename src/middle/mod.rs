@@ -22,6 +22,11 @@ pub mod ssa {
 
 #[macro_use] pub mod dot;
 pub mod ir;
+pub mod wide;
+pub mod intrinsics;
+pub mod syscalls;
+pub mod prototypes;
+pub mod c_header;
 pub mod ir_reader;
 pub mod dce;
 #[macro_use] pub mod ir_writer;
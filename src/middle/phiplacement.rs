@@ -23,6 +23,8 @@ use middle::regfile::{RegisterId, SubRegisterFile};
 
 pub type VarId = u64;
 
+const FALSE_EDGE: u8 = 0;
+const TRUE_EDGE: u8 = 1;
 const UNCOND_EDGE: u8 = 2;
 
 pub struct PhiPlacer<'a, T> 
@@ -383,6 +385,55 @@ impl<'a, T> PhiPlacer<'a, T>
         self.op_use(&op_node, 0, selector);
     }
 
+    // Function to add an indirect control flow transfer through a resolved jump
+    // table. `index` is compared against every case value in turn, each compare
+    // ending a block whose true edge leads to the target of that case. The last
+    // case needs no compare, it is the false edge of the last compare.
+    pub fn add_jump_table(&mut self, index: &T::ValueRef, targets: &[u64], current_addr: &mut MAddress) {
+        let (last, cases) = match targets.split_last() {
+            Some(split) => split,
+            None => {
+                radeco_warn!("Empty jump table @ {:?}", current_addr);
+                return;
+            }
+        };
+
+        // Case values are 64 bit constants.
+        let index = if self.operand_width(index) < 64 {
+            let ext = self.add_op(&MOpcode::OpZeroExt(64),
+                                  current_addr,
+                                  ValueInfo::new_scalar(ir::WidthSpec::Known(64)));
+            self.op_use(&ext, 0, index);
+            ext
+        } else {
+            *index
+        };
+
+        for (value, target) in cases.iter().enumerate() {
+            let value_node = self.add_const(current_addr, value as u64, None);
+            let cmp_node = self.add_op(&MOpcode::OpEq,
+                                       current_addr,
+                                       ValueInfo::new_scalar(ir::WidthSpec::Known(1)));
+            self.op_use(&cmp_node, 0, &index);
+            self.op_use(&cmp_node, 1, &value_node);
+            let op_node = self.add_op(&MOpcode::OpITE,
+                                      current_addr,
+                                      ValueInfo::new_scalar(ir::WidthSpec::Known(1)));
+            self.op_use(&op_node, 0, &cmp_node);
+
+            self.add_block(MAddress::new(*target, 0), Some(*current_addr), Some(TRUE_EDGE));
+            if value + 1 == cases.len() {
+                self.add_block(MAddress::new(*last, 0), Some(*current_addr), Some(FALSE_EDGE));
+            } else {
+                // The next compare starts a new block.
+                self.add_block(*current_addr, Some(*current_addr), Some(FALSE_EDGE));
+            }
+        }
+        if cases.is_empty() {
+            self.add_block(MAddress::new(*last, 0), Some(*current_addr), Some(UNCOND_EDGE));
+        }
+    }
+
     pub fn add_return(&mut self, current_addr: MAddress, edge_type: u8) {
         let source_block = self.block_of(current_addr).unwrap_or_else(|| {
             radeco_err!("Block not found @ {:?}", current_addr);
@@ -392,6 +443,13 @@ impl<'a, T> PhiPlacer<'a, T>
         self.ssa.insert_control_edge(source_block, exit_node, edge_type);
     }
 
+    // Ends the block at a call which never returns. The block is linked to the
+    // exit, as every block must reach it, but not to the next instruction.
+    pub fn add_noreturn(&mut self, current_addr: MAddress) {
+        radeco_trace!("phip_add_noreturn|{}", current_addr);
+        self.add_return(current_addr, UNCOND_EDGE);
+    }
+
     pub fn add_edge(&mut self, source: MAddress, target: MAddress, cftype: u8) {
         let source_block = self.block_of(source).unwrap_or_else(|| {
             radeco_err!("Block not found @ {:?}", source);
@@ -690,7 +748,7 @@ impl<'a, T> PhiPlacer<'a, T>
         let info = match self.regfile.get_subregister(var) {
             Some(reg) => reg,
             None => {
-                radeco_warn!("Float operations are not supported (yet)");
+                radeco_warn!("Read of unknown register {}", var);
                 let vi = ValueInfo::new_scalar(ir::WidthSpec::Unknown);
                 let node = self.add_undefined(*address, vi);
                 return node;
@@ -735,7 +793,7 @@ impl<'a, T> PhiPlacer<'a, T>
         let info = match self.regfile.get_subregister(var) {
             Some(reg) => reg,
             None => {
-                radeco_warn!("Nowadays, radeco could not support float operation");
+                radeco_warn!("Write to unknown register {} is dropped", var);
                 return;
             }
         };
@@ -772,6 +830,11 @@ impl<'a, T> PhiPlacer<'a, T>
             return;
         }
 
+        if width > 64 {
+            self.write_wide_subregister(address, id, info.shift, info.width, value);
+            return;
+        }
+
         // BUG: If width is not 64, every operation with OpConst will make
         // unbalanced width.
         let opcode = MOpcode::OpZeroExt(width as u16);
@@ -817,10 +880,86 @@ impl<'a, T> PhiPlacer<'a, T>
         self.propagate_reginfo(&value);
     }
 
+    // Writes `width` bits at `shift` of the register `id` wider than 64 bits,
+    // e.g. a vector register. Masks for such registers do not fit into
+    // constants, so the bits around the written ones are kept by shifting.
+    fn write_wide_subregister(&mut self,
+                              address: &mut MAddress,
+                              id: u64,
+                              shift: u64,
+                              width: u64,
+                              mut value: T::ValueRef) {
+        let vt = self.variable_types[id as usize];
+        let reg_width = vt.width().get_width().unwrap_or(64) as u64;
+
+        if (self.operand_width(&value) as u64) < reg_width {
+            let ext = self.add_op(&MOpcode::OpZeroExt(reg_width as u16), address, vt);
+            self.op_use(&ext, 0, &value);
+            value = ext;
+        }
+        if shift > 0 {
+            value = self.shift_value(address, MOpcode::OpLsl, value, shift, vt);
+        }
+
+        let old = self.read_variable(address, id);
+        let end = shift + width;
+        if end < reg_width {
+            // Keep the bits above the written ones.
+            let high = self.shift_value(address, MOpcode::OpLsr, old, end, vt);
+            let high = self.shift_value(address, MOpcode::OpLsl, high, end, vt);
+            value = self.combine(address, value, high, vt);
+        }
+        if shift > 0 {
+            // Keep the bits below the written ones.
+            let low = self.shift_value(address, MOpcode::OpLsl, old, reg_width - shift, vt);
+            let low = self.shift_value(address, MOpcode::OpLsr, low, reg_width - shift, vt);
+            value = self.combine(address, value, low, vt);
+        }
+
+        self.write_variable(*address, id, value);
+        self.propagate_reginfo(&value);
+    }
+
+    fn shift_value(&mut self,
+                   address: &mut MAddress,
+                   opcode: MOpcode,
+                   value: T::ValueRef,
+                   amount: u64,
+                   vt: ValueInfo)
+                   -> T::ValueRef {
+        // Constants are at most 64 bits wide.
+        let const_node = self.add_const(address, amount, None);
+        let amount_node = self.add_op(&MOpcode::OpZeroExt(vt.width().get_width().unwrap_or(64)),
+                                      address,
+                                      vt);
+        self.op_use(&amount_node, 0, &const_node);
+        let op_node = self.add_op(&opcode, address, vt);
+        self.op_use(&op_node, 0, &value);
+        self.op_use(&op_node, 1, &amount_node);
+        op_node
+    }
+
+    fn combine(&mut self,
+               address: &mut MAddress,
+               a: T::ValueRef,
+               b: T::ValueRef,
+               vt: ValueInfo)
+               -> T::ValueRef {
+        let op_or = self.add_op(&MOpcode::OpOr, address, vt);
+        self.op_use(&op_or, 0, &a);
+        self.op_use(&op_or, 1, &b);
+        op_or
+    }
+
     pub fn op_use(&mut self, op: &T::ValueRef, index: u8, arg: &T::ValueRef) {
         self.ssa.op_use(*op, index, *arg)
     }
 
+    /// Returns the SSA under construction.
+    pub fn ssa(&self) -> &T {
+        &*self.ssa
+    }
+
     pub fn operand_width(&self, node: &T::ValueRef) -> u16 {
         match self.ssa.node_data(*node) {
             Ok(x) => x.vt.width().get_width().unwrap_or(64),
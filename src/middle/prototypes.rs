@@ -0,0 +1,175 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Function prototypes.
+//!
+//! Prototypes of common libc and POSIX functions are bundled with radeco, as
+//! the C declarations of `prototypes_libc.h`. They are applied to the imports
+//! of a module when it is loaded: the argument and return bindings of the
+//! import follow its prototype, the call sites are then fixed to pass only
+//! the arguments of the prototype, see `analysis::functions::fix_ssa_opcalls`.
+//!
+//! The declarations are read by the C header parser, see `middle::c_header`,
+//! which also reads the prototypes of other functions. Typedefs of the
+//! standard headers are resolved, assuming a LP64 data model.
+
+use middle::c_header;
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A C type.
+pub struct CType {
+    /// Base type without qualifiers, e.g. `unsigned long` or `struct stat`.
+    pub base: String,
+    /// Number of pointer indirections.
+    pub pointers: u8,
+}
+
+impl CType {
+    pub fn new(base: &str, pointers: u8) -> CType {
+        CType {
+            base: base.to_owned(),
+            pointers: pointers,
+        }
+    }
+
+    pub fn is_void(&self) -> bool {
+        self.base == "void" && self.pointers == 0
+    }
+}
+
+impl fmt::Display for CType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.pointers == 0 {
+            write!(f, "{}", self.base)
+        } else {
+            write!(f, "{} {}", self.base, "*".repeat(self.pointers as usize))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Prototype of a function.
+pub struct Prototype {
+    pub name: String,
+    pub ret: CType,
+    /// Types of the fixed arguments.
+    pub args: Vec<CType>,
+    /// Takes a variable number of arguments after `args`.
+    pub variadic: bool,
+    /// Declared without parameters, as `f()`: the arguments are not known.
+    pub unspecified: bool,
+    /// Never returns.
+    pub noreturn: bool,
+}
+
+impl Prototype {
+    /// Whether the function takes exactly `args`, i.e. it is neither
+    /// variadic nor declared without parameters.
+    pub fn fixed_args(&self) -> bool {
+        !self.variadic && !self.unspecified
+    }
+}
+
+// Typedefs of the standard headers, with the type they stand for. Opaque
+// types are taken as `void`.
+static TYPEDEFS: &[(&str, &str, u8)] = &[
+    ("size_t", "unsigned long", 0),
+    ("ssize_t", "long", 0),
+    ("off_t", "long", 0),
+    ("ptrdiff_t", "long", 0),
+    ("intptr_t", "long", 0),
+    ("uintptr_t", "unsigned long", 0),
+    ("time_t", "long", 0),
+    ("clock_t", "long", 0),
+    ("pid_t", "int", 0),
+    ("uid_t", "unsigned int", 0),
+    ("gid_t", "unsigned int", 0),
+    ("mode_t", "unsigned int", 0),
+    ("useconds_t", "unsigned int", 0),
+    ("socklen_t", "unsigned int", 0),
+    ("wchar_t", "int", 0),
+    ("wint_t", "unsigned int", 0),
+    ("int8_t", "char", 0),
+    ("uint8_t", "unsigned char", 0),
+    ("int16_t", "short", 0),
+    ("uint16_t", "unsigned short", 0),
+    ("int32_t", "int", 0),
+    ("uint32_t", "unsigned int", 0),
+    ("int64_t", "long", 0),
+    ("uint64_t", "unsigned long", 0),
+    ("pthread_t", "unsigned long", 0),
+    ("va_list", "void", 1),
+    ("jmp_buf", "void", 1),
+    ("sigjmp_buf", "void", 1),
+    ("FILE", "void", 0),
+    ("DIR", "void", 0),
+    ("sigset_t", "void", 0),
+    ("pthread_attr_t", "void", 0),
+    ("pthread_mutex_t", "void", 0),
+    ("pthread_mutexattr_t", "void", 0),
+    ("pthread_cond_t", "void", 0),
+];
+
+lazy_static! {
+    static ref LIBC: HashMap<String, Prototype> = {
+        c_header::parse_header(include_str!("prototypes_libc.h"))
+            .expect("invalid bundled prototypes")
+            .prototypes
+            .into_iter()
+            .map(|p| (p.name.clone(), p))
+            .collect()
+    };
+}
+
+/// Strips the prefixes radare2 gives to symbol names, e.g. `sym.imp.` in
+/// `sym.imp.printf`.
+pub fn symbol_name(name: &str) -> &str {
+    ["sym.imp.", "sym.", "imp.", "reloc."]
+        .iter()
+        .find(|prefix| name.starts_with(*prefix))
+        .map_or(name, |prefix| &name[prefix.len()..])
+}
+
+/// Returns the bundled prototype of the libc function named `name`. Names
+/// are taken as radare2 gives them, see `symbol_name`.
+pub fn lookup(name: &str) -> Option<&'static Prototype> {
+    LIBC.get(symbol_name(name))
+}
+
+/// Returns the type a typedef of the standard headers stands for.
+pub fn typedef(name: &str) -> Option<CType> {
+    TYPEDEFS.iter()
+        .find(|&&(typedef, _, _)| typedef == name)
+        .map(|&(_, ty, pointers)| CType::new(ty, pointers))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn libc_lookup() {
+        // Every declaration of the database is understood.
+        let decls = include_str!("prototypes_libc.h")
+            .lines()
+            .filter(|l| l.ends_with(';') && !l.starts_with("//"))
+            .count();
+        assert_eq!(LIBC.len(), decls);
+        assert_eq!(lookup("sym.imp.strlen").unwrap().args.len(), 1);
+        assert_eq!(lookup("memcpy").unwrap().ret, CType::new("void", 1));
+        assert!(lookup("exit").unwrap().noreturn);
+        assert!(lookup("sym.imp.open").unwrap().variadic);
+        assert_eq!(lookup("readdir").unwrap().ret.to_string(), "struct dirent *");
+        assert_eq!(lookup("readdir").unwrap().args, vec![CType::new("void", 1)]);
+        assert_eq!(lookup("snprintf").unwrap().args,
+                   vec![CType::new("char", 1), CType::new("unsigned long", 0), CType::new("char", 1)]);
+        assert!(lookup("fcn.00401000").is_none());
+    }
+}
@@ -0,0 +1,310 @@
+// Prototypes of common libc and POSIX functions, see `middle::prototypes`.
+//
+// Parameters are unnamed, function pointers are written as `void *`. `_Noreturn` marks functions which never return.
+
+// stdlib.h
+void *malloc(size_t);
+void *calloc(size_t, size_t);
+void *realloc(void *, size_t);
+void *reallocarray(void *, size_t, size_t);
+void free(void *);
+int posix_memalign(void **, size_t, size_t);
+void *aligned_alloc(size_t, size_t);
+_Noreturn void exit(int);
+_Noreturn void _exit(int);
+_Noreturn void _Exit(int);
+_Noreturn void quick_exit(int);
+_Noreturn void abort(void);
+int atexit(void *);
+int atoi(const char *);
+long atol(const char *);
+long long atoll(const char *);
+double atof(const char *);
+long strtol(const char *, char **, int);
+long long strtoll(const char *, char **, int);
+unsigned long strtoul(const char *, char **, int);
+unsigned long long strtoull(const char *, char **, int);
+double strtod(const char *, char **);
+float strtof(const char *, char **);
+int abs(int);
+long labs(long);
+int rand(void);
+void srand(unsigned int);
+long random(void);
+void srandom(unsigned int);
+char *getenv(const char *);
+int setenv(const char *, const char *, int);
+int unsetenv(const char *);
+int putenv(char *);
+int system(const char *);
+void qsort(void *, size_t, size_t, void *);
+void *bsearch(const void *, const void *, size_t, size_t, void *);
+char *realpath(const char *, char *);
+int mkstemp(char *);
+
+// string.h
+void *memcpy(void *, const void *, size_t);
+void *memmove(void *, const void *, size_t);
+void *memset(void *, int, size_t);
+int memcmp(const void *, const void *, size_t);
+void *memchr(const void *, int, size_t);
+void *memrchr(const void *, int, size_t);
+void *memmem(const void *, size_t, const void *, size_t);
+size_t strlen(const char *);
+size_t strnlen(const char *, size_t);
+char *strcpy(char *, const char *);
+char *strncpy(char *, const char *, size_t);
+char *stpcpy(char *, const char *);
+char *strcat(char *, const char *);
+char *strncat(char *, const char *, size_t);
+int strcmp(const char *, const char *);
+int strncmp(const char *, const char *, size_t);
+int strcasecmp(const char *, const char *);
+int strncasecmp(const char *, const char *, size_t);
+int strcoll(const char *, const char *);
+char *strchr(const char *, int);
+char *strrchr(const char *, int);
+char *strstr(const char *, const char *);
+char *strcasestr(const char *, const char *);
+char *strpbrk(const char *, const char *);
+size_t strspn(const char *, const char *);
+size_t strcspn(const char *, const char *);
+char *strtok(char *, const char *);
+char *strtok_r(char *, const char *, char **);
+char *strsep(char **, const char *);
+char *strdup(const char *);
+char *strndup(const char *, size_t);
+char *strerror(int);
+size_t strlcpy(char *, const char *, size_t);
+size_t strlcat(char *, const char *, size_t);
+
+// ctype.h
+int isalnum(int);
+int isalpha(int);
+int isdigit(int);
+int isxdigit(int);
+int isspace(int);
+int isupper(int);
+int islower(int);
+int isprint(int);
+int ispunct(int);
+int toupper(int);
+int tolower(int);
+
+// stdio.h
+int printf(const char *, ...);
+int fprintf(FILE *, const char *, ...);
+int sprintf(char *, const char *, ...);
+int snprintf(char *, size_t, const char *, ...);
+int dprintf(int, const char *, ...);
+int asprintf(char **, const char *, ...);
+int vprintf(const char *, va_list);
+int vfprintf(FILE *, const char *, va_list);
+int vsprintf(char *, const char *, va_list);
+int vsnprintf(char *, size_t, const char *, va_list);
+int __printf_chk(int, const char *, ...);
+int __fprintf_chk(FILE *, int, const char *, ...);
+int __sprintf_chk(char *, int, size_t, const char *, ...);
+int __snprintf_chk(char *, size_t, int, size_t, const char *, ...);
+int scanf(const char *, ...);
+int fscanf(FILE *, const char *, ...);
+int sscanf(const char *, const char *, ...);
+int __isoc99_scanf(const char *, ...);
+int __isoc99_fscanf(FILE *, const char *, ...);
+int __isoc99_sscanf(const char *, const char *, ...);
+int puts(const char *);
+int fputs(const char *, FILE *);
+int putchar(int);
+int fputc(int, FILE *);
+int putc(int, FILE *);
+int getchar(void);
+int fgetc(FILE *);
+int getc(FILE *);
+int ungetc(int, FILE *);
+char *fgets(char *, int, FILE *);
+char *gets(char *);
+ssize_t getline(char **, size_t *, FILE *);
+ssize_t getdelim(char **, size_t *, int, FILE *);
+FILE *fopen(const char *, const char *);
+FILE *fdopen(int, const char *);
+FILE *freopen(const char *, const char *, FILE *);
+FILE *popen(const char *, const char *);
+int pclose(FILE *);
+int fclose(FILE *);
+int fflush(FILE *);
+size_t fread(void *, size_t, size_t, FILE *);
+size_t fwrite(const void *, size_t, size_t, FILE *);
+int fseek(FILE *, long, int);
+long ftell(FILE *);
+void rewind(FILE *);
+int feof(FILE *);
+int ferror(FILE *);
+int fileno(FILE *);
+void setbuf(FILE *, char *);
+int setvbuf(FILE *, char *, int, size_t);
+void perror(const char *);
+int remove(const char *);
+int rename(const char *, const char *);
+FILE *tmpfile(void);
+
+// unistd.h, fcntl.h
+int open(const char *, int, ...);
+int openat(int, const char *, int, ...);
+int creat(const char *, mode_t);
+int close(int);
+ssize_t read(int, void *, size_t);
+ssize_t write(int, const void *, size_t);
+ssize_t pread(int, void *, size_t, off_t);
+ssize_t pwrite(int, const void *, size_t, off_t);
+off_t lseek(int, off_t, int);
+int dup(int);
+int dup2(int, int);
+int pipe(int *);
+int fcntl(int, int, ...);
+int ioctl(int, unsigned long, ...);
+int access(const char *, int);
+int unlink(const char *);
+int rmdir(const char *);
+int mkdir(const char *, mode_t);
+int chdir(const char *);
+char *getcwd(char *, size_t);
+int chmod(const char *, mode_t);
+int chown(const char *, uid_t, gid_t);
+ssize_t readlink(const char *, char *, size_t);
+int symlink(const char *, const char *);
+int link(const char *, const char *);
+int isatty(int);
+unsigned int sleep(unsigned int);
+int usleep(useconds_t);
+unsigned int alarm(unsigned int);
+pid_t fork(void);
+pid_t vfork(void);
+int execv(const char *, char **);
+int execvp(const char *, char **);
+int execve(const char *, char **, char **);
+int execl(const char *, const char *, ...);
+int execlp(const char *, const char *, ...);
+pid_t getpid(void);
+pid_t getppid(void);
+uid_t getuid(void);
+uid_t geteuid(void);
+gid_t getgid(void);
+gid_t getegid(void);
+int setuid(uid_t);
+int setgid(gid_t);
+int getopt(int, char **, const char *);
+int getopt_long(int, char **, const char *, const struct option *, int *);
+long sysconf(int);
+
+// sys/wait.h, signal.h
+pid_t wait(int *);
+pid_t waitpid(pid_t, int *, int);
+int kill(pid_t, int);
+int raise(int);
+void *signal(int, void *);
+int sigaction(int, const struct sigaction *, struct sigaction *);
+int sigemptyset(sigset_t *);
+int sigaddset(sigset_t *, int);
+int sigprocmask(int, const sigset_t *, sigset_t *);
+
+// sys/mman.h
+void *mmap(void *, size_t, int, int, int, off_t);
+int munmap(void *, size_t);
+int mprotect(void *, size_t, int);
+
+// sys/socket.h, arpa/inet.h
+int socket(int, int, int);
+int bind(int, const struct sockaddr *, socklen_t);
+int listen(int, int);
+int accept(int, struct sockaddr *, socklen_t *);
+int connect(int, const struct sockaddr *, socklen_t);
+ssize_t send(int, const void *, size_t, int);
+ssize_t recv(int, void *, size_t, int);
+ssize_t sendto(int, const void *, size_t, int, const struct sockaddr *, socklen_t);
+ssize_t recvfrom(int, void *, size_t, int, struct sockaddr *, socklen_t *);
+int setsockopt(int, int, int, const void *, socklen_t);
+int getsockopt(int, int, int, void *, socklen_t *);
+int shutdown(int, int);
+uint16_t htons(uint16_t);
+uint16_t ntohs(uint16_t);
+uint32_t htonl(uint32_t);
+uint32_t ntohl(uint32_t);
+int inet_pton(int, const char *, void *);
+const char *inet_ntop(int, const void *, char *, socklen_t);
+int getaddrinfo(const char *, const char *, const struct addrinfo *, struct addrinfo **);
+void freeaddrinfo(struct addrinfo *);
+
+// dirent.h, sys/stat.h
+DIR *opendir(const char *);
+struct dirent *readdir(DIR *);
+int closedir(DIR *);
+int stat(const char *, struct stat *);
+int fstat(int, struct stat *);
+int lstat(const char *, struct stat *);
+mode_t umask(mode_t);
+
+// time.h
+time_t time(time_t *);
+clock_t clock(void);
+struct tm *localtime(const time_t *);
+struct tm *gmtime(const time_t *);
+time_t mktime(struct tm *);
+size_t strftime(char *, size_t, const char *, const struct tm *);
+char *ctime(const time_t *);
+int gettimeofday(struct timeval *, void *);
+int clock_gettime(int, struct timespec *);
+int nanosleep(const struct timespec *, struct timespec *);
+
+// setjmp.h, err.h, assert.h
+int setjmp(jmp_buf);
+int _setjmp(jmp_buf);
+int sigsetjmp(sigjmp_buf, int);
+_Noreturn void longjmp(jmp_buf, int);
+_Noreturn void _longjmp(jmp_buf, int);
+_Noreturn void siglongjmp(sigjmp_buf, int);
+_Noreturn void err(int, const char *, ...);
+_Noreturn void errx(int, const char *, ...);
+_Noreturn void verr(int, const char *, va_list);
+_Noreturn void verrx(int, const char *, va_list);
+void warn(const char *, ...);
+void warnx(const char *, ...);
+_Noreturn void __assert_fail(const char *, const char *, unsigned int, const char *);
+_Noreturn void __assert_perror_fail(int, const char *, unsigned int, const char *);
+_Noreturn void __assert_rtn(const char *, const char *, int, const char *);
+_Noreturn void __stack_chk_fail(void);
+_Noreturn void __chk_fail(void);
+_Noreturn void __fortify_fail(const char *);
+_Noreturn void __longjmp_chk(jmp_buf, int);
+int *__errno_location(void);
+
+// pthread.h
+int pthread_create(pthread_t *, const pthread_attr_t *, void *, void *);
+int pthread_join(pthread_t, void **);
+int pthread_detach(pthread_t);
+pthread_t pthread_self(void);
+_Noreturn void pthread_exit(void *);
+int pthread_mutex_init(pthread_mutex_t *, const pthread_mutexattr_t *);
+int pthread_mutex_destroy(pthread_mutex_t *);
+int pthread_mutex_lock(pthread_mutex_t *);
+int pthread_mutex_trylock(pthread_mutex_t *);
+int pthread_mutex_unlock(pthread_mutex_t *);
+int pthread_cond_wait(pthread_cond_t *, pthread_mutex_t *);
+int pthread_cond_signal(pthread_cond_t *);
+int pthread_cond_broadcast(pthread_cond_t *);
+
+// dlfcn.h, locale.h, glibc startup
+void *dlopen(const char *, int);
+void *dlsym(void *, const char *);
+int dlclose(void *);
+char *dlerror(void);
+char *setlocale(int, const char *);
+_Noreturn int __libc_start_main(void *, int, char **, void *, void *, void *, void *);
+int __cxa_atexit(void *, void *, void *);
+
+// C++ runtime, unwinder
+_Noreturn void __cxa_throw(void *, void *, void *);
+_Noreturn void __cxa_rethrow(void);
+_Noreturn void __cxa_bad_cast(void);
+_Noreturn void __cxa_bad_typeid(void);
+_Noreturn void __cxa_call_unexpected(void *);
+_Noreturn void _Unwind_Resume(void *);
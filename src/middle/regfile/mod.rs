@@ -82,31 +82,74 @@ impl Iterator for RegisterIter {
 
 impl SubRegisterFile {
     /// Creates a new SubRegisterFile based on a provided register profile.
+    ///
+    /// Floating point and vector registers ("fpu") are laid out separately
+    /// from the others by r2, so they are added as whole registers of their
+    /// own after all other registers. Pairs of 64 bit halves, like `xmm0l`
+    /// and `xmm0h`, are joined to a single 128 bit register, e.g. `xmm0`.
+    /// With AVX, `xmm0` and the upper lane `ymm0h` are joined to `ymm0`.
     pub fn new(reg_info: &LRegInfo) -> SubRegisterFile {
         let mut aliases: HashMap<String, String> = HashMap::new();
         for reg in &reg_info.alias_info {
             aliases.insert(reg.role_str.clone(), reg.reg.clone());
         }
 
-        let mut slices = HashMap::new();
-        let mut events: Vec<SubRegister> = Vec::new();
+        let mut events: Vec<(String, SubRegister)> = Vec::new();
+        let mut fpu_events: Vec<(String, SubRegister)> = Vec::new();
         let mut types: HashMap<String, String> = HashMap::new();
         for (i, reg) in reg_info.reg_info.iter().enumerate() {
             types.insert(reg.name.clone(), reg.type_str.clone());
-            if reg.type_str == "fpu" {
-                continue;
-            } // st7 from "fpu" overlaps with zf from "gpr" (r2 bug?)
-            if reg.name.ends_with("flags") {
+            // The flags are lifted as registers of their own, so the status
+            // register containing them is left out.
+            if is_status_register(&reg.name, &reg.type_str, reg.size as u64) {
                 continue;
-            } // HARDCODED x86
-            events.push(SubRegister::new(
-                i as u64,
-                reg.offset as u64,
-                reg.size as u64,
-            ));
+            }
+            let event = (reg.name.clone(),
+                         SubRegister::new(i as u64, reg.offset as u64, reg.size as u64));
+            if reg.type_str == "fpu" {
+                fpu_events.push(event);
+            } else {
+                events.push(event);
+            }
+        }
+
+        // Join the halves of vector registers, the SSE ones first as they
+        // are the low halves of the AVX ones.
+        let mut halves = Vec::new();
+        for _ in 0..2 {
+            let joined = vector_halves(&fpu_events);
+            for &(ref name, ref whole, _, _) in &joined {
+                fpu_events.push((name.clone(), *whole));
+                types.insert(name.clone(), "fpu".to_owned());
+            }
+            halves.extend(joined);
+        }
+
+        let mut regfile = SubRegisterFile {
+            whole_registers: Vec::new(),
+            named_registers: HashMap::new(),
+            whole_names: Vec::new(),
+            alias_info: aliases,
+            type_info: types,
+        };
+        regfile.add_registers(events);
+        regfile.add_registers(fpu_events);
+
+        // The low half holds the low bits, independent of the offsets used by r2.
+        for (_, whole, low, high) in halves {
+            for &(ref half, shift) in &[(low, 0), (high, whole.width / 2)] {
+                if let Some(subreg) = regfile.named_registers.get_mut(half) {
+                    subreg.shift = shift;
+                }
+            }
         }
+        regfile
+    }
 
-        events.sort_by(|a, b| {
+    // Adds the registers described by `events` as whole registers and the
+    // registers contained in them as their subregisters.
+    fn add_registers(&mut self, mut events: Vec<(String, SubRegister)>) {
+        events.sort_by(|&(_, ref a), &(_, ref b)| {
             let o = a.shift.cmp(&b.shift);
             if let Ordering::Equal = o {
                 (b.width + b.shift).cmp(&(a.width + a.shift))
@@ -116,37 +159,29 @@ impl SubRegisterFile {
         });
 
         let mut current = SubRegister::new(0, 0, 0);
-        let mut whole: Vec<ValueInfo> = Vec::new();
-        let mut names: Vec<String> = Vec::new();
-        for &ev in &events {
-            let name = &reg_info.reg_info[ev.base as usize].name;
+        for (name, ev) in events {
             let cur_until = current.shift + current.width;
             if ev.shift >= cur_until {
                 current = ev;
 
-                radeco_trace!("regfile_mappings|{} -> {}", whole.len(), &name);
+                radeco_trace!("regfile_mappings|{} -> {}", self.whole_registers.len(), &name);
 
-                whole.push(ValueInfo::new_unresolved(ir::WidthSpec::from(
+                self.whole_registers.push(ValueInfo::new_unresolved(ir::WidthSpec::from(
                     current.width as u16,
                 )));
-                names.push(name.clone());
-            } else {
-                let ev_until = ev.width + ev.shift;
-                assert!(ev_until <= cur_until);
+                self.whole_names.push(name.clone());
+            } else if ev.width + ev.shift > cur_until {
+                radeco_warn!("Register {} overlaps {}, ignoring it",
+                             name,
+                             self.whole_names.last().map_or("", |n| n.as_str()));
+                continue;
             }
 
-            let subreg =
-                SubRegister::new(whole.len() as u64 - 1, ev.shift - current.shift, ev.width);
+            let subreg = SubRegister::new(self.whole_registers.len() as u64 - 1,
+                                          ev.shift - current.shift,
+                                          ev.width);
 
-            slices.insert(name.clone(), subreg);
-        }
-
-        SubRegisterFile {
-            whole_registers: whole,
-            named_registers: slices,
-            whole_names: names,
-            alias_info: aliases,
-            type_info: types,
+            self.named_registers.insert(name, subreg);
         }
     }
 
@@ -189,15 +224,17 @@ impl SubRegisterFile {
     }
 
     // Get information by other way.
+    /// Name of the whole register the alias/role refers to, e.g. `x30` for
+    /// "LR" on AArch64, even if the profile names it `lr`.
     pub fn get_name_by_alias(&self, alias: &String) -> Option<&str> {
-        for id in 0..self.whole_names.len() {
-            if let Some(name) = self.get_name(RegisterId::from_usize(id)) {
-                if self.alias_info.get(name) == Some(alias) {
-                    return Some(name);
-                }
-            }
-        }
-        None
+        self.register_id_by_alias(alias).and_then(|id| self.get_name(id))
+    }
+
+    /// Alias of the register holding return values. Profiles without a "R0"
+    /// alias use the register of the syscall number, "SN", which is the same
+    /// on x86.
+    pub fn return_alias(&self) -> &'static str {
+        if self.alias_info.contains_key("R0") { "R0" } else { "SN" }
     }
 
     pub fn iter_args(&self) -> RegisterIter {
@@ -269,6 +306,50 @@ impl SubRegisterFile {
     }
 }
 
+// Finds pairs of registers which are adjacent halves of a wider register
+// not in `events` yet: `<name>l` and `<name>h` of 64 bits, or `xmm<n>` and
+// `ymm<n>h` of 128 bits. Returns the name and extent of the whole register,
+// followed by the names of the low and high halves.
+fn vector_halves(events: &[(String, SubRegister)]) -> Vec<(String, SubRegister, String, String)> {
+    let by_name = events.iter()
+        .map(|&(ref name, ref reg)| (name.as_str(), reg))
+        .collect::<HashMap<_, _>>();
+    let mut halves = Vec::new();
+    for &(ref name, ref low) in events {
+        let (whole, high_name) = if name.ends_with('l') && low.width == 64 {
+            let stem = &name[..name.len() - 1];
+            (stem.to_owned(), format!("{}h", stem))
+        } else if name.starts_with("xmm") && low.width == 128 {
+            (format!("ymm{}", &name[3..]), format!("ymm{}h", &name[3..]))
+        } else {
+            continue;
+        };
+        if whole.is_empty() || by_name.contains_key(whole.as_str()) {
+            continue;
+        }
+        let high = match by_name.get(high_name.as_str()) {
+            Some(high) if high.width == low.width => high,
+            _ => continue,
+        };
+        let (first, second) = if low.shift < high.shift { (low, high) } else { (high, low) };
+        if first.shift + low.width != second.shift {
+            continue;
+        }
+        halves.push((whole,
+                     SubRegister::new(first.base, first.shift, 2 * low.width),
+                     name.clone(),
+                     high_name));
+    }
+    halves
+}
+
+// Whether the register is a status register made up of the flags, as
+// `rflags` on x86, `cpsr` on ARM or `nzcv` on AArch64.
+fn is_status_register(name: &str, type_str: &str, size: u64) -> bool {
+    name.ends_with("flags") || (type_str == "flg" && size > 1) ||
+    ["cpsr", "apsr", "nzcv"].contains(&name)
+}
+
 /// Opaque identifier for a whole register in [`SubRegisterFile`]
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct RegisterId(u8);
@@ -309,7 +390,7 @@ fn callconv_name_to_preserved_list(cc_name: &str) -> &'static [&'static str] {
 
         // --- ARM ---
         // https://developer.arm.com/docs/ihi0042/latest
-        "arm32" => &["r4", "r5", "r6", "r7", "r8", "r10", "r11", "sp"],
+        "arm32" | "arm16" => &["r4", "r5", "r6", "r7", "r8", "r10", "fp", "sp"],
         // https://developer.arm.com/docs/ihi0055/latest
         "arm64" => &["x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28", "fp", "sp"],
 
@@ -317,3 +398,88 @@ fn callconv_name_to_preserved_list(cc_name: &str) -> &'static [&'static str] {
         _ => &[],
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json;
+    use utils::test_utils::register_profile;
+
+    fn regfile(path: &str) -> SubRegisterFile {
+        SubRegisterFile::new(&register_profile(path))
+    }
+
+    fn sub(regfile: &SubRegisterFile, name: &str) -> (u64, u64, u64) {
+        let subreg = regfile.get_subregister(name).unwrap();
+        (subreg.base, subreg.shift, subreg.width)
+    }
+
+    #[test]
+    fn vector_halves_test() {
+        let events = [("xmm0h", 0, 64), ("xmm0l", 64, 64), ("ymm0h", 128, 128),
+                      ("xmm1l", 256, 64), ("xmm1h", 384, 64), ("st0l", 512, 32)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(name, offset, size))| {
+                (name.to_owned(), SubRegister::new(i as u64, offset, size))
+            })
+            .collect::<Vec<_>>();
+        let joined = |events: &[(String, SubRegister)]| {
+            vector_halves(events)
+                .into_iter()
+                .map(|(name, whole, low, high)| (name, whole.shift, whole.width, low, high))
+                .collect::<Vec<_>>()
+        };
+        // `xmm1` is not adjacent, `st0` is too narrow. `ymm0` needs `xmm0`.
+        assert_eq!(joined(&events),
+                   vec![("xmm0".to_owned(), 0, 128, "xmm0l".to_owned(), "xmm0h".to_owned())]);
+
+        let mut events = events;
+        events.push(("xmm0".to_owned(), SubRegister::new(0, 0, 128)));
+        assert_eq!(joined(&events),
+                   vec![("ymm0".to_owned(), 0, 256, "xmm0".to_owned(), "ymm0h".to_owned())]);
+    }
+
+    #[test]
+    fn vector_registers_test() {
+        let regfile = regfile("test_files/x86_register_profile.json");
+        let xmm0 = sub(&regfile, "xmm0");
+        assert_eq!((xmm0.1, xmm0.2), (0, 128));
+        assert_eq!(sub(&regfile, "xmm0l"), (xmm0.0, 0, 64));
+        assert_eq!(sub(&regfile, "xmm0h"), (xmm0.0, 64, 64));
+        assert!(regfile.get_subregister("ymm0").is_none());
+
+        // The profile with AVX lanes following the SSE registers.
+        let reg_info = r#"{"alias_info": [],
+                           "reg_info": [{"type": 0, "type_str": "gpr", "name": "rax", "size": 64, "offset": 0},
+                                        {"type": 2, "type_str": "fpu", "name": "xmm0h", "size": 64, "offset": 0},
+                                        {"type": 2, "type_str": "fpu", "name": "xmm0l", "size": 64, "offset": 64},
+                                        {"type": 2, "type_str": "fpu", "name": "ymm0h", "size": 128, "offset": 128}]}"#;
+        let regfile = SubRegisterFile::new(&serde_json::from_str(reg_info).unwrap());
+        let ymm0 = sub(&regfile, "ymm0");
+        assert_eq!((ymm0.1, ymm0.2), (0, 256));
+        assert_eq!(sub(&regfile, "xmm0"), (ymm0.0, 0, 128));
+        assert_eq!(sub(&regfile, "xmm0l"), (ymm0.0, 0, 64));
+        assert_eq!(sub(&regfile, "xmm0h"), (ymm0.0, 64, 64));
+        assert_eq!(sub(&regfile, "ymm0h"), (ymm0.0, 128, 128));
+        assert_eq!(regfile.type_info.get("ymm0").map(|t| t.as_str()), Some("fpu"));
+    }
+
+    #[test]
+    fn preserved_lists_test() {
+        let profiles = [
+            ("test_files/x86_register_profile.json", &["amd64", "cdecl", "ms", "stdcall"][..]),
+            ("test_files/arm32_filesource/arm32_register_profile.json", &["arm32", "arm16"][..]),
+            ("test_files/arm64_filesource/arm64_register_profile.json", &["arm64"][..]),
+        ];
+        for &(path, cc_names) in &profiles {
+            let regfile = regfile(path);
+            for cc_name in cc_names {
+                for regname in callconv_name_to_preserved_list(cc_name) {
+                    assert!(regfile.register_id_by_name(regname).is_some(),
+                            "{} of {} is not in {}", regname, cc_name, path);
+                }
+            }
+        }
+    }
+}
@@ -18,6 +18,7 @@
 
 
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::marker::PhantomData;
 use petgraph::EdgeDirection;
 use petgraph::stable_graph::StableDiGraph;
@@ -27,7 +28,12 @@ use r2api::structs::LVarInfo;
 
 use super::ssa_traits::{SSA, SSAMod, SSAWalk};
 use super::ssa_traits::NodeType;
+use super::ssastorage::SSAStorage;
+use middle::intrinsics;
 use middle::ir::MOpcode;
+use analysis::vsa::abstract_set::abstract_set::{AbstractSet, inum};
+use analysis::vsa::value_set::{ALoc, MemRegion};
+use analysis::vsa::vsa::ValueSetAnalyzer;
 
 // NOTE: Until now, this file is only used to implement raw Memory SSA, in 
 // future work, it may be modified to support API for more accurate memory
@@ -39,7 +45,6 @@ use middle::ir::MOpcode;
 
 
 // TODO: Add Memory SSA into SSA_Extra.
-// TODO: Add a trait for MemorySSA as APIs for future work.
 // TODO: Split this file into two files, one for may_alias set, another for MemorySSA. 
 // Above tasks should be done after Value Set Analysis finished.
 
@@ -51,6 +56,11 @@ pub enum VariableType {
     Global(u64),
     /// Extra variable, or heap variable, type.
     Extra(NodeIndex),
+    /// A-loc found by value set analysis.
+    ALoc(ALoc),
+    /// Locations of a memory region which are not a-locs, used for accesses
+    /// whose addresses are not precise enough.
+    Region(MemRegion),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -59,6 +69,9 @@ pub enum MemOpcode {
     VDef,
     /// Used for OpLoad.
     VUse,
+    /// Used for OpCall and OpIntrinsic accessing memory, which is both a VUse
+    /// and a VDef of the memory escaped to the callee.
+    Call,
     /// Phi node for Memory SSA.
     Phi,
     /// Initial Memory Station.
@@ -69,9 +82,229 @@ pub enum MemOpcode {
 
 // TODO: Now the edge weight in MemorySSA graph is all zero, it may help
 // if the operands are in order.
-type VarId = usize;
+pub type VarId = usize;
 type ArgOrd = u16;
 
+/// Definition of memory reaching a memory access.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryDef<V> {
+    /// Memory defined by the OpStore.
+    Store(V),
+    /// Memory which may be written by the OpCall/OpIntrinsic.
+    Call(V),
+    /// Memory phi node in MemorySSA graph.
+    Phi(NodeIndex),
+    /// Memory at the entry of function.
+    Entry,
+    /// Undefined memory.
+    Undefined,
+}
+
+/// Queries on a generated MemorySSA, used by passes to treat OpLoad/OpStore
+/// precisely. Calls and intrinsics accessing memory use and define all the
+/// variables they may access, thus they are found among the definitions
+/// reaching an access and among the uses of a store.
+pub trait MemorySSAQuery {
+    type ValueRef;
+    type ActionRef;
+
+    /// Returns all variables in MemorySSA.
+    fn variables(&self) -> &[VariableType];
+
+    /// Returns the variables may be accessed by the OpLoad/OpStore.
+    fn may_alias(&self, access: &Self::ValueRef) -> Option<&HashSet<VarId>>;
+
+    /// Returns the definitions of memory directly reaching the OpLoad/OpStore.
+    fn reaching_defs(&self, access: &Self::ValueRef) -> Vec<MemoryDef<Self::ValueRef>>;
+
+    /// Returns the OpStores reaching the OpLoad/OpStore through memory phis
+    /// and calls, and whether other memory also reaches it, i.e. the memory
+    /// at function entry, undefined memory or memory written by calls.
+    fn reaching_stores(&self, access: &Self::ValueRef) -> (Vec<Self::ValueRef>, bool);
+
+    /// Returns all the OpLoads/OpStores/calls using the memory defined by the
+    /// OpStore, directly or through memory phis.
+    fn uses_of_store(&self, store: &Self::ValueRef) -> Vec<Self::ValueRef>;
+
+    /// Returns the memory phi of variable at the basic block.
+    fn memory_phi(&self, var: VarId, block: &Self::ActionRef) -> Option<NodeIndex>;
+
+    /// Returns the operands of the memory phi.
+    fn phi_operands(&self, phi: &NodeIndex) -> Vec<MemoryDef<Self::ValueRef>>;
+}
+
+/// Result of MemorySSA, which could be kept without borrowing the SSA.
+#[derive(Clone, Debug)]
+pub struct MemorySSAInfo<V, A>
+    where V: Copy + Eq + Hash,
+          A: Copy + Eq + Hash
+{
+    pub g: StableDiGraph<MemOpcode, ArgOrd>,
+    /// Transform a Memory SSA Node into its associated basic block.
+    pub associated_blocks: HashMap<NodeIndex, A>,
+    /// Transform a VDef/VUse node into its associated opcode node.
+    pub associated_nodes: HashMap<NodeIndex, V>,
+    /// All variables used in this function.
+    pub variables: Vec<VariableType>,
+    /// May-alias Set for every LOAD/STORE.
+    pub may_aliases: HashMap<V, HashSet<VarId>>,
+    /// Different variables' Phi nodes for every basic block.
+    pub phi_nodes: HashMap<NodeIndex, VarId>,
+    /// The only a-loc accessed by every precise LOAD/STORE, found by value
+    /// set analysis.
+    pub a_locs: HashMap<V, ALoc>,
+    /// Lowest escaped stack offset found by value set analysis.
+    pub escaped_stack: Option<inum>,
+    // Transform an opcode node into its VDef/VUse node.
+    mem_nodes: HashMap<V, NodeIndex>,
+}
+
+impl<V, A> MemorySSAInfo<V, A>
+    where V: Copy + Eq + Hash,
+          A: Copy + Eq + Hash
+{
+    /// Returns the VDef/VUse/Call node of the OpStore/OpLoad/call.
+    pub fn mem_node(&self, access: &V) -> Option<NodeIndex> {
+        self.mem_nodes.get(access).cloned()
+    }
+
+    /// Returns the only a-loc accessed by the OpLoad/OpStore, None if it may
+    /// access other locations.
+    pub fn accessed_a_loc(&self, access: &V) -> Option<ALoc> {
+        self.a_locs.get(access).cloned()
+    }
+
+    fn neighbors(&self, mem_node: &NodeIndex, direction: EdgeDirection) -> Vec<NodeIndex> {
+        if self.g.node_weight(*mem_node).is_none() {
+            return Vec::new();
+        }
+        self.g.neighbors_directed(*mem_node, direction).collect()
+    }
+
+    fn def_of(&self, mem_node: &NodeIndex) -> MemoryDef<V> {
+        match self.g.node_weight(*mem_node) {
+            Some(&MemOpcode::VDef) => {
+                self.associated_nodes
+                    .get(mem_node)
+                    .map_or(MemoryDef::Undefined, |v| MemoryDef::Store(*v))
+            }
+            Some(&MemOpcode::Call) => {
+                self.associated_nodes
+                    .get(mem_node)
+                    .map_or(MemoryDef::Undefined, |v| MemoryDef::Call(*v))
+            }
+            Some(&MemOpcode::Phi) => MemoryDef::Phi(*mem_node),
+            Some(&MemOpcode::MemoryAccess) => MemoryDef::Entry,
+            _ => MemoryDef::Undefined,
+        }
+    }
+
+    // Returns true if the memory node defines any of `vars`.
+    fn defines_any(&self, mem_node: &NodeIndex, vars: &HashSet<VarId>) -> bool {
+        match self.g.node_weight(*mem_node) {
+            Some(&MemOpcode::Phi) => self.phi_nodes.get(mem_node).map_or(false, |v| vars.contains(v)),
+            Some(&MemOpcode::VDef) | Some(&MemOpcode::Call) => {
+                self.associated_nodes
+                    .get(mem_node)
+                    .and_then(|v| self.may_aliases.get(v))
+                    .map_or(true, |defined| defined.intersection(vars).next().is_some())
+            }
+            _ => true,
+        }
+    }
+}
+
+impl<V, A> MemorySSAQuery for MemorySSAInfo<V, A>
+    where V: Copy + Eq + Hash,
+          A: Copy + Eq + Hash
+{
+    type ValueRef = V;
+    type ActionRef = A;
+
+    fn variables(&self) -> &[VariableType] {
+        &self.variables
+    }
+
+    fn may_alias(&self, access: &V) -> Option<&HashSet<VarId>> {
+        self.may_aliases.get(access)
+    }
+
+    fn reaching_defs(&self, access: &V) -> Vec<MemoryDef<V>> {
+        self.mem_node(access)
+            .map(|n| self.neighbors(&n, EdgeDirection::Outgoing))
+            .unwrap_or_default()
+            .iter()
+            .map(|n| self.def_of(n))
+            .collect()
+    }
+
+    fn reaching_stores(&self, access: &V) -> (Vec<V>, bool) {
+        let empty = HashSet::new();
+        let vars = self.may_alias(access).unwrap_or(&empty);
+        let mut stores = Vec::new();
+        let mut from_entry = false;
+        let mut visited = HashSet::new();
+        let mut worklist = self.mem_node(access)
+            .map(|n| self.neighbors(&n, EdgeDirection::Outgoing))
+            .unwrap_or_default();
+        while let Some(n) = worklist.pop() {
+            if !visited.insert(n) {
+                continue;
+            }
+            match self.def_of(&n) {
+                MemoryDef::Store(v) => stores.push(v),
+                MemoryDef::Phi(phi) => worklist.extend(self.neighbors(&phi, EdgeDirection::Outgoing)),
+                // Calls may keep the memory, follow the variables accessed.
+                MemoryDef::Call(_) => {
+                    from_entry = true;
+                    worklist.extend(self.neighbors(&n, EdgeDirection::Outgoing)
+                        .into_iter()
+                        .filter(|def| self.defines_any(def, vars)));
+                }
+                MemoryDef::Entry | MemoryDef::Undefined => from_entry = true,
+            }
+        }
+        (stores, from_entry)
+    }
+
+    fn uses_of_store(&self, store: &V) -> Vec<V> {
+        let mut uses = Vec::new();
+        let mut visited = HashSet::new();
+        let mut worklist = self.mem_node(store)
+            .map(|n| self.neighbors(&n, EdgeDirection::Incoming))
+            .unwrap_or_default();
+        while let Some(n) = worklist.pop() {
+            if !visited.insert(n) {
+                continue;
+            }
+            match self.g.node_weight(n) {
+                Some(&MemOpcode::Phi) => worklist.extend(self.neighbors(&n, EdgeDirection::Incoming)),
+                Some(&MemOpcode::VDef) | Some(&MemOpcode::VUse) | Some(&MemOpcode::Call) => {
+                    uses.extend(self.associated_nodes.get(&n).cloned());
+                }
+                _ => {}
+            }
+        }
+        uses
+    }
+
+    fn memory_phi(&self, var: VarId, block: &A) -> Option<NodeIndex> {
+        self.phi_nodes
+            .iter()
+            .find(|&(phi, v)| (*v == var) && (self.associated_blocks.get(phi) == Some(block)))
+            .map(|(phi, _)| *phi)
+    }
+
+    fn phi_operands(&self, phi: &NodeIndex) -> Vec<MemoryDef<V>> {
+        match self.g.node_weight(*phi) {
+            Some(&MemOpcode::Phi) => {
+                self.neighbors(phi, EdgeDirection::Outgoing).iter().map(|n| self.def_of(n)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
 pub struct MemorySSA<'a, I, T>
     where I: Iterator<Item = T::ValueRef>,
           T: 'a + SSA + SSAMod + SSAWalk<I>
@@ -98,6 +331,13 @@ pub struct MemorySSA<'a, I, T>
     pub phi_nodes: HashMap<NodeIndex, VarId>,
     // phi_nodes is used to map every phi node to its variable, which will be useful
     // in future work.
+    /// The only a-loc accessed by every precise LOAD/STORE, found by value
+    /// set analysis.
+    pub a_locs: HashMap<T::ValueRef, ALoc>,
+    /// Lowest escaped stack offset found by value set analysis.
+    pub escaped_stack: Option<inum>,
+    /// May_alias sets are computed from value set analysis.
+    vsa_aliases: bool,
     sealed_blocks: HashSet<T::ActionRef>,
     current_def: Vec<HashMap<T::ActionRef, NodeIndex>>,
     incomplete_phis: Vec<HashMap<T::ActionRef, NodeIndex>>, 
@@ -121,6 +361,9 @@ impl<'a, I, T> MemorySSA<'a, I, T>
             global_nodes: HashSet::new(),
             sealed_blocks: HashSet::new(),
             phi_nodes: HashMap::new(),
+            a_locs: HashMap::new(),
+            escaped_stack: None,
+            vsa_aliases: false,
             current_def: Vec::new(),
             incomplete_phis: Vec::new(),
             foo: PhantomData,
@@ -129,7 +372,9 @@ impl<'a, I, T> MemorySSA<'a, I, T>
 
     /// Run to generate MemorySSA.
     pub fn run(&mut self) {
-        self.gather_may_aliases();
+        if !self.vsa_aliases {
+            self.gather_may_aliases();
+        }
         radeco_trace!("MemorrySSA|May_alias Set: {:?}", self.may_aliases);
         // Gather may_alias sets.
         radeco_trace!("MemorrySSA|Gather may_alias done!");
@@ -139,6 +384,26 @@ impl<'a, I, T> MemorySSA<'a, I, T>
         radeco_trace!("MemorrySSA|generate done!");
     }
 
+    /// Returns the result of MemorySSA, which could be queried by
+    /// `MemorySSAQuery` after the SSA is released.
+    pub fn into_info(self) -> MemorySSAInfo<T::ValueRef, T::ActionRef> {
+        let mem_nodes = self.associated_nodes
+            .iter()
+            .map(|(mem_node, node)| (*node, *mem_node))
+            .collect();
+        MemorySSAInfo {
+            g: self.g,
+            associated_blocks: self.associated_blocks,
+            associated_nodes: self.associated_nodes,
+            variables: self.variables,
+            may_aliases: self.may_aliases,
+            phi_nodes: self.phi_nodes,
+            a_locs: self.a_locs,
+            escaped_stack: self.escaped_stack,
+            mem_nodes: mem_nodes,
+        }
+    }
+
     // Gather variables in following rules:
     //      local: from r2api to get local;
     //      global:from r2api to get datafers;
@@ -176,8 +441,12 @@ impl<'a, I, T> MemorySSA<'a, I, T>
 
         radeco_trace!("MemorrySSA|Gather variables: {:?}", self.variables);
 
-        // Resize associated data structures
-        for _ in 0..self.variables.len() {
+        self.resize_defs();
+    }
+
+    // Resize associated data structures
+    fn resize_defs(&mut self) {
+        while self.current_def.len() < self.variables.len() {
             self.current_def.push(HashMap::new());
             self.incomplete_phis.push(HashMap::new());
         }
@@ -330,6 +599,12 @@ impl<'a, I, T> MemorySSA<'a, I, T>
                         may_alias.insert(i);
                     }
                 }
+
+                VariableType::ALoc(_) | VariableType::Region(_) => {
+                    if involve_all {
+                        may_alias.insert(i);
+                    }
+                }
             } 
         }
 
@@ -364,6 +639,12 @@ impl<'a, I, T> MemorySSA<'a, I, T>
                                 self.calculate_may_alias(&expr, &operands[1]);
                             }
 
+                            ref opc if is_call(opc) => {
+                                // Callees may access every variable.
+                                let may_alias = (0..self.variables.len()).collect();
+                                self.may_aliases.insert(expr, may_alias);
+                            }
+
                             _ => {
                                 self.propagate_nodes_type(&expr, &operands);
                             }
@@ -619,6 +900,31 @@ impl<'a, I, T> MemorySSA<'a, I, T>
         self.sealed_blocks.insert(block.clone());
     }
 
+    // Adds the memory node of the access, which uses the variables of its
+    // may_alias set. Every node but VUse also defines them.
+    fn add_access(&mut self, expr: &T::ValueRef, data: MemOpcode) {
+        let set: Vec<VarId> = match self.may_aliases.get(expr) {
+            Some(set) => set.iter().cloned().collect(),
+            None => {
+                radeco_err!("Cannot find may_alias set!");
+                Vec::new()
+            }
+        };
+        let mem_node = self.add_node(expr, data);
+        let block = self.ssa.block_for(*expr)
+                            .unwrap_or_else(|| {
+                                radeco_err!("Value node doesn't belong to any block");
+                                self.ssa.invalid_action().unwrap()
+                            });
+        for i in set {
+            let arg = self.read_variable(i, &block);
+            self.add_use(&mem_node, &arg);
+            if data != MemOpcode::VUse {
+                self.write_variable(i, &block, &mem_node);
+            }
+        }
+    }
+
     // Main function of MemorySSA, generate Memory SSA using the similar way of 
     // phiplacement.rs
     fn generate(&mut self) {
@@ -631,52 +937,13 @@ impl<'a, I, T> MemorySSA<'a, I, T>
         // Init MemorySSA, making MemoryAccess as all variables' define.
 
         for expr in self.ssa.inorder_walk() {
-            let dummy = HashSet::new();
             if let Ok(ndata) = self.ssa.node_data(expr) {
                 radeco_trace!("MemorrySSA|Deal with node: {:?}", expr);
 
                 match ndata.nt {
-                    NodeType::Op(MOpcode::OpLoad) => {
-                        let set: Vec<VarId> = self.may_aliases.get(&expr)
-                                                                .unwrap_or_else(|| {
-                                                                    radeco_err!("Cannot find may_alias set!");
-                                                                    &dummy
-                                                                }).iter()
-                                                                .cloned()
-                                                                .collect();
-                        let vuse = self.add_node(&expr, MemOpcode::VUse);
-                        for i in set {
-                            let block = self.ssa.block_for(expr)
-                                                .unwrap_or_else(|| {
-                                                    radeco_err!("Value node doesn't belong to any block");
-                                                    self.ssa.invalid_action().unwrap()
-                                                });
-                            let arg = self.read_variable(i, &block);
-                            self.add_use(&vuse, &arg);
-                        }
-                    }
-
-                    NodeType::Op(MOpcode::OpStore) => {  
-                        let set: Vec<VarId> = self.may_aliases.get(&expr)
-                                                                .unwrap_or_else(|| {
-                                                                    radeco_err!("Cannot find may_alias set!");
-                                                                    &dummy
-                                                                }).iter()
-                                                                .cloned()
-                                                                .collect();
-                        let vdef = self.add_node(&expr, MemOpcode::VDef);
-                        for i in set {
-                            let block = self.ssa.block_for(expr)
-                                                .unwrap_or_else(|| {
-                                                    radeco_err!("Value node doesn't belong to any block");
-                                                    self.ssa.invalid_action().unwrap()
-                                                });
-                            let arg = self.read_variable(i, &block);
-                            self.add_use(&vdef, &arg);
-                            self.write_variable(i, &block, &vdef);
-                        }
-                    }
-                    // TODO: Combine above code chunk
+                    NodeType::Op(MOpcode::OpLoad) => self.add_access(&expr, MemOpcode::VUse),
+                    NodeType::Op(MOpcode::OpStore) => self.add_access(&expr, MemOpcode::VDef),
+                    NodeType::Op(ref opc) if is_call(opc) => self.add_access(&expr, MemOpcode::Call),
                     _ => {}
                 }
             } 
@@ -690,3 +957,251 @@ impl<'a, I, T> MemorySSA<'a, I, T>
         radeco_trace!("MemorrySSA|Memory SSA Graph: {:?}", self.g);
     }
 }
+
+impl<'a, I> MemorySSA<'a, I, SSAStorage>
+    where I: Iterator<Item = NodeIndex>,
+          SSAStorage: SSAWalk<I>
+{
+    /// Use results of value set analysis to gather variables and may_alias
+    /// sets, instead of the heuristics of raw MemorySSA. The accessed a-locs
+    /// and the escaped stack are kept for the passes querying MemorySSA.
+    ///
+    /// Variables are gathered in following rules:
+    ///
+    /// * a-loc: every a-loc accessed directly;
+    /// * region: the rest of every region accessed.
+    ///
+    /// And the may_alias set of a LOAD/STORE is:
+    ///
+    /// * precise access: a-locs overlapping with the accessed a-loc;
+    /// * imprecise access: all a-locs and the rest of accessed regions;
+    /// * unknown access: all the variables;
+    /// * call: the global and heap variables, and the stack variables at or
+    ///   above the lowest escaped stack address.
+    pub fn gather_a_locs<S: AbstractSet>(&mut self, vsa: &ValueSetAnalyzer<S>) {
+        let mut a_locs: Vec<ALoc> = vsa.a_locs().iter().cloned().collect();
+        a_locs.sort();
+        let mut regions: Vec<MemRegion> = Vec::new();
+        let mut accesses = Vec::new();
+        let mut calls = Vec::new();
+        for expr in self.ssa.inorder_walk() {
+            match self.ssa.opcode(expr) {
+                Some(MOpcode::OpLoad) | Some(MOpcode::OpStore) => {
+                    if let Some(rs) = vsa.accessed_regions(&expr) {
+                        for r in rs {
+                            if !regions.contains(&r) {
+                                regions.push(r);
+                            }
+                        }
+                    }
+                    accesses.push(expr);
+                }
+                Some(ref opc) if is_call(opc) => calls.push(expr),
+                _ => {}
+            }
+        }
+        for a_loc in &a_locs {
+            if !regions.contains(&a_loc.region) {
+                regions.push(a_loc.region);
+            }
+        }
+        regions.sort();
+
+        let a_loc_base = self.variables.len();
+        self.variables.extend(a_locs.iter().map(|a| VariableType::ALoc(*a)));
+        let region_base = self.variables.len();
+        self.variables.extend(regions.iter().map(|r| VariableType::Region(*r)));
+        self.resize_defs();
+        radeco_trace!("MemorrySSA|Gather variables from VSA: {:?}", self.variables);
+
+        let region_var = |r: &MemRegion| regions.iter().position(|x| x == r).map(|i| region_base + i);
+        for expr in accesses {
+            let mut may_alias = HashSet::new();
+            if let Some(accessed) = vsa.accessed_a_locs(&expr) {
+                for a in &accessed {
+                    for (i, a_loc) in a_locs.iter().enumerate() {
+                        if a_loc.overlaps(a) {
+                            may_alias.insert(a_loc_base + i);
+                        }
+                    }
+                }
+            } else if let Some(accessed) = vsa.accessed_regions(&expr) {
+                for r in &accessed {
+                    for (i, a_loc) in a_locs.iter().enumerate() {
+                        if a_loc.region == *r {
+                            may_alias.insert(a_loc_base + i);
+                        }
+                    }
+                    may_alias.extend(region_var(r));
+                }
+            } else {
+                may_alias.extend(0..self.variables.len());
+            }
+            radeco_trace!("MemorrySSA|New may_alias set {:?} for {:?}", may_alias, expr);
+            self.may_aliases.insert(expr, may_alias);
+            if let Some(a_loc) = vsa.accessed_a_loc(&expr) {
+                self.a_locs.insert(expr, a_loc);
+            }
+        }
+
+        let escaped = vsa.escaped_stack();
+        self.escaped_stack = escaped;
+        let escapes = |a_loc: &ALoc| match a_loc.region {
+            MemRegion::Stack => {
+                escaped.map_or(false, |lowest| a_loc.offset.saturating_add(a_loc.size as inum) > lowest)
+            }
+            _ => true,
+        };
+        let mut call_alias: HashSet<VarId> = (0..a_loc_base).collect();
+        for (i, a_loc) in a_locs.iter().enumerate() {
+            if escapes(a_loc) {
+                call_alias.insert(a_loc_base + i);
+            }
+        }
+        for (i, r) in regions.iter().enumerate() {
+            if *r != MemRegion::Stack || escaped.is_some() {
+                call_alias.insert(region_base + i);
+            }
+        }
+        radeco_trace!("MemorrySSA|May_alias set {:?} for calls {:?}", call_alias, calls);
+        for call in calls {
+            self.may_aliases.insert(call, call_alias.clone());
+        }
+        self.vsa_aliases = true;
+    }
+}
+
+// OpCall and OpIntrinsic accessing memory, which use and define the memory
+// escaped to them.
+fn is_call(opc: &MOpcode) -> bool {
+    match *opc {
+        MOpcode::OpCall => true,
+        MOpcode::OpIntrinsic(ref name) => intrinsics::may_access_memory(name),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::test_utils::{function, il_function, load_il_function, REGISTER_PROFILE};
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    const STACK_GLOBAL_TXT: &str = "\
+define-fun sym.stack_global(unknown) -> unknown {
+    entry-register-state:
+        %1: $Unknown64(*?) = $rsp;
+        %2: $Unknown0 = $mem;
+    bb_0x001000.0000(sz 0x8):
+        [@0x001000.0001] %3: $Unknown64 = %1 - #x8;
+        [@0x001000.0002] %4: $Unknown0 = Store(%2, %3, #x2a);
+        [@0x001004.0001] %5: $Unknown64 = Load(%4, #x3000);
+        RETURN
+    exit-node:
+    final-register-state:
+        $rax = %5;
+        $rsp = %1;
+        $mem = %4;
+}
+";
+
+    #[test]
+    fn memory_ssa_vsa_test() {
+        let mut rfn = load_il_function("./test_files/bin1_main_ssa");
+        rfn.build_memory_ssa();
+        let mssa = rfn.memory_ssa().expect("MemorySSA not built");
+
+        // `push rbp` and the local at `rbp - 0xc` are both stack a-locs.
+        let has_a_loc = |a_loc: ALoc| mssa.variables().iter().any(|v| match *v {
+            VariableType::ALoc(ref a) => *a == a_loc,
+            _ => false,
+        });
+        assert!(has_a_loc(ALoc::new(MemRegion::Stack, -8, 8)));
+        assert!(has_a_loc(ALoc::new(MemRegion::Stack, -0x14, 4)));
+
+        let accesses = rfn.ssa().values().into_iter().filter(|n| {
+            match rfn.ssa().opcode(*n) {
+                Some(MOpcode::OpLoad) | Some(MOpcode::OpStore) => true,
+                _ => false,
+            }
+        }).collect::<Vec<_>>();
+        assert!(!accesses.is_empty());
+        for access in &accesses {
+            assert!(mssa.may_alias(access).is_some());
+        }
+
+        // Only stores sharing a variable with a load may reach it.
+        for access in &accesses {
+            if rfn.ssa().opcode(*access) != Some(MOpcode::OpLoad) {
+                continue;
+            }
+            let (stores, _) = mssa.reaching_stores(access);
+            for store in stores {
+                let store_vars = mssa.may_alias(&store).unwrap();
+                let load_vars = mssa.may_alias(access).unwrap();
+                assert!(store_vars.intersection(load_vars).next().is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn memory_ssa_stack_global_test() {
+        // A store to the stack does not clobber a global.
+        let mut rfn = il_function(STACK_GLOBAL_TXT);
+        rfn.build_memory_ssa();
+        let mssa = rfn.memory_ssa().expect("MemorySSA not built");
+        let node = |opcode: MOpcode| {
+            rfn.ssa()
+                .values()
+                .into_iter()
+                .find(|n| rfn.ssa().opcode(*n) == Some(opcode.clone()))
+                .expect("Access not found")
+        };
+        let store = node(MOpcode::OpStore);
+        let load = node(MOpcode::OpLoad);
+
+        let store_vars = mssa.may_alias(&store).expect("No may_alias set for the store");
+        let load_vars = mssa.may_alias(&load).expect("No may_alias set for the load");
+        assert!(store_vars.intersection(load_vars).next().is_none());
+        let (stores, from_entry) = mssa.reaching_stores(&load);
+        assert!(stores.is_empty());
+        assert!(from_entry);
+
+        // Results of value set analysis are kept for the passes.
+        assert_eq!(mssa.accessed_a_loc(&store), Some(ALoc::new(MemRegion::Stack, -8, 8)));
+        assert_eq!(mssa.accessed_a_loc(&load), Some(ALoc::new(MemRegion::Global, 0x3000, 8)));
+        assert_eq!(mssa.escaped_stack, None);
+    }
+
+    #[test]
+    fn memory_ssa_call_test() {
+        // The address of the slot is passed in `rsi`, the callee may read
+        // and write it.
+        let ops = r#"[{"offset":4096,"size":4,"opcode":"sub rsp, 0x10","esil":"16,rsp,-=","type":"sub"},
+                      {"offset":4100,"size":5,"opcode":"mov qword [rsp + 8], rdi","esil":"rdi,8,rsp,+,=[8]","type":"mov"},
+                      {"offset":4105,"size":5,"opcode":"lea rsi, [rsp + 8]","esil":"8,rsp,+,rsi,=","type":"lea"},
+                      {"offset":4110,"size":5,"opcode":"call 0x2000","esil":"rip,8,rsp,-=,rsp,=[],8192,rip,=","type":"call","jump":8192},
+                      {"offset":4115,"size":5,"opcode":"mov rax, qword [rsp + 8]","esil":"8,rsp,+,[8],rax,=","type":"mov"},
+                      {"offset":4120,"size":4,"opcode":"add rsp, 0x10","esil":"16,rsp,+=","type":"add"},
+                      {"offset":4124,"size":1,"opcode":"ret","esil":"rsp,[8],rip,=,8,rsp,+=","type":"ret"}]"#;
+        let mut rfn = function(REGISTER_PROFILE, ops, true);
+        rfn.build_memory_ssa();
+        let mssa = rfn.memory_ssa().expect("MemorySSA not built");
+        let node = |opcode: MOpcode| {
+            rfn.ssa()
+                .values()
+                .into_iter()
+                .find(|n| rfn.ssa().opcode(*n) == Some(opcode.clone()))
+                .expect("Node not found")
+        };
+        let store = node(MOpcode::OpStore);
+        let call = node(MOpcode::OpCall);
+        let load = node(MOpcode::OpLoad);
+
+        let store_vars = mssa.may_alias(&store).expect("No may_alias set for the store");
+        assert!(mssa.may_alias(&call).expect("No may_alias set for the call").is_superset(store_vars));
+        assert_eq!(mssa.uses_of_store(&store), vec![call]);
+        assert_eq!(mssa.reaching_defs(&load), vec![MemoryDef::Call(call)]);
+        assert_eq!(mssa.reaching_stores(&load), (vec![store], true));
+    }
+}
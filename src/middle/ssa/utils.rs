@@ -1,8 +1,9 @@
 //! A few utility functions for working with an [`SSAStorage`].
 
+use middle::ir::MOpcode;
 use middle::regfile::{RegisterId, RegisterMap};
 use middle::ssa::ssa_traits::ValueInfo;
-use middle::ssa::ssastorage::{EdgeData, SSAStorage};
+use middle::ssa::ssastorage::{EdgeData, NodeData, SSAStorage};
 
 use petgraph::prelude::*;
 
@@ -48,6 +49,46 @@ pub fn call_rets(call_node: NodeIndex, ssa: &SSAStorage) -> RegisterMap<(NodeInd
     ret
 }
 
+/// Structured information about an intrinsic.
+pub struct IntrinsicInfo {
+    /// Mnemonic of the intrinsic.
+    pub name: String,
+    /// Operands, in order.
+    pub args: Vec<NodeIndex>,
+    /// Values defined by the intrinsic, in order.
+    pub outputs: Vec<NodeIndex>,
+}
+
+/// Extracts the operands and the outputs of an `OpIntrinsic` node.
+/// Returns `None` if the node is not an intrinsic.
+pub fn intrinsic_info(node: NodeIndex, ssa: &SSAStorage) -> Option<IntrinsicInfo> {
+    let name = match ssa.g[node] {
+        NodeData::Op(MOpcode::OpIntrinsic(ref name), _) => name.clone(),
+        _ => return None,
+    };
+    let mut args = Vec::new();
+    for edge_ref in ssa.g.edges_directed(node, Outgoing) {
+        if let &EdgeData::Data(op_idx) = edge_ref.weight() {
+            args.push((op_idx, edge_ref.target()));
+        }
+    }
+    let mut outputs = Vec::new();
+    for edge_ref in ssa.g.edges_directed(node, Incoming) {
+        if let (&EdgeData::Data(idx), &NodeData::Comment(..)) =
+            (edge_ref.weight(), &ssa.g[edge_ref.source()])
+        {
+            outputs.push((idx, edge_ref.source()));
+        }
+    }
+    args.sort();
+    outputs.sort();
+    Some(IntrinsicInfo {
+        name,
+        args: args.into_iter().map(|(_, n)| n).collect(),
+        outputs: outputs.into_iter().map(|(_, n)| n).collect(),
+    })
+}
+
 /// Extracts the value of all registers at a `RegisterState` SSA node.
 pub fn register_state_info(regstate_node: NodeIndex, ssa: &SSAStorage) -> RegisterMap<(NodeIndex, ValueInfo)> {
     let mut ret = ssa.regfile.new_register_map();
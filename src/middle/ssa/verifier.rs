@@ -192,7 +192,7 @@ impl Verify for SSAStorage {
                     };
 
                     if opcode != MOpcode::OpCall
-                        && opcode.idx() != MOpcode::OpCustom(String::new()).idx()
+                        && opcode.idx() != MOpcode::OpIntrinsic(String::new()).idx()
                     {
                         check!(op_len == n, SSAErr::WrongNumOperands(*exi, n, op_len));
                     }
@@ -222,9 +222,16 @@ impl Verify for SSAStorage {
                             check!(opw < w0, SSAErr::IncompatibleWidth(*exi, opw, w0));
                             check!(w == w0, SSAErr::IncompatibleWidth(*exi, w, w0));
                         }
+                        MOpcode::OpIntToFloat(w0) |
+                        MOpcode::OpFloatToInt(w0) |
+                        MOpcode::OpFloatConv(w0) => {
+                            check!(w == w0, SSAErr::IncompatibleWidth(*exi, w, w0));
+                        }
                         MOpcode::OpEq |
                         MOpcode::OpGt |
-                        MOpcode::OpLt => {
+                        MOpcode::OpLt |
+                        MOpcode::OpFEq |
+                        MOpcode::OpFLt => {
                             check!(w == 1, SSAErr::IncompatibleWidth(*exi, 1, w));
                         }
                         // TODO: Width of OpStore and OpLoad now is not certain.
@@ -0,0 +1,273 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! 256-bit unsigned integers.
+//!
+//! Values of AVX registers are 256 bits wide, which no native integer holds.
+//! `U256` implements the wrapping arithmetic needed to evaluate operations on
+//! them, see `MOpcode::eval_binop_width`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+
+const LIMBS: usize = 4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// 256-bit unsigned integer, with wrapping arithmetic.
+pub struct U256 {
+    // 64-bit limbs, least significant first.
+    limbs: [u64; LIMBS],
+}
+
+impl U256 {
+    pub fn zero() -> U256 {
+        U256::default()
+    }
+
+    pub fn max_value() -> U256 {
+        U256 { limbs: [u64::max_value(); LIMBS] }
+    }
+
+    /// Returns the 64-bit limbs of the value, least significant first.
+    pub fn limbs(&self) -> [u64; LIMBS] {
+        self.limbs
+    }
+
+    /// Returns the low 128 bits of the value.
+    pub fn low_u128(&self) -> u128 {
+        (self.limbs[1] as u128) << 64 | self.limbs[0] as u128
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.iter().all(|&l| l == 0)
+    }
+
+    /// Returns true if the value fits in `width` bits.
+    pub fn fits(&self, width: u16) -> bool {
+        *self & !width_mask(width) == U256::zero()
+    }
+
+    /// Returns bit `i` of the value.
+    pub fn bit(&self, i: u32) -> bool {
+        i < 256 && (self.limbs[(i / 64) as usize] >> (i % 64)) & 1 == 1
+    }
+
+    pub fn wrapping_add(self, rhs: U256) -> U256 {
+        let mut res = U256::zero();
+        let mut carry = 0;
+        for i in 0..LIMBS {
+            let sum = self.limbs[i] as u128 + rhs.limbs[i] as u128 + carry;
+            res.limbs[i] = sum as u64;
+            carry = sum >> 64;
+        }
+        res
+    }
+
+    pub fn wrapping_sub(self, rhs: U256) -> U256 {
+        self.wrapping_add((!rhs).wrapping_add(U256::from(1u64)))
+    }
+
+    pub fn wrapping_mul(self, rhs: U256) -> U256 {
+        let mut res = U256::zero();
+        for i in 0..LIMBS {
+            let mut carry = 0;
+            // Products of limbs above the 256 bits are dropped.
+            for j in 0..LIMBS - i {
+                let prod = self.limbs[i] as u128 * rhs.limbs[j] as u128 +
+                           res.limbs[i + j] as u128 + carry;
+                res.limbs[i + j] = prod as u64;
+                carry = prod >> 64;
+            }
+        }
+        res
+    }
+
+    pub fn checked_div(self, rhs: U256) -> Option<U256> {
+        self.div_rem(rhs).map(|(quot, _)| quot)
+    }
+
+    pub fn checked_rem(self, rhs: U256) -> Option<U256> {
+        self.div_rem(rhs).map(|(_, rem)| rem)
+    }
+
+    // Long division, one bit at a time.
+    fn div_rem(self, rhs: U256) -> Option<(U256, U256)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let mut quot = U256::zero();
+        let mut rem = U256::zero();
+        for i in (0..256).rev() {
+            rem = rem << 1;
+            if self.bit(i) {
+                rem.limbs[0] |= 1;
+            }
+            if rem >= rhs {
+                rem = rem.wrapping_sub(rhs);
+                quot.limbs[(i / 64) as usize] |= 1 << (i % 64);
+            }
+        }
+        Some((quot, rem))
+    }
+}
+
+/// Returns the mask of the low `width` bits, `width` must not be wider than
+/// 256 bits.
+pub fn width_mask(width: u16) -> U256 {
+    if width >= 256 {
+        U256::max_value()
+    } else {
+        (U256::from(1u64) << width as u32).wrapping_sub(U256::from(1u64))
+    }
+}
+
+impl From<u64> for U256 {
+    fn from(val: u64) -> U256 {
+        U256 { limbs: [val, 0, 0, 0] }
+    }
+}
+
+impl From<u128> for U256 {
+    fn from(val: u128) -> U256 {
+        U256 { limbs: [val as u64, (val >> 64) as u64, 0, 0] }
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &U256) -> Ordering {
+        self.limbs.iter().rev().cmp(other.limbs.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &U256) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Not for U256 {
+    type Output = U256;
+
+    fn not(self) -> U256 {
+        let mut res = self;
+        for l in &mut res.limbs {
+            *l = !*l;
+        }
+        res
+    }
+}
+
+macro_rules! bitwise_impl {
+    ($trait_:ident, $fn_:ident, $op:tt) => {
+        impl $trait_ for U256 {
+            type Output = U256;
+
+            fn $fn_(self, rhs: U256) -> U256 {
+                let mut res = self;
+                for i in 0..LIMBS {
+                    res.limbs[i] = self.limbs[i] $op rhs.limbs[i];
+                }
+                res
+            }
+        }
+    };
+}
+
+bitwise_impl!(BitAnd, bitand, &);
+bitwise_impl!(BitOr, bitor, |);
+bitwise_impl!(BitXor, bitxor, ^);
+
+// Bits shifted out of the 256 bits are lost.
+impl Shl<u32> for U256 {
+    type Output = U256;
+
+    fn shl(self, amount: u32) -> U256 {
+        let mut res = U256::zero();
+        let (limbs, bits) = ((amount / 64) as usize, amount % 64);
+        for i in limbs..LIMBS {
+            res.limbs[i] = self.limbs[i - limbs] << bits;
+            if bits > 0 && i > limbs {
+                res.limbs[i] |= self.limbs[i - limbs - 1] >> (64 - bits);
+            }
+        }
+        res
+    }
+}
+
+impl Shr<u32> for U256 {
+    type Output = U256;
+
+    fn shr(self, amount: u32) -> U256 {
+        let mut res = U256::zero();
+        let (limbs, bits) = ((amount / 64) as usize, amount % 64);
+        for i in limbs..LIMBS {
+            res.limbs[i - limbs] = self.limbs[i] >> bits;
+            if bits > 0 && i + 1 < LIMBS {
+                res.limbs[i - limbs] |= self.limbs[i + 1] << (64 - bits);
+            }
+        }
+        res
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        match self.limbs.iter().rposition(|&l| l != 0) {
+            None => write!(f, "0"),
+            Some(top) => {
+                write!(f, "{:x}", self.limbs[top])?;
+                for l in self.limbs[..top].iter().rev() {
+                    write!(f, "{:016x}", l)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arithmetic_test() {
+        let max128 = U256::from(u128::max_value());
+        let one = U256::from(1u64);
+        // Carries across the 128 bits.
+        assert_eq!(max128.wrapping_add(one).limbs(), [0, 0, 1, 0]);
+        assert_eq!(U256::zero().wrapping_sub(one), U256::max_value());
+        // (2^128 - 1)^2 = 2^256 - 2^129 + 1
+        assert_eq!(max128.wrapping_mul(max128).limbs(),
+                   [1, 0, u64::max_value() - 1, u64::max_value()]);
+        assert_eq!(U256::max_value().wrapping_mul(U256::max_value()), one);
+
+        let (quot, rem) = max128.wrapping_mul(max128).wrapping_add(U256::from(5u64))
+            .div_rem(max128)
+            .unwrap();
+        assert_eq!((quot, rem), (max128, U256::from(5u64)));
+        assert_eq!(one.checked_div(U256::zero()), None);
+    }
+
+    #[test]
+    fn bits_test() {
+        let one = U256::from(1u64);
+        assert_eq!((one << 200).limbs(), [0, 0, 0, 1 << 8]);
+        assert_eq!((one << 200) >> 137, one << 63);
+        assert_eq!(U256::max_value() >> 255, one);
+        assert_eq!(width_mask(256), U256::max_value());
+        assert_eq!(width_mask(130), (one << 130).wrapping_sub(one));
+        assert!(one.bit(0) && !one.bit(1) && !one.bit(256));
+        assert!(U256::from(u128::max_value()).fits(128) && !(one << 128).fits(128));
+        assert!(U256::zero() < one && one << 255 > U256::from(u128::max_value()));
+        assert_eq!(format!("{:#x}", (one << 128) | one),
+                   "0x100000000000000000000000000000001");
+    }
+}
@@ -10,6 +10,8 @@
 
 #[macro_use]
 pub mod logger;
+#[cfg(test)]
+pub mod test_utils;
 
 //use std::io::prelude::*;
 //use std::fs;
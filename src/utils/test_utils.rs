@@ -0,0 +1,88 @@
+// Copyright (c) 2018, The Radare Project. All rights reserved.
+// See the COPYING file at the top-level directory of this distribution.
+// Licensed under the BSD 3-Clause License:
+// <http://opensource.org/licenses/BSD-3-Clause>
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fixtures shared by the unit tests.
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::sync::Arc;
+
+use r2api::structs::{LOpInfo, LRegInfo};
+use serde_json;
+
+use frontend::radeco_containers::RadecoFunction;
+use frontend::ssaconstructor::SSAConstruct;
+use middle::ir_reader;
+use middle::regfile::SubRegisterFile;
+use middle::ssa::ssastorage::SSAStorage;
+
+pub const REGISTER_PROFILE: &'static str = "test_files/x86_register_profile.json";
+pub const REGISTER_PROFILE_32: &'static str = "test_files/x86_32_register_profile.json";
+
+/// Reads the register profile at `path`.
+pub fn register_profile(path: &str) -> LRegInfo {
+    let mut s = String::new();
+    File::open(path).expect("Failed to open file").read_to_string(&mut s).expect(
+        "Failed to read file",
+    );
+    serde_json::from_str(&s).expect("Unable to load register profile")
+}
+
+/// Returns the register file of the x86_64 profile.
+pub fn regfile() -> Arc<SubRegisterFile> {
+    Arc::new(SubRegisterFile::new(&register_profile(REGISTER_PROFILE)))
+}
+
+/// Parses the IR text `ir` against the x86_64 profile.
+pub fn parse_il(ir: &str) -> SSAStorage {
+    ir_reader::parse_il(ir, regfile())
+}
+
+/// Parses the IR in the file at `path` against the x86_64 profile.
+pub fn load_il(path: &str) -> SSAStorage {
+    let mut ir = String::new();
+    File::open(path).expect("file not found").read_to_string(&mut ir).expect(
+        "something went wrong reading the file",
+    );
+    parse_il(&ir)
+}
+
+/// Returns a function holding the SSA of the IR text `ir`.
+pub fn il_function(ir: &str) -> RadecoFunction {
+    with_ssa(parse_il(ir))
+}
+
+/// Returns a function holding the SSA of the IR in the file at `path`.
+pub fn load_il_function(path: &str) -> RadecoFunction {
+    with_ssa(load_il(path))
+}
+
+fn with_ssa(ssa: SSAStorage) -> RadecoFunction {
+    let mut rfn = RadecoFunction::default();
+    *rfn.ssa_mut() = ssa;
+    rfn
+}
+
+/// Constructs the SSA of `ops`, a JSON array of instructions, with the
+/// x86_64 profile.
+pub fn construct(ops: &str) -> SSAStorage {
+    let ops: Vec<LOpInfo> = serde_json::from_str(ops).expect("Invalid instructions");
+    let regfile = regfile();
+    let mut ssa = SSAStorage::new();
+    ssa.regfile = regfile.clone();
+    SSAConstruct::new(&mut ssa, &regfile).run(ops.as_slice());
+    ssa
+}
+
+/// Returns a function of `ops`, a JSON array of instructions, with its SSA
+/// constructed with the register profile at `path`.
+pub fn function(path: &str, ops: &str, assume_cc: bool) -> RadecoFunction {
+    let mut rfn = RadecoFunction::new();
+    rfn.instructions = serde_json::from_str(ops).expect("Invalid instructions");
+    SSAConstruct::construct(&mut rfn, &register_profile(path), assume_cc, true);
+    rfn
+}